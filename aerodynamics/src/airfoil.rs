@@ -0,0 +1,140 @@
+// Airfoil - lift and drag coefficients as functions of angle of attack
+use std::f32::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+/// Lift and drag curve component for a wing
+///
+/// Below the stall, lift grows linearly with angle of attack and drag is the
+/// zero-lift drag plus induced drag, CL² / (π e AR). Past the stall the flow
+/// separates and the wing behaves like a flat plate pushed through the air,
+/// with a force normal to its surface. A logistic blend joins the two, so
+/// lift peaks just before `stall_angle` and drops away after it
+///
+/// Entities with an Airfoil get their AeroProperties coefficients filled in
+/// from the curve every step; without one the coefficients stay constant
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Airfoil {
+    /// CL per radian in the linear region; 2π for a thin airfoil, less for a finite wing
+    pub lift_slope: f32,
+
+    /// Angle of attack with no lift, in radians; negative for cambered airfoils
+    pub zero_lift_angle: f32,
+
+    /// Angle where the flow is half separated, in radians; the same either side of zero
+    pub stall_angle: f32,
+
+    /// How abruptly the flow separates, per radian; larger is a sharper stall
+    pub stall_sharpness: f32,
+
+    /// Drag with no lift: skin friction and pressure drag
+    pub zero_lift_drag: f32,
+
+    /// Span² / wing area; long thin wings make less induced drag
+    pub aspect_ratio: f32,
+
+    /// Oswald efficiency, 1 for an ideal elliptical lift distribution, about 0.8 in practice
+    pub oswald_efficiency: f32,
+
+    /// Normal force coefficient of the stalled wing seen as a flat plate, about 2
+    pub flat_plate_normal: f32,
+}
+
+impl Airfoil {
+    /// A typical cambered wing of the given aspect ratio: zero lift at -2°,
+    /// stalling at 15°
+    ///
+    /// The lift slope is lifting-line theory's 2π·AR / (AR + 2), which
+    /// tends to the thin-airfoil 2π as the wing gets longer
+    pub fn wing(aspect_ratio: f32) -> Self {
+        Self {
+            lift_slope: 2.0 * PI * aspect_ratio / (aspect_ratio + 2.0),
+            zero_lift_angle: (-2.0f32).to_radians(),
+            stall_angle: 15.0f32.to_radians(),
+            stall_sharpness: 50.0,
+            zero_lift_drag: 0.025,
+            aspect_ratio,
+            oswald_efficiency: 0.8,
+            flat_plate_normal: 2.0,
+        }
+    }
+
+    /// How much of the flow is still attached, from 1 (not stalled) to 0
+    ///
+    /// Two logistic steps, one at +stall_angle and one at -stall_angle
+    fn attached(&self, angle_of_attack: f32) -> f32 {
+        let logistic = |x: f32| 1.0 / (1.0 + (-x).exp());
+        logistic(self.stall_sharpness * (self.stall_angle - angle_of_attack)) * logistic(self.stall_sharpness * (self.stall_angle + angle_of_attack))
+    }
+
+    /// Induced drag per CL²: 1 / (π e AR)
+    pub fn induced_drag_factor(&self) -> f32 {
+        1.0 / (PI * self.oswald_efficiency * self.aspect_ratio)
+    }
+
+    /// Lift and drag coefficients at an angle of attack in radians
+    ///
+    /// Any angle works, including flying backwards: the flat plate model
+    /// covers the whole circle once the wing has stalled
+    pub fn coefficients(&self, angle_of_attack: f32) -> (f32, f32) {
+        let attached = self.attached(angle_of_attack);
+        let (sin, cos) = angle_of_attack.sin_cos();
+
+        let linear_lift = self.lift_slope * (angle_of_attack - self.zero_lift_angle);
+        let plate_lift = self.flat_plate_normal * sin * cos;
+        let lift = attached * linear_lift + (1.0 - attached) * plate_lift;
+
+        let induced_drag = self.induced_drag_factor() * linear_lift * linear_lift;
+        let plate_drag = self.flat_plate_normal * sin * sin;
+        let drag = self.zero_lift_drag + attached * induced_drag + (1.0 - attached) * plate_drag;
+        (lift, drag)
+    }
+
+    pub fn lift_coefficient(&self, angle_of_attack: f32) -> f32 {
+        self.coefficients(angle_of_attack).0
+    }
+
+    pub fn drag_coefficient(&self, angle_of_attack: f32) -> f32 {
+        self.coefficients(angle_of_attack).1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lift_and_drag_curves() {
+        let airfoil = Airfoil::wing(8.0);
+        let degrees = |angle: f32| angle.to_radians();
+
+        // Linear region: zero lift at -2°, slope 2π·8/10 per radian
+        assert!(airfoil.lift_coefficient(degrees(-2.0)).abs() < 1e-4);
+        let slope = (airfoil.lift_coefficient(degrees(6.0)) - airfoil.lift_coefficient(degrees(2.0))) / degrees(4.0);
+        assert!((slope - airfoil.lift_slope).abs() < 0.01, "slope {}", slope);
+
+        // Lift peaks before the stall angle and drops after it
+        let angles: Vec<f32> = (0..900).map(|tenth| degrees(tenth as f32 * 0.1)).collect();
+        let peak = angles.iter().copied().max_by(|a, b| airfoil.lift_coefficient(*a).total_cmp(&airfoil.lift_coefficient(*b))).unwrap();
+        assert!(peak > degrees(10.0) && peak < airfoil.stall_angle, "peak at {}°", peak.to_degrees());
+        assert!(airfoil.lift_coefficient(degrees(20.0)) < 0.75 * airfoil.lift_coefficient(peak));
+
+        // A stalled wing at 90° is a flat plate: no lift, all drag
+        let (lift, drag) = airfoil.coefficients(degrees(90.0));
+        assert!(lift.abs() < 1e-3);
+        assert!((drag - 2.025).abs() < 1e-3);
+
+        // Induced drag grows with CL² and shrinks with aspect ratio
+        let glider = Airfoil::wing(20.0);
+        let extra_drag = |airfoil: &Airfoil| airfoil.drag_coefficient(degrees(8.0)) - airfoil.zero_lift_drag;
+        let expected = airfoil.induced_drag_factor() * (airfoil.lift_slope * degrees(10.0)).powi(2);
+        assert!((extra_drag(&airfoil) - expected).abs() < 0.01 * expected);
+        assert!(extra_drag(&glider) < extra_drag(&airfoil));
+
+        // Every angle gives finite values, and drag never helps
+        for step in -360..=360 {
+            let (lift, drag) = airfoil.coefficients(degrees(step as f32));
+            assert!(lift.is_finite() && drag >= airfoil.zero_lift_drag, "{}°: {} {}", step, lift, drag);
+        }
+    }
+}
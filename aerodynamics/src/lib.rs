@@ -1,9 +1,15 @@
-// Aerodynamics module - placeholder for Phase 2 implementation
-// This will contain lift/drag calculations, airfoil modeling, etc.
+// Aerodynamics module - lift, drag and wind
+//
+// AeroProperties describes a wing, Airfoil how its coefficients change with
+// angle of attack, and the systems turn the airflow into forces
 
+use nalgebra::Vector3;
 use serde::{Deserialize, Serialize};
 
 /// Aerodynamic properties component
+/// 
+/// The aero systems store the angle of attack they compute here, and with an
+/// Airfoil on the entity, the lift and drag coefficients at that angle
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AeroProperties {
     pub lift_coefficient: f32,
@@ -51,7 +57,66 @@ impl Wind {
     }
 }
 
-/// Placeholder for aerodynamic systems (to be implemented in Phase 2)
-pub mod systems {
-    // Will contain lift/drag calculation systems, wind effects, etc.
-}
\ No newline at end of file
+/// Wind conditions for 3D mode
+/// 
+/// The 2D Wind plus a z component: with x forward and y up, that's a
+/// crosswind blowing towards the left of an aircraft flying along +x
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Wind3 {
+    pub velocity_x: f32,
+    pub velocity_y: f32,
+    pub velocity_z: f32,
+    pub turbulence: f32,
+}
+
+impl Wind3 {
+    pub fn calm() -> Self {
+        Self {
+            velocity_x: 0.0,
+            velocity_y: 0.0,
+            velocity_z: 0.0,
+            turbulence: 0.0,
+        }
+    }
+    
+    pub fn velocity(&self) -> Vector3<f32> {
+        Vector3::new(self.velocity_x, self.velocity_y, self.velocity_z)
+    }
+}
+
+impl From<Wind> for Wind3 {
+    /// The same wind, with no crosswind
+    fn from(wind: Wind) -> Self {
+        Self {
+            velocity_x: wind.velocity_x,
+            velocity_y: wind.velocity_y,
+            velocity_z: 0.0,
+            turbulence: wind.turbulence,
+        }
+    }
+}
+
+/// Turbulent gust on top of the mean wind
+///
+/// The aero systems add it to Wind (or Wind3) when it's there; whatever
+/// simulates the turbulence keeps it up to date. The 2D systems ignore z
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Gust {
+    pub velocity_x: f32,
+    pub velocity_y: f32,
+    pub velocity_z: f32,
+}
+
+impl Gust {
+    pub fn velocity(&self) -> Vector3<f32> {
+        Vector3::new(self.velocity_x, self.velocity_y, self.velocity_z)
+    }
+}
+
+pub mod airfoil;
+
+/// Aerodynamic systems, which turn airflow into forces
+pub mod systems;
+
+pub use airfoil::Airfoil;
+pub use systems::{AeroSystem, AeroSystem3};
\ No newline at end of file
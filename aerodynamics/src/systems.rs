@@ -0,0 +1,262 @@
+// Aerodynamic systems
+use ecs::{Access, EcsResult, System, World};
+use nalgebra::{Rotation2, Vector2, Vector3};
+use physics::constants::AIR_DENSITY;
+use physics::{Force, Force3, Orientation, Rotation, Velocity, Velocity3};
+
+use crate::{AeroProperties, Airfoil, Gust, Wind, Wind3};
+
+/// Below this airspeed there's too little airflow to give it a direction, in m/s
+const MIN_AIRSPEED: f32 = 0.01;
+
+/// Store the angle of attack, and the coefficients at it when there's a curve
+fn update_coefficients(aero: &mut AeroProperties, airfoil: Option<&Airfoil>, angle_of_attack: f32) {
+    aero.angle_of_attack = angle_of_attack;
+    if let Some(airfoil) = airfoil {
+        (aero.lift_coefficient, aero.drag_coefficient) = airfoil.coefficients(angle_of_attack);
+    }
+}
+
+/// Lift and drag for 2D bodies
+/// 
+/// For each entity with Velocity, Rotation, AeroProperties and Force:
+/// - the airflow is the body's velocity relative to the Wind resource
+///   plus any Gust (calm air if there are none)
+/// - angle of attack is the angle from the airflow up to the nose, and is
+///   stored in AeroProperties
+/// - with an Airfoil, the lift and drag coefficients come from its curve at
+///   that angle (and are stored too); without one they stay constant
+/// - drag pushes straight against the airflow, lift at right angles to it,
+///   towards the top of the aircraft when flying forwards
+/// 
+/// Both are ½ ρ V² S C, added to Force at the centre of mass
+#[derive(Clone)]
+pub struct AeroSystem {
+    name: String,
+}
+
+impl AeroSystem {
+    pub fn new() -> Self {
+        Self {
+            name: "AeroSystem".to_string(),
+        }
+    }
+}
+
+impl Default for AeroSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl System for AeroSystem {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    
+    fn access(&self) -> Access {
+        Access::new()
+            .read::<Velocity>()
+            .read::<Rotation>()
+            .read::<Airfoil>()
+            .write::<AeroProperties>()
+            .write::<Force>()
+            .read_resource::<Wind>()
+            .read_resource::<Gust>()
+    }
+    
+    fn run_shared(&mut self, world: &World, _delta_time: f32) -> EcsResult<()> {
+        let gust = world.resource::<Gust>().map(|gust| *gust).unwrap_or_default();
+        let wind = world.resource::<Wind>().map(|wind| Vector2::new(wind.velocity_x, wind.velocity_y)).unwrap_or_else(|_| Vector2::zeros())
+            + Vector2::new(gust.velocity_x, gust.velocity_y);
+        
+        let mut query = world.query::<(&Velocity, &Rotation, Option<&Airfoil>, &mut AeroProperties, &mut Force)>()?;
+        for (velocity, rotation, airfoil, aero, force) in query.iter() {
+            let airflow = velocity.to_vector() - wind;
+            let airspeed = airflow.norm();
+            if airspeed < MIN_AIRSPEED {
+                update_coefficients(aero, airfoil, 0.0);
+                continue;
+            }
+            
+            // The airflow seen from the body: positive when the air comes from below the nose
+            let body_airflow = Rotation2::new(-rotation.angle) * airflow;
+            update_coefficients(aero, airfoil, (-body_airflow.y).atan2(body_airflow.x));
+            
+            let direction = airflow / airspeed;
+            let lift_direction = Vector2::new(-direction.y, direction.x);
+            
+            let dynamic_pressure = 0.5 * AIR_DENSITY * airspeed * airspeed * aero.wing_area;
+            let total = lift_direction * (dynamic_pressure * aero.lift_coefficient) - direction * (dynamic_pressure * aero.drag_coefficient);
+            force.add(Force::new(total.x, total.y));
+        }
+        Ok(())
+    }
+    
+    fn initialize(&mut self, world: &mut World) -> EcsResult<()> {
+        world.register_component::<AeroProperties>();
+        world.register_component::<Airfoil>();
+        world.register_component::<Force>();
+        Ok(())
+    }
+    
+    fn fork(&self) -> Option<Box<dyn System>> {
+        Some(Box::new(self.clone()))
+    }
+}
+
+/// Lift and drag for 3D bodies
+/// 
+/// For each entity with Velocity3, Orientation, AeroProperties and Force3:
+/// - the airflow is the body's velocity relative to the Wind3 resource
+///   plus any Gust (calm air if there are none)
+/// - angle of attack is the angle between the nose and the airflow, seen
+///   from the side (in the body's x-y plane), and is stored in AeroProperties
+/// - with an Airfoil, the coefficients come from its curve, as in AeroSystem
+/// - drag pushes straight against the airflow, lift at right angles to both
+///   the airflow and the wings, so banking the wings tilts the lift and
+///   turns the aircraft
+/// 
+/// Both are ½ ρ V² S C, with the coefficients from AeroProperties, and are
+/// added to Force3 at the centre of mass
+#[derive(Clone)]
+pub struct AeroSystem3 {
+    name: String,
+}
+
+impl AeroSystem3 {
+    pub fn new() -> Self {
+        Self {
+            name: "AeroSystem3".to_string(),
+        }
+    }
+}
+
+impl Default for AeroSystem3 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl System for AeroSystem3 {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    
+    fn access(&self) -> Access {
+        Access::new()
+            .read::<Velocity3>()
+            .read::<Orientation>()
+            .read::<Airfoil>()
+            .write::<AeroProperties>()
+            .write::<Force3>()
+            .read_resource::<Wind3>()
+            .read_resource::<Gust>()
+    }
+    
+    fn run_shared(&mut self, world: &World, _delta_time: f32) -> EcsResult<()> {
+        let gust = world.resource::<Gust>().map(|gust| gust.velocity()).unwrap_or_else(|_| Vector3::zeros());
+        let wind = world.resource::<Wind3>().map(|wind| wind.velocity()).unwrap_or_else(|_| Vector3::zeros()) + gust;
+        
+        let mut query = world.query::<(&Velocity3, &Orientation, Option<&Airfoil>, &mut AeroProperties, &mut Force3)>()?;
+        for (velocity, orientation, airfoil, aero, force) in query.iter() {
+            let airflow = velocity.to_vector() - wind;
+            let airspeed = airflow.norm();
+            if airspeed < MIN_AIRSPEED {
+                update_coefficients(aero, airfoil, 0.0);
+                continue;
+            }
+            
+            // Positive when the air comes from below the nose
+            let body_airflow = orientation.to_body(airflow);
+            update_coefficients(aero, airfoil, (-body_airflow.y).atan2(body_airflow.x));
+            
+            let direction = airflow / airspeed;
+            let span = orientation.to_world(Vector3::z());
+            // Zero when the air blows straight along the wings
+            let lift_direction = span.cross(&direction).try_normalize(1e-6).unwrap_or_else(Vector3::zeros);
+            
+            let dynamic_pressure = 0.5 * AIR_DENSITY * airspeed * airspeed * aero.wing_area;
+            let total = lift_direction * (dynamic_pressure * aero.lift_coefficient) - direction * (dynamic_pressure * aero.drag_coefficient);
+            force.add(Force3::new(total.x, total.y, total.z));
+        }
+        Ok(())
+    }
+    
+    fn initialize(&mut self, world: &mut World) -> EcsResult<()> {
+        world.register_component::<AeroProperties>();
+        world.register_component::<Airfoil>();
+        world.register_component::<Force3>();
+        Ok(())
+    }
+    
+    fn fork(&self) -> Option<Box<dyn System>> {
+        Some(Box::new(self.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lift_and_drag_curve_2d() {
+        let mut world = World::new();
+        let mut system = AeroSystem::new();
+        system.initialize(&mut world).unwrap();
+        // 10 m/s tailwind under 50 m/s flying speed leaves 40 m/s of airflow
+        world.insert_resource(Wind { velocity_x: 10.0, ..Wind::calm() });
+
+        let airfoil = Airfoil::wing(8.0);
+        let aero = AeroProperties::new(0.0, 0.0, 10.0);
+        let spawn = |world: &mut World, angle: f32| world.spawn((Velocity::new(50.0, 0.0), Rotation::new(angle), airfoil, aero.clone(), Force::zero())).unwrap();
+        let cruising = spawn(&mut world, 0.05);
+        let climbing = spawn(&mut world, 0.2);
+        let stalled = spawn(&mut world, 0.35);
+        // No curve: the constant coefficients apply at any angle
+        let constant = world.spawn((Velocity::new(50.0, 0.0), Rotation::new(0.5), AeroProperties::new(0.5, 0.05, 10.0), Force::zero())).unwrap();
+        system.run_shared(&world, 0.01).unwrap();
+
+        let q = 0.5 * AIR_DENSITY * 40.0 * 40.0 * 10.0;
+        let properties = world.get_component::<AeroProperties>(cruising).unwrap().clone();
+        assert!((properties.angle_of_attack - 0.05).abs() < 1e-5);
+        let (lift, drag) = airfoil.coefficients(properties.angle_of_attack);
+        assert_eq!((properties.lift_coefficient, properties.drag_coefficient), (lift, drag));
+        let force = world.get_component::<Force>(cruising).unwrap().to_vector();
+        assert!((force - Vector2::new(-drag * q, lift * q)).norm() < 1e-3 * q, "{:?}", force);
+
+        // Just past the stall the wing lifts less than near the peak and drags a lot more
+        let peak_force = world.get_component::<Force>(climbing).unwrap().to_vector();
+        let stalled_force = world.get_component::<Force>(stalled).unwrap().to_vector();
+        assert!(stalled_force.y < 0.75 * peak_force.y && stalled_force.x < 2.0 * peak_force.x, "{:?} {:?}", peak_force, stalled_force);
+
+        let properties = world.get_component::<AeroProperties>(constant).unwrap().clone();
+        assert_eq!((properties.lift_coefficient, properties.angle_of_attack), (0.5, 0.5));
+    }
+
+    #[test]
+    fn test_lift_and_drag_3d() {
+        let mut world = World::new();
+        let mut system = AeroSystem3::new();
+        system.initialize(&mut world).unwrap();
+        // 10 m/s headwind on top of 40 m/s flying speed
+        world.insert_resource(Wind3 { velocity_x: -10.0, ..Wind3::calm() });
+        
+        let aero = AeroProperties::new(0.5, 0.05, 10.0);
+        let level = world.spawn((Velocity3::new(40.0, 0.0, 0.0), Orientation::identity(), aero.clone(), Force3::zero())).unwrap();
+        // Banked 90° with the left wing down: lift points left (+z), into the turn
+        let banked = world.spawn((Velocity3::new(40.0, 0.0, 0.0), Orientation::from_euler(std::f32::consts::FRAC_PI_2, 0.0, 0.0), aero.clone(), Force3::zero())).unwrap();
+        // Nose 0.1 rad up in level flight: the air meets the wing from below
+        let pitched = world.spawn((Velocity3::new(40.0, 0.0, 0.0), Orientation::from_euler(0.0, 0.1, 0.0), aero, Force3::zero())).unwrap();
+        system.run_shared(&world, 0.01).unwrap();
+        
+        // ½ ρ V² S at 50 m/s airspeed
+        let q = 0.5 * AIR_DENSITY * 50.0 * 50.0 * 10.0;
+        let force = world.get_component::<Force3>(level).unwrap().to_vector();
+        assert!((force - Vector3::new(-0.05 * q, 0.5 * q, 0.0)).norm() < 1e-2 * q, "{:?}", force);
+        let force = world.get_component::<Force3>(banked).unwrap().to_vector();
+        assert!((force - Vector3::new(-0.05 * q, 0.0, 0.5 * q)).norm() < 1e-2 * q, "{:?}", force);
+        let angle_of_attack = world.get_component::<AeroProperties>(pitched).unwrap().angle_of_attack;
+        assert!((angle_of_attack - 0.1).abs() < 1e-5);
+    }
+}
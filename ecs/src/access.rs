@@ -0,0 +1,163 @@
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::{Component, EcsError, EcsResult, Resource};
+
+thread_local! {
+    /// Declared access of the shared system running on this thread, if any
+    static CURRENT_ACCESS: RefCell<Option<Arc<Access>>> = const { RefCell::new(None) };
+}
+
+/// Marks the running system's declared access until dropped
+///
+/// While it's set, every query and resource borrow on this thread is checked
+/// against it, in release builds too. An undeclared borrow would only conflict
+/// when another system happens to run at the same time; checking makes it fail
+/// every time
+pub(crate) struct AccessGuard(Option<Arc<Access>>);
+
+impl AccessGuard {
+    pub(crate) fn enter(access: &Arc<Access>) -> Self {
+        Self(CURRENT_ACCESS.with(|current| current.replace(Some(Arc::clone(access)))))
+    }
+}
+
+impl Drop for AccessGuard {
+    fn drop(&mut self) {
+        CURRENT_ACCESS.with(|current| *current.borrow_mut() = self.0.take());
+    }
+}
+
+/// Fail unless the running system, if any, declared the borrow
+fn check_declared(declared: impl Fn(&Access) -> bool, describe: impl Fn() -> String) -> EcsResult<()> {
+    CURRENT_ACCESS.with(|current| match &*current.borrow() {
+        Some(access) if !declared(access) => Err(EcsError::UndeclaredAccess(describe())),
+        _ => Ok(()),
+    })
+}
+
+pub(crate) fn check_read<T: Component>() -> EcsResult<()> {
+    let type_id = TypeId::of::<T>();
+    check_declared(
+        |access| access.components_read.contains(&type_id) || access.components_written.contains(&type_id),
+        || format!("reads component {}", T::type_name()),
+    )
+}
+
+pub(crate) fn check_write<T: Component>() -> EcsResult<()> {
+    let type_id = TypeId::of::<T>();
+    check_declared(|access| access.components_written.contains(&type_id), || format!("writes component {}", T::type_name()))
+}
+
+pub(crate) fn check_read_resource<R: Resource>() -> EcsResult<()> {
+    let type_id = TypeId::of::<R>();
+    check_declared(
+        |access| access.resources_read.contains(&type_id) || access.resources_written.contains(&type_id),
+        || format!("reads resource {}", R::type_name()),
+    )
+}
+
+pub(crate) fn check_write_resource<R: Resource>() -> EcsResult<()> {
+    let type_id = TypeId::of::<R>();
+    check_declared(|access| access.resources_written.contains(&type_id), || format!("writes resource {}", R::type_name()))
+}
+
+/// The component and resource types a system reads and writes
+///
+/// The `SystemDispatcher` runs systems whose accesses don't conflict at the same
+/// time. Two accesses conflict when either one writes a type the other reads or
+/// writes, or when either is exclusive
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Access {
+    exclusive: bool,
+    components_read: HashSet<TypeId>,
+    components_written: HashSet<TypeId>,
+    resources_read: HashSet<TypeId>,
+    resources_written: HashSet<TypeId>,
+}
+
+impl Access {
+    /// Access to nothing; add types with the builder methods
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Full `&mut World` access, conflicting with every other system
+    pub fn exclusive() -> Self {
+        Self {
+            exclusive: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn read<T: Component>(mut self) -> Self {
+        self.components_read.insert(TypeId::of::<T>());
+        self
+    }
+
+    pub fn write<T: Component>(mut self) -> Self {
+        self.components_written.insert(TypeId::of::<T>());
+        self
+    }
+
+    pub fn read_resource<R: Resource>(mut self) -> Self {
+        self.resources_read.insert(TypeId::of::<R>());
+        self
+    }
+
+    pub fn write_resource<R: Resource>(mut self) -> Self {
+        self.resources_written.insert(TypeId::of::<R>());
+        self
+    }
+
+    pub fn is_exclusive(&self) -> bool {
+        self.exclusive
+    }
+
+    /// Whether systems with these two accesses must not run at the same time
+    pub fn conflicts_with(&self, other: &Access) -> bool {
+        fn overlaps(
+            written: &HashSet<TypeId>,
+            other_read: &HashSet<TypeId>,
+            other_written: &HashSet<TypeId>,
+        ) -> bool {
+            written.iter().any(|type_id| other_read.contains(type_id) || other_written.contains(type_id))
+        }
+
+        self.exclusive
+            || other.exclusive
+            || overlaps(&self.components_written, &other.components_read, &other.components_written)
+            || overlaps(&other.components_written, &self.components_read, &self.components_written)
+            || overlaps(&self.resources_written, &other.resources_read, &other.resources_written)
+            || overlaps(&other.resources_written, &self.resources_read, &self.resources_written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Position;
+    struct Velocity;
+    struct Wind;
+
+    #[test]
+    fn test_access_conflicts() {
+        let movement = Access::new().write::<Position>().read::<Velocity>();
+        let drag = Access::new().write::<Velocity>().read_resource::<Wind>();
+        let render = Access::new().read::<Position>();
+        let gusts = Access::new().write_resource::<Wind>();
+
+        assert!(movement.conflicts_with(&drag));
+        assert!(movement.conflicts_with(&render));
+        assert!(!render.conflicts_with(&drag));
+        assert!(drag.conflicts_with(&gusts));
+        assert!(!movement.conflicts_with(&gusts));
+
+        // Components and resources of the same type don't alias
+        assert!(!Access::new().read::<Wind>().conflicts_with(&gusts));
+        assert!(Access::exclusive().conflicts_with(&Access::new()));
+    }
+}
@@ -0,0 +1,291 @@
+use std::any::TypeId;
+use std::collections::HashMap;
+use parking_lot::{MappedRwLockReadGuard, MappedRwLockWriteGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::{Component, ComponentInfo, ComponentStorage, ComponentTicks, EntityId, EcsError, EcsResult, TypedComponentStorage};
+
+/// Index of an archetype in the World's archetype list
+pub type ArchetypeId = usize;
+
+/// The archetype every entity starts in: no components at all
+pub const EMPTY_ARCHETYPE: ArchetypeId = 0;
+
+/// Where an entity's components live: which archetype table, and which row in it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EntityLocation {
+    pub archetype: ArchetypeId,
+    pub row: usize,
+}
+
+/// One component type's data in a table, with a change-tick entry per row
+///
+/// The ticks sit outside the lock so change filters can read them while a
+/// query is borrowing the data
+struct Column {
+    data: RwLock<Box<dyn ComponentStorage>>,
+    ticks: Vec<ComponentTicks>,
+}
+
+/// A table holding every entity with exactly the same set of component types
+///
+/// Each component type gets its own dense column, and row `i` of every column
+/// belongs to `entities[i]`. Adding or removing a component moves the entity's
+/// row into the archetype for its new component set
+pub struct Archetype {
+    id: ArchetypeId,
+    /// Component types stored here, sorted so the set has a single representation
+    types: Vec<TypeId>,
+    entities: Vec<EntityId>,
+    columns: HashMap<TypeId, Column>,
+    /// Cached transitions: archetype reached by adding a component type
+    add_edges: HashMap<TypeId, ArchetypeId>,
+    /// Cached transitions: archetype reached by removing a component type
+    remove_edges: HashMap<TypeId, ArchetypeId>,
+}
+
+impl Archetype {
+    /// Create an empty table with one column per entry in `columns`
+    pub(crate) fn new(id: ArchetypeId, columns: Vec<(TypeId, Box<dyn ComponentStorage>)>) -> Self {
+        let mut types: Vec<TypeId> = columns.iter().map(|(type_id, _)| *type_id).collect();
+        types.sort();
+
+        Self {
+            id,
+            types,
+            entities: Vec::new(),
+            columns: columns
+                .into_iter()
+                .map(|(type_id, storage)| {
+                    let column = Column {
+                        data: RwLock::new(storage),
+                        ticks: Vec::new(),
+                    };
+                    (type_id, column)
+                })
+                .collect(),
+            add_edges: HashMap::new(),
+            remove_edges: HashMap::new(),
+        }
+    }
+
+    /// Deep-copy the table for a forked world
+    ///
+    /// Fails if a column is borrowed, or holds components that can't be cloned
+    pub(crate) fn try_clone(&self, components: &HashMap<TypeId, ComponentInfo>) -> EcsResult<Self> {
+        let mut columns = HashMap::with_capacity(self.columns.len());
+        for (type_id, column) in &self.columns {
+            let info = &components[type_id];
+            let data = column
+                .data
+                .try_read()
+                .ok_or_else(|| EcsError::BorrowConflict(info.name().to_string()))?;
+            let column = Column {
+                data: RwLock::new(info.fork_storage(&**data)?),
+                ticks: column.ticks.clone(),
+            };
+            columns.insert(*type_id, column);
+        }
+
+        Ok(Self {
+            id: self.id,
+            types: self.types.clone(),
+            entities: self.entities.clone(),
+            columns,
+            add_edges: self.add_edges.clone(),
+            remove_edges: self.remove_edges.clone(),
+        })
+    }
+
+    pub fn id(&self) -> ArchetypeId {
+        self.id
+    }
+
+    /// Component types stored in this archetype, sorted
+    pub fn types(&self) -> &[TypeId] {
+        &self.types
+    }
+
+    /// Whether this archetype stores component type `type_id`
+    pub fn contains(&self, type_id: TypeId) -> bool {
+        self.columns.contains_key(&type_id)
+    }
+
+    /// Entities in row order
+    pub fn entities(&self) -> &[EntityId] {
+        &self.entities
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Borrow the `T` column for reading
+    ///
+    /// Returns Ok(None) if this archetype has no `T` column
+    pub fn read_column<T: Component>(&self) -> EcsResult<Option<MappedRwLockReadGuard<'_, TypedComponentStorage<T>>>> {
+        let Some(column) = self.columns.get(&TypeId::of::<T>()) else {
+            return Ok(None);
+        };
+        let guard = column
+            .data
+            .try_read()
+            .ok_or_else(|| EcsError::BorrowConflict(T::type_name().to_string()))?;
+
+        RwLockReadGuard::try_map(guard, |s| s.as_any().downcast_ref::<TypedComponentStorage<T>>())
+            .map(Some)
+            .map_err(|_| EcsError::ComponentNotRegistered(T::type_name().to_string()))
+    }
+
+    /// Borrow the `T` column for writing
+    ///
+    /// Returns Ok(None) if this archetype has no `T` column
+    pub fn write_column<T: Component>(&self) -> EcsResult<Option<MappedRwLockWriteGuard<'_, TypedComponentStorage<T>>>> {
+        let Some(column) = self.columns.get(&TypeId::of::<T>()) else {
+            return Ok(None);
+        };
+        let guard = column
+            .data
+            .try_write()
+            .ok_or_else(|| EcsError::BorrowConflict(T::type_name().to_string()))?;
+
+        RwLockWriteGuard::try_map(guard, |s| s.as_any_mut().downcast_mut::<TypedComponentStorage<T>>())
+            .map(Some)
+            .map_err(|_| EcsError::ComponentNotRegistered(T::type_name().to_string()))
+    }
+
+    /// Change ticks of the `type_id` column, in row order
+    pub fn column_ticks(&self, type_id: TypeId) -> Option<&[ComponentTicks]> {
+        self.columns.get(&type_id).map(|column| column.ticks.as_slice())
+    }
+
+    /// Exclusive access to the `T` column, no locking needed
+    pub(crate) fn column_mut<T: Component>(&mut self) -> Option<&mut TypedComponentStorage<T>> {
+        self.columns
+            .get_mut(&TypeId::of::<T>())?
+            .data
+            .get_mut()
+            .as_any_mut()
+            .downcast_mut::<TypedComponentStorage<T>>()
+    }
+
+    /// Append a `T` for the newest row, stamped as added at `tick`
+    ///
+    /// Hands the component back if this archetype has no `T` column
+    pub(crate) fn push_component<T: Component>(&mut self, component: T, tick: u64) -> Result<(), T> {
+        let Some(column) = self.columns.get_mut(&TypeId::of::<T>()) else {
+            return Err(component);
+        };
+        let Some(storage) = column.data.get_mut().as_any_mut().downcast_mut::<TypedComponentStorage<T>>() else {
+            return Err(component);
+        };
+        storage.push(component);
+        column.ticks.push(ComponentTicks::new(tick));
+        Ok(())
+    }
+
+    /// Take the `T` out of `row`, leaving the rest of the row in place
+    ///
+    /// The caller must then move or remove the row, as its columns are now uneven
+    pub(crate) fn take_component<T: Component>(&mut self, row: usize) -> Option<T> {
+        let column = self.columns.get_mut(&TypeId::of::<T>())?;
+        let storage = column.data.get_mut().as_any_mut().downcast_mut::<TypedComponentStorage<T>>()?;
+        column.ticks.swap_remove(row);
+        Some(storage.swap_remove(row))
+    }
+
+    pub(crate) fn add_edge(&self, type_id: TypeId) -> Option<ArchetypeId> {
+        self.add_edges.get(&type_id).copied()
+    }
+
+    pub(crate) fn remove_edge(&self, type_id: TypeId) -> Option<ArchetypeId> {
+        self.remove_edges.get(&type_id).copied()
+    }
+
+    pub(crate) fn set_add_edge(&mut self, type_id: TypeId, target: ArchetypeId) {
+        self.add_edges.insert(type_id, target);
+    }
+
+    pub(crate) fn set_remove_edge(&mut self, type_id: TypeId, target: ArchetypeId) {
+        self.remove_edges.insert(type_id, target);
+    }
+
+    /// Append an entity row; the caller pushes the matching column values
+    pub(crate) fn push_entity(&mut self, entity: EntityId) -> usize {
+        self.entities.push(entity);
+        self.entities.len() - 1
+    }
+
+    /// Drop the entity at `row` and all its components
+    ///
+    /// Returns the entity that was moved into `row` to fill the gap, if any
+    pub(crate) fn swap_remove(&mut self, row: usize) -> Option<EntityId> {
+        for column in self.columns.values_mut() {
+            column.data.get_mut().swap_remove(row);
+            column.ticks.swap_remove(row);
+        }
+        self.swap_remove_entity(row)
+    }
+
+    /// Move the entity at `row` into `dst`, carrying over every column both tables share
+    ///
+    /// Columns that only exist in `self` must already have had `row` taken by the
+    /// caller. Returns the new row in `dst` and the entity moved into `row`, if any
+    pub(crate) fn move_row(&mut self, row: usize, dst: &mut Archetype) -> (usize, Option<EntityId>) {
+        for (type_id, column) in self.columns.iter_mut() {
+            if let Some(dst_column) = dst.columns.get_mut(type_id) {
+                column.data.get_mut().swap_remove_into(row, dst_column.data.get_mut().as_mut());
+                dst_column.ticks.push(column.ticks.swap_remove(row));
+            }
+        }
+        let entity = self.entities[row];
+        let moved = self.swap_remove_entity(row);
+        (dst.push_entity(entity), moved)
+    }
+
+    fn swap_remove_entity(&mut self, row: usize) -> Option<EntityId> {
+        self.entities.swap_remove(row);
+        self.entities.get(row).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slotmap::SlotMap;
+
+    fn column<T: Component>() -> (TypeId, Box<dyn ComponentStorage>) {
+        (TypeId::of::<T>(), Box::new(TypedComponentStorage::<T>::new()))
+    }
+
+    #[test]
+    fn test_archetype_move_row() {
+        let mut ids: SlotMap<EntityId, ()> = SlotMap::new();
+        let (a, b) = (ids.insert(()), ids.insert(()));
+
+        let mut small = Archetype::new(1, vec![column::<u32>()]);
+        let mut large = Archetype::new(2, vec![column::<u32>(), column::<f32>()]);
+
+        for (entity, tick, value) in [(a, 1, 1u32), (b, 2, 2u32)] {
+            small.push_entity(entity);
+            small.push_component(value, tick).unwrap();
+        }
+
+        // Moving row 0 pulls `b` down into the gap, ticks and all
+        let (row, moved) = small.move_row(0, &mut large);
+        large.push_component(0.5f32, 3).unwrap();
+
+        assert_eq!((row, moved), (0, Some(b)));
+        assert_eq!(small.entities(), &[b]);
+        assert_eq!(large.entities(), &[a]);
+        assert_eq!(small.read_column::<u32>().unwrap().unwrap().get(0), Some(&2));
+        assert_eq!(large.read_column::<u32>().unwrap().unwrap().get(0), Some(&1));
+        assert!(large.read_column::<i8>().unwrap().is_none());
+        assert_eq!(small.column_ticks(TypeId::of::<u32>()).unwrap()[0].added(), 2);
+        assert_eq!(large.column_ticks(TypeId::of::<u32>()).unwrap()[0].added(), 1);
+        assert_eq!(large.push_component(7i8, 4), Err(7));
+    }
+}
@@ -0,0 +1,234 @@
+use std::any::TypeId;
+
+use crate::{Component, EntityId, World};
+
+/// A set of components spawned together with `World::spawn`
+///
+/// Implemented for tuples of up to eight components. User-defined bundles
+/// list their component types and then write their fields:
+///
+/// ```ignore
+/// impl Bundle for AircraftBundle {
+///     fn register_components(&self, types: &mut BundleTypes<'_>) {
+///         types.add::<Position>();
+///         types.add::<Velocity>();
+///     }
+///     fn write_components(self, writer: &mut BundleWriter<'_>) {
+///         writer.write(self.position);
+///         writer.write(self.velocity);
+///     }
+/// }
+/// ```
+pub trait Bundle: Send + 'static {
+    /// Add every component type in the bundle to `types`
+    fn register_components(&self, types: &mut BundleTypes<'_>);
+
+    /// Hand each component to `writer`, exactly one per type added in `register_components`
+    fn write_components(self, writer: &mut BundleWriter<'_>);
+}
+
+/// Collects a bundle's component types, registering them with the World
+pub struct BundleTypes<'w> {
+    world: &'w mut World,
+    types: Vec<TypeId>,
+}
+
+impl<'w> BundleTypes<'w> {
+    pub(crate) fn new(world: &'w mut World) -> Self {
+        Self {
+            world,
+            types: Vec::new(),
+        }
+    }
+
+    pub fn add<T: Component>(&mut self) {
+        self.world.register_component::<T>();
+        self.types.push(TypeId::of::<T>());
+    }
+
+    pub(crate) fn into_types(self) -> Vec<TypeId> {
+        self.types
+    }
+}
+
+/// Stores a bundle's components into the row `World::spawn` set up for them
+pub struct BundleWriter<'w> {
+    world: &'w mut World,
+    entity: EntityId,
+    tick: u64,
+    written: usize,
+}
+
+impl<'w> BundleWriter<'w> {
+    pub(crate) fn new(world: &'w mut World, entity: EntityId, tick: u64) -> Self {
+        Self {
+            world,
+            entity,
+            tick,
+            written: 0,
+        }
+    }
+
+    pub fn write<T: Component>(&mut self, component: T) {
+        self.world.init_component(self.entity, component, self.tick);
+        self.written += 1;
+    }
+
+    /// Number of components written so far
+    pub(crate) fn written(&self) -> usize {
+        self.written
+    }
+}
+
+macro_rules! impl_bundle_tuple {
+    ($($name:ident),+) => {
+        #[allow(non_snake_case)]
+        impl<$($name: Component),+> Bundle for ($($name,)+) {
+            fn register_components(&self, types: &mut BundleTypes<'_>) {
+                $(types.add::<$name>();)+
+            }
+
+            fn write_components(self, writer: &mut BundleWriter<'_>) {
+                let ($($name,)+) = self;
+                $(writer.write($name);)+
+            }
+        }
+    };
+}
+
+impl_bundle_tuple!(A);
+impl_bundle_tuple!(A, B);
+impl_bundle_tuple!(A, B, C);
+impl_bundle_tuple!(A, B, C, D);
+impl_bundle_tuple!(A, B, C, D, E);
+impl_bundle_tuple!(A, B, C, D, E, F);
+impl_bundle_tuple!(A, B, C, D, E, F, G);
+impl_bundle_tuple!(A, B, C, D, E, F, G, H);
+
+/// Object-safe view of a Bundle, so a builder can hold bundles of any type
+trait ErasedBundle: Send {
+    fn register(&self, types: &mut BundleTypes<'_>);
+    fn write(self: Box<Self>, writer: &mut BundleWriter<'_>);
+}
+
+impl<B: Bundle> ErasedBundle for B {
+    fn register(&self, types: &mut BundleTypes<'_>) {
+        self.register_components(types);
+    }
+
+    fn write(self: Box<Self>, writer: &mut BundleWriter<'_>) {
+        (*self).write_components(writer);
+    }
+}
+
+/// Bundle assembled at runtime, for prefabs whose components come from data
+///
+/// Spawning it still inserts everything in one structural change. Adding the
+/// same component type twice makes `World::spawn` fail
+#[derive(Default)]
+pub struct EntityBuilder {
+    parts: Vec<Box<dyn ErasedBundle>>,
+}
+
+impl EntityBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with<T: Component>(mut self, component: T) -> Self {
+        self.add(component);
+        self
+    }
+
+    pub fn with_bundle<B: Bundle>(mut self, bundle: B) -> Self {
+        self.add_bundle(bundle);
+        self
+    }
+
+    /// Like `with`, for adding components in a loop
+    pub fn add<T: Component>(&mut self, component: T) -> &mut Self {
+        self.add_bundle((component,))
+    }
+
+    pub fn add_bundle<B: Bundle>(&mut self, bundle: B) -> &mut Self {
+        self.parts.push(Box::new(bundle));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+}
+
+impl Bundle for EntityBuilder {
+    fn register_components(&self, types: &mut BundleTypes<'_>) {
+        for part in &self.parts {
+            part.register(types);
+        }
+    }
+
+    fn write_components(self, writer: &mut BundleWriter<'_>) {
+        for part in self.parts {
+            part.write(writer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EcsError, StorageType};
+
+    #[derive(Debug, PartialEq)]
+    struct Position(f32);
+
+    #[derive(Debug, PartialEq)]
+    struct Velocity(f32);
+
+    #[derive(Debug, PartialEq)]
+    struct Marker;
+
+    struct BodyBundle {
+        position: Position,
+        velocity: Velocity,
+    }
+
+    impl Bundle for BodyBundle {
+        fn register_components(&self, types: &mut BundleTypes<'_>) {
+            types.add::<Position>();
+            types.add::<Velocity>();
+        }
+
+        fn write_components(self, writer: &mut BundleWriter<'_>) {
+            writer.write(self.position);
+            writer.write(self.velocity);
+        }
+    }
+
+    #[test]
+    fn test_spawn_bundles() {
+        let mut world = World::new();
+        world.register_component_with::<Marker>(StorageType::SparseSet).unwrap();
+        let cached = world.query_entities(&[TypeId::of::<Position>()]);
+        assert!(cached.is_empty());
+
+        let a = world.spawn((Position(1.0), Velocity(2.0), Marker)).unwrap();
+        let b = world.spawn(BodyBundle { position: Position(3.0), velocity: Velocity(4.0) }).unwrap();
+        let c = world
+            .spawn(EntityBuilder::new().with(Marker).with_bundle((Position(5.0),)))
+            .unwrap();
+
+        // a and b share one table, c only has a Position column
+        assert_eq!(world.archetypes().len(), 3);
+        assert_eq!(*world.get_component::<Velocity>(b).unwrap(), Velocity(4.0));
+        assert!(world.has_component::<Marker>(a) && world.has_component::<Marker>(c));
+        assert_eq!(world.query_entities(&[TypeId::of::<Position>()]), vec![a, b, c]);
+
+        let mut query = world.query_filtered::<&Position, crate::Added<Position>>().unwrap();
+        assert_eq!(query.iter().count(), 3);
+        drop(query);
+
+        assert!(matches!(world.spawn((Marker, Marker)), Err(EcsError::DuplicateComponent(_))));
+        assert_eq!(world.entity_count(), 3);
+    }
+}
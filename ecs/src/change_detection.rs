@@ -0,0 +1,158 @@
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{Component, Entity, EventReader, World};
+
+thread_local! {
+    /// Change ticks of the system running on this thread, so queries know
+    /// which changes are new to it and what to stamp its writes with
+    static SYSTEM_TICKS: Cell<Option<SystemTicks>> = const { Cell::new(None) };
+}
+
+/// When a component was added and when it was last changed, as World change ticks
+///
+/// The ticks are atomics so queries can stamp writes (and filters read them)
+/// through a shared World borrow, without taking the component column's lock
+#[derive(Debug)]
+pub struct ComponentTicks {
+    added: AtomicU64,
+    changed: AtomicU64,
+}
+
+impl ComponentTicks {
+    pub(crate) fn new(tick: u64) -> Self {
+        Self {
+            added: AtomicU64::new(tick),
+            changed: AtomicU64::new(tick),
+        }
+    }
+
+    /// Tick the component was added at
+    pub fn added(&self) -> u64 {
+        self.added.load(Ordering::Relaxed)
+    }
+
+    /// Tick the component was last added, replaced or borrowed mutably at
+    pub fn changed(&self) -> u64 {
+        self.changed.load(Ordering::Relaxed)
+    }
+
+    /// Whether the component was added after `last_run`
+    pub fn is_added(&self, last_run: u64) -> bool {
+        self.added() > last_run
+    }
+
+    /// Whether the component changed after `last_run`
+    pub fn is_changed(&self, last_run: u64) -> bool {
+        self.changed() > last_run
+    }
+
+    pub(crate) fn set_changed(&self, tick: u64) {
+        self.changed.store(tick, Ordering::Relaxed);
+    }
+}
+
+impl Clone for ComponentTicks {
+    fn clone(&self) -> Self {
+        Self {
+            added: AtomicU64::new(self.added()),
+            changed: AtomicU64::new(self.changed()),
+        }
+    }
+}
+
+/// Change ticks of one system run
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SystemTicks {
+    /// Tick of the system's previous run; changes after it are new to the system
+    pub(crate) last_run: u64,
+    /// Tick the system's own writes are stamped with
+    pub(crate) this_run: u64,
+}
+
+/// Ticks of the system currently running on this thread, if any
+pub(crate) fn current_system_ticks() -> Option<SystemTicks> {
+    SYSTEM_TICKS.with(Cell::get)
+}
+
+/// Marks the running system's change ticks until dropped
+pub(crate) struct SystemTicksGuard(Option<SystemTicks>);
+
+impl SystemTicksGuard {
+    pub(crate) fn enter(ticks: SystemTicks) -> Self {
+        Self(SYSTEM_TICKS.with(|current| current.replace(Some(ticks))))
+    }
+}
+
+impl Drop for SystemTicksGuard {
+    fn drop(&mut self) {
+        SYSTEM_TICKS.with(|current| current.set(self.0));
+    }
+}
+
+/// Cursor over the entities that lost component `T`, owned by the system that reads it
+///
+/// `World::remove_component` and `World::remove_entity` record removals. Like
+/// events they stay readable for the frame they happened in and the frame after
+pub struct RemovedComponents<T: Component> {
+    reader: EventReader<Entity>,
+    _component: PhantomData<fn() -> T>,
+}
+
+impl<T: Component> RemovedComponents<T> {
+    pub fn new() -> Self {
+        Self {
+            reader: EventReader::default(),
+            _component: PhantomData,
+        }
+    }
+
+    /// Entities that lost their `T` since this reader last read, oldest first
+    ///
+    /// Entities removed outright are included; they no longer exist
+    pub fn read<'a>(&mut self, world: &'a World) -> impl Iterator<Item = Entity> + 'a {
+        let removed = world.removed_components::<T>().map(|removed| self.reader.read(removed));
+        removed.into_iter().flatten().copied()
+    }
+}
+
+impl<T: Component> Default for RemovedComponents<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Payload;
+
+    #[test]
+    fn test_removed_components() {
+        let mut world = World::new();
+        let mut removed = RemovedComponents::<Payload>::new();
+        assert_eq!(removed.read(&world).count(), 0);
+
+        let (a, b, c) = (world.create_entity(), world.create_entity(), world.create_entity());
+        for entity in [a, b, c] {
+            world.add_component(entity, Payload).unwrap();
+        }
+
+        world.remove_component::<Payload>(a).unwrap();
+        world.remove_component::<Payload>(a).unwrap();
+        world.remove_entity(b).unwrap();
+        assert_eq!(removed.read(&world).collect::<Vec<_>>(), vec![a, b]);
+        assert_eq!(removed.read(&world).count(), 0);
+
+        // Removals are kept for one more frame, then dropped
+        let mut late = RemovedComponents::<Payload>::new();
+        world.clear_trackers();
+        world.remove_entity(c).unwrap();
+        assert_eq!(late.read(&world).collect::<Vec<_>>(), vec![a, b, c]);
+        world.clear_trackers();
+        world.clear_trackers();
+        assert_eq!(removed.read(&world).count(), 0);
+    }
+}
@@ -0,0 +1,218 @@
+use std::cell::Cell;
+
+use crate::{Bundle, Component, EcsResult, Entity, World};
+
+thread_local! {
+    /// Position of the system running on this thread, so buffers deferred from
+    /// systems running in parallel can be applied in system order
+    static SYSTEM_ORDER: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Position of the system currently running on this thread
+pub(crate) fn current_system_order() -> usize {
+    SYSTEM_ORDER.with(Cell::get)
+}
+
+/// Marks the running system's position until dropped
+pub(crate) struct SystemOrderGuard(usize);
+
+impl SystemOrderGuard {
+    pub(crate) fn enter(order: usize) -> Self {
+        Self(SYSTEM_ORDER.with(|current| current.replace(order)))
+    }
+}
+
+impl Drop for SystemOrderGuard {
+    fn drop(&mut self) {
+        SYSTEM_ORDER.with(|current| current.set(self.0));
+    }
+}
+
+/// An entity referenced by a command: either one that already exists, or one
+/// spawned earlier in the same `Commands` buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandEntity {
+    Existing(Entity),
+    /// Index of the entity among the buffer's `create_entity` calls
+    Spawned(usize),
+}
+
+impl CommandEntity {
+    fn resolve(self, spawned: &[Entity]) -> Entity {
+        match self {
+            CommandEntity::Existing(entity) => entity,
+            CommandEntity::Spawned(index) => spawned[index],
+        }
+    }
+}
+
+impl From<Entity> for CommandEntity {
+    fn from(entity: Entity) -> Self {
+        CommandEntity::Existing(entity)
+    }
+}
+
+type Command = Box<dyn FnOnce(&mut World, &mut Vec<Entity>) -> EcsResult<()> + Send>;
+
+/// Buffer of structural changes recorded while the World is borrowed
+///
+/// Systems record into a `Commands` while iterating queries and hand it to
+/// `World::defer`; the `SystemDispatcher` applies deferred buffers at sync points
+#[derive(Default)]
+pub struct Commands {
+    commands: Vec<Command>,
+    spawned: usize,
+}
+
+impl Commands {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record spawning a new entity, returning a handle later commands can use
+    pub fn create_entity(&mut self) -> CommandEntity {
+        let index = self.spawned;
+        self.spawned += 1;
+        self.push(|world, spawned| {
+            spawned.push(world.create_entity());
+            Ok(())
+        });
+        CommandEntity::Spawned(index)
+    }
+
+    /// Record spawning an entity with a bundle, see `World::spawn`
+    pub fn spawn<B: Bundle>(&mut self, bundle: B) -> CommandEntity {
+        let index = self.spawned;
+        self.spawned += 1;
+        self.push(move |world, spawned| {
+            spawned.push(world.spawn(bundle)?);
+            Ok(())
+        });
+        CommandEntity::Spawned(index)
+    }
+
+    pub fn add_component<T: Component>(&mut self, entity: impl Into<CommandEntity>, component: T) {
+        let entity = entity.into();
+        self.push(move |world, spawned| world.add_component(entity.resolve(spawned), component));
+    }
+
+    pub fn remove_component<T: Component>(&mut self, entity: impl Into<CommandEntity>) {
+        let entity = entity.into();
+        self.push(move |world, spawned| world.remove_component::<T>(entity.resolve(spawned)).map(drop));
+    }
+
+    pub fn remove_entity(&mut self, entity: impl Into<CommandEntity>) {
+        let entity = entity.into();
+        self.push(move |world, spawned| world.remove_entity(entity.resolve(spawned)));
+    }
+
+    /// Record attaching `child` to `parent`, see `World::set_parent`
+    pub fn set_parent(&mut self, child: impl Into<CommandEntity>, parent: impl Into<CommandEntity>) {
+        let (child, parent) = (child.into(), parent.into());
+        self.push(move |world, spawned| world.set_parent(child.resolve(spawned), parent.resolve(spawned)));
+    }
+
+    pub fn remove_parent(&mut self, child: impl Into<CommandEntity>) {
+        let child = child.into();
+        self.push(move |world, spawned| world.remove_parent(child.resolve(spawned)).map(drop));
+    }
+
+    /// Record an arbitrary change to the World
+    pub fn add<F>(&mut self, command: F)
+    where
+        F: FnOnce(&mut World) -> EcsResult<()> + Send + 'static,
+    {
+        self.push(move |world, _| command(world));
+    }
+
+    /// Number of recorded commands
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Apply every command in recording order
+    ///
+    /// Returns the spawned entities, indexed like their `CommandEntity::Spawned`
+    /// handles. Stops at the first failing command and returns its error
+    pub fn apply(self, world: &mut World) -> EcsResult<Vec<Entity>> {
+        let mut spawned = Vec::with_capacity(self.spawned);
+        for command in self.commands {
+            command(world, &mut spawned)?;
+        }
+        Ok(spawned)
+    }
+
+    fn push<F>(&mut self, command: F)
+    where
+        F: FnOnce(&mut World, &mut Vec<Entity>) -> EcsResult<()> + Send + 'static,
+    {
+        self.commands.push(Box::new(command));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EcsError;
+
+    #[derive(Debug, PartialEq)]
+    struct Health(i32);
+
+    #[derive(Debug, PartialEq)]
+    struct Debris;
+
+    #[test]
+    fn test_commands_apply_in_order() {
+        let mut world = World::new();
+        let crashed = world.create_entity();
+        world.add_component(crashed, Health(0)).unwrap();
+        let damaged = world.create_entity();
+        world.add_component(damaged, Health(5)).unwrap();
+
+        let mut commands = Commands::new();
+        let debris = commands.create_entity();
+        commands.add_component(debris, Debris);
+        commands.add_component(debris, Health(1));
+        commands.remove_component::<Health>(damaged);
+        commands.remove_entity(crashed);
+        assert_eq!(commands.len(), 5);
+
+        // Nothing changes until the buffer is applied
+        assert_eq!(world.entity_count(), 2);
+        let spawned = commands.apply(&mut world).unwrap();
+
+        assert_eq!(spawned.len(), 1);
+        assert_eq!(*world.get_component::<Health>(spawned[0]).unwrap(), Health(1));
+        assert!(world.has_component::<Debris>(spawned[0]));
+        assert!(!world.has_component::<Health>(damaged));
+        assert!(!world.entity_exists(crashed));
+
+        let mut commands = Commands::new();
+        commands.remove_entity(crashed);
+        assert!(matches!(commands.apply(&mut world), Err(EcsError::StaleEntity(_))));
+    }
+
+    #[test]
+    fn test_failing_buffer_keeps_the_others() {
+        let mut world = World::new();
+        let crashed = world.create_entity();
+        world.remove_entity(crashed).unwrap();
+
+        // The first buffer fails on a stale entity; the second is unrelated
+        let mut stale = Commands::new();
+        stale.remove_entity(crashed);
+        world.defer(stale);
+        let mut spawns = Commands::new();
+        let debris = spawns.create_entity();
+        spawns.add_component(debris, Debris);
+        world.defer(spawns);
+
+        assert!(matches!(world.apply_commands(), Err(EcsError::StaleEntity(_))));
+        assert_eq!(world.entity_count(), 1);
+        assert!(world.apply_commands().is_ok());
+    }
+}
@@ -1,4 +1,7 @@
-use std::any::Any;
+use std::any::{Any, TypeId};
+use bitvec::vec::BitVec;
+
+use crate::{ComponentStorage, EcsError, EcsResult, SparseSetStorage, TypedComponentStorage};
 
 /// Trait that all components must implement
 /// Components are pure data with no behavior
@@ -12,6 +15,179 @@ pub trait Component: Any + Send + Sync {
 /// Automatically implement Component for types that meet the requirements
 impl<T> Component for T where T: Any + Send + Sync {}
 
+/// Dense index the World assigns to each registered component type, in registration order
+pub type ComponentId = usize;
+
+/// Set of component types as a bitset indexed by ComponentId
+///
+/// Each entity carries one as its signature, so membership checks are a single
+/// bit test and "has all of these" is an AND against a query mask
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ComponentMask(BitVec);
+
+impl ComponentMask {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, id: ComponentId) {
+        if id >= self.0.len() {
+            self.0.resize(id + 1, false);
+        }
+        self.0.set(id, true);
+    }
+
+    pub fn remove(&mut self, id: ComponentId) {
+        if id < self.0.len() {
+            self.0.set(id, false);
+        }
+        // Trim trailing zeros so equal sets always compare (and hash) equal
+        let len = self.0.last_one().map_or(0, |last| last + 1);
+        self.0.truncate(len);
+    }
+
+    pub fn contains(&self, id: ComponentId) -> bool {
+        self.0.get(id).is_some_and(|bit| *bit)
+    }
+
+    /// Whether every component in `other` is also in this set
+    pub fn contains_all(&self, other: &ComponentMask) -> bool {
+        other.0.len() <= self.0.len() && other.0.iter_ones().all(|id| self.0[id])
+    }
+
+    /// Component ids in the set, ascending
+    pub fn ones(&self) -> impl Iterator<Item = ComponentId> + '_ {
+        self.0.iter_ones()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.not_any()
+    }
+}
+
+impl FromIterator<ComponentId> for ComponentMask {
+    fn from_iter<I: IntoIterator<Item = ComponentId>>(ids: I) -> Self {
+        let mut mask = Self::new();
+        for id in ids {
+            mask.insert(id);
+        }
+        mask
+    }
+}
+
+/// How a component type's data is laid out in the World
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StorageType {
+    /// Dense columns in archetype tables: fastest to iterate, but adding or
+    /// removing the component moves the entity to another table
+    #[default]
+    Table,
+    /// One sparse set per type, keyed by entity: cheap to add and remove,
+    /// a little slower to iterate. Good for tags and toggled markers
+    SparseSet,
+}
+
+/// Deep-copies a type-erased storage
+type CloneStorage = fn(&dyn ComponentStorage) -> Box<dyn ComponentStorage>;
+
+/// Type-erased metadata the World keeps for each registered component type
+#[derive(Debug, Clone)]
+pub struct ComponentInfo {
+    id: ComponentId,
+    type_id: TypeId,
+    name: &'static str,
+    storage_type: StorageType,
+    new_storage: fn() -> Box<dyn ComponentStorage>,
+    /// Deep-copies a storage of this type; None unless registered as cloneable
+    clone_storage: Option<CloneStorage>,
+}
+
+impl ComponentInfo {
+    /// Describe component type `T`, stored in archetype tables
+    pub fn of<T: Component>() -> Self {
+        Self::with_storage::<T>(StorageType::Table)
+    }
+
+    /// Describe component type `T` with an explicit storage type
+    pub fn with_storage<T: Component>(storage_type: StorageType) -> Self {
+        Self {
+            id: 0,
+            type_id: TypeId::of::<T>(),
+            name: T::type_name(),
+            storage_type,
+            new_storage: match storage_type {
+                StorageType::Table => || Box::new(TypedComponentStorage::<T>::new()),
+                StorageType::SparseSet => || Box::new(SparseSetStorage::<T>::new()),
+            },
+            clone_storage: None,
+        }
+    }
+
+    /// Describe component type `T`, letting `World::fork` copy its storage
+    pub fn cloneable<T: Component + Clone>(storage_type: StorageType) -> Self {
+        Self {
+            clone_storage: Some(match storage_type {
+                StorageType::Table => clone_storage::<TypedComponentStorage<T>>,
+                StorageType::SparseSet => clone_storage::<SparseSetStorage<T>>,
+            }),
+            ..Self::with_storage::<T>(storage_type)
+        }
+    }
+
+    /// Dense index assigned when the type was registered with a World
+    pub fn id(&self) -> ComponentId {
+        self.id
+    }
+
+    pub(crate) fn with_id(mut self, id: ComponentId) -> Self {
+        self.id = id;
+        self
+    }
+
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn storage_type(&self) -> StorageType {
+        self.storage_type
+    }
+
+    /// Create an empty storage for this component type
+    pub fn new_storage(&self) -> Box<dyn ComponentStorage> {
+        (self.new_storage)()
+    }
+
+    /// Whether `World::fork` can copy components of this type
+    pub fn is_cloneable(&self) -> bool {
+        self.clone_storage.is_some()
+    }
+
+    /// Copy of `storage` for a forked world
+    ///
+    /// Empty storages never need copying, so only a non-cloneable type
+    /// with components in `storage` fails
+    pub(crate) fn fork_storage(&self, storage: &dyn ComponentStorage) -> EcsResult<Box<dyn ComponentStorage>> {
+        match self.clone_storage {
+            Some(clone) => Ok(clone(storage)),
+            None if storage.is_empty() => Ok(self.new_storage()),
+            None => Err(EcsError::NotCloneable(self.name.to_string())),
+        }
+    }
+}
+
+/// Deep-copy a type-erased storage known to be an `S`
+fn clone_storage<S: ComponentStorage + Clone>(storage: &dyn ComponentStorage) -> Box<dyn ComponentStorage> {
+    let storage = storage
+        .as_any()
+        .downcast_ref::<S>()
+        .expect("storage type doesn't match its ComponentInfo");
+    Box::new(storage.clone())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -24,6 +200,32 @@ mod tests {
     #[test]
     fn test_component_trait() {
         let component = TestComponent { value: 42 };
+        assert_eq!(component.value, 42);
         assert_eq!(TestComponent::type_name(), "ecs::component::tests::TestComponent");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_component_info() {
+        let info = ComponentInfo::of::<TestComponent>();
+        assert_eq!(info.type_id(), TypeId::of::<TestComponent>());
+        assert_eq!(info.name(), TestComponent::type_name());
+        assert_eq!(info.storage_type(), StorageType::Table);
+        assert!(info.new_storage().as_any().is::<TypedComponentStorage<TestComponent>>());
+
+        let sparse = ComponentInfo::with_storage::<TestComponent>(StorageType::SparseSet);
+        assert!(sparse.new_storage().as_any().is::<SparseSetStorage<TestComponent>>());
+    }
+
+    #[test]
+    fn test_component_mask() {
+        let mut signature: ComponentMask = [0, 3, 70].into_iter().collect();
+        let query: ComponentMask = [3, 70].into_iter().collect();
+        assert!(signature.contains(70));
+        assert!(signature.contains_all(&query));
+
+        signature.remove(70);
+        assert!(!signature.contains_all(&query));
+        assert_eq!(signature, [3, 0].into_iter().collect());
+        assert_eq!(signature.ones().collect::<Vec<_>>(), vec![0, 3]);
+    }
+}
@@ -1,8 +1,17 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use slotmap::{Key, KeyData};
+
 use crate::EntityId;
 
 /// Entity is just a unique identifier
 /// All entity logic is handled by the World
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+///
+/// The identifier is a slot index plus a generation that goes up each time the
+/// slot is reused, so a handle to a despawned entity never finds its successor.
+/// It serializes as the 64-bit `to_bits` form, for logs and replay files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "u64", from = "u64")]
 pub struct Entity {
     id: EntityId,
 }
@@ -17,6 +26,46 @@ impl Entity {
     pub fn id(&self) -> EntityId {
         self.id
     }
+
+    /// Slot index, shared with earlier entities that were despawned
+    pub fn index(&self) -> u32 {
+        self.to_bits() as u32
+    }
+
+    /// Number of earlier entities that used the same slot
+    pub fn generation(&self) -> u32 {
+        // Live slots have odd versions, starting at 1
+        ((self.to_bits() >> 32) as u32) / 2
+    }
+
+    /// Stable 64-bit form: generation in the high half, index in the low half
+    pub fn to_bits(&self) -> u64 {
+        self.id.data().as_ffi()
+    }
+
+    /// Rebuild an entity from `to_bits`
+    pub fn from_bits(bits: u64) -> Self {
+        Self::new(KeyData::from_ffi(bits).into())
+    }
+}
+
+impl fmt::Display for Entity {
+    /// Index and generation, e.g. `4v2`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}v{}", self.index(), self.generation())
+    }
+}
+
+impl From<Entity> for u64 {
+    fn from(entity: Entity) -> Self {
+        entity.to_bits()
+    }
+}
+
+impl From<u64> for Entity {
+    fn from(bits: u64) -> Self {
+        Entity::from_bits(bits)
+    }
 }
 
 impl From<EntityId> for Entity {
@@ -45,4 +94,21 @@ mod tests {
         assert_eq!(entity.id(), id);
         assert_eq!(EntityId::from(entity), id);
     }
+
+    #[test]
+    fn test_entity_generations() {
+        let mut slot_map: SlotMap<EntityId, ()> = SlotMap::new();
+        let first = Entity::new(slot_map.insert(()));
+        slot_map.remove(first.id());
+        let second = Entity::new(slot_map.insert(()));
+
+        // The slot is reused under a new generation
+        assert_eq!(first.index(), second.index());
+        assert_eq!((first.generation(), second.generation()), (0, 1));
+        assert_ne!(first, second);
+        assert_eq!(second.to_string(), format!("{}v1", second.index()));
+
+        assert_eq!(Entity::from_bits(second.to_bits()), second);
+        assert_ne!(Entity::from_bits(first.to_bits()), second);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,198 @@
+use std::marker::PhantomData;
+
+/// Trait for data sent between systems through an `Events<E>` channel
+pub trait Event: Send + Sync + 'static {}
+
+/// Automatically implement Event for types that meet the requirements
+impl<T> Event for T where T: Send + Sync + 'static {}
+
+/// Events sent during one frame, numbered from `start`
+#[derive(Clone)]
+struct EventBuffer<E> {
+    start: usize,
+    events: Vec<E>,
+}
+
+impl<E> EventBuffer<E> {
+    fn new(start: usize) -> Self {
+        Self {
+            start,
+            events: Vec::new(),
+        }
+    }
+
+    /// Events numbered `from` or later
+    fn since(&self, from: usize) -> &[E] {
+        let skip = from.saturating_sub(self.start).min(self.events.len());
+        &self.events[skip..]
+    }
+}
+
+/// Double-buffered event channel, stored in the World as a resource
+///
+/// Events stay readable for the frame they were sent in and the frame after,
+/// then `update` drops them. Systems read with their own `EventReader`, so
+/// any number of readers see every event exactly once
+#[derive(Clone)]
+pub struct Events<E: Event> {
+    previous: EventBuffer<E>,
+    current: EventBuffer<E>,
+    /// Total events ever sent; also the number the next event will get
+    event_count: usize,
+}
+
+impl<E: Event> Events<E> {
+    pub fn new() -> Self {
+        Self {
+            previous: EventBuffer::new(0),
+            current: EventBuffer::new(0),
+            event_count: 0,
+        }
+    }
+
+    /// Send an event to every reader
+    pub fn send(&mut self, event: E) {
+        self.current.events.push(event);
+        self.event_count += 1;
+    }
+
+    /// Send several events at once, in order
+    pub fn send_batch(&mut self, events: impl IntoIterator<Item = E>) {
+        for event in events {
+            self.send(event);
+        }
+    }
+
+    /// Advance one frame: drop last frame's events and start a new buffer
+    pub fn update(&mut self) {
+        std::mem::swap(&mut self.previous, &mut self.current);
+        self.current = EventBuffer::new(self.event_count);
+    }
+
+    /// A reader that will see every event still buffered
+    pub fn reader(&self) -> EventReader<E> {
+        EventReader::starting_at(self.previous.start)
+    }
+
+    /// A reader that will only see events sent from now on
+    pub fn reader_current(&self) -> EventReader<E> {
+        EventReader::starting_at(self.event_count)
+    }
+
+    /// Number of events currently buffered
+    pub fn len(&self) -> usize {
+        self.previous.events.len() + self.current.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove every buffered event, oldest first
+    pub fn drain(&mut self) -> impl Iterator<Item = E> + '_ {
+        self.previous.start = self.event_count;
+        self.current.start = self.event_count;
+        self.previous.events.drain(..).chain(self.current.events.drain(..))
+    }
+
+    /// Drop every buffered event
+    pub fn clear(&mut self) {
+        self.drain().for_each(drop);
+    }
+}
+
+impl<E: Event> Default for Events<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Cursor into an `Events<E>` channel, owned by the system that reads it
+///
+/// Events dropped by `update` before a reader got to them are skipped
+pub struct EventReader<E: Event> {
+    next: usize,
+    _event: PhantomData<fn() -> E>,
+}
+
+impl<E: Event> EventReader<E> {
+    fn starting_at(next: usize) -> Self {
+        Self {
+            next,
+            _event: PhantomData,
+        }
+    }
+
+    /// Events sent since this reader last read, oldest first
+    pub fn read<'a>(&mut self, events: &'a Events<E>) -> impl Iterator<Item = &'a E> + 'a {
+        let from = self.next;
+        self.next = events.event_count;
+        events.previous.since(from).iter().chain(events.current.since(from))
+    }
+
+    /// Whether there are events this reader hasn't seen yet
+    pub fn has_unread(&self, events: &Events<E>) -> bool {
+        !events.previous.since(self.next).is_empty() || !events.current.since(self.next).is_empty()
+    }
+}
+
+impl<E: Event> Default for EventReader<E> {
+    /// A reader that starts with the oldest buffered events
+    fn default() -> Self {
+        Self::starting_at(0)
+    }
+}
+
+impl<E: Event> Clone for EventReader<E> {
+    fn clone(&self) -> Self {
+        Self::starting_at(self.next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Impact(u32);
+
+    #[test]
+    fn test_events_double_buffering() {
+        let mut events = Events::new();
+        let mut early = events.reader();
+        let mut late = EventReader::default();
+
+        events.send(Impact(1));
+        events.update();
+        events.send(Impact(2));
+
+        // Events from this frame and the last are both visible
+        assert_eq!(early.read(&events).collect::<Vec<_>>(), vec![&Impact(1), &Impact(2)]);
+        assert_eq!(early.read(&events).count(), 0);
+
+        // After another update, Impact(1) is gone and each reader keeps its own place
+        events.update();
+        events.send(Impact(3));
+        assert_eq!(late.read(&events).collect::<Vec<_>>(), vec![&Impact(2), &Impact(3)]);
+        assert_eq!(early.read(&events).collect::<Vec<_>>(), vec![&Impact(3)]);
+
+        let mut current = events.reader_current();
+        events.update();
+        events.update();
+        assert!(events.is_empty());
+        assert!(!current.has_unread(&events));
+        assert_eq!(current.read(&events).count(), 0);
+    }
+
+    #[test]
+    fn test_events_drain() {
+        let mut events = Events::new();
+        let mut reader = events.reader();
+        events.send_batch([Impact(1), Impact(2)]);
+
+        assert_eq!(events.drain().collect::<Vec<_>>(), vec![Impact(1), Impact(2)]);
+        assert!(!reader.has_unread(&events));
+        events.send(Impact(3));
+        assert_eq!(reader.read(&events).collect::<Vec<_>>(), vec![&Impact(3)]);
+    }
+}
@@ -0,0 +1,173 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{EcsError, EcsResult, Entity, EntityMap, MapEntities, World};
+
+/// The entity this one is attached to
+///
+/// Maintained by `World::set_parent` and `World::remove_parent`, which keep it
+/// in sync with the parent's `Children`; don't remove it directly
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Parent(Entity);
+
+impl Parent {
+    pub fn get(&self) -> Entity {
+        self.0
+    }
+}
+
+impl MapEntities for Parent {
+    fn map_entities(&mut self, map: &EntityMap) {
+        self.0.map_entities(map);
+    }
+}
+
+/// Entities attached to this one, in the order they were attached
+///
+/// Maintained by the World alongside each child's `Parent`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Children(Vec<Entity>);
+
+impl Children {
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.0.iter().copied()
+    }
+
+    pub fn as_slice(&self) -> &[Entity] {
+        &self.0
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.0.contains(&entity)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl MapEntities for Children {
+    fn map_entities(&mut self, map: &EntityMap) {
+        self.0.map_entities(map);
+    }
+}
+
+impl World {
+    /// Attach `child` to `parent`, detaching it from its current parent first
+    ///
+    /// Fails with EcsError::HierarchyCycle if `parent` is `child` or one of its
+    /// descendants. Removing a parent also removes all its descendants
+    pub fn set_parent(&mut self, child: Entity, parent: Entity) -> EcsResult<()> {
+        self.entity_location(child)?;
+        self.entity_location(parent)?;
+
+        let mut ancestor = Some(parent);
+        while let Some(entity) = ancestor {
+            if entity == child {
+                return Err(EcsError::HierarchyCycle(child, parent));
+            }
+            ancestor = self.parent(entity)?;
+        }
+
+        // Forks copy the hierarchy along with everything else
+        self.register_cloneable::<Parent>();
+        self.register_cloneable::<Children>();
+
+        self.remove_parent(child)?;
+        self.add_component(child, Parent(parent))?;
+        match self.get_component_mut::<Children>(parent) {
+            Some(children) => children.0.push(child),
+            None => self.add_component(parent, Children(vec![child]))?,
+        }
+        Ok(())
+    }
+
+    /// Detach `child` from its parent, returning the parent it had
+    ///
+    /// The child becomes a root; its own children stay attached to it
+    pub fn remove_parent(&mut self, child: Entity) -> EcsResult<Option<Entity>> {
+        self.entity_location(child)?;
+        let Some(parent) = self.parent(child)? else {
+            return Ok(None);
+        };
+        self.remove_component::<Parent>(child)?;
+
+        let now_empty = self.get_component_mut::<Children>(parent).is_some_and(|children| {
+            children.0.retain(|&entity| entity != child);
+            children.is_empty()
+        });
+        if now_empty {
+            self.remove_component::<Children>(parent)?;
+        }
+        Ok(Some(parent))
+    }
+
+    /// The entity's parent, if it has one
+    pub fn parent(&self, entity: Entity) -> EcsResult<Option<Entity>> {
+        Ok(self.get_optional_component::<Parent>(entity)?.map(|parent| parent.get()))
+    }
+
+    /// The entity's children, in the order they were attached
+    pub fn children(&self, entity: Entity) -> EcsResult<Vec<Entity>> {
+        Ok(self
+            .get_optional_component::<Children>(entity)?
+            .map(|children| children.0.clone())
+            .unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hierarchy_reparenting_and_cycles() {
+        let mut world = World::new();
+        let fuselage = world.create_entity();
+        let wing = world.create_entity();
+        let aileron = world.create_entity();
+
+        world.set_parent(wing, fuselage).unwrap();
+        world.set_parent(aileron, wing).unwrap();
+        assert_eq!(world.children(fuselage).unwrap(), vec![wing]);
+        assert_eq!(world.parent(aileron).unwrap(), Some(wing));
+
+        // An entity can't end up as its own ancestor
+        assert!(matches!(world.set_parent(fuselage, aileron), Err(EcsError::HierarchyCycle(..))));
+        assert!(matches!(world.set_parent(wing, wing), Err(EcsError::HierarchyCycle(..))));
+
+        // Reparenting updates both the old and the new parent
+        world.set_parent(aileron, fuselage).unwrap();
+        assert_eq!(world.children(fuselage).unwrap(), vec![wing, aileron]);
+        assert!(!world.has_component::<Children>(wing));
+
+        assert_eq!(world.remove_parent(wing).unwrap(), Some(fuselage));
+        assert_eq!(world.remove_parent(wing).unwrap(), None);
+        assert_eq!(world.children(fuselage).unwrap(), vec![aileron]);
+    }
+
+    #[test]
+    fn test_hierarchy_recursive_despawn() {
+        let mut world = World::new();
+        let carrier = world.create_entity();
+        let aircraft = world.create_entity();
+        let pylon = world.create_entity();
+        let store = world.create_entity();
+        world.set_parent(aircraft, carrier).unwrap();
+        world.set_parent(pylon, aircraft).unwrap();
+        world.set_parent(store, pylon).unwrap();
+
+        // Removing an entity takes its whole subtree with it, and
+        // detaches it from its own parent
+        world.remove_entity(aircraft).unwrap();
+        assert_eq!(world.entity_count(), 1);
+        assert!(!world.entity_exists(store));
+        assert!(world.children(carrier).unwrap().is_empty());
+
+        let fork = world.fork().unwrap();
+        assert_eq!(fork.entity_count(), 1);
+    }
+}
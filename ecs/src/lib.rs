@@ -1,27 +1,78 @@
 use std::any::Any;
-use std::collections::HashMap;
 use slotmap::DefaultKey;
 use anyhow::Result;
 
+pub mod access;
+pub mod archetype;
+pub mod bundle;
+pub mod change_detection;
+pub mod command;
 pub mod component;
 pub mod entity;
+pub mod event;
+pub mod hierarchy;
+pub mod query;
+pub mod resource;
+pub mod run_criteria;
+pub mod snapshot;
+pub mod sparse_set;
 pub mod system;
 pub mod world;
 
-pub use component::Component;
+pub use access::Access;
+pub use archetype::{Archetype, ArchetypeId};
+pub use bundle::{Bundle, BundleTypes, BundleWriter, EntityBuilder};
+pub use change_detection::{ComponentTicks, RemovedComponents};
+pub use command::{CommandEntity, Commands};
+pub use component::{Component, ComponentId, ComponentInfo, ComponentMask, StorageType};
 pub use entity::Entity;
-pub use system::{System, SystemDispatcher};
+pub use event::{Event, EventReader, Events};
+pub use hierarchy::{Children, Parent};
+pub use query::{Added, Changed, Query, QueryData, QueryFilter, With, Without};
+pub use resource::Resource;
+pub use run_criteria::{RunCriteria, RunSteps, DEFAULT_MAX_SUBSTEPS};
+pub use snapshot::{EntityMap, EntitySnapshot, MapEntities, SnapshotRegistry, WorldSnapshot};
+pub use sparse_set::SparseSetStorage;
+pub use system::{System, SystemConfig, SystemDispatcher, DEFAULT_STAGE};
 pub use world::World;
 
 /// Core ECS error types
 #[derive(thiserror::Error, Debug)]
 pub enum EcsError {
-    #[error("Entity {0:?} not found")]
+    #[error("Entity {0} never existed in this world")]
     EntityNotFound(Entity),
+    #[error("Entity {0} was despawned")]
+    StaleEntity(Entity),
+    #[error("Entity {0} has no {1} component")]
+    MissingComponent(Entity, String),
     #[error("Component not registered: {0}")]
     ComponentNotRegistered(String),
     #[error("System error: {0}")]
     SystemError(String),
+    #[error("{0} is already borrowed")]
+    BorrowConflict(String),
+    #[error("Component {0} is already registered with a different storage type")]
+    StorageTypeMismatch(String),
+    #[error("Resource not found: {0}")]
+    ResourceNotFound(String),
+    #[error("Stage not found: {0}")]
+    StageNotFound(String),
+    #[error("No system is named or labelled {0}")]
+    UnknownSystemLabel(String),
+    #[error("System ordering cycle: {0}")]
+    SystemOrderCycle(String),
+    #[error("Snapshot error: {0}")]
+    Snapshot(String),
+    #[error("Bundle has more than one {0} component")]
+    DuplicateComponent(String),
+    #[error("Making {1} the parent of {0} would create a cycle")]
+    HierarchyCycle(Entity, Entity),
+    #[error("{0} can't be cloned into a forked world")]
+    NotCloneable(String),
+    #[error("Frame time must be finite and non-negative, got {0}")]
+    InvalidDeltaTime(f32),
+    #[error("System {0} without declaring it in access()")]
+    UndeclaredAccess(String),
 }
 
 /// Type alias for ECS results
@@ -30,47 +81,89 @@ pub type EcsResult<T> = Result<T, EcsError>;
 /// Entity ID type using slotmap for efficient storage
 pub type EntityId = DefaultKey;
 
+/// Shared borrow of a component, released when dropped
+pub type Ref<'a, T> = parking_lot::MappedRwLockReadGuard<'a, T>;
+
+/// Exclusive borrow of a resource, released when dropped
+pub type RefMut<'a, T> = parking_lot::MappedRwLockWriteGuard<'a, T>;
+
 /// Component storage trait for type erasure
-pub trait ComponentStorage: Any {
+///
+/// Components are packed densely and addressed by index. For archetype table
+/// columns the index is the row, owned by the archetype's entity list; storages
+/// that track their own entities (like sparse sets) also report `index_of`
+pub trait ComponentStorage: Any + Send + Sync {
     fn as_any(&self) -> &dyn Any;
     fn as_any_mut(&mut self) -> &mut dyn Any;
-    fn remove(&mut self, entity: EntityId) -> bool;
+    /// Number of components stored
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Iterate the stored components without knowing their type, in index order
+    fn iter_any(&self) -> Box<dyn Iterator<Item = &dyn Any> + '_>;
+    /// Index of the entity's component, for storages keyed by entity
+    fn index_of(&self, _entity: EntityId) -> Option<usize> {
+        None
+    }
+    /// Drop the component at `index`, moving the last one into its place
+    fn swap_remove(&mut self, index: usize);
+    /// Move the component at `index` to the end of `dst`, which must store the same type
+    fn swap_remove_into(&mut self, index: usize, dst: &mut dyn ComponentStorage);
 }
 
-/// Concrete component storage implementation
+/// Dense column of components of a single type
+///
+/// Archetype tables keep one of these per component type, so iterating a
+/// column walks a contiguous array
+#[derive(Clone)]
 pub struct TypedComponentStorage<T: Component> {
-    components: HashMap<EntityId, T>,
+    components: Vec<T>,
+}
+
+impl<T: Component> Default for TypedComponentStorage<T> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<T: Component> TypedComponentStorage<T> {
     pub fn new() -> Self {
         Self {
-            components: HashMap::new(),
+            components: Vec::new(),
         }
     }
 
-    pub fn insert(&mut self, entity: EntityId, component: T) {
-        self.components.insert(entity, component);
+    pub fn push(&mut self, component: T) {
+        self.components.push(component);
+    }
+
+    pub fn get(&self, row: usize) -> Option<&T> {
+        self.components.get(row)
     }
 
-    pub fn get(&self, entity: EntityId) -> Option<&T> {
-        self.components.get(&entity)
+    pub fn get_mut(&mut self, row: usize) -> Option<&mut T> {
+        self.components.get_mut(row)
     }
 
-    pub fn get_mut(&mut self, entity: EntityId) -> Option<&mut T> {
-        self.components.get_mut(&entity)
+    pub fn swap_remove(&mut self, row: usize) -> T {
+        self.components.swap_remove(row)
     }
 
-    pub fn remove(&mut self, entity: EntityId) -> Option<T> {
-        self.components.remove(&entity)
+    pub fn len(&self) -> usize {
+        self.components.len()
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = (EntityId, &T)> {
-        self.components.iter().map(|(id, comp)| (*id, comp))
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
     }
 
-    pub fn iter_mut(&mut self) -> impl Iterator<Item = (EntityId, &mut T)> {
-        self.components.iter_mut().map(|(id, comp)| (*id, comp))
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.components.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, T> {
+        self.components.iter_mut()
     }
 }
 
@@ -83,7 +176,23 @@ impl<T: Component> ComponentStorage for TypedComponentStorage<T> {
         self
     }
 
-    fn remove(&mut self, entity: EntityId) -> bool {
-        self.components.remove(&entity).is_some()
+    fn len(&self) -> usize {
+        self.components.len()
     }
-}
\ No newline at end of file
+
+    fn iter_any(&self) -> Box<dyn Iterator<Item = &dyn Any> + '_> {
+        Box::new(self.components.iter().map(|component| component as &dyn Any))
+    }
+
+    fn swap_remove(&mut self, row: usize) {
+        self.components.swap_remove(row);
+    }
+
+    fn swap_remove_into(&mut self, row: usize, dst: &mut dyn ComponentStorage) {
+        let component = self.components.swap_remove(row);
+        dst.as_any_mut()
+            .downcast_mut::<Self>()
+            .expect("component moved into a storage of a different type")
+            .push(component);
+    }
+}
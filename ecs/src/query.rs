@@ -0,0 +1,834 @@
+use std::any::TypeId;
+use std::borrow::Cow;
+use std::marker::PhantomData;
+use parking_lot::{MappedRwLockReadGuard, MappedRwLockWriteGuard};
+use slotmap::SecondaryMap;
+
+use crate::access;
+use crate::{
+    Archetype, Component, ComponentTicks, Entity, EntityId, EcsResult, SparseSetStorage, TypedComponentStorage, World,
+};
+
+/// Boxed iterator yielded by a query fetch
+pub type FetchIter<'a, T> = Box<dyn Iterator<Item = T> + 'a>;
+
+/// Types that can be requested from a query, e.g. `&Position`, `&mut Velocity`,
+/// `Option<&Mass>`, `Entity`, or tuples of those
+///
+/// Borrows are checked at runtime when the query is created: requesting the same
+/// component mutably twice (or mutably and immutably) fails with `EcsError::BorrowConflict`,
+/// and inside a system, touching a component its `Access` doesn't declare fails with
+/// `EcsError::UndeclaredAccess`
+pub trait QueryData {
+    /// Item yielded for each matching entity
+    type Item<'a>
+    where
+        Self: 'a;
+
+    /// Borrowed storage state for every matched table, held for the lifetime of the query
+    type Fetch<'w>;
+
+    /// Whether entities in this archetype can match; sparse-set components are
+    /// checked per entity by `matches_entity`
+    fn matches(world: &World, archetype: &Archetype) -> bool;
+
+    /// Whether `matches` alone decides the match, i.e. no sparse-set components are required
+    fn is_dense(world: &World) -> bool;
+
+    /// Whether this entity has the sparse-set components this query requires
+    fn matches_entity(world: &World, entity: EntityId) -> bool;
+
+    /// Fail unless the running system, if any, declared the components this reads and writes
+    fn check_access() -> EcsResult<()>;
+
+    /// Borrow the columns (or sparse sets) this query reads or writes
+    fn fetch<'w>(world: &'w World, archetypes: &[&'w Archetype]) -> EcsResult<Self::Fetch<'w>>;
+
+    /// Iterate the items for the selected rows of each table, in order
+    fn iter<'a, 'w>(fetch: &'a mut Self::Fetch<'w>, tables: &'a [TableRows<'w>]) -> FetchIter<'a, Self::Item<'a>>
+    where
+        Self: 'a;
+}
+
+/// Filters that restrict which entities a query matches without fetching data
+pub trait QueryFilter {
+    /// Whether entities in this archetype can pass this filter
+    fn matches(world: &World, archetype: &Archetype) -> bool;
+
+    /// Whether `matches` alone decides the filter
+    fn is_dense(world: &World) -> bool;
+
+    /// Whether this entity passes the per-entity part of the filter
+    /// (sparse-set components, change ticks)
+    fn matches_entity(world: &World, entity: EntityId) -> bool;
+
+    /// Fail unless the running system, if any, declared the components this
+    /// reads; presence filters read nothing, change filters read `T`'s ticks
+    fn check_access() -> EcsResult<()> {
+        Ok(())
+    }
+}
+
+/// The rows of one archetype table selected by a query
+pub struct TableRows<'w> {
+    /// Selected entities, in row order
+    entities: Cow<'w, [EntityId]>,
+    /// Selected row indices (sorted), or None when every row is selected
+    rows: Option<Vec<usize>>,
+}
+
+impl<'w> TableRows<'w> {
+    fn all(entities: &'w [EntityId]) -> Self {
+        Self {
+            entities: Cow::Borrowed(entities),
+            rows: None,
+        }
+    }
+
+    fn select(entities: &'w [EntityId], mut keep: impl FnMut(EntityId) -> bool) -> Self {
+        let (rows, entities): (Vec<usize>, Vec<EntityId>) = entities
+            .iter()
+            .enumerate()
+            .filter(|&(_, &id)| keep(id))
+            .unzip();
+        Self {
+            entities: Cow::Owned(entities),
+            rows: Some(rows),
+        }
+    }
+
+    /// Selected entities, in row order
+    pub fn entities(&self) -> &[EntityId] {
+        &self.entities
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+}
+
+/// Borrowed state for reading component `T`
+pub enum ReadFetch<'w, T: Component> {
+    /// One column guard per matched table (None where the table lacks `T`)
+    Table(Vec<Option<MappedRwLockReadGuard<'w, TypedComponentStorage<T>>>>),
+    Sparse(MappedRwLockReadGuard<'w, SparseSetStorage<T>>),
+}
+
+/// A table column borrowed for writing, with its change ticks
+pub type ColumnMut<'w, T> = (MappedRwLockWriteGuard<'w, TypedComponentStorage<T>>, &'w [ComponentTicks]);
+
+/// Borrowed state for writing component `T`
+///
+/// Each variant also holds the components' change ticks and the tick to stamp
+/// them with: every component the query hands out counts as changed
+pub enum WriteFetch<'w, T: Component> {
+    /// One column guard per matched table (None where the table lacks `T`)
+    Table(Vec<Option<ColumnMut<'w, T>>>, u64),
+    Sparse(MappedRwLockWriteGuard<'w, SparseSetStorage<T>>, &'w SecondaryMap<EntityId, ComponentTicks>, u64),
+}
+
+fn fetch_read<'w, T: Component>(world: &'w World, archetypes: &[&'w Archetype]) -> EcsResult<ReadFetch<'w, T>> {
+    if let Some(sparse) = world.sparse_set::<T>()? {
+        return Ok(ReadFetch::Sparse(sparse));
+    }
+    let columns = archetypes
+        .iter()
+        .map(|archetype| archetype.read_column::<T>())
+        .collect::<EcsResult<_>>()?;
+    Ok(ReadFetch::Table(columns))
+}
+
+fn fetch_write<'w, T: Component>(world: &'w World, archetypes: &[&'w Archetype]) -> EcsResult<WriteFetch<'w, T>> {
+    let type_id = TypeId::of::<T>();
+    let tick = world.change_tick();
+    if let Some(sparse) = world.write_sparse_set::<T>()? {
+        let ticks = world.sparse_ticks(type_id).expect("sparse set without ticks");
+        return Ok(WriteFetch::Sparse(sparse, ticks, tick));
+    }
+    let columns = archetypes
+        .iter()
+        .map(|archetype| {
+            let column = archetype.write_column::<T>()?;
+            Ok(column.map(|column| (column, archetype.column_ticks(type_id).unwrap_or_default())))
+        })
+        .collect::<EcsResult<_>>()?;
+    Ok(WriteFetch::Table(columns, tick))
+}
+
+/// The selected rows of a table column
+fn select_rows<'a, T: Component>(column: &'a TypedComponentStorage<T>, table: &'a TableRows<'_>) -> FetchIter<'a, &'a T> {
+    match &table.rows {
+        None => Box::new(column.iter()),
+        Some(rows) => Box::new(rows.iter().map(move |&row| column.get(row).expect("selected row out of bounds"))),
+    }
+}
+
+/// The selected rows of a table column, mutably
+fn select_rows_mut<'a, T: Component>(column: &'a mut TypedComponentStorage<T>, table: &'a TableRows<'_>) -> FetchIter<'a, &'a mut T> {
+    match &table.rows {
+        None => Box::new(column.iter_mut()),
+        Some(rows) => {
+            // Rows are sorted, so one pass over the column picks them all out
+            let mut rows = rows.iter().copied().peekable();
+            Box::new(column.iter_mut().enumerate().filter_map(move |(row, component)| {
+                rows.next_if_eq(&row).map(|_| component)
+            }))
+        }
+    }
+}
+
+/// Stamp the selected rows of a table column as changed
+fn mark_changed(ticks: &[ComponentTicks], table: &TableRows<'_>, tick: u64) {
+    match &table.rows {
+        None => ticks.iter().for_each(|ticks| ticks.set_changed(tick)),
+        Some(rows) => rows.iter().for_each(|&row| ticks[row].set_changed(tick)),
+    }
+}
+
+/// Stamp the selected entities' sparse-set components as changed
+fn mark_sparse_changed(ticks: &SecondaryMap<EntityId, ComponentTicks>, tables: &[TableRows<'_>], tick: u64) {
+    for id in selected_entities(tables) {
+        if let Some(ticks) = ticks.get(id) {
+            ticks.set_changed(tick);
+        }
+    }
+}
+
+/// Every selected entity across the matched tables, in iteration order
+fn selected_entities<'a>(tables: &'a [TableRows<'_>]) -> impl Iterator<Item = EntityId> + 'a {
+    tables.iter().flat_map(|table| table.entities.iter().copied())
+}
+
+fn missing<'a, I: 'a>(table: &'a TableRows<'_>) -> FetchIter<'a, I> {
+    Box::new(table.entities.iter().map(|_| -> I {
+        panic!("query matched an archetype without the requested component")
+    }))
+}
+
+/// Filter matching entities that have component `T`
+pub struct With<T>(PhantomData<fn() -> T>);
+
+/// Filter matching entities that do not have component `T`
+pub struct Without<T>(PhantomData<fn() -> T>);
+
+impl<T: Component> QueryFilter for With<T> {
+    fn matches(world: &World, archetype: &Archetype) -> bool {
+        world.is_sparse(TypeId::of::<T>()) || archetype.contains(TypeId::of::<T>())
+    }
+
+    fn is_dense(world: &World) -> bool {
+        !world.is_sparse(TypeId::of::<T>())
+    }
+
+    fn matches_entity(world: &World, entity: EntityId) -> bool {
+        Self::is_dense(world) || world.has_component_type(entity, TypeId::of::<T>())
+    }
+}
+
+impl<T: Component> QueryFilter for Without<T> {
+    fn matches(world: &World, archetype: &Archetype) -> bool {
+        world.is_sparse(TypeId::of::<T>()) || !archetype.contains(TypeId::of::<T>())
+    }
+
+    fn is_dense(world: &World) -> bool {
+        !world.is_sparse(TypeId::of::<T>())
+    }
+
+    fn matches_entity(world: &World, entity: EntityId) -> bool {
+        Self::is_dense(world) || !world.has_component_type(entity, TypeId::of::<T>())
+    }
+}
+
+/// Filter matching entities whose `T` was added since the running system's
+/// previous run (outside systems: since the last `World::clear_trackers`)
+pub struct Added<T>(PhantomData<fn() -> T>);
+
+/// Filter matching entities whose `T` was added or changed since the running
+/// system's previous run (outside systems: since the last `World::clear_trackers`)
+///
+/// Any mutable borrow counts as a change: `get_component_mut`, a `&mut T`
+/// query, or replacing the component with `add_component`
+pub struct Changed<T>(PhantomData<fn() -> T>);
+
+impl<T: Component> QueryFilter for Added<T> {
+    fn matches(world: &World, archetype: &Archetype) -> bool {
+        With::<T>::matches(world, archetype)
+    }
+
+    fn is_dense(_world: &World) -> bool {
+        false
+    }
+
+    fn matches_entity(world: &World, entity: EntityId) -> bool {
+        world
+            .component_ticks(entity, TypeId::of::<T>())
+            .is_some_and(|ticks| ticks.is_added(world.last_change_tick()))
+    }
+
+    fn check_access() -> EcsResult<()> {
+        access::check_read::<T>()
+    }
+}
+
+impl<T: Component> QueryFilter for Changed<T> {
+    fn matches(world: &World, archetype: &Archetype) -> bool {
+        With::<T>::matches(world, archetype)
+    }
+
+    fn is_dense(_world: &World) -> bool {
+        false
+    }
+
+    fn matches_entity(world: &World, entity: EntityId) -> bool {
+        world
+            .component_ticks(entity, TypeId::of::<T>())
+            .is_some_and(|ticks| ticks.is_changed(world.last_change_tick()))
+    }
+
+    fn check_access() -> EcsResult<()> {
+        access::check_read::<T>()
+    }
+}
+
+impl QueryFilter for () {
+    fn matches(_world: &World, _archetype: &Archetype) -> bool {
+        true
+    }
+
+    fn is_dense(_world: &World) -> bool {
+        true
+    }
+
+    fn matches_entity(_world: &World, _entity: EntityId) -> bool {
+        true
+    }
+}
+
+impl QueryData for Entity {
+    type Item<'a> = Entity;
+    type Fetch<'w> = ();
+
+    fn matches(_world: &World, _archetype: &Archetype) -> bool {
+        true
+    }
+
+    fn is_dense(_world: &World) -> bool {
+        true
+    }
+
+    fn matches_entity(_world: &World, _entity: EntityId) -> bool {
+        true
+    }
+
+    fn check_access() -> EcsResult<()> {
+        Ok(())
+    }
+
+    fn fetch<'w>(_world: &'w World, _archetypes: &[&'w Archetype]) -> EcsResult<Self::Fetch<'w>> {
+        Ok(())
+    }
+
+    fn iter<'a, 'w>(_fetch: &'a mut Self::Fetch<'w>, tables: &'a [TableRows<'w>]) -> FetchIter<'a, Self::Item<'a>>
+    where
+        Self: 'a,
+    {
+        Box::new(selected_entities(tables).map(Entity::new))
+    }
+}
+
+impl<T: Component> QueryData for &T {
+    type Item<'a> = &'a T
+    where
+        Self: 'a;
+    type Fetch<'w> = ReadFetch<'w, T>;
+
+    fn matches(world: &World, archetype: &Archetype) -> bool {
+        With::<T>::matches(world, archetype)
+    }
+
+    fn is_dense(world: &World) -> bool {
+        With::<T>::is_dense(world)
+    }
+
+    fn matches_entity(world: &World, entity: EntityId) -> bool {
+        With::<T>::matches_entity(world, entity)
+    }
+
+    fn check_access() -> EcsResult<()> {
+        access::check_read::<T>()
+    }
+
+    fn fetch<'w>(world: &'w World, archetypes: &[&'w Archetype]) -> EcsResult<Self::Fetch<'w>> {
+        fetch_read(world, archetypes)
+    }
+
+    fn iter<'a, 'w>(fetch: &'a mut Self::Fetch<'w>, tables: &'a [TableRows<'w>]) -> FetchIter<'a, Self::Item<'a>>
+    where
+        Self: 'a,
+    {
+        match fetch {
+            ReadFetch::Table(columns) => Box::new(columns.iter().zip(tables).flat_map(|(column, table)| {
+                match column.as_deref() {
+                    Some(column) => select_rows(column, table),
+                    None => missing(table),
+                }
+            })),
+            ReadFetch::Sparse(set) => {
+                let set: &SparseSetStorage<T> = set;
+                Box::new(selected_entities(tables).map(move |id| {
+                    set.get(id).expect("query matched an entity without the requested component")
+                }))
+            }
+        }
+    }
+}
+
+impl<T: Component> QueryData for &mut T {
+    type Item<'a> = &'a mut T
+    where
+        Self: 'a;
+    type Fetch<'w> = WriteFetch<'w, T>;
+
+    fn matches(world: &World, archetype: &Archetype) -> bool {
+        With::<T>::matches(world, archetype)
+    }
+
+    fn is_dense(world: &World) -> bool {
+        With::<T>::is_dense(world)
+    }
+
+    fn matches_entity(world: &World, entity: EntityId) -> bool {
+        With::<T>::matches_entity(world, entity)
+    }
+
+    fn check_access() -> EcsResult<()> {
+        access::check_write::<T>()
+    }
+
+    fn fetch<'w>(world: &'w World, archetypes: &[&'w Archetype]) -> EcsResult<Self::Fetch<'w>> {
+        fetch_write(world, archetypes)
+    }
+
+    fn iter<'a, 'w>(fetch: &'a mut Self::Fetch<'w>, tables: &'a [TableRows<'w>]) -> FetchIter<'a, Self::Item<'a>>
+    where
+        Self: 'a,
+    {
+        match fetch {
+            WriteFetch::Table(columns, tick) => {
+                let tick = *tick;
+                Box::new(columns.iter_mut().zip(tables).flat_map(move |(column, table)| {
+                    match column {
+                        Some((column, ticks)) => {
+                            mark_changed(ticks, table, tick);
+                            select_rows_mut(column, table)
+                        }
+                        None => missing(table),
+                    }
+                }))
+            }
+            WriteFetch::Sparse(set, ticks, tick) => {
+                mark_sparse_changed(ticks, tables, *tick);
+                Box::new(set.get_many_mut(selected_entities(tables)).into_iter().map(|component| {
+                    component.expect("query matched an entity without the requested component")
+                }))
+            }
+        }
+    }
+}
+
+impl<T: Component> QueryData for Option<&T> {
+    type Item<'a> = Option<&'a T>
+    where
+        Self: 'a;
+    type Fetch<'w> = ReadFetch<'w, T>;
+
+    fn matches(_world: &World, _archetype: &Archetype) -> bool {
+        true
+    }
+
+    fn is_dense(_world: &World) -> bool {
+        true
+    }
+
+    fn matches_entity(_world: &World, _entity: EntityId) -> bool {
+        true
+    }
+
+    fn check_access() -> EcsResult<()> {
+        access::check_read::<T>()
+    }
+
+    fn fetch<'w>(world: &'w World, archetypes: &[&'w Archetype]) -> EcsResult<Self::Fetch<'w>> {
+        fetch_read(world, archetypes)
+    }
+
+    fn iter<'a, 'w>(fetch: &'a mut Self::Fetch<'w>, tables: &'a [TableRows<'w>]) -> FetchIter<'a, Self::Item<'a>>
+    where
+        Self: 'a,
+    {
+        match fetch {
+            ReadFetch::Table(columns) => Box::new(columns.iter().zip(tables).flat_map(|(column, table)| -> FetchIter<'a, Option<&'a T>> {
+                match column.as_deref() {
+                    Some(column) => Box::new(select_rows(column, table).map(Some)),
+                    None => Box::new(table.entities.iter().map(|_| None)),
+                }
+            })),
+            ReadFetch::Sparse(set) => {
+                let set: &SparseSetStorage<T> = set;
+                Box::new(selected_entities(tables).map(move |id| set.get(id)))
+            }
+        }
+    }
+}
+
+impl<T: Component> QueryData for Option<&mut T> {
+    type Item<'a> = Option<&'a mut T>
+    where
+        Self: 'a;
+    type Fetch<'w> = WriteFetch<'w, T>;
+
+    fn matches(_world: &World, _archetype: &Archetype) -> bool {
+        true
+    }
+
+    fn is_dense(_world: &World) -> bool {
+        true
+    }
+
+    fn matches_entity(_world: &World, _entity: EntityId) -> bool {
+        true
+    }
+
+    fn check_access() -> EcsResult<()> {
+        access::check_write::<T>()
+    }
+
+    fn fetch<'w>(world: &'w World, archetypes: &[&'w Archetype]) -> EcsResult<Self::Fetch<'w>> {
+        fetch_write(world, archetypes)
+    }
+
+    fn iter<'a, 'w>(fetch: &'a mut Self::Fetch<'w>, tables: &'a [TableRows<'w>]) -> FetchIter<'a, Self::Item<'a>>
+    where
+        Self: 'a,
+    {
+        match fetch {
+            WriteFetch::Table(columns, tick) => {
+                let tick = *tick;
+                Box::new(columns.iter_mut().zip(tables).flat_map(move |(column, table)| -> FetchIter<'a, Option<&'a mut T>> {
+                    match column {
+                        Some((column, ticks)) => {
+                            mark_changed(ticks, table, tick);
+                            Box::new(select_rows_mut(column, table).map(Some))
+                        }
+                        None => Box::new(table.entities.iter().map(|_| None)),
+                    }
+                }))
+            }
+            WriteFetch::Sparse(set, ticks, tick) => {
+                mark_sparse_changed(ticks, tables, *tick);
+                Box::new(set.get_many_mut(selected_entities(tables)).into_iter())
+            }
+        }
+    }
+}
+
+/// Lock-step iterator over a tuple of per-component iterators
+pub struct TupleIter<I>(I);
+
+macro_rules! impl_query_tuple {
+    ($($name:ident),+) => {
+        #[allow(non_snake_case)]
+        impl<$($name: QueryData),+> QueryData for ($($name,)+) {
+            type Item<'a> = ($($name::Item<'a>,)+)
+            where
+                Self: 'a;
+            type Fetch<'w> = ($($name::Fetch<'w>,)+);
+
+            fn matches(world: &World, archetype: &Archetype) -> bool {
+                $($name::matches(world, archetype))&&+
+            }
+
+            fn is_dense(world: &World) -> bool {
+                $($name::is_dense(world))&&+
+            }
+
+            fn matches_entity(world: &World, entity: EntityId) -> bool {
+                $($name::matches_entity(world, entity))&&+
+            }
+
+            fn check_access() -> EcsResult<()> {
+                $($name::check_access()?;)+
+                Ok(())
+            }
+
+            fn fetch<'w>(world: &'w World, archetypes: &[&'w Archetype]) -> EcsResult<Self::Fetch<'w>> {
+                Ok(($($name::fetch(world, archetypes)?,)+))
+            }
+
+            fn iter<'a, 'w>(fetch: &'a mut Self::Fetch<'w>, tables: &'a [TableRows<'w>]) -> FetchIter<'a, Self::Item<'a>>
+            where
+                Self: 'a,
+            {
+                let ($($name,)+) = fetch;
+                Box::new(TupleIter(($($name::iter($name, tables),)+)))
+            }
+        }
+
+        #[allow(non_snake_case)]
+        impl<$($name: Iterator),+> Iterator for TupleIter<($($name,)+)> {
+            type Item = ($($name::Item,)+);
+
+            fn next(&mut self) -> Option<Self::Item> {
+                let ($($name,)+) = &mut self.0;
+                Some(($($name.next()?,)+))
+            }
+        }
+
+        #[allow(non_snake_case)]
+        impl<$($name: QueryFilter),+> QueryFilter for ($($name,)+) {
+            fn matches(world: &World, archetype: &Archetype) -> bool {
+                $($name::matches(world, archetype))&&+
+            }
+
+            fn is_dense(world: &World) -> bool {
+                $($name::is_dense(world))&&+
+            }
+
+            fn matches_entity(world: &World, entity: EntityId) -> bool {
+                $($name::matches_entity(world, entity))&&+
+            }
+
+            fn check_access() -> EcsResult<()> {
+                $($name::check_access()?;)+
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_query_tuple!(A);
+impl_query_tuple!(A, B);
+impl_query_tuple!(A, B, C);
+impl_query_tuple!(A, B, C, D);
+impl_query_tuple!(A, B, C, D, E);
+impl_query_tuple!(A, B, C, D, E, F);
+impl_query_tuple!(A, B, C, D, E, F, G);
+impl_query_tuple!(A, B, C, D, E, F, G, H);
+
+/// A borrowed view over every entity matching `Q` and `F`
+///
+/// The matching archetype columns stay borrowed until the query is dropped, so
+/// other queries touching the same components conflict while this one is alive
+pub struct Query<'w, Q: QueryData, F: QueryFilter = ()> {
+    tables: Vec<TableRows<'w>>,
+    fetch: Q::Fetch<'w>,
+    _filter: PhantomData<fn() -> F>,
+}
+
+impl<'w, Q: QueryData, F: QueryFilter> Query<'w, Q, F> {
+    pub(crate) fn new(world: &'w World) -> EcsResult<Self> {
+        // Checked up front, so an undeclared component fails even when no entity has it
+        Q::check_access()?;
+        F::check_access()?;
+
+        // Sparse-set components can't be matched per table, only per entity
+        let dense = Q::is_dense(world) && F::is_dense(world);
+
+        let mut tables = Vec::new();
+        let mut archetypes = Vec::new();
+        for archetype in world.archetypes() {
+            if archetype.is_empty() || !Q::matches(world, archetype) || !F::matches(world, archetype) {
+                continue;
+            }
+            let rows = if dense {
+                TableRows::all(archetype.entities())
+            } else {
+                TableRows::select(archetype.entities(), |id| {
+                    Q::matches_entity(world, id) && F::matches_entity(world, id)
+                })
+            };
+            if !rows.is_empty() {
+                tables.push(rows);
+                archetypes.push(archetype);
+            }
+        }
+
+        Ok(Self {
+            fetch: Q::fetch(world, &archetypes)?,
+            tables,
+            _filter: PhantomData,
+        })
+    }
+
+    /// Iterate the matching entities' components, one archetype table at a time
+    pub fn iter(&mut self) -> FetchIter<'_, Q::Item<'_>> {
+        Q::iter(&mut self.fetch, &self.tables)
+    }
+
+    /// Matching entity handles, in iteration order
+    pub fn entities(&self) -> FetchIter<'_, Entity> {
+        Box::new(selected_entities(&self.tables).map(Entity::new))
+    }
+
+    /// Number of matching entities
+    pub fn len(&self) -> usize {
+        self.tables.iter().map(TableRows::len).sum()
+    }
+
+    /// Whether no entities matched
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EcsError, StorageType};
+
+    #[derive(Debug, PartialEq)]
+    struct Position {
+        x: f32,
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Velocity {
+        x: f32,
+    }
+
+    struct Frozen;
+
+    fn setup() -> (World, Entity, Entity, Entity) {
+        let mut world = World::new();
+        let moving = world.create_entity();
+        world.add_component(moving, Position { x: 0.0 }).unwrap();
+        world.add_component(moving, Velocity { x: 2.0 }).unwrap();
+
+        let frozen = world.create_entity();
+        world.add_component(frozen, Position { x: 5.0 }).unwrap();
+        world.add_component(frozen, Velocity { x: 3.0 }).unwrap();
+        world.add_component(frozen, Frozen).unwrap();
+
+        let still = world.create_entity();
+        world.add_component(still, Position { x: 9.0 }).unwrap();
+
+        (world, moving, frozen, still)
+    }
+
+    #[test]
+    fn test_query_mutable_tuple() {
+        let (world, moving, frozen, still) = setup();
+
+        let mut query = world.query::<(&mut Position, &Velocity)>().unwrap();
+        assert_eq!(query.len(), 2);
+        for (pos, vel) in query.iter() {
+            pos.x += vel.x;
+        }
+        drop(query);
+
+        assert_eq!(world.get_component::<Position>(moving).unwrap().x, 2.0);
+        assert_eq!(world.get_component::<Position>(frozen).unwrap().x, 8.0);
+        assert_eq!(world.get_component::<Position>(still).unwrap().x, 9.0);
+    }
+
+    #[test]
+    fn test_query_filters_and_options() {
+        let (world, moving, _frozen, still) = setup();
+
+        let mut query = world
+            .query_filtered::<(Entity, &Position), (With<Velocity>, Without<Frozen>)>()
+            .unwrap();
+        let matched: Vec<_> = query.iter().map(|(entity, _)| entity).collect();
+        assert_eq!(matched, vec![moving]);
+
+        let mut query = world.query::<(Entity, Option<&Velocity>)>().unwrap();
+        let missing: Vec<_> = query
+            .iter()
+            .filter(|(_, vel)| vel.is_none())
+            .map(|(entity, _)| entity)
+            .collect();
+        assert_eq!(missing, vec![still]);
+    }
+
+    #[test]
+    fn test_query_borrow_conflicts() {
+        let (world, _, _, _) = setup();
+
+        let result = world.query::<(&mut Position, &Position)>();
+        assert!(matches!(result, Err(EcsError::BorrowConflict(_))));
+
+        let reader = world.query::<&Position>().unwrap();
+        assert!(world.query::<&Position>().is_ok());
+        assert!(matches!(world.query::<&mut Position>(), Err(EcsError::BorrowConflict(_))));
+        drop(reader);
+        assert!(world.query::<&mut Position>().is_ok());
+    }
+
+    #[test]
+    fn test_query_sparse_components() {
+        let (mut world, moving, frozen, still) = setup();
+
+        #[derive(Debug, PartialEq)]
+        struct Boost(f32);
+        world.register_component_with::<Boost>(StorageType::SparseSet).unwrap();
+        world.add_component(frozen, Boost(10.0)).unwrap();
+        world.add_component(still, Boost(1.0)).unwrap();
+
+        let mut query = world.query::<(&mut Position, &Boost)>().unwrap();
+        assert_eq!(query.len(), 2);
+        for (pos, boost) in query.iter() {
+            pos.x += boost.0;
+        }
+        drop(query);
+
+        assert_eq!(world.get_component::<Position>(moving).unwrap().x, 0.0);
+        assert_eq!(world.get_component::<Position>(frozen).unwrap().x, 15.0);
+        assert_eq!(world.get_component::<Position>(still).unwrap().x, 10.0);
+
+        let mut query = world.query_filtered::<(Entity, &mut Boost), Without<Frozen>>().unwrap();
+        for (entity, boost) in query.iter() {
+            assert_eq!(entity, still);
+            boost.0 *= 2.0;
+        }
+        drop(query);
+        assert_eq!(*world.get_component::<Boost>(still).unwrap(), Boost(2.0));
+
+        let query = world.query_filtered::<Entity, Without<Boost>>().unwrap();
+        assert_eq!(query.entities().collect::<Vec<_>>(), vec![moving]);
+    }
+
+    #[test]
+    fn test_query_change_filters() {
+        let (mut world, moving, frozen, still) = setup();
+        let changed = |world: &World| world.query_filtered::<Entity, Changed<Position>>().unwrap().entities().collect::<Vec<_>>();
+
+        // Everything counts as added (and changed) until the trackers are cleared
+        assert_eq!(world.query_filtered::<Entity, Added<Position>>().unwrap().len(), 3);
+        world.clear_trackers();
+        assert!(world.query_filtered::<Entity, Added<Position>>().unwrap().is_empty());
+        assert!(changed(&world).is_empty());
+
+        // A mutable query marks what it hands out, and replacing a component marks it too
+        let mut query = world.query_filtered::<&mut Position, With<Frozen>>().unwrap();
+        query.iter().for_each(|pos| pos.x = 0.0);
+        drop(query);
+        world.add_component(still, Position { x: 1.0 }).unwrap();
+        assert_eq!(changed(&world), vec![still, frozen]);
+        assert!(world.query_filtered::<Entity, Added<Position>>().unwrap().is_empty());
+
+        // Sparse-set components are tracked per entity
+        #[derive(Debug, PartialEq)]
+        struct Boost(f32);
+        world.register_component_with::<Boost>(StorageType::SparseSet).unwrap();
+        world.add_component(moving, Boost(1.0)).unwrap();
+        world.add_component(frozen, Boost(2.0)).unwrap();
+        world.clear_trackers();
+        world.query_filtered::<&mut Boost, Without<Frozen>>().unwrap().iter().for_each(|boost| boost.0 += 1.0);
+        let query = world.query_filtered::<Entity, Changed<Boost>>().unwrap();
+        assert_eq!(query.entities().collect::<Vec<_>>(), vec![moving]);
+    }
+}
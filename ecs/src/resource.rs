@@ -0,0 +1,147 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::{EcsError, EcsResult, Ref, RefMut};
+
+/// Trait for world-wide singleton data, such as the simulation clock or wind
+///
+/// Resources are keyed by type, so a World holds at most one of each
+pub trait Resource: Any + Send + Sync {
+    /// Get the type name for debugging
+    fn type_name() -> &'static str where Self: Sized {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// Automatically implement Resource for types that meet the requirements
+impl<T> Resource for T where T: Any + Send + Sync {}
+
+/// Builds a resource's value in a forked world from the original's
+pub(crate) type ResourceFork = fn(&(dyn Any + Send + Sync)) -> Box<dyn Any + Send + Sync>;
+
+/// What Resources know about a resource type besides its value
+#[derive(Clone)]
+struct ResourceInfo {
+    name: &'static str,
+    fork: Option<ResourceFork>,
+}
+
+/// Type-keyed resource storage
+///
+/// Each resource sits behind its own lock, so systems holding `&World` can
+/// read one resource while writing another
+#[derive(Default)]
+pub(crate) struct Resources {
+    values: HashMap<TypeId, RwLock<Box<dyn Any + Send + Sync>>>,
+    info: HashMap<TypeId, ResourceInfo>,
+}
+
+impl Resources {
+    /// Insert a resource, returning the one it replaced
+    pub fn insert<R: Resource>(&mut self, resource: R) -> Option<R> {
+        self.info_mut::<R>();
+        self.values
+            .insert(TypeId::of::<R>(), RwLock::new(Box::new(resource)))
+            .and_then(|old| old.into_inner().downcast::<R>().ok())
+            .map(|old| *old)
+    }
+
+    pub fn get<R: Resource>(&self) -> EcsResult<Ref<'_, R>> {
+        let guard = self.lock::<R>()?
+            .try_read()
+            .ok_or_else(|| EcsError::BorrowConflict(R::type_name().to_string()))?;
+
+        RwLockReadGuard::try_map(guard, |value| value.downcast_ref::<R>())
+            .map_err(|_| EcsError::ResourceNotFound(R::type_name().to_string()))
+    }
+
+    pub fn get_mut<R: Resource>(&self) -> EcsResult<RefMut<'_, R>> {
+        let guard = self.lock::<R>()?
+            .try_write()
+            .ok_or_else(|| EcsError::BorrowConflict(R::type_name().to_string()))?;
+
+        RwLockWriteGuard::try_map(guard, |value| value.downcast_mut::<R>())
+            .map_err(|_| EcsError::ResourceNotFound(R::type_name().to_string()))
+    }
+
+    pub fn remove<R: Resource>(&mut self) -> Option<R> {
+        let value = self.values.remove(&TypeId::of::<R>())?;
+        value.into_inner().downcast::<R>().ok().map(|value| *value)
+    }
+
+    pub fn contains<R: Resource>(&self) -> bool {
+        self.values.contains_key(&TypeId::of::<R>())
+    }
+
+    /// Decide how `try_clone` copies resources of type R
+    pub fn set_fork<R: Resource>(&mut self, fork: ResourceFork) {
+        self.info_mut::<R>().fork = Some(fork);
+    }
+
+    /// Copy every resource for a forked world
+    ///
+    /// Fails if a resource is borrowed mutably or has no fork function
+    pub fn try_clone(&self) -> EcsResult<Self> {
+        let mut values = HashMap::with_capacity(self.values.len());
+        for (type_id, value) in &self.values {
+            let info = &self.info[type_id];
+            let fork = info.fork.ok_or_else(|| EcsError::NotCloneable(info.name.to_string()))?;
+            let value = value
+                .try_read()
+                .ok_or_else(|| EcsError::BorrowConflict(info.name.to_string()))?;
+            values.insert(*type_id, RwLock::new(fork(&**value)));
+        }
+
+        Ok(Self {
+            values,
+            info: self.info.clone(),
+        })
+    }
+
+    fn info_mut<R: Resource>(&mut self) -> &mut ResourceInfo {
+        self.info.entry(TypeId::of::<R>()).or_insert_with(|| ResourceInfo {
+            name: R::type_name(),
+            fork: None,
+        })
+    }
+
+    fn lock<R: Resource>(&self) -> EcsResult<&RwLock<Box<dyn Any + Send + Sync>>> {
+        self.values
+            .get(&TypeId::of::<R>())
+            .ok_or_else(|| EcsError::ResourceNotFound(R::type_name().to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{EcsError, World};
+
+    #[derive(Debug, PartialEq)]
+    struct Clock {
+        elapsed: f32,
+    }
+
+    struct Gravity(f32);
+
+    #[test]
+    fn test_world_resources() {
+        let mut world = World::new();
+        assert!(matches!(world.resource::<Clock>(), Err(EcsError::ResourceNotFound(_))));
+
+        world.insert_resource(Clock { elapsed: 0.0 });
+        world.insert_resource(Gravity(-9.81));
+        assert!(world.contains_resource::<Clock>());
+
+        // Different resources borrow independently, the same one conflicts
+        let gravity = world.resource::<Gravity>().unwrap();
+        world.resource_mut::<Clock>().unwrap().elapsed += 0.5;
+        assert!(matches!(world.resource_mut::<Gravity>(), Err(EcsError::BorrowConflict(_))));
+        assert_eq!(gravity.0, -9.81);
+        drop(gravity);
+
+        assert_eq!(world.insert_resource(Clock { elapsed: 2.0 }), Some(Clock { elapsed: 0.5 }));
+        assert_eq!(world.remove_resource::<Clock>(), Some(Clock { elapsed: 2.0 }));
+        assert_eq!(world.remove_resource::<Clock>(), None);
+    }
+}
@@ -0,0 +1,183 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::{Resource, World};
+
+/// Most substeps a fixed timestep runs in one frame, unless changed with
+/// `with_max_substeps`
+///
+/// A frame that takes longer than its substeps can simulate (a debugger pause,
+/// a slow host) would otherwise need even more substeps the next frame, and
+/// never catch up
+pub const DEFAULT_MAX_SUBSTEPS: usize = 1000;
+
+/// Decides when, and how many times, a system or group of stages runs in a frame
+///
+/// Criteria keep their own state (timers, tick counters), so each system or
+/// stage group gets its own copy
+#[derive(Clone, Default)]
+pub enum RunCriteria {
+    /// Run once per frame with the frame's delta time
+    #[default]
+    Always,
+    /// Run once for every whole `step` of accumulated frame time, each time with
+    /// `step` as the delta time. Leftover time carries over to the next frame,
+    /// unless `max_substeps` ran out before it was used up; then it's dropped
+    FixedTimestep { step: f32, accumulator: f32, max_substeps: usize },
+    /// Run on every `n`th frame, with the time accumulated since the last run
+    EveryNTicks { n: u32, ticks: u32, elapsed: f32 },
+    /// Run once per frame while the predicate holds
+    Condition(Arc<dyn Fn(&World) -> bool + Send + Sync>),
+}
+
+/// How many times to run this frame, and with which delta time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RunSteps {
+    pub count: usize,
+    pub delta_time: f32,
+}
+
+impl RunCriteria {
+    /// Substep at a fixed `step` seconds
+    pub fn fixed_timestep(step: f32) -> Self {
+        assert!(step > 0.0, "fixed timestep must be positive");
+        RunCriteria::FixedTimestep {
+            step,
+            accumulator: 0.0,
+            max_substeps: DEFAULT_MAX_SUBSTEPS,
+        }
+    }
+
+    /// Cap a fixed timestep's substeps per frame; other criteria are unchanged
+    pub fn with_max_substeps(mut self, max: usize) -> Self {
+        assert!(max > 0, "substep limit must be positive");
+        if let RunCriteria::FixedTimestep { max_substeps, .. } = &mut self {
+            *max_substeps = max;
+        }
+        self
+    }
+
+    /// Substep at a fixed rate in Hz
+    pub fn fixed_rate(hz: f32) -> Self {
+        Self::fixed_timestep(1.0 / hz)
+    }
+
+    pub fn every_n_ticks(n: u32) -> Self {
+        assert!(n > 0, "tick interval must be positive");
+        RunCriteria::EveryNTicks { n, ticks: 0, elapsed: 0.0 }
+    }
+
+    /// Run while `predicate` returns true
+    pub fn run_if(predicate: impl Fn(&World) -> bool + Send + Sync + 'static) -> Self {
+        RunCriteria::Condition(Arc::new(predicate))
+    }
+
+    /// Run while resource `R` exists and satisfies `predicate`
+    pub fn resource_matches<R: Resource>(predicate: impl Fn(&R) -> bool + Send + Sync + 'static) -> Self {
+        Self::run_if(move |world| world.resource::<R>().is_ok_and(|resource| predicate(&resource)))
+    }
+
+    /// Advance the criteria by one frame of `delta_time` seconds
+    ///
+    /// A fixed timestep ignores frame times that aren't finite and
+    /// non-negative, rather than let NaN or infinity into its accumulator;
+    /// SystemDispatcher::run_systems rejects them before they get here
+    pub fn poll(&mut self, world: &World, delta_time: f32) -> RunSteps {
+        match self {
+            RunCriteria::Always => RunSteps { count: 1, delta_time },
+            RunCriteria::FixedTimestep { step, accumulator, max_substeps } => {
+                if delta_time.is_finite() && delta_time >= 0.0 {
+                    *accumulator += delta_time;
+                }
+                let mut count = 0;
+                while *accumulator >= *step && count < *max_substeps {
+                    *accumulator -= *step;
+                    count += 1;
+                }
+                // Too far behind to catch up: drop the time that's left
+                if *accumulator >= *step {
+                    *accumulator = 0.0;
+                }
+                RunSteps { count, delta_time: *step }
+            }
+            RunCriteria::EveryNTicks { n, ticks, elapsed } => {
+                *ticks += 1;
+                *elapsed += delta_time;
+                if *ticks < *n {
+                    return RunSteps { count: 0, delta_time };
+                }
+                let delta_time = std::mem::take(elapsed);
+                *ticks = 0;
+                RunSteps { count: 1, delta_time }
+            }
+            RunCriteria::Condition(predicate) => RunSteps {
+                count: usize::from(predicate(world)),
+                delta_time,
+            },
+        }
+    }
+}
+
+impl fmt::Debug for RunCriteria {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunCriteria::Always => write!(f, "Always"),
+            RunCriteria::FixedTimestep { step, accumulator, max_substeps } => f
+                .debug_struct("FixedTimestep")
+                .field("step", step)
+                .field("accumulator", accumulator)
+                .field("max_substeps", max_substeps)
+                .finish(),
+            RunCriteria::EveryNTicks { n, ticks, elapsed } => f
+                .debug_struct("EveryNTicks")
+                .field("n", n)
+                .field("ticks", ticks)
+                .field("elapsed", elapsed)
+                .finish(),
+            RunCriteria::Condition(_) => write!(f, "Condition(..)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Paused(bool);
+
+    #[test]
+    fn test_run_criteria_poll() {
+        let mut world = World::new();
+
+        // 1 kHz physics under a 60 Hz frame: 16 or 17 substeps, never drifting
+        let mut fixed = RunCriteria::fixed_rate(1000.0);
+        let substeps: usize = (0..60).map(|_| fixed.poll(&world, 1.0 / 60.0).count).sum();
+        assert!((999..=1000).contains(&substeps));
+        assert_eq!(fixed.poll(&world, 0.0).delta_time, 0.001);
+
+        // A long frame runs at most max_substeps and drops the rest
+        let mut capped = RunCriteria::fixed_rate(1000.0).with_max_substeps(50);
+        assert_eq!(capped.poll(&world, 10.0).count, 50);
+        assert_eq!(capped.poll(&world, 0.0).count, 0);
+
+        // Infinite, NaN and negative frame times add nothing
+        for bad in [f32::INFINITY, f32::NAN, -1.0] {
+            assert_eq!(capped.poll(&world, bad).count, 0);
+        }
+        assert_eq!(capped.poll(&world, 0.0105).count, 10);
+
+        let mut every_third = RunCriteria::every_n_ticks(3);
+        let runs: Vec<usize> = (0..6).map(|_| every_third.poll(&world, 0.5).count).collect();
+        assert_eq!(runs, vec![0, 0, 1, 0, 0, 1]);
+        every_third.poll(&world, 0.5);
+        every_third.poll(&world, 0.5);
+        assert_eq!(every_third.poll(&world, 0.5), RunSteps { count: 1, delta_time: 1.5 });
+
+        let mut unpaused = RunCriteria::resource_matches::<Paused>(|paused| !paused.0);
+        assert_eq!(unpaused.poll(&world, 0.1).count, 0);
+        world.insert_resource(Paused(false));
+        assert_eq!(unpaused.poll(&world, 0.1).count, 1);
+        world.insert_resource(Paused(true));
+        assert_eq!(unpaused.poll(&world, 0.1).count, 0);
+    }
+}
@@ -0,0 +1,468 @@
+use std::any::TypeId;
+use std::collections::{BTreeMap, HashMap};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::{Component, EcsError, EcsResult, Entity, Resource, World};
+
+/// Entity handles from a snapshot, mapped to the entities spawned in their place
+pub type EntityMap = HashMap<Entity, Entity>;
+
+/// Components and resources that hold entity handles
+///
+/// Handles only mean something in the world that made them, so after a restore
+/// every handle is rewritten to the entity that replaced it
+pub trait MapEntities {
+    fn map_entities(&mut self, map: &EntityMap);
+}
+
+impl MapEntities for Entity {
+    /// Handles to entities outside the snapshot are left unchanged
+    fn map_entities(&mut self, map: &EntityMap) {
+        if let Some(&mapped) = map.get(self) {
+            *self = mapped;
+        }
+    }
+}
+
+impl<T: MapEntities> MapEntities for Option<T> {
+    fn map_entities(&mut self, map: &EntityMap) {
+        if let Some(value) = self {
+            value.map_entities(map);
+        }
+    }
+}
+
+impl<T: MapEntities> MapEntities for Vec<T> {
+    fn map_entities(&mut self, map: &EntityMap) {
+        self.iter_mut().for_each(|value| value.map_entities(map));
+    }
+}
+
+/// One entity's registered components, keyed by registered name
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntitySnapshot<B> {
+    /// Handle the entity had when the snapshot was taken
+    pub entity: Entity,
+    pub components: BTreeMap<String, B>,
+}
+
+/// A World as plain data
+///
+/// `B` is one encoded value: RON text, or bincode bytes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldSnapshot<B> {
+    pub entities: Vec<EntitySnapshot<B>>,
+    pub resources: BTreeMap<String, B>,
+}
+
+/// Type-erased save and load for one component type in one format
+#[derive(Clone)]
+struct ComponentCodec<B> {
+    save: fn(&World, Entity) -> EcsResult<Option<B>>,
+    load: fn(&mut World, Entity, &B) -> EcsResult<()>,
+}
+
+/// Type-erased save and load for one resource type in one format
+#[derive(Clone)]
+struct ResourceCodec<B> {
+    save: fn(&World) -> EcsResult<Option<B>>,
+    load: fn(&mut World, &B) -> EcsResult<()>,
+}
+
+#[derive(Clone)]
+struct ComponentEntry {
+    name: String,
+    type_id: TypeId,
+    ron: ComponentCodec<String>,
+    bincode: ComponentCodec<Vec<u8>>,
+    /// Rewrites entity handles after a load, for types that hold them
+    remap: Option<fn(&mut World, Entity, &EntityMap)>,
+}
+
+#[derive(Clone)]
+struct ResourceEntry {
+    name: String,
+    type_id: TypeId,
+    ron: ResourceCodec<String>,
+    bincode: ResourceCodec<Vec<u8>>,
+    remap: Option<fn(&mut World, &EntityMap)>,
+}
+
+/// How values inside a snapshot are encoded
+trait Format {
+    type Blob;
+
+    fn encode<T: Serialize>(value: &T) -> EcsResult<Self::Blob>;
+    fn decode<T: DeserializeOwned>(blob: &Self::Blob) -> EcsResult<T>;
+    fn component(entry: &ComponentEntry) -> &ComponentCodec<Self::Blob>;
+    fn resource(entry: &ResourceEntry) -> &ResourceCodec<Self::Blob>;
+}
+
+/// RON text, readable and diffable
+struct Ron;
+
+/// bincode bytes, compact and fast
+struct Bincode;
+
+fn snapshot_error(err: impl std::fmt::Display) -> EcsError {
+    EcsError::Snapshot(err.to_string())
+}
+
+impl Format for Ron {
+    type Blob = String;
+
+    fn encode<T: Serialize>(value: &T) -> EcsResult<String> {
+        ron::to_string(value).map_err(snapshot_error)
+    }
+
+    fn decode<T: DeserializeOwned>(blob: &String) -> EcsResult<T> {
+        ron::from_str(blob).map_err(snapshot_error)
+    }
+
+    fn component(entry: &ComponentEntry) -> &ComponentCodec<String> {
+        &entry.ron
+    }
+
+    fn resource(entry: &ResourceEntry) -> &ResourceCodec<String> {
+        &entry.ron
+    }
+}
+
+impl Format for Bincode {
+    type Blob = Vec<u8>;
+
+    fn encode<T: Serialize>(value: &T) -> EcsResult<Vec<u8>> {
+        bincode::serialize(value).map_err(snapshot_error)
+    }
+
+    fn decode<T: DeserializeOwned>(blob: &Vec<u8>) -> EcsResult<T> {
+        bincode::deserialize(blob).map_err(snapshot_error)
+    }
+
+    fn component(entry: &ComponentEntry) -> &ComponentCodec<Vec<u8>> {
+        &entry.bincode
+    }
+
+    fn resource(entry: &ResourceEntry) -> &ResourceCodec<Vec<u8>> {
+        &entry.bincode
+    }
+}
+
+impl<B> ComponentCodec<B> {
+    fn of<T, F>() -> Self
+    where
+        T: Component + Serialize + DeserializeOwned,
+        F: Format<Blob = B>,
+    {
+        Self {
+            save: |world, entity| match world.try_get_component::<T>(entity) {
+                Ok(component) => F::encode(&*component).map(Some),
+                Err(EcsError::MissingComponent(..)) => Ok(None),
+                Err(err) => Err(err),
+            },
+            load: |world, entity, blob| world.add_component(entity, F::decode::<T>(blob)?),
+        }
+    }
+}
+
+impl<B> ResourceCodec<B> {
+    fn of<R, F>() -> Self
+    where
+        R: Resource + Serialize + DeserializeOwned,
+        F: Format<Blob = B>,
+    {
+        Self {
+            save: |world| match world.resource::<R>() {
+                Ok(resource) => F::encode(&*resource).map(Some),
+                Err(EcsError::ResourceNotFound(_)) => Ok(None),
+                Err(err) => Err(err),
+            },
+            load: |world, blob| {
+                world.insert_resource(F::decode::<R>(blob)?);
+                Ok(())
+            },
+        }
+    }
+}
+
+fn remap_component<T: Component + MapEntities>(world: &mut World, entity: Entity, map: &EntityMap) {
+    if let Some(component) = world.get_component_mut::<T>(entity) {
+        component.map_entities(map);
+    }
+}
+
+fn remap_resource<R: Resource + MapEntities>(world: &mut World, map: &EntityMap) {
+    if let Ok(mut resource) = world.resource_mut::<R>() {
+        resource.map_entities(map);
+    }
+}
+
+/// Maps component and resource types to the serializers used for World snapshots
+///
+/// Each type is registered under a name, which is what snapshot files refer to,
+/// so names must stay stable across builds. Components and resources without
+/// a registered serializer are left out of snapshots
+#[derive(Clone, Default)]
+pub struct SnapshotRegistry {
+    components: Vec<ComponentEntry>,
+    resources: Vec<ResourceEntry>,
+}
+
+impl SnapshotRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Save and restore component `T` under `name`
+    pub fn register_component<T>(&mut self, name: &str)
+    where
+        T: Component + Serialize + DeserializeOwned,
+    {
+        self.insert_component::<T>(name, None);
+    }
+
+    /// Save and restore component `T`, rewriting its entity handles on restore
+    pub fn register_component_with_entities<T>(&mut self, name: &str)
+    where
+        T: Component + Serialize + DeserializeOwned + MapEntities,
+    {
+        self.insert_component::<T>(name, Some(remap_component::<T>));
+    }
+
+    /// Save and restore resource `R` under `name`
+    pub fn register_resource<R>(&mut self, name: &str)
+    where
+        R: Resource + Serialize + DeserializeOwned,
+    {
+        self.insert_resource::<R>(name, None);
+    }
+
+    /// Save and restore resource `R`, rewriting its entity handles on restore
+    pub fn register_resource_with_entities<R>(&mut self, name: &str)
+    where
+        R: Resource + Serialize + DeserializeOwned + MapEntities,
+    {
+        self.insert_resource::<R>(name, Some(remap_resource::<R>));
+    }
+
+    /// Whether component `T` has a registered serializer
+    pub fn contains_component<T: Component>(&self) -> bool {
+        self.components.iter().any(|entry| entry.type_id == TypeId::of::<T>())
+    }
+
+    /// Snapshot every entity and registered resource as pretty-printed RON
+    pub fn save_ron(&self, world: &World) -> EcsResult<String> {
+        let snapshot = self.save::<Ron>(world)?;
+        ron::ser::to_string_pretty(&snapshot, ron::ser::PrettyConfig::default()).map_err(snapshot_error)
+    }
+
+    /// Restore a RON snapshot into `world`, returning the old-to-new entity mapping
+    ///
+    /// Entities are spawned alongside any already in the world. A failed
+    /// restore can leave some of them behind
+    pub fn load_ron(&self, world: &mut World, text: &str) -> EcsResult<EntityMap> {
+        let snapshot = ron::from_str(text).map_err(snapshot_error)?;
+        self.load::<Ron>(world, &snapshot)
+    }
+
+    /// Snapshot every entity and registered resource as bincode
+    pub fn save_bincode(&self, world: &World) -> EcsResult<Vec<u8>> {
+        let snapshot = self.save::<Bincode>(world)?;
+        bincode::serialize(&snapshot).map_err(snapshot_error)
+    }
+
+    /// Restore a bincode snapshot into `world`, like `load_ron`
+    pub fn load_bincode(&self, world: &mut World, bytes: &[u8]) -> EcsResult<EntityMap> {
+        let snapshot = bincode::deserialize(bytes).map_err(snapshot_error)?;
+        self.load::<Bincode>(world, &snapshot)
+    }
+
+    fn insert_component<T>(&mut self, name: &str, remap: Option<fn(&mut World, Entity, &EntityMap)>)
+    where
+        T: Component + Serialize + DeserializeOwned,
+    {
+        // Registering a name or type again replaces its entry
+        self.components.retain(|entry| entry.name != name && entry.type_id != TypeId::of::<T>());
+        self.components.push(ComponentEntry {
+            name: name.to_string(),
+            type_id: TypeId::of::<T>(),
+            ron: ComponentCodec::of::<T, Ron>(),
+            bincode: ComponentCodec::of::<T, Bincode>(),
+            remap,
+        });
+    }
+
+    fn insert_resource<R>(&mut self, name: &str, remap: Option<fn(&mut World, &EntityMap)>)
+    where
+        R: Resource + Serialize + DeserializeOwned,
+    {
+        self.resources.retain(|entry| entry.name != name && entry.type_id != TypeId::of::<R>());
+        self.resources.push(ResourceEntry {
+            name: name.to_string(),
+            type_id: TypeId::of::<R>(),
+            ron: ResourceCodec::of::<R, Ron>(),
+            bincode: ResourceCodec::of::<R, Bincode>(),
+            remap,
+        });
+    }
+
+    fn save<F: Format>(&self, world: &World) -> EcsResult<WorldSnapshot<F::Blob>> {
+        let mut entities = Vec::with_capacity(world.entity_count());
+        for entity in world.entities() {
+            let mut components = BTreeMap::new();
+            for entry in &self.components {
+                if let Some(blob) = (F::component(entry).save)(world, entity)? {
+                    components.insert(entry.name.clone(), blob);
+                }
+            }
+            entities.push(EntitySnapshot { entity, components });
+        }
+
+        let mut resources = BTreeMap::new();
+        for entry in &self.resources {
+            if let Some(blob) = (F::resource(entry).save)(world)? {
+                resources.insert(entry.name.clone(), blob);
+            }
+        }
+        Ok(WorldSnapshot { entities, resources })
+    }
+
+    fn load<F: Format>(&self, world: &mut World, snapshot: &WorldSnapshot<F::Blob>) -> EcsResult<EntityMap> {
+        // Spawn every entity up front so handles between them can be remapped
+        let map: EntityMap = snapshot
+            .entities
+            .iter()
+            .map(|saved| (saved.entity, world.create_entity()))
+            .collect();
+
+        for saved in &snapshot.entities {
+            let entity = map[&saved.entity];
+            for (name, blob) in &saved.components {
+                let entry = self
+                    .components
+                    .iter()
+                    .find(|entry| &entry.name == name)
+                    .ok_or_else(|| snapshot_error(format!("no component registered as {}", name)))?;
+                (F::component(entry).load)(world, entity, blob)?;
+                if let Some(remap) = entry.remap {
+                    remap(world, entity, &map);
+                }
+            }
+        }
+
+        for (name, blob) in &snapshot.resources {
+            let entry = self
+                .resources
+                .iter()
+                .find(|entry| &entry.name == name)
+                .ok_or_else(|| snapshot_error(format!("no resource registered as {}", name)))?;
+            (F::resource(entry).load)(world, blob)?;
+            if let Some(remap) = entry.remap {
+                remap(world, &map);
+            }
+        }
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Position {
+        x: f32,
+        y: f32,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Phase {
+        Taxi,
+        Climb { rate: f32 },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Wingman(Entity);
+
+    impl MapEntities for Wingman {
+        fn map_entities(&mut self, map: &EntityMap) {
+            self.0.map_entities(map);
+        }
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Episode(u32);
+
+    /// Not registered, so left out of snapshots
+    struct Scratch;
+
+    fn registry() -> SnapshotRegistry {
+        let mut registry = SnapshotRegistry::new();
+        registry.register_component::<Position>("Position");
+        registry.register_component::<Phase>("Phase");
+        registry.register_component_with_entities::<Wingman>("Wingman");
+        registry.register_resource::<Episode>("Episode");
+        registry
+    }
+
+    fn world() -> (World, Entity, Entity) {
+        let mut world = World::new();
+        // Leave a gap so the restored world hands out different handles
+        let gap = world.create_entity();
+        let lead = world.create_entity();
+        let wing = world.create_entity();
+        world.remove_entity(gap).unwrap();
+
+        world.add_component(lead, Position { x: 1.5, y: -2.0 }).unwrap();
+        world.add_component(lead, Phase::Climb { rate: 4.0 }).unwrap();
+        world.add_component(lead, Scratch).unwrap();
+        world.add_component(wing, Phase::Taxi).unwrap();
+        world.add_component(wing, Wingman(lead)).unwrap();
+        world.insert_resource(Episode(7));
+        (world, lead, wing)
+    }
+
+    fn check_restored(restored: &World, map: &EntityMap, lead: Entity, wing: Entity) {
+        let (new_lead, new_wing) = (map[&lead], map[&wing]);
+        assert_ne!(new_lead, lead);
+        assert_eq!(restored.entity_count(), 2);
+        assert_eq!(*restored.get_component::<Position>(new_lead).unwrap(), Position { x: 1.5, y: -2.0 });
+        assert_eq!(*restored.get_component::<Phase>(new_lead).unwrap(), Phase::Climb { rate: 4.0 });
+        assert_eq!(*restored.get_component::<Phase>(new_wing).unwrap(), Phase::Taxi);
+        assert_eq!(*restored.get_component::<Wingman>(new_wing).unwrap(), Wingman(new_lead));
+        assert!(!restored.has_component::<Scratch>(new_lead));
+        assert_eq!(*restored.resource::<Episode>().unwrap(), Episode(7));
+    }
+
+    #[test]
+    fn test_snapshot_ron_round_trip() {
+        let (world, lead, wing) = world();
+        let registry = registry();
+
+        let text = registry.save_ron(&world).unwrap();
+        assert!(text.contains("Climb(rate:4.0)"));
+
+        let mut restored = World::new();
+        let spacer = restored.create_entity();
+        restored.remove_entity(spacer).unwrap();
+        let map = registry.load_ron(&mut restored, &text).unwrap();
+        check_restored(&restored, &map, lead, wing);
+    }
+
+    #[test]
+    fn test_snapshot_bincode_round_trip() {
+        let (world, lead, wing) = world();
+        let registry = registry();
+
+        let bytes = registry.save_bincode(&world).unwrap();
+        let mut restored = World::new();
+        let map = registry.load_bincode(&mut restored, &bytes).unwrap();
+        check_restored(&restored, &map, lead, wing);
+
+        // Loading needs every saved name to be registered
+        let mut partial = SnapshotRegistry::new();
+        partial.register_component::<Position>("Position");
+        assert!(matches!(partial.load_bincode(&mut World::new(), &bytes), Err(EcsError::Snapshot(_))));
+    }
+}
@@ -0,0 +1,194 @@
+use std::any::Any;
+use slotmap::SecondaryMap;
+
+use crate::{Component, ComponentStorage, EntityId};
+
+/// Sparse set storage keyed directly by entity
+///
+/// Components are packed into a dense array for iteration, with a sparse map from
+/// entity to dense index. Adding or removing a component never moves the entity
+/// between archetype tables, which suits tags and frequently toggled markers
+#[derive(Clone)]
+pub struct SparseSetStorage<T: Component> {
+    dense: Vec<T>,
+    entities: Vec<EntityId>,
+    sparse: SecondaryMap<EntityId, usize>,
+}
+
+impl<T: Component> Default for SparseSetStorage<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Component> SparseSetStorage<T> {
+    pub fn new() -> Self {
+        Self {
+            dense: Vec::new(),
+            entities: Vec::new(),
+            sparse: SecondaryMap::new(),
+        }
+    }
+
+    /// Insert or replace the entity's component, returning the previous value
+    pub fn insert(&mut self, entity: EntityId, component: T) -> Option<T> {
+        if let Some(&index) = self.sparse.get(entity) {
+            return Some(std::mem::replace(&mut self.dense[index], component));
+        }
+        self.sparse.insert(entity, self.dense.len());
+        self.dense.push(component);
+        self.entities.push(entity);
+        None
+    }
+
+    pub fn get(&self, entity: EntityId) -> Option<&T> {
+        self.sparse.get(entity).map(|&index| &self.dense[index])
+    }
+
+    pub fn get_mut(&mut self, entity: EntityId) -> Option<&mut T> {
+        let index = *self.sparse.get(entity)?;
+        self.dense.get_mut(index)
+    }
+
+    pub fn contains(&self, entity: EntityId) -> bool {
+        self.sparse.contains_key(entity)
+    }
+
+    /// Remove the entity's component, returning it if present
+    pub fn remove(&mut self, entity: EntityId) -> Option<T> {
+        let index = self.sparse.remove(entity)?;
+        self.entities.swap_remove(index);
+        let component = self.dense.swap_remove(index);
+        if let Some(&moved) = self.entities.get(index) {
+            self.sparse[moved] = index;
+        }
+        Some(component)
+    }
+
+    pub fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dense.is_empty()
+    }
+
+    /// Iterate `(entity, component)` pairs in dense order
+    pub fn iter(&self) -> impl Iterator<Item = (EntityId, &T)> {
+        self.entities.iter().copied().zip(self.dense.iter())
+    }
+
+    /// Iterate `(entity, component)` pairs in dense order, mutably
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (EntityId, &mut T)> {
+        self.entities.iter().copied().zip(self.dense.iter_mut())
+    }
+
+    /// Mutable borrows of several entities' components at once, in the order given
+    ///
+    /// Entities without the component (or repeated ones) yield None
+    pub fn get_many_mut<I>(&mut self, entities: I) -> Vec<Option<&mut T>>
+    where
+        I: IntoIterator<Item = EntityId>,
+    {
+        let mut wanted: Vec<(usize, usize)> = Vec::new();
+        let mut out: Vec<Option<&mut T>> = Vec::new();
+        for (position, entity) in entities.into_iter().enumerate() {
+            if let Some(&index) = self.sparse.get(entity) {
+                wanted.push((index, position));
+            }
+            out.push(None);
+        }
+
+        // Walk the dense array once in index order, handing each borrow to its slot
+        wanted.sort_unstable();
+        let mut wanted = wanted.into_iter().peekable();
+        for (index, component) in self.dense.iter_mut().enumerate() {
+            if let Some(&(_, position)) = wanted.peek().filter(|(want, _)| *want == index) {
+                out[position] = Some(component);
+                while wanted.peek().is_some_and(|(want, _)| *want == index) {
+                    wanted.next();
+                }
+            }
+        }
+        out
+    }
+}
+
+impl<T: Component> ComponentStorage for SparseSetStorage<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+    fn iter_any(&self) -> Box<dyn Iterator<Item = &dyn Any> + '_> {
+        Box::new(self.dense.iter().map(|component| component as &dyn Any))
+    }
+
+    fn index_of(&self, entity: EntityId) -> Option<usize> {
+        self.sparse.get(entity).copied()
+    }
+
+    fn swap_remove(&mut self, index: usize) {
+        let entity = self.entities[index];
+        self.remove(entity);
+    }
+
+    fn swap_remove_into(&mut self, index: usize, dst: &mut dyn ComponentStorage) {
+        let entity = self.entities[index];
+        if let Some(component) = self.remove(entity) {
+            dst.as_any_mut()
+                .downcast_mut::<Self>()
+                .expect("component moved into a storage of a different type")
+                .insert(entity, component);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slotmap::SlotMap;
+
+    #[test]
+    fn test_sparse_set_insert_remove() {
+        let mut ids: SlotMap<EntityId, ()> = SlotMap::new();
+        let (a, b, c) = (ids.insert(()), ids.insert(()), ids.insert(()));
+
+        let mut storage = SparseSetStorage::new();
+        storage.insert(a, 1);
+        storage.insert(b, 2);
+        storage.insert(c, 3);
+        assert_eq!(storage.insert(b, 20), Some(2));
+
+        // Removing from the middle swaps the last element into its slot
+        assert_eq!(storage.remove(a), Some(1));
+        assert_eq!(storage.remove(a), None);
+        assert_eq!(storage.get(c), Some(&3));
+        assert_eq!(storage.index_of(c), Some(0));
+        assert_eq!(ComponentStorage::len(&storage), 2);
+
+        let values: Vec<i32> = storage.iter_any().map(|v| *v.downcast_ref::<i32>().unwrap()).collect();
+        assert_eq!(values, vec![3, 20]);
+    }
+
+    #[test]
+    fn test_sparse_set_get_many_mut() {
+        let mut ids: SlotMap<EntityId, ()> = SlotMap::new();
+        let (a, b, missing) = (ids.insert(()), ids.insert(()), ids.insert(()));
+
+        let mut storage = SparseSetStorage::new();
+        storage.insert(a, 1);
+        storage.insert(b, 2);
+
+        let borrows = storage.get_many_mut([b, missing, a]);
+        let values: Vec<Option<i32>> = borrows.into_iter().map(|v| v.map(|v| *v)).collect();
+        assert_eq!(values, vec![Some(2), None, Some(1)]);
+    }
+}
@@ -1,12 +1,42 @@
-use crate::{World, EcsResult};
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Range;
+use std::sync::Arc;
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use crate::change_detection::{SystemTicks, SystemTicksGuard};
+use crate::command::SystemOrderGuard;
+use crate::access::AccessGuard;
+use crate::{Access, EcsError, EcsResult, RunCriteria, RunSteps, World};
 
 /// Trait for systems that operate on the ECS world
-pub trait System {
+///
+/// Systems are exclusive by default: `run` gets `&mut World` and nothing else
+/// runs alongside it. Systems that declare their `access` and implement
+/// `run_shared` can run in parallel with systems they don't conflict with
+pub trait System: Send {
     /// System name for debugging and identification
     fn name(&self) -> &str;
     
+    /// Component and resource types this system reads and writes
+    fn access(&self) -> Access {
+        Access::exclusive()
+    }
+    
     /// Run the system for one update cycle
-    fn run(&mut self, world: &mut World, delta_time: f32) -> EcsResult<()>;
+    fn run(&mut self, world: &mut World, delta_time: f32) -> EcsResult<()> {
+        self.run_shared(world, delta_time)
+    }
+    
+    /// Run the system for one update cycle through a shared World borrow
+    ///
+    /// Called instead of `run` when `access` isn't exclusive. Every query and
+    /// resource borrowed here must be covered by the declared access; debug
+    /// builds check, and fail undeclared borrows with EcsError::UndeclaredAccess
+    fn run_shared(&mut self, _world: &World, _delta_time: f32) -> EcsResult<()> {
+        Err(EcsError::SystemError(format!("{} implements neither run nor run_shared", self.name())))
+    }
     
     /// Called when the system is first added to the world
     fn initialize(&mut self, _world: &mut World) -> EcsResult<()> {
@@ -17,32 +47,342 @@ pub trait System {
     fn cleanup(&mut self, _world: &mut World) -> EcsResult<()> {
         Ok(())
     }
+    
+    /// Copy of this system, state included, for a forked dispatcher
+    ///
+    /// Systems that return None (the default) can't be forked
+    fn fork(&self) -> Option<Box<dyn System>> {
+        None
+    }
+}
+
+/// Name of the stage systems run in when their config doesn't name one
+pub const DEFAULT_STAGE: &str = "update";
+
+/// Where and when a system runs relative to the others
+///
+/// `before` and `after` take system names (`System::name`) or labels. They
+/// order systems within a stage; stages always run in the order they were added
+#[derive(Debug, Clone, Default)]
+pub struct SystemConfig {
+    stage: Option<String>,
+    labels: Vec<String>,
+    before: Vec<String>,
+    after: Vec<String>,
+    criteria: RunCriteria,
 }
 
-/// System dispatcher manages and runs systems in order
+impl SystemConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run in the named stage instead of `DEFAULT_STAGE`
+    pub fn in_stage(mut self, stage: &str) -> Self {
+        self.stage = Some(stage.to_string());
+        self
+    }
+
+    /// Extra name other systems can order against, shared by any number of systems
+    pub fn label(mut self, label: &str) -> Self {
+        self.labels.push(label.to_string());
+        self
+    }
+
+    /// Run before every system with this name or label
+    pub fn before(mut self, label: &str) -> Self {
+        self.before.push(label.to_string());
+        self
+    }
+
+    /// Run after every system with this name or label
+    pub fn after(mut self, label: &str) -> Self {
+        self.after.push(label.to_string());
+        self
+    }
+
+    /// Decide per frame whether (and how many times) the system runs
+    pub fn run_criteria(mut self, criteria: RunCriteria) -> Self {
+        self.criteria = criteria;
+        self
+    }
+}
+
+/// Consecutive stages sharing one run criteria
+///
+/// With a fixed timestep, each substep runs every stage in the group before
+/// the next substep starts, so e.g. forces and integration stay interleaved
+#[derive(Clone)]
+struct StageGroup {
+    stages: Range<usize>,
+    criteria: RunCriteria,
+}
+
+/// A registered system with its scheduling metadata
+struct SystemEntry {
+    system: Box<dyn System>,
+    /// Declared access, captured when the system was added; shared with
+    /// the AccessGuard that checks it while the system runs
+    access: Arc<Access>,
+    config: SystemConfig,
+    /// Registration position, used to break ordering ties
+    added: usize,
+    /// Change tick of the system's latest run; 0 before it first runs
+    last_run: u64,
+}
+
+impl SystemEntry {
+    /// Copy the entry with a fork of its system
+    fn fork(&self) -> EcsResult<Self> {
+        let system = self
+            .system
+            .fork()
+            .ok_or_else(|| EcsError::NotCloneable(format!("system {}", self.system.name())))?;
+        Ok(Self {
+            system,
+            access: self.access.clone(),
+            config: self.config.clone(),
+            added: self.added,
+            last_run: self.last_run,
+        })
+    }
+    
+    /// Start one run of the system at change tick `this_run`
+    ///
+    /// The returned guard lets queries in the system see what changed since
+    /// its previous run, and stamps the system's own writes with the new tick
+    fn start_run(&mut self, this_run: u64) -> SystemTicksGuard {
+        let last_run = std::mem::replace(&mut self.last_run, this_run);
+        SystemTicksGuard::enter(SystemTicks { last_run, this_run })
+    }
+}
+
+/// System dispatcher manages and runs systems
+///
+/// Systems run stage by stage. Inside a stage they are sorted by their
+/// `before`/`after` constraints, falling back to registration order, then
+/// grouped into batches of consecutive systems that neither conflict nor are
+/// ordered against each other. The systems inside a batch run in parallel, so
+/// results don't depend on the number of threads
+///
+/// Run criteria on stage groups and on individual systems decide how often
+/// they run, e.g. physics substepping at a fixed rate under a variable frame time
 pub struct SystemDispatcher {
-    systems: Vec<Box<dyn System>>,
+    stages: Vec<String>,
+    stage_groups: Vec<StageGroup>,
+    /// Systems in execution order once the schedule is built
+    systems: Vec<SystemEntry>,
+    /// Ranges of `systems` that can run at the same time
+    batches: Vec<Range<usize>>,
+    /// Stage index of each batch
+    batch_stages: Vec<usize>,
+    /// Whether systems were added since the schedule was last built
+    dirty: bool,
+    /// Pool for parallel batches; rayon's global pool when None
+    /// Forked dispatchers share their original's pool
+    thread_pool: Option<Arc<ThreadPool>>,
 }
 
 impl SystemDispatcher {
-    /// Create a new system dispatcher
+    /// Create a new system dispatcher with only the default stage
     pub fn new() -> Self {
         Self {
+            stages: vec![DEFAULT_STAGE.to_string()],
+            stage_groups: Vec::new(),
             systems: Vec::new(),
+            batches: Vec::new(),
+            batch_stages: Vec::new(),
+            dirty: false,
+            thread_pool: None,
         }
     }
     
-    /// Add a system to the dispatcher
-    pub fn add_system<S: System + 'static>(&mut self, mut system: S, world: &mut World) -> EcsResult<()> {
+    /// Create a dispatcher running parallel batches on its own pool of `num_threads` threads
+    pub fn with_threads(num_threads: usize) -> EcsResult<Self> {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .build()
+            .map_err(|err| EcsError::SystemError(err.to_string()))?;
+        Ok(Self {
+            thread_pool: Some(Arc::new(pool)),
+            ..Self::new()
+        })
+    }
+    
+    /// Copy the dispatcher to step a forked World
+    ///
+    /// Every system is copied with `System::fork`, and run criteria keep
+    /// their timers, so the copy runs exactly like the original would.
+    /// Returns EcsError::NotCloneable if any system can't be forked
+    pub fn fork(&self) -> EcsResult<Self> {
+        Ok(Self {
+            stages: self.stages.clone(),
+            stage_groups: self.stage_groups.clone(),
+            systems: self.systems.iter().map(SystemEntry::fork).collect::<EcsResult<_>>()?,
+            batches: self.batches.clone(),
+            batch_stages: self.batch_stages.clone(),
+            dirty: self.dirty,
+            thread_pool: self.thread_pool.clone(),
+        })
+    }
+    
+    /// Append a stage, running after every existing stage
+    ///
+    /// Adding a stage that already exists does nothing
+    pub fn add_stage(&mut self, name: &str) {
+        if !self.stages.iter().any(|stage| stage == name) {
+            self.stages.push(name.to_string());
+            self.dirty = true;
+        }
+    }
+    
+    /// Run a group of consecutive stages under one run criteria
+    ///
+    /// With `RunCriteria::fixed_timestep`, every substep runs all the group's
+    /// stages in order with the fixed delta time. Setting criteria for the exact
+    /// same group again replaces them
+    pub fn set_stage_criteria(&mut self, stages: &[&str], criteria: RunCriteria) -> EcsResult<()> {
+        let indices = stages
+            .iter()
+            .map(|&name| {
+                self.stages
+                    .iter()
+                    .position(|stage| stage == name)
+                    .ok_or_else(|| EcsError::StageNotFound(name.to_string()))
+            })
+            .collect::<EcsResult<Vec<_>>>()?;
+        
+        let (Some(&first), Some(&last)) = (indices.first(), indices.last()) else {
+            return Ok(());
+        };
+        if indices.windows(2).any(|pair| pair[1] != pair[0] + 1) {
+            return Err(EcsError::SystemError(format!("stages {:?} are not consecutive", stages)));
+        }
+        
+        let range = first..last + 1;
+        self.stage_groups.retain(|group| group.stages != range);
+        if let Some(group) = self.stage_groups.iter().find(|group| group.stages.start < range.end && range.start < group.stages.end) {
+            return Err(EcsError::SystemError(format!(
+                "stages {:?} overlap the group starting at {}",
+                stages,
+                self.stages[group.stages.start]
+            )));
+        }
+        self.stage_groups.push(StageGroup { stages: range, criteria });
+        Ok(())
+    }
+    
+    /// Add a system to the default stage, ordered by registration
+    pub fn add_system<S: System + 'static>(&mut self, system: S, world: &mut World) -> EcsResult<()> {
+        self.add_system_with(system, SystemConfig::new(), world)
+    }
+    
+    /// Add a system with a stage, labels, ordering constraints and run criteria
+    pub fn add_system_with<S: System + 'static>(&mut self, mut system: S, config: SystemConfig, world: &mut World) -> EcsResult<()> {
+        if let Some(stage) = &config.stage {
+            if !self.stages.contains(stage) {
+                return Err(EcsError::StageNotFound(stage.clone()));
+            }
+        }
         system.initialize(world)?;
-        self.systems.push(Box::new(system));
+        
+        self.systems.push(SystemEntry {
+            access: Arc::new(system.access()),
+            system: Box::new(system),
+            config,
+            added: self.systems.len(),
+            last_run: 0,
+        });
+        self.dirty = true;
         Ok(())
     }
     
-    /// Run all systems in order
+    /// Sort the systems and group them into batches
+    ///
+    /// Runs automatically before the next `run_systems`; call it directly to
+    /// catch unknown labels and ordering cycles early
+    pub fn build(&mut self) -> EcsResult<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        
+        // Start from registration order so ties always break the same way
+        self.systems.sort_by_key(|entry| entry.added);
+        let graph = OrderGraph::new(&self.stages, &self.systems)?;
+        let order = graph.sort(&self.systems)?;
+        
+        // Split into batches at stage boundaries, data conflicts and ordering edges
+        let mut batches: Vec<Range<usize>> = Vec::new();
+        let mut batch_stages = Vec::new();
+        for (position, &index) in order.iter().enumerate() {
+            let joins_last = batches.last().is_some_and(|batch| {
+                order[batch.clone()].iter().all(|&other| {
+                    graph.stage[other] == graph.stage[index]
+                        && !graph.successors[other].contains(&index)
+                        && !self.systems[other].access.conflicts_with(&self.systems[index].access)
+                })
+            });
+            match batches.last_mut() {
+                Some(batch) if joins_last => batch.end = position + 1,
+                _ => {
+                    batches.push(position..position + 1);
+                    batch_stages.push(graph.stage[index]);
+                }
+            }
+        }
+        
+        // Store the systems in execution order so every batch is a contiguous slice
+        let mut slots: Vec<Option<SystemEntry>> = std::mem::take(&mut self.systems).into_iter().map(Some).collect();
+        self.systems = order.iter().map(|&index| slots[index].take().expect("system scheduled twice")).collect();
+        self.batches = batches;
+        self.batch_stages = batch_stages;
+        self.dirty = false;
+        Ok(())
+    }
+    
+    /// Run one frame of `delta_time` seconds, then advance the world's event
+    /// channels and change trackers
+    ///
+    /// Stages run in order, as often as their group's criteria say. Commands
+    /// deferred by a batch's systems are applied once the whole batch has
+    /// finished, in system order, so later batches see the changes
     pub fn run_systems(&mut self, world: &mut World, delta_time: f32) -> EcsResult<()> {
-        for system in &mut self.systems {
-            system.run(world, delta_time)?;
+        if !(delta_time.is_finite() && delta_time >= 0.0) {
+            return Err(EcsError::InvalidDeltaTime(delta_time));
+        }
+        self.build()?;
+        
+        let mut stage = 0;
+        while stage < self.stages.len() {
+            let group = self.stage_groups.iter_mut().find(|group| group.stages.start == stage);
+            let Some(group) = group else {
+                self.run_stage(stage, world, delta_time)?;
+                stage += 1;
+                continue;
+            };
+            
+            let steps = group.criteria.poll(world, delta_time);
+            let stages = group.stages.clone();
+            for _ in 0..steps.count {
+                for stage in stages.clone() {
+                    self.run_stage(stage, world, steps.delta_time)?;
+                }
+            }
+            stage = stages.end;
+        }
+        
+        world.update_events();
+        world.clear_trackers();
+        Ok(())
+    }
+    
+    /// Run every batch of one stage once
+    fn run_stage(&mut self, stage: usize, world: &mut World, delta_time: f32) -> EcsResult<()> {
+        for (batch, &batch_stage) in self.batches.iter().zip(&self.batch_stages) {
+            if batch_stage == stage {
+                run_batch(&mut self.systems[batch.clone()], batch.start, self.thread_pool.as_deref(), world, delta_time)?;
+                world.apply_commands()?;
+            }
         }
         Ok(())
     }
@@ -51,6 +391,15 @@ impl SystemDispatcher {
     pub fn system_count(&self) -> usize {
         self.systems.len()
     }
+    
+    /// System names grouped by the batches they run in, in execution order
+    pub fn batches(&mut self) -> EcsResult<Vec<Vec<&str>>> {
+        self.build()?;
+        Ok(self.batches
+            .iter()
+            .map(|batch| self.systems[batch.clone()].iter().map(|entry| entry.system.name()).collect())
+            .collect())
+    }
 }
 
 impl Default for SystemDispatcher {
@@ -59,10 +408,207 @@ impl Default for SystemDispatcher {
     }
 }
 
+/// Ordering constraints between systems, indexed by registration order
+struct OrderGraph {
+    /// Stage index of each system
+    stage: Vec<usize>,
+    /// Systems that must run after each system, within its stage
+    successors: Vec<Vec<usize>>,
+}
+
+impl OrderGraph {
+    fn new(stages: &[String], systems: &[SystemEntry]) -> EcsResult<Self> {
+        let stage = systems
+            .iter()
+            .map(|entry| {
+                let name = entry.config.stage.as_deref().unwrap_or(DEFAULT_STAGE);
+                stages
+                    .iter()
+                    .position(|stage| stage == name)
+                    .ok_or_else(|| EcsError::StageNotFound(name.to_string()))
+            })
+            .collect::<EcsResult<Vec<_>>>()?;
+        
+        // Every system answers to its own name and to its labels
+        let mut labelled: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (index, entry) in systems.iter().enumerate() {
+            labelled.entry(entry.system.name()).or_default().push(index);
+            for label in &entry.config.labels {
+                labelled.entry(label.as_str()).or_default().push(index);
+            }
+        }
+        let resolve = |label: &str| {
+            labelled
+                .get(label)
+                .ok_or_else(|| EcsError::UnknownSystemLabel(label.to_string()))
+        };
+        
+        let mut graph = Self {
+            stage,
+            successors: vec![Vec::new(); systems.len()],
+        };
+        for (index, entry) in systems.iter().enumerate() {
+            for label in &entry.config.before {
+                for &later in resolve(label)? {
+                    graph.add_edge(index, later, systems)?;
+                }
+            }
+            for label in &entry.config.after {
+                for &earlier in resolve(label)? {
+                    graph.add_edge(earlier, index, systems)?;
+                }
+            }
+        }
+        Ok(graph)
+    }
+    
+    fn add_edge(&mut self, from: usize, to: usize, systems: &[SystemEntry]) -> EcsResult<()> {
+        // Earlier stages already run first; a constraint against the stage order can't hold
+        match self.stage[from].cmp(&self.stage[to]) {
+            Ordering::Less => Ok(()),
+            Ordering::Greater => Err(EcsError::SystemOrderCycle(format!(
+                "{} -> {} contradicts the stage order",
+                systems[from].system.name(),
+                systems[to].system.name()
+            ))),
+            Ordering::Equal if from == to => Ok(()),
+            Ordering::Equal => {
+                self.successors[from].push(to);
+                Ok(())
+            }
+        }
+    }
+    
+    /// Topological sort, picking the earliest stage and then the earliest
+    /// registered system whenever several are ready
+    fn sort(&self, systems: &[SystemEntry]) -> EcsResult<Vec<usize>> {
+        let mut indegree = vec![0; systems.len()];
+        for &to in self.successors.iter().flatten() {
+            indegree[to] += 1;
+        }
+        
+        let mut ready: BTreeSet<(usize, usize)> = (0..systems.len())
+            .filter(|&index| indegree[index] == 0)
+            .map(|index| (self.stage[index], index))
+            .collect();
+        let mut order = Vec::with_capacity(systems.len());
+        while let Some((_, index)) = ready.pop_first() {
+            order.push(index);
+            for &next in &self.successors[index] {
+                indegree[next] -= 1;
+                if indegree[next] == 0 {
+                    ready.insert((self.stage[next], next));
+                }
+            }
+        }
+        
+        if order.len() < systems.len() {
+            return Err(EcsError::SystemOrderCycle(self.describe_cycle(&indegree, systems)));
+        }
+        Ok(order)
+    }
+    
+    /// Name the systems on one cycle among those the sort couldn't place
+    fn describe_cycle(&self, indegree: &[usize], systems: &[SystemEntry]) -> String {
+        // Every unplaced system still waits on some unplaced predecessor,
+        // so walking predecessors must eventually revisit a system
+        let predecessor = |index: usize| {
+            (0..systems.len())
+                .find(|&from| indegree[from] > 0 && self.successors[from].contains(&index))
+                .expect("unplaced system without an unplaced predecessor")
+        };
+        
+        let mut path = Vec::new();
+        let mut current = (0..systems.len()).find(|&index| indegree[index] > 0).expect("no unplaced system");
+        while !path.contains(&current) {
+            path.push(current);
+            current = predecessor(current);
+        }
+        let start = path.iter().position(|&index| index == current).unwrap_or(0);
+        // The path runs against the edges and ends on `current`; reversed, it
+        // runs along them, and starting it at `current` closes the loop
+        let mut cycle = vec![systems[current].system.name()];
+        cycle.extend(path[start..].iter().rev().map(|&index| systems[index].system.name()));
+        cycle.join(" -> ")
+    }
+}
+
+/// Run one batch; `first` is the position of its first system in the dispatcher
+///
+/// Each system's run criteria are polled first, then systems run as many
+/// times as their criteria say. Returns the error of the first failing system
+/// in execution order
+fn run_batch(
+    systems: &mut [SystemEntry],
+    first: usize,
+    thread_pool: Option<&ThreadPool>,
+    world: &mut World,
+    delta_time: f32,
+) -> EcsResult<()> {
+    let steps: Vec<RunSteps> = systems
+        .iter_mut()
+        .map(|entry| entry.config.criteria.poll(world, delta_time))
+        .collect();
+    
+    if let ([entry], [steps]) = (&mut *systems, steps.as_slice()) {
+        let _order = SystemOrderGuard::enter(first);
+        for _ in 0..steps.count {
+            let _ticks = entry.start_run(world.increment_change_tick());
+            if entry.access.is_exclusive() {
+                entry.system.run(world, steps.delta_time)?;
+            } else {
+                let _access = AccessGuard::enter(&entry.access);
+                entry.system.run_shared(world, steps.delta_time)?;
+            }
+        }
+        return Ok(());
+    }
+    
+    // Ticks are handed out in system order before the batch starts, so they
+    // don't depend on which thread gets going first
+    let ticks: Vec<Vec<u64>> = steps
+        .iter()
+        .map(|steps| (0..steps.count).map(|_| world.increment_change_tick()).collect())
+        .collect();
+    let world: &World = world;
+    let results = match thread_pool {
+        Some(pool) => pool.install(|| run_parallel(systems, &steps, &ticks, first, world)),
+        None => run_parallel(systems, &steps, &ticks, first, world),
+    };
+    results.into_iter().collect()
+}
+
+/// Run systems concurrently on the current rayon pool, keeping results in system order
+///
+/// `ticks` holds each system's change tick for every one of its runs
+fn run_parallel(
+    systems: &mut [SystemEntry],
+    steps: &[RunSteps],
+    ticks: &[Vec<u64>],
+    first: usize,
+    world: &World,
+) -> Vec<EcsResult<()>> {
+    systems
+        .par_iter_mut()
+        .zip(steps)
+        .zip(ticks)
+        .enumerate()
+        .map(|(offset, ((entry, steps), ticks))| {
+            let _order = SystemOrderGuard::enter(first + offset);
+            let _access = AccessGuard::enter(&entry.access);
+            ticks.iter().try_for_each(|&tick| {
+                let _ticks = entry.start_run(tick);
+                entry.system.run_shared(world, steps.delta_time)
+            })
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::World;
+    use std::any::TypeId;
+    use crate::{Changed, Commands, EcsError, Entity, EventReader, Events, RunCriteria, World};
 
     struct TestSystem {
         name: String,
@@ -101,4 +647,524 @@ mod tests {
         
         dispatcher.run_systems(&mut world, 0.016).unwrap();
     }
-}
\ No newline at end of file
+
+    struct Landed(usize);
+
+    struct LandingSystem {
+        frame: usize,
+    }
+
+    impl System for LandingSystem {
+        fn name(&self) -> &str {
+            "landing"
+        }
+
+        fn initialize(&mut self, world: &mut World) -> EcsResult<()> {
+            world.add_event::<Landed>();
+            Ok(())
+        }
+
+        fn run(&mut self, world: &mut World, _delta_time: f32) -> EcsResult<()> {
+            if self.frame == 0 {
+                world.send_event(Landed(self.frame))?;
+            }
+            self.frame += 1;
+            Ok(())
+        }
+    }
+
+    /// Frames at which the termination system saw a landing
+    struct Terminations(Vec<usize>);
+
+    struct TerminationSystem {
+        reader: EventReader<Landed>,
+    }
+
+    impl System for TerminationSystem {
+        fn name(&self) -> &str {
+            "termination"
+        }
+
+        fn run(&mut self, world: &mut World, _delta_time: f32) -> EcsResult<()> {
+            let events = world.resource::<Events<Landed>>()?;
+            let mut terminations = world.resource_mut::<Terminations>()?;
+            terminations.0.extend(self.reader.read(&events).map(|landed| landed.0));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_events_between_systems() {
+        let mut world = World::new();
+        world.insert_resource(Terminations(Vec::new()));
+        let mut dispatcher = SystemDispatcher::new();
+        dispatcher.add_system(LandingSystem { frame: 0 }, &mut world).unwrap();
+        dispatcher
+            .add_system(TerminationSystem { reader: EventReader::default() }, &mut world)
+            .unwrap();
+
+        for _ in 0..3 {
+            dispatcher.run_systems(&mut world, 0.016).unwrap();
+        }
+
+        // The reader sees the landing exactly once, and the dispatcher's
+        // per-frame updates drop it after one extra frame
+        assert_eq!(world.resource::<Terminations>().unwrap().0, vec![0]);
+        assert!(world.resource::<Events<Landed>>().unwrap().is_empty());
+        assert!(matches!(world.send_event(0u8), Err(EcsError::ResourceNotFound(_))));
+    }
+
+    struct Altitude(f32);
+
+    /// Replaces every aircraft below ground with a piece of debris
+    struct CrashSystem;
+
+    impl System for CrashSystem {
+        fn name(&self) -> &str {
+            "crash"
+        }
+
+        fn run(&mut self, world: &mut World, _delta_time: f32) -> EcsResult<()> {
+            let mut commands = Commands::new();
+            let mut query = world.query::<(Entity, &Altitude)>()?;
+            for (entity, altitude) in query.iter() {
+                if altitude.0 < 0.0 {
+                    commands.remove_entity(entity);
+                    let debris = commands.create_entity();
+                    commands.add_component(debris, Altitude(0.0));
+                }
+            }
+            drop(query);
+            world.defer(commands);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_deferred_commands_applied_after_system() {
+        let mut world = World::new();
+        for altitude in [-1.0, 100.0, -5.0] {
+            let entity = world.create_entity();
+            world.add_component(entity, Altitude(altitude)).unwrap();
+        }
+
+        let mut dispatcher = SystemDispatcher::new();
+        dispatcher.add_system(CrashSystem, &mut world).unwrap();
+        dispatcher.run_systems(&mut world, 0.016).unwrap();
+
+        let mut query = world.query::<&Altitude>().unwrap();
+        let mut altitudes: Vec<f32> = query.iter().map(|altitude| altitude.0).collect();
+        altitudes.sort_by(f32::total_cmp);
+        assert_eq!(altitudes, vec![0.0, 0.0, 100.0]);
+    }
+
+    /// Shared-access system whose behaviour is a plain function
+    struct FnSystem {
+        name: &'static str,
+        access: Access,
+        body: fn(&World, f32) -> EcsResult<()>,
+    }
+
+    impl System for FnSystem {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn access(&self) -> Access {
+            self.access.clone()
+        }
+
+        fn run_shared(&mut self, world: &World, delta_time: f32) -> EcsResult<()> {
+            (self.body)(world, delta_time)
+        }
+    }
+
+    struct Velocity(f32);
+    struct Spin(f32);
+    struct Tag(usize);
+
+    /// Sum of altitudes recorded every frame
+    struct Log(Vec<f32>);
+
+    fn spawn_tag(world: &World, tag: usize) {
+        let mut commands = Commands::new();
+        let entity = commands.create_entity();
+        commands.add_component(entity, Tag(tag));
+        world.defer(commands);
+    }
+
+    fn parallel_dispatcher(world: &mut World, threads: usize) -> SystemDispatcher {
+        let mut dispatcher = SystemDispatcher::with_threads(threads).unwrap();
+        let systems = [
+            FnSystem {
+                name: "climb",
+                access: Access::new().write::<Altitude>().read::<Velocity>(),
+                body: |world, dt| {
+                    for (altitude, velocity) in world.query::<(&mut Altitude, &Velocity)>()?.iter() {
+                        altitude.0 += velocity.0 * dt;
+                    }
+                    spawn_tag(world, 0);
+                    Ok(())
+                },
+            },
+            FnSystem {
+                name: "spin",
+                access: Access::new().write::<Spin>(),
+                body: |world, dt| {
+                    for spin in world.query::<&mut Spin>()?.iter() {
+                        spin.0 += dt;
+                    }
+                    spawn_tag(world, 1);
+                    Ok(())
+                },
+            },
+            FnSystem {
+                name: "log",
+                access: Access::new().read::<Altitude>().write_resource::<Log>(),
+                body: |world, _| {
+                    let total = world.query::<&Altitude>()?.iter().map(|altitude| altitude.0).sum();
+                    world.resource_mut::<Log>()?.0.push(total);
+                    Ok(())
+                },
+            },
+            FnSystem {
+                name: "accelerate",
+                access: Access::new().write::<Velocity>(),
+                body: |world, dt| {
+                    for velocity in world.query::<&mut Velocity>()?.iter() {
+                        velocity.0 += 9.81 * dt;
+                    }
+                    Ok(())
+                },
+            },
+        ];
+        for system in systems {
+            dispatcher.add_system(system, world).unwrap();
+        }
+        dispatcher
+    }
+
+    fn run_parallel_world(threads: usize) -> (Vec<f32>, Vec<usize>, Vec<u64>) {
+        let mut world = World::new();
+        world.insert_resource(Log(Vec::new()));
+        for i in 0..100 {
+            let entity = world.create_entity();
+            world.add_component(entity, Altitude(i as f32)).unwrap();
+            world.add_component(entity, Velocity(1.0)).unwrap();
+            world.add_component(entity, Spin(0.0)).unwrap();
+        }
+
+        let mut dispatcher = parallel_dispatcher(&mut world, threads);
+        assert_eq!(dispatcher.batches().unwrap(), vec![vec!["climb", "spin"], vec!["log", "accelerate"]]);
+        for _ in 0..20 {
+            dispatcher.run_systems(&mut world, 0.01).unwrap();
+        }
+
+        let tags = world.query::<&Tag>().unwrap().iter().map(|tag| tag.0).collect();
+        let log = world.remove_resource::<Log>().unwrap().0;
+        let first = world.query::<Entity>().unwrap().iter().next().unwrap().id();
+        let ticks = [TypeId::of::<Altitude>(), TypeId::of::<Spin>(), TypeId::of::<Velocity>()]
+            .into_iter()
+            .map(|type_id| world.component_ticks(first, type_id).unwrap().changed())
+            .collect();
+        (log, tags, ticks)
+    }
+
+    #[test]
+    fn test_parallel_batches_are_deterministic() {
+        let (log, tags, ticks) = run_parallel_world(1);
+        assert_eq!(log.len(), 20);
+        assert_eq!(&tags[..4], &[0, 1, 0, 1]);
+        // Systems in a batch take their change ticks in system order
+        assert!(ticks[0] < ticks[1] && ticks[1] < ticks[2]);
+
+        for threads in [2, 4] {
+            assert_eq!(run_parallel_world(threads), (log.clone(), tags.clone(), ticks.clone()));
+        }
+    }
+
+    #[test]
+    fn test_exclusive_systems_run_alone() {
+        let mut world = World::new();
+        let mut dispatcher = parallel_dispatcher(&mut world, 2);
+        dispatcher.add_system(TestSystem::new("exclusive"), &mut world).unwrap();
+        dispatcher.add_system(CrashSystem, &mut world).unwrap();
+
+        assert_eq!(dispatcher.batches().unwrap().len(), 4);
+        assert_eq!(dispatcher.batches().unwrap()[2], vec!["exclusive"]);
+
+        // A system that only implements run has no shared entry point
+        assert!(matches!(TestSystem::new("t").run_shared(&world, 0.0), Err(EcsError::SystemError(_))));
+    }
+
+    #[test]
+    fn test_undeclared_access_fails() {
+        let mut world = World::new();
+        world.insert_resource(Log(Vec::new()));
+        let entity = world.create_entity();
+        world.add_component(entity, Velocity(1.0)).unwrap();
+        world.add_component(entity, Spin(0.0)).unwrap();
+
+        // Declares a read but writes; spin runs alongside it in the same batch
+        let mut dispatcher = SystemDispatcher::with_threads(2).unwrap();
+        let sneaky = FnSystem {
+            name: "sneaky",
+            access: Access::new().read::<Velocity>(),
+            body: |world, dt| {
+                for velocity in world.query::<&mut Velocity>()?.iter() {
+                    velocity.0 += dt;
+                }
+                Ok(())
+            },
+        };
+        let spin = FnSystem {
+            name: "spin",
+            access: Access::new().write::<Spin>(),
+            body: |world, dt| {
+                for spin in world.query::<&mut Spin>()?.iter() {
+                    spin.0 += dt;
+                }
+                Ok(())
+            },
+        };
+        dispatcher.add_system(sneaky, &mut world).unwrap();
+        dispatcher.add_system(spin, &mut world).unwrap();
+        assert_eq!(dispatcher.batches().unwrap(), vec![vec!["sneaky", "spin"]]);
+
+        for _ in 0..10 {
+            assert!(matches!(dispatcher.run_systems(&mut world, 0.1), Err(EcsError::UndeclaredAccess(_))));
+        }
+        assert_eq!(world.get_component::<Velocity>(entity).unwrap().0, 1.0);
+
+        // Undeclared resources fail too, and outside systems nothing is checked
+        let mut dispatcher = SystemDispatcher::new();
+        dispatcher
+            .add_system(
+                FnSystem {
+                    name: "logger",
+                    access: Access::new().read::<Velocity>(),
+                    body: |world, _| {
+                        world.resource_mut::<Log>()?.0.push(0.0);
+                        Ok(())
+                    },
+                },
+                &mut world,
+            )
+            .unwrap();
+        assert!(matches!(dispatcher.run_systems(&mut world, 0.1), Err(EcsError::UndeclaredAccess(_))));
+
+        // Change filters read the component's ticks, so they need it declared
+        let mut dispatcher = SystemDispatcher::new();
+        dispatcher
+            .add_system(
+                FnSystem {
+                    name: "watcher",
+                    access: Access::new().read::<Spin>(),
+                    body: |world, _| {
+                        world.query_filtered::<&Spin, Changed<Velocity>>()?;
+                        Ok(())
+                    },
+                },
+                &mut world,
+            )
+            .unwrap();
+        assert!(matches!(dispatcher.run_systems(&mut world, 0.1), Err(EcsError::UndeclaredAccess(_))));
+        world.resource_mut::<Log>().unwrap().0.push(0.0);
+        assert!(world.query::<&mut Velocity>().is_ok());
+    }
+
+    /// Appends its name to the Trace resource when it runs
+    struct TraceSystem(&'static str);
+
+    /// Names of the systems in the order they ran
+    struct Trace(Vec<&'static str>);
+
+    impl System for TraceSystem {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn access(&self) -> Access {
+            Access::new().write_resource::<Trace>()
+        }
+
+        fn run_shared(&mut self, world: &World, _delta_time: f32) -> EcsResult<()> {
+            world.resource_mut::<Trace>()?.0.push(self.0);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_stages_and_ordering_constraints() {
+        let mut world = World::new();
+        world.insert_resource(Trace(Vec::new()));
+        let mut dispatcher = SystemDispatcher::new();
+        for stage in ["forces", "integration", "telemetry"] {
+            dispatcher.add_stage(stage);
+        }
+
+        // Registered in the "wrong" order on purpose
+        let systems = [
+            ("telemetry", SystemConfig::new().in_stage("telemetry")),
+            ("integrate", SystemConfig::new().in_stage("integration")),
+            ("drag", SystemConfig::new().in_stage("forces").label("aero").after("gravity")),
+            ("lift", SystemConfig::new().in_stage("forces").label("aero")),
+            ("gravity", SystemConfig::new().in_stage("forces")),
+            ("sum_forces", SystemConfig::new().in_stage("forces").after("aero").after("gravity")),
+            ("input", SystemConfig::new().before("telemetry")),
+        ];
+        for (name, config) in systems {
+            dispatcher.add_system_with(TraceSystem(name), config, &mut world).unwrap();
+        }
+
+        dispatcher.run_systems(&mut world, 0.016).unwrap();
+        assert_eq!(
+            world.resource::<Trace>().unwrap().0,
+            vec!["input", "lift", "gravity", "drag", "sum_forces", "integrate", "telemetry"]
+        );
+    }
+
+    #[test]
+    fn test_ordering_errors() {
+        let mut world = World::new();
+        let mut dispatcher = SystemDispatcher::new();
+        let missing_stage = dispatcher.add_system_with(TraceSystem("a"), SystemConfig::new().in_stage("physics"), &mut world);
+        assert!(matches!(missing_stage, Err(EcsError::StageNotFound(_))));
+
+        dispatcher.add_system_with(TraceSystem("a"), SystemConfig::new().after("c"), &mut world).unwrap();
+        dispatcher.add_system_with(TraceSystem("b"), SystemConfig::new().after("a"), &mut world).unwrap();
+        assert!(matches!(dispatcher.build(), Err(EcsError::UnknownSystemLabel(label)) if label == "c"));
+
+        dispatcher.add_system_with(TraceSystem("c"), SystemConfig::new().after("b"), &mut world).unwrap();
+        match dispatcher.run_systems(&mut world, 0.016) {
+            Err(EcsError::SystemOrderCycle(cycle)) => assert_eq!(cycle, "a -> b -> c -> a"),
+            other => panic!("expected a cycle, got {:?}", other),
+        }
+
+        let mut dispatcher = SystemDispatcher::new();
+        dispatcher.add_stage("late");
+        dispatcher.add_system_with(TraceSystem("early"), SystemConfig::new().after("late"), &mut world).unwrap();
+        dispatcher.add_system_with(TraceSystem("late"), SystemConfig::new().in_stage("late"), &mut world).unwrap();
+        assert!(matches!(dispatcher.build(), Err(EcsError::SystemOrderCycle(_))));
+    }
+
+    struct Paused(bool);
+
+    #[test]
+    fn test_run_criteria_and_substeps() {
+        let mut world = World::new();
+        world.insert_resource(Trace(Vec::new()));
+        world.insert_resource(Paused(false));
+        let mut dispatcher = SystemDispatcher::new();
+        for stage in ["forces", "integration", "telemetry"] {
+            dispatcher.add_stage(stage);
+        }
+        dispatcher
+            .set_stage_criteria(&["forces", "integration"], RunCriteria::fixed_timestep(0.25))
+            .unwrap();
+        assert!(dispatcher.set_stage_criteria(&["forces", "telemetry"], RunCriteria::Always).is_err());
+
+        let systems = [
+            ("input", SystemConfig::new().run_criteria(RunCriteria::resource_matches::<Paused>(|p| !p.0))),
+            ("force", SystemConfig::new().in_stage("forces")),
+            ("integrate", SystemConfig::new().in_stage("integration")),
+            ("telemetry", SystemConfig::new().in_stage("telemetry").run_criteria(RunCriteria::every_n_ticks(2))),
+        ];
+        for (name, config) in systems {
+            dispatcher.add_system_with(TraceSystem(name), config, &mut world).unwrap();
+        }
+
+        // 0.6s is two whole substeps, interleaved across both physics stages
+        dispatcher.run_systems(&mut world, 0.6).unwrap();
+        assert_eq!(world.resource::<Trace>().unwrap().0, vec!["input", "force", "integrate", "force", "integrate"]);
+
+        // The 0.1s left over plus 0.2s makes one more substep
+        world.resource_mut::<Trace>().unwrap().0.clear();
+        world.insert_resource(Paused(true));
+        dispatcher.run_systems(&mut world, 0.2).unwrap();
+        assert_eq!(world.resource::<Trace>().unwrap().0, vec!["force", "integrate", "telemetry"]);
+
+        // Frame times that can't be simulated are rejected before anything runs
+        world.resource_mut::<Trace>().unwrap().0.clear();
+        for bad in [f32::INFINITY, f32::NAN, -0.5] {
+            assert!(matches!(dispatcher.run_systems(&mut world, bad), Err(EcsError::InvalidDeltaTime(_))));
+        }
+        assert!(world.resource::<Trace>().unwrap().0.is_empty());
+    }
+
+    struct Mass(f32);
+
+    /// Number of changed masses seen on each run
+    struct Recomputed(Vec<usize>);
+
+    #[test]
+    fn test_change_detection_per_system() {
+        let mut world = World::new();
+        world.insert_resource(Recomputed(Vec::new()));
+        let light = world.create_entity();
+        world.add_component(light, Mass(1.0)).unwrap();
+        let heavy = world.create_entity();
+        world.add_component(heavy, Mass(9.0)).unwrap();
+
+        let mut dispatcher = SystemDispatcher::new();
+        let inertia = FnSystem {
+            name: "inertia",
+            access: Access::new().read::<Mass>().write_resource::<Recomputed>(),
+            body: |world, _| {
+                let count = world.query_filtered::<&Mass, Changed<Mass>>()?.len();
+                world.resource_mut::<Recomputed>()?.0.push(count);
+                Ok(())
+            },
+        };
+        dispatcher.add_system(inertia, &mut world).unwrap();
+
+        // The first run sees every mass, the second none, the third only the one written since
+        dispatcher.run_systems(&mut world, 0.1).unwrap();
+        dispatcher.run_systems(&mut world, 0.1).unwrap();
+        world.get_component_mut::<Mass>(heavy).unwrap().0 = 10.0;
+        dispatcher.run_systems(&mut world, 0.1).unwrap();
+        assert_eq!(world.resource::<Recomputed>().unwrap().0, vec![2, 0, 1]);
+
+        // Outside systems, changes count from the end of the last frame
+        assert!(world.query_filtered::<Entity, Changed<Mass>>().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_dispatcher_fork() {
+        #[derive(Clone)]
+        struct Counter(u32);
+        
+        impl System for Counter {
+            fn name(&self) -> &str {
+                "counter"
+            }
+            
+            fn run(&mut self, world: &mut World, _delta_time: f32) -> EcsResult<()> {
+                self.0 += 1;
+                world.insert_resource(self.0);
+                Ok(())
+            }
+            
+            fn fork(&self) -> Option<Box<dyn System>> {
+                Some(Box::new(self.clone()))
+            }
+        }
+        
+        let mut world = World::new();
+        world.register_cloneable_resource::<u32>();
+        let mut dispatcher = SystemDispatcher::new();
+        let config = SystemConfig::new().run_criteria(RunCriteria::fixed_timestep(1.0));
+        dispatcher.add_system_with(Counter(0), config, &mut world).unwrap();
+        dispatcher.run_systems(&mut world, 1.5).unwrap();
+        
+        // The fork keeps both the system's count and the half step left over
+        let mut fork_world = world.fork().unwrap();
+        let mut fork = dispatcher.fork().unwrap();
+        fork.run_systems(&mut fork_world, 0.5).unwrap();
+        assert_eq!(*fork_world.resource::<u32>().unwrap(), 2);
+        assert_eq!(*world.resource::<u32>().unwrap(), 1);
+        
+        dispatcher.add_system(TestSystem::new("unforkable"), &mut world).unwrap();
+        assert!(matches!(dispatcher.fork(), Err(EcsError::NotCloneable(_))));
+    }
+}
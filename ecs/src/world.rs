@@ -1,35 +1,106 @@
 // Import statements - these bring types and functions from other modules into scope
 use std::any::TypeId;           // Rust's runtime type identification
-use std::collections::HashMap;  // Hash table for key-value storage
-use slotmap::SlotMap;          // Efficient sparse array for entities
+use std::collections::{BTreeSet, HashMap};  // Ordered set and hash table
+use slotmap::{SecondaryMap, SlotMap};  // Efficient sparse array for entities
+use parking_lot::{MappedRwLockReadGuard, MappedRwLockWriteGuard, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 // Import our own types from other files in this crate
+use crate::access;
+use crate::archetype::{EntityLocation, EMPTY_ARCHETYPE};
+use crate::change_detection::current_system_ticks;
+use crate::command::current_system_order;
+use crate::resource::Resources;
 use crate::{
-    Component, ComponentStorage, Entity, EntityId, EcsError, EcsResult,
-    TypedComponentStorage,
+    Archetype, ArchetypeId, Bundle, BundleTypes, BundleWriter, Commands, Component, ComponentId, ComponentInfo, ComponentMask, ComponentStorage, ComponentTicks, Entity, EntityId,
+    EcsError, EcsResult, Event, Events, Query, QueryData, QueryFilter, Ref, RefMut, Resource, SparseSetStorage, StorageType,
 };
 
+/// Everything the World tracks per entity
+#[derive(Clone)]
+struct EntityMeta {
+    /// Archetype table and row holding the entity's table components
+    location: EntityLocation,
+    /// Bitset of every component the entity has, table and sparse-set alike
+    signature: ComponentMask,
+}
+
 /// The World manages all entities and their components
 /// 
 /// In Rust, 'pub' means this struct is public (visible outside this module)
 /// The World is the central data structure that stores all game objects (entities)
 /// and their data (components) in an efficient way
+/// 
+/// Components are stored in archetype tables: every entity with exactly the same
+/// set of component types shares one table, with one dense column per type
+/// Queries then walk contiguous arrays instead of chasing pointers per entity
 pub struct World {
     /// Entity storage using SlotMap for efficient allocation/deallocation
     /// SlotMap<K, V> is like Vec<V> but allows gaps and reuses indices
     /// This prevents the "dangling pointer" problem when entities are deleted
-    entities: SlotMap<EntityId, Entity>,
+    /// Each entity maps to its location (which archetype table and which row)
+    /// plus its signature: a bitset with one bit per registered component type
+    entities: SlotMap<EntityId, EntityMeta>,
     
-    /// Component storages indexed by TypeId
+    /// Metadata for every registered component type, indexed by TypeId
     /// HashMap<K, V> is Rust's hash table - like a dictionary in Python
     /// TypeId is Rust's way to identify types at runtime
-    /// Box<dyn ComponentStorage> is a "trait object" - it can hold any type
-    /// that implements ComponentStorage. 'dyn' means "dynamic dispatch"
-    component_storages: HashMap<TypeId, Box<dyn ComponentStorage>>,
+    /// ComponentInfo knows how to create an empty column for its type,
+    /// which is all we need to build new archetype tables
+    components: HashMap<TypeId, ComponentInfo>,
+    
+    /// Reverse lookup from ComponentId (a bit in a signature) to the component type
+    component_types: Vec<TypeId>,
+    
+    /// All archetype tables, indexed by ArchetypeId
+    /// Index 0 is always the empty archetype that new entities start in
+    archetypes: Vec<Archetype>,
+    
+    /// Find an archetype from its (sorted) set of component types
+    archetype_index: HashMap<Vec<TypeId>, ArchetypeId>,
+    
+    /// Storages for component types registered with StorageType::SparseSet
+    /// These are keyed by entity, so they sit outside the archetype tables
+    sparse_sets: HashMap<TypeId, RwLock<Box<dyn ComponentStorage>>>,
+    
+    /// Change ticks of sparse-set components, per type and then per entity
+    /// Archetype tables keep their own ticks next to each column
+    sparse_ticks: HashMap<TypeId, SecondaryMap<EntityId, ComponentTicks>>,
+    
+    /// Results of query_entities, keyed by the query's component mask
+    /// Every signature change updates the cached sets, so repeated queries
+    /// never rescan the world. BTreeSet keeps entities in a stable order
+    /// The Mutex lets query_entities fill the cache through '&self'
+    query_cache: Mutex<HashMap<ComponentMask, BTreeSet<EntityId>>>,
+    
+    /// World-wide singletons (clock, wind, RNG...), at most one per type
+    /// They belong to no entity, so they live outside the component storage
+    resources: Resources,
     
-    /// Track which entities have which component types (for queries)
-    /// This lets us quickly find "all entities with Position AND Velocity"
-    entity_component_masks: HashMap<EntityId, Vec<TypeId>>,
+    /// One function per registered event type that advances its Events<E> buffer
+    /// fn(&World) is a plain function pointer - each one knows its own E
+    event_updaters: Vec<fn(&World)>,
+    
+    /// Command buffers handed over by systems, waiting for the next sync point
+    /// Each is tagged with the position of the system that deferred it
+    /// The Mutex lets systems defer commands through '&self'
+    deferred: Mutex<Vec<(usize, Commands)>>,
+    
+    /// Counter that orders every change: component writes are stamped with it,
+    /// and each system run takes a new value so it can tell what changed since
+    /// its previous run. The dispatcher hands a batch's ticks out in system order
+    /// before running it, so parallel systems never take ticks themselves
+    change_tick: u64,
+    
+    /// Changes stamped after this tick are new to queries run outside a system
+    last_change_tick: u64,
+    
+    /// Entities that lost each component type, indexed by ComponentId
+    /// Double-buffered like events and advanced by clear_trackers
+    removed_components: Vec<Events<Entity>>,
+    
+    /// Highest slot index any entity has used
+    /// A handle that isn't live but falls under it must have been despawned
+    entity_slots: u32,
 }
 
 // Implementation block - this is where we define methods for the World struct
@@ -39,12 +110,27 @@ impl World {
     /// 
     /// In Rust, 'Self' refers to the current type (World)
     /// This is a "constructor" - it creates and returns a new World instance
-    /// All the collections start empty
+    /// All the collections start empty, except for the empty archetype
     pub fn new() -> Self {
+        let mut archetype_index = HashMap::new();
+        archetype_index.insert(Vec::new(), EMPTY_ARCHETYPE);
+        
         Self {
             entities: SlotMap::new(),                    // Empty entity storage
-            component_storages: HashMap::new(),          // Empty component storage
-            entity_component_masks: HashMap::new(),      // Empty component masks
+            components: HashMap::new(),                  // No registered components
+            component_types: Vec::new(),
+            archetypes: vec![Archetype::new(EMPTY_ARCHETYPE, Vec::new())],
+            archetype_index,
+            sparse_sets: HashMap::new(),
+            sparse_ticks: HashMap::new(),
+            query_cache: Mutex::new(HashMap::new()),
+            resources: Resources::default(),
+            event_updaters: Vec::new(),
+            deferred: Mutex::new(Vec::new()),
+            change_tick: 1,  // 0 means "before anything happened"
+            last_change_tick: 0,
+            removed_components: Vec::new(),
+            entity_slots: 0,
         }
     }
     
@@ -54,17 +140,88 @@ impl World {
     /// In Rust, you can only have ONE mutable reference at a time (prevents data races)
     /// This method modifies the World by adding a new entity
     pub fn create_entity(&mut self) -> Entity {
-        // insert_with_key is a SlotMap method that gives us the key (ID) when inserting
-        // The closure |id| Entity::new(id) creates an Entity with the generated ID
-        // Closures in Rust are like lambda functions in other languages
-        let id = self.entities.insert_with_key(|id| Entity::new(id));
+        // New entities have no components, so they live in the empty archetype
+        // .insert() gives us the key (ID) that SlotMap generated
+        let id = self.entities.insert(EntityMeta {
+            location: EntityLocation { archetype: EMPTY_ARCHETYPE, row: 0 },
+            signature: ComponentMask::new(),
+        });
+        
+        // Append a row to the empty archetype and remember where it went
+        let row = self.archetypes[EMPTY_ARCHETYPE].push_entity(id);
+        self.entities[id].location.row = row;
+        
+        let entity = Entity::new(id);
+        self.entity_slots = self.entity_slots.max(entity.index());
         
-        // Initialize empty component mask for this entity
-        // .insert() returns Option<T> of the old value, but we ignore it here
-        self.entity_component_masks.insert(id, Vec::new());
+        // An empty signature still matches cached queries for no components
+        self.update_signature(id, |_| {});
         
         // Return the new entity
-        Entity::new(id)
+        entity
+    }
+    
+    /// Create an entity with every component in a bundle
+    /// 
+    /// A bundle is a tuple of components like (Position, Velocity), a struct
+    /// implementing Bundle, or an EntityBuilder assembled at runtime
+    /// 
+    /// Calling add_component once per component moves the entity to a new
+    /// archetype table each time; spawn works out the final table up front
+    /// and writes the entity's row there directly, in one structural change
+    /// 
+    /// Returns EcsError::DuplicateComponent if the bundle has a type twice
+    pub fn spawn<B: Bundle>(&mut self, bundle: B) -> EcsResult<Entity> {
+        // Step 1: Register the bundle's types, and check none repeats
+        let mut types = BundleTypes::new(self);
+        bundle.register_components(&mut types);
+        let mut types = types.into_types();
+        types.sort();
+        if let Some(pair) = types.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(EcsError::DuplicateComponent(self.components[&pair[0]].name().to_string()));
+        }
+        
+        // Step 2: Find the table for the bundle's table components
+        // Sparse-set components live outside the tables, so they don't count
+        let table_types: Vec<TypeId> = types.iter().copied().filter(|&type_id| !self.is_sparse(type_id)).collect();
+        let archetype = self.find_or_create_archetype(table_types);
+        
+        // Step 3: Create the entity straight in that table, with its full signature
+        let signature: ComponentMask = types.iter().map(|type_id| self.components[type_id].id()).collect();
+        let id = self.entities.insert(EntityMeta {
+            location: EntityLocation { archetype, row: 0 },
+            signature,
+        });
+        self.entities[id].location.row = self.archetypes[archetype].push_entity(id);
+        let entity = Entity::new(id);
+        self.entity_slots = self.entity_slots.max(entity.index());
+        
+        // Step 4: Fill in the row (and the sparse sets), stamped as just added
+        let tick = self.change_tick();
+        let mut writer = BundleWriter::new(self, id, tick);
+        bundle.write_components(&mut writer);
+        assert_eq!(writer.written(), types.len(), "bundle wrote a different number of components than it registered");
+        
+        // Step 5: Add the entity to every cached query it matches
+        self.update_signature(id, |_| {});
+        Ok(entity)
+    }
+    
+    /// Store one of a freshly spawned entity's components, for BundleWriter
+    /// 
+    /// The entity's row must already be in the table holding its components
+    pub(crate) fn init_component<T: Component>(&mut self, id: EntityId, component: T, tick: u64) {
+        let type_id = TypeId::of::<T>();
+        if let Some(storage) = self.sparse_set_mut::<T>() {
+            storage.insert(id, component);
+            self.sparse_ticks.get_mut(&type_id).expect("sparse set without ticks").insert(id, ComponentTicks::new(tick));
+            return;
+        }
+        
+        let archetype = self.entities[id].location.archetype;
+        if self.archetypes[archetype].push_component(component, tick).is_err() {
+            panic!("{} wasn't registered by the bundle that wrote it", T::type_name());
+        }
     }
     
     /// Remove an entity and all its components
@@ -73,27 +230,59 @@ impl World {
     /// Result<T, E> is Rust's way of handling errors (no exceptions!)
     /// Ok(()) means success with no return value
     /// Err(error) means something went wrong
+    /// 
+    /// Children go with their parent: removing an entity also removes every
+    /// entity below it in the hierarchy (see set_parent)
     pub fn remove_entity(&mut self, entity: Entity) -> EcsResult<()> {
-        let id = entity.id();  // Get the internal ID from the entity
+        // Detach from the parent first, so its Children never lists a dead entity
+        // This also fails early if the entity doesn't exist
+        self.remove_parent(entity)?;
         
-        // Check if entity exists - contains_key() returns bool
-        if !self.entities.contains_key(id) {
-            // Return an error if entity doesn't exist
-            // The '?' operator would propagate this error to the caller
-            return Err(EcsError::EntityNotFound(entity));
+        // Walk the subtree with an explicit stack instead of recursion,
+        // so deep hierarchies can't overflow the call stack
+        let mut doomed = vec![entity];
+        while let Some(next) = doomed.pop() {
+            doomed.extend(self.children(next)?);
+            self.despawn(next)?;
         }
+        Ok(())
+    }
+    
+    /// Remove a single entity and its components, leaving its children alone
+    fn despawn(&mut self, entity: Entity) -> EcsResult<()> {
+        let id = entity.id();  // Get the internal ID from the entity
+        
+        // Remove entity from SlotMap - this frees up the ID for reuse
+        // .remove() returns None if the entity doesn't exist, which we turn into an error
+        let meta = self.entities.remove(id)
+            .ok_or_else(|| self.entity_error(entity))?;
+        
+        // Drop the entity's row (and all its components) from its archetype table
+        // The last row is swapped into the gap, so that entity's location changes
+        self.remove_row(meta.location);
         
-        // Remove all components for this entity
-        // .values_mut() gives us mutable references to all values in the HashMap
-        // 'for' loops in Rust automatically handle borrowing
-        for storage in self.component_storages.values_mut() {
-            storage.remove(id);  // ComponentStorage trait method
+        // Sparse-set components aren't in the table, so remove them one by one
+        // The signature tells us which ones the entity has
+        for component_id in meta.signature.ones() {
+            let type_id = self.component_types[component_id];
+            if let Some(storage) = self.sparse_sets.get_mut(&type_id) {
+                let storage = storage.get_mut();
+                if let Some(index) = storage.index_of(id) {
+                    storage.swap_remove(index);
+                }
+            }
+            if let Some(ticks) = self.sparse_ticks.get_mut(&type_id) {
+                ticks.remove(id);
+            }
+            
+            // Every component the entity had counts as removed
+            self.removed_components[component_id].send(entity);
         }
         
-        // Remove entity from SlotMap - this frees up the ID for reuse
-        self.entities.remove(id);
-        // Remove component mask
-        self.entity_component_masks.remove(&id);
+        // The entity no longer matches any cached query
+        for matches in self.query_cache.get_mut().values_mut() {
+            matches.remove(&id);
+        }
         
         // Return success (the () is called "unit type" - like void in C)
         Ok(())
@@ -107,25 +296,116 @@ impl World {
         self.entities.contains_key(entity.id())
     }
     
+    /// Where an entity's table components live, or why it can't be found
+    pub(crate) fn entity_location(&self, entity: Entity) -> EcsResult<EntityLocation> {
+        // EntityLocation is Copy, so .location copies it out and we stop borrowing self.entities
+        self.entities
+            .get(entity.id())
+            .map(|meta| meta.location)
+            .ok_or_else(|| self.entity_error(entity))
+    }
+    
+    /// The error for a handle that doesn't name a live entity
+    /// 
+    /// Slots are reused with a new generation, so a dead handle whose slot has
+    /// been handed out before belonged to a despawned entity
+    fn entity_error(&self, entity: Entity) -> EcsError {
+        if entity.index() <= self.entity_slots {
+            EcsError::StaleEntity(entity)
+        } else {
+            EcsError::EntityNotFound(entity)
+        }
+    }
+    
     /// Register a component type
     /// 
     /// Generic function: <T: Component> means T can be any type that implements Component
     /// This is like templates in C++ or generics in Java/C#
     /// The 'Component' after the colon is a "trait bound" - T must implement Component
+    /// 
+    /// Uses table storage unless the type was already registered otherwise
     pub fn register_component<T: Component>(&mut self) {
         // TypeId::of::<T>() gets a unique identifier for type T at runtime
-        // This lets us store different component types in the same HashMap
-        let type_id = TypeId::of::<T>();
+        // Only record the type if it isn't registered yet
+        if !self.components.contains_key(&TypeId::of::<T>()) {
+            self.insert_component_info(ComponentInfo::of::<T>());
+        }
+    }
+    
+    /// Register a component type with a specific storage type
+    /// 
+    /// StorageType::Table (the default) packs components into archetype tables
+    /// StorageType::SparseSet keeps them in a per-type sparse set, so adding and
+    /// removing them never moves the entity between tables
+    /// 
+    /// Must be called before the first add_component of that type,
+    /// otherwise the type is already registered as a table component
+    pub fn register_component_with<T: Component>(&mut self, storage_type: StorageType) -> EcsResult<()> {
+        self.register_component_info(ComponentInfo::with_storage::<T>(storage_type))
+    }
+    
+    /// Register a component type that fork() can copy, with table storage
+    /// 
+    /// The T: Clone bound is what makes it cloneable: fork() clones every
+    /// component of the type into the new world
+    /// Types registered before (e.g. by add_component) keep their storage type
+    /// and just become cloneable
+    pub fn register_cloneable<T: Component + Clone>(&mut self) {
+        let storage_type = self.components
+            .get(&TypeId::of::<T>())
+            .map_or(StorageType::Table, ComponentInfo::storage_type);
         
-        // Check if this component type is already registered
-        if !self.component_storages.contains_key(&type_id) {
-            // Create a new storage for this component type
-            let storage = TypedComponentStorage::<T>::new();
-            
-            // Box::new() puts the storage on the heap (dynamic allocation)
-            // This is necessary because we're storing different types in the same HashMap
-            self.component_storages.insert(type_id, Box::new(storage));
+        // Can't fail: the storage type matches any earlier registration
+        let _ = self.register_cloneable_with::<T>(storage_type);
+    }
+    
+    /// Register a component type that fork() can copy, with a specific storage type
+    /// 
+    /// Like register_component_with, fails if T was already registered
+    /// with the other storage type
+    pub fn register_cloneable_with<T: Component + Clone>(&mut self, storage_type: StorageType) -> EcsResult<()> {
+        self.register_component_info(ComponentInfo::cloneable::<T>(storage_type))
+    }
+    
+    /// Register a component type from its ComponentInfo, or update an existing registration
+    fn register_component_info(&mut self, info: ComponentInfo) -> EcsResult<()> {
+        let type_id = info.type_id();
+        let storage_type = info.storage_type();
+        
+        if let Some(existing) = self.components.get_mut(&type_id) {
+            // Re-registering is fine, changing the layout of existing data is not
+            if existing.storage_type() != storage_type {
+                return Err(EcsError::StorageTypeMismatch(info.name().to_string()));
+            }
+            // A type can become cloneable later, but never stops being cloneable
+            if info.is_cloneable() {
+                *existing = info.with_id(existing.id());
+            }
+            return Ok(());
+        }
+        
+        if storage_type == StorageType::SparseSet {
+            self.sparse_sets.insert(type_id, RwLock::new(info.new_storage()));
+            self.sparse_ticks.insert(type_id, SecondaryMap::new());
         }
+        self.insert_component_info(info);
+        Ok(())
+    }
+    
+    /// Record a new component type, giving it the next free ComponentId
+    fn insert_component_info(&mut self, info: ComponentInfo) -> ComponentId {
+        let id = self.component_types.len();
+        self.component_types.push(info.type_id());
+        self.removed_components.push(Events::new());
+        self.components.insert(info.type_id(), info.with_id(id));
+        id
+    }
+    
+    /// The dense index of a registered component type
+    /// 
+    /// This is the bit that represents the type in entity signatures
+    pub fn component_id(&self, type_id: TypeId) -> Option<ComponentId> {
+        self.components.get(&type_id).map(ComponentInfo::id)
     }
     
     /// Add a component to an entity
@@ -133,67 +413,101 @@ impl World {
     /// This is where Rust's ownership system really shines
     /// 'component: T' means we take ownership of the component data
     /// The component is "moved" into this function and can't be used by the caller anymore
+    /// 
+    /// If the entity already has a T, it is replaced (which counts as a change)
+    /// Otherwise the entity moves to the archetype for its new component set
     pub fn add_component<T: Component>(&mut self, entity: Entity, component: T) -> EcsResult<()> {
         let id = entity.id();
+        let type_id = TypeId::of::<T>();
+        let tick = self.change_tick();
         
         // Verify entity exists
-        if !self.entities.contains_key(id) {
-            return Err(EcsError::EntityNotFound(entity));
-        }
-        
-        let type_id = TypeId::of::<T>();
+        let location = self.entity_location(entity)?;
         
         // Ensure component type is registered
         self.register_component::<T>();
+        let component_id = self.components[&type_id].id();
         
-        // Get the storage for this component type
-        // .get_mut() returns Option<&mut V> - either Some(reference) or None
-        // .ok_or_else() converts None to an Error
-        let storage = self.component_storages.get_mut(&type_id)
-            .ok_or_else(|| EcsError::ComponentNotRegistered(T::type_name().to_string()))?;
-        
-        // This is called "downcasting" - converting from trait object back to concrete type
-        // .as_any_mut() returns &mut dyn Any (the most general trait object)
-        // .downcast_mut() tries to convert it back to our specific type
-        // If the cast fails, it returns None
-        let typed_storage = storage.as_any_mut()
-            .downcast_mut::<TypedComponentStorage<T>>()
-            .ok_or_else(|| EcsError::ComponentNotRegistered(T::type_name().to_string()))?;
-        
-        // Actually store the component data
-        typed_storage.insert(id, component);
-        
-        // Update component mask - track that this entity has this component type
-        if let Some(mask) = self.entity_component_masks.get_mut(&id) {
-            // Only add if not already present (no duplicates)
-            if !mask.contains(&type_id) {
-                mask.push(type_id);
+        // Sparse-set components go straight into their set, the entity doesn't move
+        if let Some(storage) = self.sparse_set_mut::<T>() {
+            if storage.insert(id, component).is_none() {
+                self.sparse_ticks.get_mut(&type_id).expect("sparse set without ticks").insert(id, ComponentTicks::new(tick));
+                self.update_signature(id, |signature| signature.insert(component_id));
+            } else if let Some(ticks) = self.component_ticks(id, type_id) {
+                ticks.set_changed(tick);
             }
+            return Ok(());
         }
         
+        // Fast path: the entity's table already has a T column, just overwrite the value
+        if let Some(existing) = self.archetypes[location.archetype].column_mut::<T>().and_then(|column| column.get_mut(location.row)) {
+            *existing = component;
+            if let Some(ticks) = self.component_ticks(id, type_id) {
+                ticks.set_changed(tick);
+            }
+            return Ok(());
+        }
+        
+        // Otherwise move the entity's row into the table that also has a T column
+        let target = self.archetype_with(location.archetype, type_id);
+        let new_location = self.move_entity(id, location, target);
+        
+        // Actually store the component data in the new row, stamped as just added
+        self.archetypes[new_location.archetype]
+            .push_component(component, tick)
+            .map_err(|_| EcsError::ComponentNotRegistered(T::type_name().to_string()))?;
+        
+        self.update_signature(id, |signature| signature.insert(component_id));
         Ok(())
     }
     
     /// Get a component from an entity
     /// 
-    /// Returns Option<&T> - either Some(reference to component) or None
-    /// The &T is an immutable reference - you can read but not modify
-    /// Option<T> is Rust's way of representing "maybe has a value"
-    /// It's much safer than null pointers!
-    pub fn get_component<T: Component>(&self, entity: Entity) -> Option<&T> {
-        let id = entity.id();
-        let type_id = TypeId::of::<T>();
+    /// Returns EcsResult<Ref<T>> - the borrowed component, or why it couldn't be borrowed
+    /// Ref<T> derefs to &T, so you can read but not modify
+    /// The borrow is released when the Ref is dropped
+    /// 
+    /// Same as try_get_component; use get_optional_component when the
+    /// entity may simply not have a T
+    pub fn get_component<T: Component>(&self, entity: Entity) -> EcsResult<Ref<'_, T>> {
+        self.try_get_component(entity)
+    }
+    
+    /// Get a component the entity may not have
+    /// 
+    /// A missing T is Ok(None); every other failure of try_get_component
+    /// (stale entity, borrow conflict, undeclared access) is still an error
+    pub fn get_optional_component<T: Component>(&self, entity: Entity) -> EcsResult<Option<Ref<'_, T>>> {
+        match self.try_get_component(entity) {
+            Ok(component) => Ok(Some(component)),
+            Err(EcsError::MissingComponent(..)) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+    
+    /// Get a component from an entity, explaining any failure
+    /// 
+    /// Returns EcsError::StaleEntity if the entity was despawned,
+    /// EcsError::EntityNotFound if it never existed in this world,
+    /// EcsError::MissingComponent if it has no T, and
+    /// EcsError::BorrowConflict if a live query is mutably borrowing T
+    pub fn try_get_component<T: Component>(&self, entity: Entity) -> EcsResult<Ref<'_, T>> {
+        access::check_read::<T>()?;
+        let location = self.entity_location(entity)?;
+        let missing = || EcsError::MissingComponent(entity, T::type_name().to_string());
         
-        // Try to get the storage for this component type
-        // The ? operator here is different - it converts None to None and continues if Some
-        let storage = self.component_storages.get(&type_id)?;
+        // Sparse-set components are looked up by entity instead of by row
+        if let Some(storage) = self.sparse_set::<T>()? {
+            return MappedRwLockReadGuard::try_map(storage, |storage| storage.get(entity.id())).map_err(|_| missing());
+        }
         
-        // Downcast from trait object to concrete type (immutable version)
-        let typed_storage = storage.as_any()
-            .downcast_ref::<TypedComponentStorage<T>>()?;
+        // Borrow the T column of the entity's archetype table
+        let column = self.archetypes[location.archetype]
+            .read_column::<T>()?
+            .ok_or_else(missing)?;
         
-        // Get the component for this specific entity
-        typed_storage.get(id)
+        // Narrow the borrow down to this specific entity's row
+        MappedRwLockReadGuard::try_map(column, |column| column.get(location.row)).map_err(|_| missing())
     }
     
     /// Get a mutable component from an entity
@@ -201,16 +515,34 @@ impl World {
     /// Returns Option<&mut T> - mutable reference if found
     /// &mut T means you can both read AND modify the component
     /// Rust ensures only ONE mutable reference exists at a time (no data races!)
+    /// 
+    /// Handing out a mutable reference marks the component as changed,
+    /// whether or not the caller ends up writing to it
     pub fn get_component_mut<T: Component>(&mut self, entity: Entity) -> Option<&mut T> {
-        let id = entity.id();
-        let type_id = TypeId::of::<T>();
+        self.try_get_component_mut(entity).ok()
+    }
+    
+    /// Get a mutable component from an entity, explaining any failure
+    /// 
+    /// Fails like try_get_component, except that '&mut self' rules out borrow conflicts
+    pub fn try_get_component_mut<T: Component>(&mut self, entity: Entity) -> EcsResult<&mut T> {
+        let location = self.entity_location(entity)?;
+        let missing = || EcsError::MissingComponent(entity, T::type_name().to_string());
         
-        // Same pattern but with mutable references
-        let storage = self.component_storages.get_mut(&type_id)?;
-        let typed_storage = storage.as_any_mut()
-            .downcast_mut::<TypedComponentStorage<T>>()?;
+        let tick = self.change_tick();
+        if let Some(ticks) = self.component_ticks(entity.id(), TypeId::of::<T>()) {
+            ticks.set_changed(tick);
+        }
+        
+        if self.is_sparse(TypeId::of::<T>()) {
+            return self.sparse_set_mut::<T>().and_then(|storage| storage.get_mut(entity.id())).ok_or_else(missing);
+        }
         
-        typed_storage.get_mut(id)
+        // '&mut self' proves nothing else is borrowing the column, so no locking is needed
+        self.archetypes[location.archetype]
+            .column_mut::<T>()
+            .and_then(|column| column.get_mut(location.row))
+            .ok_or_else(missing)
     }
     
     /// Remove a component from an entity
@@ -224,46 +556,105 @@ impl World {
         let type_id = TypeId::of::<T>();
         
         // Verify entity exists first
-        if !self.entities.contains_key(id) {
-            return Err(EcsError::EntityNotFound(entity));
-        }
+        let location = self.entity_location(entity)?;
         
-        // Get mutable storage
-        let storage = self.component_storages.get_mut(&type_id)
-            .ok_or_else(|| EcsError::ComponentNotRegistered(T::type_name().to_string()))?;
+        let Some(component_id) = self.component_id(type_id) else {
+            return Err(EcsError::ComponentNotRegistered(T::type_name().to_string()));
+        };
         
-        let typed_storage = storage.as_any_mut()
-            .downcast_mut::<TypedComponentStorage<T>>()
-            .ok_or_else(|| EcsError::ComponentNotRegistered(T::type_name().to_string()))?;
+        // Sparse-set components are removed from their set, the entity doesn't move
+        if let Some(storage) = self.sparse_set_mut::<T>() {
+            let component = storage.remove(id);
+            if component.is_some() {
+                if let Some(ticks) = self.sparse_ticks.get_mut(&type_id) {
+                    ticks.remove(id);
+                }
+                self.update_signature(id, |signature| signature.remove(component_id));
+                self.removed_components[component_id].send(entity);
+            }
+            return Ok(component);
+        }
         
-        // Remove and get the component data
-        let component = typed_storage.remove(id);
+        // Take the component data out of its column first...
+        // If the entity's table has no T column, there's nothing to remove
+        let Some(component) = self.archetypes[location.archetype].take_component::<T>(location.row) else {
+            return Ok(None);
+        };
         
-        // Update component mask - remove this type from the entity's list
-        if let Some(mask) = self.entity_component_masks.get_mut(&id) {
-            // .retain() keeps only elements that match the condition
-            // |&t| means the closure takes a reference to each element
-            mask.retain(|&t| t != type_id);
-        }
+        // ...then move the rest of the row to the table without a T column
+        let target = self.archetype_without(location.archetype, type_id);
+        self.move_entity(id, location, target);
+        self.update_signature(id, |signature| signature.remove(component_id));
+        self.removed_components[component_id].send(entity);
         
-        Ok(component)
+        Ok(Some(component))
     }
     
     /// Check if an entity has a specific component
     /// 
     /// Simple boolean check - useful for filtering entities
+    /// This is a single bit test on the entity's signature
     pub fn has_component<T: Component>(&self, entity: Entity) -> bool {
-        let id = entity.id();
-        let type_id = TypeId::of::<T>();
-        
+        self.has_component_type(entity.id(), TypeId::of::<T>())
+    }
+    
+    /// Type-erased version of has_component, used by query matching
+    pub(crate) fn has_component_type(&self, id: EntityId, type_id: TypeId) -> bool {
         // Chain of Option operations:
-        // 1. Get the component mask for this entity (returns Option)
-        // 2. If found, check if it contains the type ID (returns Option<bool>)  
-        // 3. If not found, default to false
-        self.entity_component_masks
-            .get(&id)
-            .map(|mask| mask.contains(&type_id))  // .map() transforms Some(mask) to Some(bool)
-            .unwrap_or(false)                     // .unwrap_or() converts None to false
+        // 1. Look up the component's bit (None if the type was never registered)
+        // 2. Get the metadata for this entity (returns Option)
+        // 3. If both exist, test the bit in the entity's signature
+        // 4. Otherwise default to false
+        self.component_id(type_id)
+            .zip(self.entities.get(id))
+            .map(|(component_id, meta)| meta.signature.contains(component_id))
+            .unwrap_or(false)
+    }
+    
+    /// When an entity's component was added and last changed
+    /// 
+    /// Returns None if the entity doesn't exist or doesn't have the component
+    pub fn component_ticks(&self, id: EntityId, type_id: TypeId) -> Option<&ComponentTicks> {
+        // Sparse-set ticks are keyed by entity, table ticks by row
+        if let Some(ticks) = self.sparse_ticks.get(&type_id) {
+            return ticks.get(id);
+        }
+        let location = self.entities.get(id)?.location;
+        self.archetypes[location.archetype].column_ticks(type_id)?.get(location.row)
+    }
+    
+    /// Query all entities matching a typed component set
+    /// 
+    /// Q describes what to borrow for each entity, for example:
+    /// - `(&mut Position, &Velocity)` - entities with both, Position writable
+    /// - `(Entity, Option<&Mass>)` - every entity, with its Mass if it has one
+    /// 
+    /// Borrows are checked at runtime: asking for a component that another live
+    /// query is writing (or writing one it is reading) returns EcsError::BorrowConflict
+    pub fn query<Q: QueryData>(&self) -> EcsResult<Query<'_, Q>> {
+        Query::new(self)
+    }
+    
+    /// Query with an extra filter such as `With<T>`, `Without<T>` or a tuple of them
+    pub fn query_filtered<Q: QueryData, F: QueryFilter>(&self) -> EcsResult<Query<'_, Q, F>> {
+        Query::new(self)
+    }
+    
+    /// Get component storage for iteration
+    /// 
+    /// Components now live in one column per archetype table rather than in a
+    /// single storage per type, so this hands out a query over every T instead
+    #[deprecated(note = "use query::<&T>()")]
+    pub fn get_component_storage<T: Component>(&self) -> EcsResult<Query<'_, &T>> {
+        self.query()
+    }
+    
+    /// Get mutable component storage for iteration
+    /// 
+    /// Same as above, with every T writable
+    #[deprecated(note = "use query::<&mut T>()")]
+    pub fn get_component_storage_mut<T: Component>(&mut self) -> EcsResult<Query<'_, &mut T>> {
+        self.query()
     }
     
     /// Query entities that have all specified component types
@@ -271,15 +662,32 @@ impl World {
     /// Takes a slice (&[TypeId]) of type IDs to search for
     /// Returns a Vec<Entity> containing all matching entities
     /// This is how we implement queries like "find all entities with Position AND Velocity"
+    /// 
+    /// The first call for a given set of types scans every entity signature;
+    /// after that the result is cached and kept up to date as components change
     pub fn query_entities(&self, component_types: &[TypeId]) -> Vec<Entity> {
-        self.entity_component_masks
-            .iter()                                    // Iterate over all entities and their masks
-            .filter(|(_, mask)| {                     // Filter to only entities that match
-                // .all() returns true if every component type is in the entity's mask
-                component_types.iter().all(|&type_id| mask.contains(&type_id))
-            })
-            .map(|(&id, _)| Entity::new(id))          // Convert from (EntityId, &Vec<TypeId>) to Entity
-            .collect()                                // Collect the iterator into a Vec
+        // Turn the types into a mask with one bit per type
+        // Collecting into Option<...> gives None if any type was never registered,
+        // in which case no entity can possibly match
+        let Some(mask) = component_types
+            .iter()
+            .map(|&type_id| self.component_id(type_id))
+            .collect::<Option<ComponentMask>>()
+        else {
+            return Vec::new();
+        };
+        
+        let mut cache = self.query_cache.lock();
+        let matches = cache.entry(mask).or_insert_with_key(|mask| {
+            self.entities
+                .iter()                                // Iterate over all entities
+                // An entity matches if its signature has every bit of the mask set
+                .filter(|(_, meta)| meta.signature.contains_all(mask))
+                .map(|(id, _)| id)
+                .collect()                             // Collect the IDs into a BTreeSet
+        });
+        
+        matches.iter().copied().map(Entity::new).collect()
     }
     
     /// Get all entities
@@ -290,10 +698,9 @@ impl World {
     /// borrows from self and can't outlive this World instance
     /// This is much more efficient than collecting into a Vec!
     pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
-        // .values() gets an iterator over all entities in the SlotMap
-        // .copied() converts from Iterator<&Entity> to Iterator<Entity>
-        // Since Entity is Copy, this is very cheap (just copying a small ID)
-        self.entities.values().copied()
+        // .keys() gets an iterator over all entity IDs in the SlotMap
+        // Entity::new wraps each ID - Entity is Copy, so this is very cheap
+        self.entities.keys().map(Entity::new)
     }
     
     /// Get the number of entities
@@ -301,28 +708,397 @@ impl World {
         self.entities.len()
     }
     
-    /// Get component storage for iteration
+    /// Get all archetype tables
     /// 
-    /// This allows systems to iterate over all components of a specific type
-    /// Returns Option<&TypedComponentStorage<T>> - reference to the storage
-    /// The storage lets you iterate over all entities that have component T
-    pub fn get_component_storage<T: Component>(&self) -> Option<&TypedComponentStorage<T>> {
-        let type_id = TypeId::of::<T>();
+    /// Useful for diagnostics, and used by queries to find matching tables
+    pub fn archetypes(&self) -> &[Archetype] {
+        &self.archetypes
+    }
+    
+    /// Insert a global resource, replacing any existing resource of the same type
+    /// 
+    /// Returns the replaced resource, if there was one
+    /// Resources are for state that isn't tied to any entity,
+    /// like the simulation clock or the current wind
+    pub fn insert_resource<R: Resource>(&mut self, resource: R) -> Option<R> {
+        self.resources.insert(resource)
+    }
+    
+    /// Borrow a resource for reading
+    /// 
+    /// Returns EcsError::ResourceNotFound if no R was inserted,
+    /// or EcsError::BorrowConflict if it is currently borrowed mutably
+    /// Like get_component, the borrow is released when the Ref is dropped
+    pub fn resource<R: Resource>(&self) -> EcsResult<Ref<'_, R>> {
+        access::check_read_resource::<R>()?;
+        self.resources.get::<R>()
+    }
+    
+    /// Borrow a resource for writing
+    /// 
+    /// This only needs '&self': each resource has its own lock, so a system can
+    /// write the clock while another reads the wind. Borrowing the same resource
+    /// twice returns EcsError::BorrowConflict instead of aliasing it
+    pub fn resource_mut<R: Resource>(&self) -> EcsResult<RefMut<'_, R>> {
+        access::check_write_resource::<R>()?;
+        self.resources.get_mut::<R>()
+    }
+    
+    /// Remove a resource, giving ownership back to the caller
+    pub fn remove_resource<R: Resource>(&mut self) -> Option<R> {
+        self.resources.remove::<R>()
+    }
+    
+    /// Check whether a resource of type R exists
+    pub fn contains_resource<R: Resource>(&self) -> bool {
+        self.resources.contains::<R>()
+    }
+    
+    /// Let fork() copy resources of type R
+    /// 
+    /// Can be called before or after the resource is inserted
+    /// Resources that weren't registered this way make fork() fail
+    pub fn register_cloneable_resource<R: Resource + Clone>(&mut self) {
+        // The closure captures nothing, so it coerces to a function pointer
+        self.resources.set_fork::<R>(|value| {
+            let value = value.downcast_ref::<R>().expect("resource stored under the wrong type");
+            Box::new(value.clone())
+        });
+    }
+    
+    /// Register an event type, storing its Events<E> channel as a resource
+    /// 
+    /// Registered channels are advanced by update_events, which the
+    /// SystemDispatcher calls once per frame after running every system
+    /// Calling this again for the same E does nothing
+    /// 
+    /// Forked worlds get an empty channel: pending events stay with the original
+    pub fn add_event<E: Event>(&mut self) {
+        if self.contains_resource::<Events<E>>() {
+            return;
+        }
+        self.insert_resource(Events::<E>::new());
+        self.resources.set_fork::<Events<E>>(|_| Box::new(Events::<E>::new()));
         
-        // Get the storage and try to downcast it
-        let storage = self.component_storages.get(&type_id)?;
-        storage.as_any().downcast_ref::<TypedComponentStorage<T>>()
+        // This closure captures nothing, so it coerces to a function pointer
+        self.event_updaters.push(|world| {
+            if let Ok(mut events) = world.resource_mut::<Events<E>>() {
+                events.update();
+            }
+        });
     }
     
-    /// Get mutable component storage for iteration
+    /// Send an event through its channel
     /// 
-    /// Same as above but allows modification of components during iteration
-    /// Systems use this to update component data efficiently
-    pub fn get_component_storage_mut<T: Component>(&mut self) -> Option<&mut TypedComponentStorage<T>> {
-        let type_id = TypeId::of::<T>();
+    /// Returns EcsError::ResourceNotFound if add_event::<E>() was never called
+    pub fn send_event<E: Event>(&self, event: E) -> EcsResult<()> {
+        self.resource_mut::<Events<E>>()?.send(event);
+        Ok(())
+    }
+    
+    /// Advance every registered event channel by one frame
+    /// 
+    /// Events sent before the previous update are dropped
+    pub fn update_events(&mut self) {
+        for update in &self.event_updaters {
+            update(self);
+        }
+    }
+    
+    /// The tick component writes are stamped with right now
+    /// 
+    /// Inside a system this is the tick the system took when it started,
+    /// otherwise the World's latest tick
+    pub fn change_tick(&self) -> u64 {
+        match current_system_ticks() {
+            Some(ticks) => ticks.this_run,
+            None => self.change_tick,
+        }
+    }
+    
+    /// Changes stamped after this tick count as new for Added and Changed filters
+    /// 
+    /// Inside a system this is the tick of the system's previous run,
+    /// otherwise the tick of the last clear_trackers
+    pub fn last_change_tick(&self) -> u64 {
+        match current_system_ticks() {
+            Some(ticks) => ticks.last_run,
+            None => self.last_change_tick,
+        }
+    }
+    
+    /// Take a new change tick, returning it
+    pub(crate) fn increment_change_tick(&mut self) -> u64 {
+        self.change_tick += 1;
+        self.change_tick
+    }
+    
+    /// Start a new change-detection frame
+    /// 
+    /// Changes made so far stop counting as new outside systems, and
+    /// component removals older than the previous frame are dropped
+    /// The SystemDispatcher calls this at the end of every run_systems
+    pub fn clear_trackers(&mut self) {
+        self.last_change_tick = self.change_tick;
+        self.change_tick += 1;
+        
+        for removed in &mut self.removed_components {
+            removed.update();
+        }
+    }
+    
+    /// Entities that recently lost component T, read through RemovedComponents
+    /// 
+    /// Returns None if T was never registered
+    pub(crate) fn removed_components<T: Component>(&self) -> Option<&Events<Entity>> {
+        let component_id = self.component_id(TypeId::of::<T>())?;
+        self.removed_components.get(component_id)
+    }
+    
+    /// Queue a command buffer to be applied at the next sync point
+    /// 
+    /// This is how systems spawn or despawn entities while queries are borrowing
+    /// the World: record the changes into Commands, then defer them
+    /// The SystemDispatcher calls apply_commands after each system runs
+    pub fn defer(&self, commands: Commands) {
+        if !commands.is_empty() {
+            self.deferred.lock().push((current_system_order(), commands));
+        }
+    }
+    
+    /// Apply every deferred command buffer
+    /// 
+    /// Buffers are applied in the order of the systems that deferred them
+    /// (and in deferral order within one system), even when those systems
+    /// ran in parallel and finished in any order
+    /// A failing command stops the rest of its own buffer, but every other
+    /// buffer is still applied; the first error is returned afterwards
+    pub fn apply_commands(&mut self) -> EcsResult<()> {
+        // std::mem::take swaps in an empty Vec so we own the queued buffers
+        let mut deferred = std::mem::take(self.deferred.get_mut());
+        
+        // Commands get a tick of their own, after every system that deferred them,
+        // so those systems see the changes as new on their next run
+        if !deferred.is_empty() {
+            self.increment_change_tick();
+        }
+        
+        // sort_by_key is stable, so one system's buffers keep their order
+        deferred.sort_by_key(|(order, _)| *order);
+        let mut first_error = None;
+        for (_, commands) in deferred {
+            if let Err(err) = commands.apply(self) {
+                first_error.get_or_insert(err);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+    
+    /// Deep-copy the world, for rollouts and what-if runs
+    /// 
+    /// The fork has the same entities (existing Entity handles work in both
+    /// worlds), components, resources and change ticks, but shares nothing with
+    /// the original: changes to one never show up in the other
+    /// 
+    /// Copying is a plain clone of every column, with no encoding step, so it's
+    /// much faster than a snapshot round trip. That needs every component type
+    /// with data to be registered with register_cloneable, and every resource
+    /// with register_cloneable_resource; otherwise this returns
+    /// EcsError::NotCloneable. It also fails with EcsError::BorrowConflict while
+    /// anything is borrowed mutably, and with NotCloneable while commands are
+    /// deferred (apply them first, since commands can't be copied)
+    pub fn fork(&self) -> EcsResult<World> {
+        if !self.deferred.lock().is_empty() {
+            return Err(EcsError::NotCloneable("deferred commands".to_string()));
+        }
+        
+        let archetypes = self.archetypes
+            .iter()
+            .map(|archetype| archetype.try_clone(&self.components))
+            .collect::<EcsResult<Vec<_>>>()?;
+        
+        let mut sparse_sets = HashMap::with_capacity(self.sparse_sets.len());
+        for (type_id, storage) in &self.sparse_sets {
+            let info = &self.components[type_id];
+            let storage = storage
+                .try_read()
+                .ok_or_else(|| EcsError::BorrowConflict(info.name().to_string()))?;
+            sparse_sets.insert(*type_id, RwLock::new(info.fork_storage(&**storage)?));
+        }
+        
+        Ok(World {
+            // Cloning the SlotMap keeps every key, so entity handles stay valid
+            entities: self.entities.clone(),
+            components: self.components.clone(),
+            component_types: self.component_types.clone(),
+            archetypes,
+            archetype_index: self.archetype_index.clone(),
+            sparse_sets,
+            sparse_ticks: self.sparse_ticks.clone(),
+            query_cache: Mutex::new(self.query_cache.lock().clone()),
+            resources: self.resources.try_clone()?,
+            event_updaters: self.event_updaters.clone(),
+            deferred: Mutex::new(Vec::new()),
+            change_tick: self.change_tick,
+            last_change_tick: self.last_change_tick,
+            removed_components: self.removed_components.clone(),
+            entity_slots: self.entity_slots,
+        })
+    }
+    
+    /// Whether a component type was registered with sparse-set storage
+    pub(crate) fn is_sparse(&self, type_id: TypeId) -> bool {
+        self.sparse_sets.contains_key(&type_id)
+    }
+    
+    /// Borrow the sparse set for T through '&self'
+    /// 
+    /// Returns Ok(None) if T isn't a sparse-set component
+    /// Uses try_read() so a conflicting borrow is reported instead of deadlocking
+    pub(crate) fn sparse_set<T: Component>(&self) -> EcsResult<Option<MappedRwLockReadGuard<'_, SparseSetStorage<T>>>> {
+        let Some(storage) = self.sparse_sets.get(&TypeId::of::<T>()) else {
+            return Ok(None);
+        };
+        let guard = storage.try_read()
+            .ok_or_else(|| EcsError::BorrowConflict(T::type_name().to_string()))?;
+        
+        RwLockReadGuard::try_map(guard, |s| s.as_any().downcast_ref::<SparseSetStorage<T>>())
+            .map(Some)
+            .map_err(|_| EcsError::ComponentNotRegistered(T::type_name().to_string()))
+    }
+    
+    /// Borrow the sparse set for T for writing through '&self'
+    pub(crate) fn write_sparse_set<T: Component>(&self) -> EcsResult<Option<MappedRwLockWriteGuard<'_, SparseSetStorage<T>>>> {
+        let Some(storage) = self.sparse_sets.get(&TypeId::of::<T>()) else {
+            return Ok(None);
+        };
+        let guard = storage.try_write()
+            .ok_or_else(|| EcsError::BorrowConflict(T::type_name().to_string()))?;
+        
+        RwLockWriteGuard::try_map(guard, |s| s.as_any_mut().downcast_mut::<SparseSetStorage<T>>())
+            .map(Some)
+            .map_err(|_| EcsError::ComponentNotRegistered(T::type_name().to_string()))
+    }
+    
+    /// Change ticks of a sparse-set component type, keyed by entity
+    pub(crate) fn sparse_ticks(&self, type_id: TypeId) -> Option<&SecondaryMap<EntityId, ComponentTicks>> {
+        self.sparse_ticks.get(&type_id)
+    }
+    
+    /// Exclusive access to the sparse set for T, no locking needed
+    fn sparse_set_mut<T: Component>(&mut self) -> Option<&mut SparseSetStorage<T>> {
+        self.sparse_sets
+            .get_mut(&TypeId::of::<T>())?
+            .get_mut()
+            .as_any_mut()
+            .downcast_mut::<SparseSetStorage<T>>()
+    }
+    
+    /// Change an entity's signature and bring the query cache up to date
+    fn update_signature(&mut self, id: EntityId, change: impl FnOnce(&mut ComponentMask)) {
+        let signature = &mut self.entities[id].signature;
+        change(signature);
+        
+        // Each cached query either gains or loses this entity
+        for (mask, matches) in self.query_cache.get_mut().iter_mut() {
+            if signature.contains_all(mask) {
+                matches.insert(id);
+            } else {
+                matches.remove(&id);
+            }
+        }
+    }
+    
+    /// Find (or create) the archetype reached by adding `type_id` to `from`
+    /// 
+    /// The answer is cached on the source archetype as an "edge",
+    /// so repeated adds of the same component type skip the lookup
+    fn archetype_with(&mut self, from: ArchetypeId, type_id: TypeId) -> ArchetypeId {
+        if let Some(target) = self.archetypes[from].add_edge(type_id) {
+            return target;
+        }
+        
+        let mut types = self.archetypes[from].types().to_vec();
+        types.push(type_id);
+        types.sort();
+        
+        let target = self.find_or_create_archetype(types);
+        self.archetypes[from].set_add_edge(type_id, target);
+        self.archetypes[target].set_remove_edge(type_id, from);
+        target
+    }
+    
+    /// Find (or create) the archetype reached by removing `type_id` from `from`
+    fn archetype_without(&mut self, from: ArchetypeId, type_id: TypeId) -> ArchetypeId {
+        if let Some(target) = self.archetypes[from].remove_edge(type_id) {
+            return target;
+        }
+        
+        let mut types = self.archetypes[from].types().to_vec();
+        types.retain(|&t| t != type_id);
+        
+        let target = self.find_or_create_archetype(types);
+        self.archetypes[from].set_remove_edge(type_id, target);
+        self.archetypes[target].set_add_edge(type_id, from);
+        target
+    }
+    
+    /// Look up the archetype for a sorted set of component types, creating it if needed
+    fn find_or_create_archetype(&mut self, types: Vec<TypeId>) -> ArchetypeId {
+        if let Some(&id) = self.archetype_index.get(&types) {
+            return id;
+        }
+        
+        // Every type in the set was registered before we got here,
+        // so the registry can build an empty column for each of them
+        let id = self.archetypes.len();
+        let columns = types
+            .iter()
+            .map(|type_id| (*type_id, self.components[type_id].new_storage()))
+            .collect();
+        
+        self.archetypes.push(Archetype::new(id, columns));
+        self.archetype_index.insert(types, id);
+        id
+    }
+    
+    /// Move an entity's row from its current archetype into `target`
+    /// 
+    /// Returns the entity's new location
+    fn move_entity(&mut self, id: EntityId, location: EntityLocation, target: ArchetypeId) -> EntityLocation {
+        let (source, destination) = pair_mut(&mut self.archetypes, location.archetype, target);
+        let (row, moved) = source.move_row(location.row, destination);
+        
+        // Whichever entity was swapped into our old row now lives there
+        if let Some(moved) = moved {
+            self.entities[moved].location.row = location.row;
+        }
         
-        let storage = self.component_storages.get_mut(&type_id)?;
-        storage.as_any_mut().downcast_mut::<TypedComponentStorage<T>>()
+        let new_location = EntityLocation { archetype: target, row };
+        self.entities[id].location = new_location;
+        new_location
+    }
+    
+    /// Drop a row from its archetype table, fixing up the entity swapped into its place
+    fn remove_row(&mut self, location: EntityLocation) {
+        if let Some(moved) = self.archetypes[location.archetype].swap_remove(location.row) {
+            self.entities[moved].location.row = location.row;
+        }
+    }
+}
+
+/// Borrow two different elements of a slice mutably at the same time
+/// 
+/// Rust won't let us write &mut items[a] and &mut items[b] together,
+/// but split_at_mut() proves to the compiler that the halves don't overlap
+fn pair_mut<T>(items: &mut [T], a: usize, b: usize) -> (&mut T, &mut T) {
+    assert_ne!(a, b, "pair_mut needs two different indices");
+    if a < b {
+        let (low, high) = items.split_at_mut(b);
+        (&mut low[a], &mut high[0])
+    } else {
+        let (low, high) = items.split_at_mut(a);
+        (&mut high[0], &mut low[b])
     }
 }
 
@@ -343,13 +1119,13 @@ mod tests {
     use super::*;  // Import everything from the parent module
 
     // Test components - simple structs for testing
-    #[derive(Debug, PartialEq)]  // Automatic implementations for debugging and comparison
+    #[derive(Debug, Clone, PartialEq)]  // Automatic implementations for debugging and comparison
     struct Position {
         x: f32,
         y: f32,
     }
 
-    #[derive(Debug, PartialEq)]
+    #[derive(Debug, Clone, PartialEq)]
     struct Velocity {
         x: f32,
         y: f32,
@@ -377,10 +1153,19 @@ mod tests {
         assert!(world.has_component::<Position>(entity));
         assert!(!world.has_component::<Velocity>(entity));
         
-        // .unwrap() here panics if Option is None - we expect Some(component)
+        // .unwrap() here panics if Result is Err - we expect Ok(component)
         let retrieved_pos = world.get_component::<Position>(entity).unwrap();
         assert_eq!(retrieved_pos.x, 1.0);
         assert_eq!(retrieved_pos.y, 2.0);
+        drop(retrieved_pos);
+        
+        // The old storage accessors still iterate every component of a type
+        #[allow(deprecated)]
+        {
+            world.get_component_storage_mut::<Position>().unwrap().iter().for_each(|pos| pos.x += 1.0);
+            assert_eq!(world.get_component_storage::<Position>().unwrap().len(), 1);
+        }
+        assert_eq!(world.get_component::<Position>(entity).unwrap().x, 2.0);
     }
 
     #[test]
@@ -395,4 +1180,168 @@ mod tests {
         assert!(!world.entity_exists(entity));
         assert_eq!(world.entity_count(), 0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_world_archetype_moves() {
+        let mut world = World::new();
+        let first = world.create_entity();
+        let second = world.create_entity();
+        
+        // Both entities end up in the same (Position, Velocity) table
+        for entity in [first, second] {
+            world.add_component(entity, Position { x: 1.0, y: 1.0 }).unwrap();
+            world.add_component(entity, Velocity { x: 2.0, y: 2.0 }).unwrap();
+        }
+        let both = TypeId::of::<Velocity>();
+        assert_eq!(world.query_entities(&[both]).len(), 2);
+        
+        // Removing a component moves the entity and hands the data back
+        let removed = world.remove_component::<Velocity>(first).unwrap();
+        assert_eq!(removed, Some(Velocity { x: 2.0, y: 2.0 }));
+        assert_eq!(world.remove_component::<Velocity>(first).unwrap(), None);
+        assert_eq!(world.query_entities(&[both]), vec![second]);
+        
+        // The entity swapped into the vacated row is still found correctly
+        world.get_component_mut::<Position>(second).unwrap().x = 5.0;
+        assert_eq!(world.get_component::<Position>(second).unwrap().x, 5.0);
+        assert_eq!(world.get_component::<Position>(first).unwrap().x, 1.0);
+        
+        // Adding a component the entity already has replaces it in place
+        world.add_component(first, Position { x: 3.0, y: 3.0 }).unwrap();
+        assert_eq!(*world.get_component::<Position>(first).unwrap(), Position { x: 3.0, y: 3.0 });
+        
+        world.remove_entity(first).unwrap();
+        assert_eq!(world.get_component::<Position>(second).unwrap().x, 5.0);
+    }
+
+    #[test]
+    fn test_world_sparse_set_components() {
+        #[derive(Debug, PartialEq)]
+        struct Stalled;
+        
+        let mut world = World::new();
+        world.register_component_with::<Stalled>(StorageType::SparseSet).unwrap();
+        assert!(matches!(
+            world.register_component_with::<Stalled>(StorageType::Table),
+            Err(EcsError::StorageTypeMismatch(_))
+        ));
+        
+        let entity = world.create_entity();
+        world.add_component(entity, Position { x: 1.0, y: 2.0 }).unwrap();
+        let archetype_count = world.archetypes().len();
+        
+        // Toggling a sparse component never creates or changes tables
+        world.add_component(entity, Stalled).unwrap();
+        assert!(world.has_component::<Stalled>(entity));
+        assert_eq!(world.archetypes().len(), archetype_count);
+        assert_eq!(world.query_entities(&[TypeId::of::<Stalled>(), TypeId::of::<Position>()]), vec![entity]);
+        
+        assert_eq!(world.remove_component::<Stalled>(entity).unwrap(), Some(Stalled));
+        assert!(!world.has_component::<Stalled>(entity));
+        assert!(world.query_entities(&[TypeId::of::<Stalled>()]).is_empty());
+        
+        world.add_component(entity, Stalled).unwrap();
+        world.remove_entity(entity).unwrap();
+        assert_eq!(world.archetypes().len(), archetype_count);
+    }
+
+    #[test]
+    fn test_world_query_cache_tracks_signatures() {
+        let mut world = World::new();
+        let moving = world.create_entity();
+        world.add_component(moving, Position { x: 0.0, y: 0.0 }).unwrap();
+        world.add_component(moving, Velocity { x: 1.0, y: 0.0 }).unwrap();
+        
+        let both = [TypeId::of::<Position>(), TypeId::of::<Velocity>()];
+        assert_eq!(world.query_entities(&both), vec![moving]);
+        assert!(world.query_entities(&[TypeId::of::<u8>()]).is_empty());
+        
+        // Later changes show up in the cached result without a rescan
+        let late = world.create_entity();
+        world.add_component(late, Velocity { x: 0.0, y: 1.0 }).unwrap();
+        world.add_component(late, Position { x: 0.0, y: 0.0 }).unwrap();
+        assert_eq!(world.query_entities(&both), vec![moving, late]);
+        assert_eq!(world.query_entities(&[]).len(), 2);
+        
+        world.remove_component::<Velocity>(moving).unwrap();
+        assert_eq!(world.query_entities(&both), vec![late]);
+        
+        world.remove_entity(late).unwrap();
+        assert!(world.query_entities(&both).is_empty());
+        assert_eq!(world.query_entities(&[]), vec![moving]);
+    }
+
+    #[test]
+    fn test_world_stale_and_unknown_entities() {
+        let mut world = World::new();
+        let despawned = world.create_entity();
+        world.add_component(despawned, Position { x: 1.0, y: 0.0 }).unwrap();
+        world.remove_entity(despawned).unwrap();
+        
+        // The slot is reused, but the old handle doesn't reach the new entity
+        let reused = world.create_entity();
+        assert_eq!(reused.index(), despawned.index());
+        assert!(matches!(world.get_component::<Position>(despawned), Err(EcsError::StaleEntity(e)) if e == despawned));
+        assert!(matches!(world.remove_entity(despawned), Err(EcsError::StaleEntity(_))));
+        assert!(matches!(world.add_component(despawned, Velocity { x: 0.0, y: 0.0 }), Err(EcsError::StaleEntity(_))));
+        
+        // A handle from a bigger world names a slot this one never used
+        let unknown = Entity::from_bits(u64::from(u32::MAX));
+        assert!(matches!(world.try_get_component_mut::<Position>(unknown), Err(EcsError::EntityNotFound(_))));
+        
+        assert!(matches!(world.try_get_component::<Position>(reused), Err(EcsError::MissingComponent(e, _)) if e == reused));
+        assert!(world.get_optional_component::<Position>(reused).unwrap().is_none());
+        world.add_component(reused, Position { x: 2.0, y: 0.0 }).unwrap();
+        assert_eq!(world.try_get_component::<Position>(reused).unwrap().x, 2.0);
+        
+        // A live writer makes the lookup fail instead of panicking
+        let reader = world.query::<&mut Position>().unwrap();
+        assert!(matches!(world.get_component::<Position>(reused), Err(EcsError::BorrowConflict(_))));
+        assert!(matches!(world.get_optional_component::<Position>(reused), Err(EcsError::BorrowConflict(_))));
+        drop(reader);
+    }
+    
+    #[test]
+    fn test_world_fork() {
+        #[derive(Clone, Debug, PartialEq)]
+        struct Tag;
+        
+        let mut world = World::new();
+        world.register_cloneable::<Position>();
+        world.register_cloneable_with::<Tag>(StorageType::SparseSet).unwrap();
+        world.register_cloneable_resource::<u32>();
+        world.insert_resource(7u32);
+        world.add_event::<u8>();
+        
+        let a = world.create_entity();
+        let b = world.create_entity();
+        world.add_component(a, Position { x: 1.0, y: 0.0 }).unwrap();
+        world.add_component(b, Position { x: 2.0, y: 0.0 }).unwrap();
+        world.add_component(b, Tag).unwrap();
+        world.send_event(3u8).unwrap();
+        
+        // Velocity isn't cloneable, so the fork fails once an entity has one
+        world.add_component(a, Velocity { x: 0.0, y: 0.0 }).unwrap();
+        assert!(matches!(world.fork(), Err(EcsError::NotCloneable(name)) if name.contains("Velocity")));
+        world.remove_component::<Velocity>(a).unwrap();
+        
+        let mut fork = world.fork().unwrap();
+        assert_eq!(fork.entity_count(), 2);
+        assert_eq!(fork.get_component::<Position>(b).as_deref().ok(), Some(&Position { x: 2.0, y: 0.0 }));
+        assert!(fork.has_component::<Tag>(b));
+        assert_eq!(*fork.resource::<u32>().unwrap(), 7);
+        assert!(fork.resource::<Events<u8>>().unwrap().is_empty());
+        
+        // The two worlds share nothing
+        fork.get_component_mut::<Position>(a).unwrap().x = 10.0;
+        fork.remove_entity(b).unwrap();
+        let c = fork.create_entity();
+        assert_eq!(world.get_component::<Position>(a).unwrap().x, 1.0);
+        assert!(world.has_component::<Tag>(b));
+        assert!(!world.entity_exists(c));
+        
+        // Unregistered resources can't be copied
+        world.insert_resource(1.5f64);
+        assert!(matches!(world.fork(), Err(EcsError::NotCloneable(_))));
+    }
+}
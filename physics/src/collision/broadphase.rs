@@ -0,0 +1,63 @@
+// Broadphase - cheaply rule out pairs that can't be touching
+use super::Aabb;
+
+/// Pairs of boxes that overlap, found by sweep and prune
+///
+/// Boxes are sorted by their left edge and swept from left to right; each box
+/// is only compared with the boxes whose X range it's still inside. For
+/// bodies spread out along the flight path that's close to linear time,
+/// instead of comparing every box with every other one
+///
+/// Pairs are (lower index, higher index), in a deterministic order
+pub fn sweep_and_prune(aabbs: &[Aabb]) -> Vec<(usize, usize)> {
+    let mut order: Vec<usize> = (0..aabbs.len()).collect();
+    order.sort_by(|&a, &b| aabbs[a].min.x.total_cmp(&aabbs[b].min.x).then(a.cmp(&b)));
+
+    let mut pairs = Vec::new();
+    // Boxes whose X range the sweep line is still inside
+    let mut active: Vec<usize> = Vec::new();
+    for &index in &order {
+        let aabb = &aabbs[index];
+        active.retain(|&other| aabbs[other].max.x >= aabb.min.x);
+        for &other in &active {
+            if aabbs[other].overlaps(aabb) {
+                pairs.push((other.min(index), other.max(index)));
+            }
+        }
+        active.push(index);
+    }
+
+    pairs.sort_unstable();
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Vector2;
+
+    fn aabb(x: f32, y: f32, size: f32) -> Aabb {
+        Aabb {
+            min: Vector2::new(x, y),
+            max: Vector2::new(x + size, y + size),
+        }
+    }
+
+    #[test]
+    fn test_sweep_and_prune_matches_brute_force() {
+        let boxes: Vec<Aabb> = (0..40)
+            .map(|i| aabb((i * 37 % 23) as f32, (i * 11 % 7) as f32, 1.0 + (i % 3) as f32))
+            .collect();
+
+        let mut expected = Vec::new();
+        for a in 0..boxes.len() {
+            for b in a + 1..boxes.len() {
+                if boxes[a].overlaps(&boxes[b]) {
+                    expected.push((a, b));
+                }
+            }
+        }
+        assert!(!expected.is_empty());
+        assert_eq!(sweep_and_prune(&boxes), expected);
+    }
+}
@@ -0,0 +1,319 @@
+// Collision detection and response
+//
+// Every step the CollisionSystem:
+// 1. broadphase: finds pairs of colliders whose bounding boxes overlap
+// 2. narrowphase: computes contact manifolds for those pairs
+// 3. response: applies impulses for restitution and friction, and pushes
+//    overlapping bodies apart
+// Contacts are published in the Contacts resource, and new ones are also
+// sent as CollisionEvents
+pub mod broadphase;
+pub mod narrowphase;
+pub mod response;
+
+use ecs::Entity;
+use nalgebra::Vector2;
+use serde::{Deserialize, Serialize};
+
+use crate::components::Position;
+
+/// Shape of a collider, in the entity's local frame (centred on its Position)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Shape {
+    Circle {
+        radius: f32,
+    },
+
+    /// A box, rotated with the entity
+    Rectangle {
+        half_width: f32,
+        half_height: f32,
+    },
+
+    /// A segment along the local X axis with rounded ends, like a fuselage
+    Capsule {
+        half_length: f32,
+        radius: f32,
+    },
+
+    /// Convex polygon, vertices in counter-clockwise order
+    /// Build it with Collider::polygon, which checks both
+    Polygon {
+        vertices: Vec<Position>,
+    },
+
+    /// Terrain: heights[i] is the ground height at x = i * spacing,
+    /// both relative to the entity's Position. Rotation is ignored
+    Heightfield {
+        heights: Vec<f32>,
+        spacing: f32,
+    },
+}
+
+impl Shape {
+    /// The shape as a convex core plus a rounding radius
+    ///
+    /// Every shape except heightfields is a convex polygon (a single point
+    /// for circles, a segment for capsules) inflated by a radius, so the
+    /// narrowphase only needs one algorithm
+    pub(crate) fn core(&self) -> Option<(Vec<Vector2<f32>>, f32)> {
+        match self {
+            Shape::Circle { radius } => Some((vec![Vector2::zeros()], *radius)),
+            Shape::Rectangle { half_width: w, half_height: h } => Some((
+                vec![Vector2::new(-w, -h), Vector2::new(*w, -h), Vector2::new(*w, *h), Vector2::new(-w, *h)],
+                0.0,
+            )),
+            Shape::Capsule { half_length, radius } => Some((
+                vec![Vector2::new(-half_length, 0.0), Vector2::new(*half_length, 0.0)],
+                *radius,
+            )),
+            Shape::Polygon { vertices } => Some((vertices.iter().map(Position::to_vector).collect(), 0.0)),
+            Shape::Heightfield { .. } => None,
+        }
+    }
+}
+
+/// Collider component - gives an entity a shape other colliders bump into
+///
+/// Entities with a RigidBody and a positive Mass are pushed around by
+/// collisions; every other collider (terrain, buildings, kinematic objects
+/// moved by MovementSystem) is treated as immovable
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Collider {
+    pub shape: Shape,
+
+    /// Bounciness: 0 stops dead on impact, 1 bounces back at full speed
+    /// The larger value of the two colliders is used
+    pub restitution: f32,
+
+    /// Coulomb friction coefficient; the two colliders' values are combined
+    /// with a geometric mean, so ice on anything stays slippery
+    pub friction: f32,
+}
+
+impl Collider {
+    /// A collider that doesn't bounce, with moderate friction
+    pub fn new(shape: Shape) -> Self {
+        Self {
+            shape,
+            restitution: 0.0,
+            friction: 0.5,
+        }
+    }
+
+    pub fn circle(radius: f32) -> Self {
+        Self::new(Shape::Circle { radius })
+    }
+
+    pub fn rectangle(half_width: f32, half_height: f32) -> Self {
+        Self::new(Shape::Rectangle { half_width, half_height })
+    }
+
+    pub fn capsule(half_length: f32, radius: f32) -> Self {
+        Self::new(Shape::Capsule { half_length, radius })
+    }
+
+    /// A convex polygon; vertices may be in either winding order
+    ///
+    /// Returns None for fewer than three vertices or a non-convex outline
+    pub fn polygon(mut vertices: Vec<Position>) -> Option<Self> {
+        if vertices.len() < 3 {
+            return None;
+        }
+
+        // Every turn must go the same way: the 2D cross product of
+        // consecutive edges has the same sign all round
+        let n = vertices.len();
+        let turn = |i: usize| {
+            let a = vertices[i].to_vector();
+            let b = vertices[(i + 1) % n].to_vector();
+            let c = vertices[(i + 2) % n].to_vector();
+            cross(b - a, c - b)
+        };
+        let turns: Vec<f32> = (0..n).map(turn).collect();
+        if turns.iter().all(|&t| t < 0.0) {
+            vertices.reverse();
+        } else if !turns.iter().all(|&t| t > 0.0) {
+            return None;
+        }
+        Some(Self::new(Shape::Polygon { vertices }))
+    }
+
+    /// Terrain with ground heights sampled every `spacing` metres
+    pub fn heightfield(heights: Vec<f32>, spacing: f32) -> Self {
+        Self::new(Shape::Heightfield { heights, spacing })
+    }
+
+    pub fn with_restitution(mut self, restitution: f32) -> Self {
+        self.restitution = restitution;
+        self
+    }
+
+    pub fn with_friction(mut self, friction: f32) -> Self {
+        self.friction = friction;
+        self
+    }
+
+    /// World-space bounding box of this collider at the given placement
+    pub fn aabb(&self, position: Vector2<f32>, angle: f32) -> Aabb {
+        match &self.shape {
+            Shape::Heightfield { heights, spacing } => {
+                let low = heights.iter().copied().fold(f32::INFINITY, f32::min);
+                let high = heights.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+                let width = spacing * heights.len().saturating_sub(1) as f32;
+                Aabb {
+                    // Cells reach below the lowest point, see narrowphase
+                    min: position + Vector2::new(0.0, low - narrowphase::TERRAIN_DEPTH),
+                    max: position + Vector2::new(width, high),
+                }
+            }
+            shape => {
+                let (core, radius) = shape.core().expect("only heightfields have no core");
+                let rotation = nalgebra::Rotation2::new(angle);
+                let mut aabb = Aabb::empty();
+                for vertex in core {
+                    aabb.include(position + rotation * vertex);
+                }
+                aabb.grow(radius)
+            }
+        }
+    }
+}
+
+/// 2D cross product: the z component of (a, 0) × (b, 0)
+pub(crate) fn cross(a: Vector2<f32>, b: Vector2<f32>) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+/// Axis-aligned bounding box
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vector2<f32>,
+    pub max: Vector2<f32>,
+}
+
+impl Aabb {
+    /// A box containing nothing; include() grows it
+    fn empty() -> Self {
+        Self {
+            min: Vector2::repeat(f32::INFINITY),
+            max: Vector2::repeat(f32::NEG_INFINITY),
+        }
+    }
+
+    fn include(&mut self, point: Vector2<f32>) {
+        self.min = self.min.inf(&point);
+        self.max = self.max.sup(&point);
+    }
+
+    fn grow(self, margin: f32) -> Self {
+        Self {
+            min: self.min - Vector2::repeat(margin),
+            max: self.max + Vector2::repeat(margin),
+        }
+    }
+
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        self.min.x <= other.max.x && other.min.x <= self.max.x && self.min.y <= other.max.y && other.min.y <= self.max.y
+    }
+}
+
+/// One point where two colliders touch
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContactPoint {
+    /// World-space point, halfway between the two surfaces
+    pub point: Vector2<f32>,
+    /// How far the colliders overlap here, in metres
+    pub depth: f32,
+}
+
+/// Everything known about two colliders touching
+///
+/// Flat faces resting on each other get two points, so boxes sit still
+/// instead of rocking; other contacts get one
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContactManifold {
+    pub a: Entity,
+    pub b: Entity,
+    /// Unit vector pointing from a towards b
+    pub normal: Vector2<f32>,
+    pub points: Vec<ContactPoint>,
+}
+
+impl ContactManifold {
+    /// The entity a is touching, if `entity` is b, and the other way round
+    pub fn other(&self, entity: Entity) -> Option<Entity> {
+        match entity {
+            e if e == self.a => Some(self.b),
+            e if e == self.b => Some(self.a),
+            _ => None,
+        }
+    }
+
+    pub fn depth(&self) -> f32 {
+        self.points.iter().map(|point| point.depth).fold(0.0, f32::max)
+    }
+}
+
+/// Every contact from the latest collision step, stored as a resource
+#[derive(Debug, Clone, Default)]
+pub struct Contacts {
+    manifolds: Vec<ContactManifold>,
+}
+
+impl Contacts {
+    pub fn new(manifolds: Vec<ContactManifold>) -> Self {
+        Self { manifolds }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ContactManifold> {
+        self.manifolds.iter()
+    }
+
+    /// Contacts involving `entity`
+    pub fn involving(&self, entity: Entity) -> impl Iterator<Item = &ContactManifold> {
+        self.manifolds.iter().filter(move |manifold| manifold.other(entity).is_some())
+    }
+
+    pub fn touching(&self, a: Entity, b: Entity) -> bool {
+        self.manifolds.iter().any(|manifold| manifold.other(a) == Some(b))
+    }
+
+    pub fn len(&self) -> usize {
+        self.manifolds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.manifolds.is_empty()
+    }
+}
+
+/// Sent when two colliders start touching
+///
+/// Read it with an EventReader to react to impacts, e.g. ending an RL
+/// episode when the aircraft hits the ground:
+///
+/// for event in reader.read(&world.resource::<Events<CollisionEvent>>()?) {
+///     if event.other(aircraft) == Some(terrain) { done = true; }
+/// }
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CollisionEvent {
+    pub a: Entity,
+    pub b: Entity,
+    /// Unit vector pointing from a towards b
+    pub normal: Vector2<f32>,
+    pub point: Vector2<f32>,
+    /// Closing speed along the normal at impact, in m/s
+    pub impact_speed: f32,
+}
+
+impl CollisionEvent {
+    /// The entity `entity` collided with, if it's part of this collision
+    pub fn other(&self, entity: Entity) -> Option<Entity> {
+        match entity {
+            e if e == self.a => Some(self.b),
+            e if e == self.b => Some(self.a),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,397 @@
+// Narrowphase - exact contacts between two shapes
+//
+// Shapes other than terrain are convex "cores" (a point, a segment or a
+// polygon) inflated by a radius. Overlapping cores are found with the
+// separating axis test; cores that don't overlap may still touch through
+// their radii, which the closest points between the cores tell us
+use nalgebra::{Rotation2, Vector2};
+
+use super::{ContactPoint, Shape};
+
+/// How far below its lowest point terrain counts as solid, in metres
+///
+/// Heightfield cells are closed off this far down, so a body that sinks
+/// into the ground is still pushed back up rather than falling through
+pub(crate) const TERRAIN_DEPTH: f32 = 10.0;
+
+/// Where a shape is: its entity's position and rotation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Placement {
+    pub position: Vector2<f32>,
+    pub angle: f32,
+}
+
+/// Contact between two shapes, before it's tied to entities
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contact {
+    /// Unit vector pointing from the first shape towards the second
+    pub normal: Vector2<f32>,
+    pub points: Vec<ContactPoint>,
+}
+
+impl Contact {
+    fn flipped(self) -> Self {
+        Self {
+            normal: -self.normal,
+            ..self
+        }
+    }
+}
+
+/// One edge of a core, with its outward unit normal
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+    start: Vector2<f32>,
+    end: Vector2<f32>,
+    normal: Vector2<f32>,
+}
+
+/// Contacts between two placed shapes, normals pointing from `a` towards `b`
+///
+/// Usually zero or one contact; a body resting across several terrain
+/// cells gets one per cell it touches
+pub fn collide(a: &Shape, placement_a: Placement, b: &Shape, placement_b: Placement) -> Vec<Contact> {
+    match (a, b) {
+        (Shape::Heightfield { .. }, Shape::Heightfield { .. }) => Vec::new(),
+        (Shape::Heightfield { heights, spacing }, other) => collide_terrain(heights, *spacing, placement_a.position, other, placement_b),
+        (other, Shape::Heightfield { heights, spacing }) => collide_terrain(heights, *spacing, placement_b.position, other, placement_a)
+            .into_iter()
+            .map(Contact::flipped)
+            .collect(),
+        _ => {
+            let (core_a, radius_a) = world_core(a, placement_a);
+            let (core_b, radius_b) = world_core(b, placement_b);
+            collide_convex(&core_a, radius_a, &core_b, radius_b).into_iter().collect()
+        }
+    }
+}
+
+/// A shape's core vertices in world space, and its radius
+fn world_core(shape: &Shape, placement: Placement) -> (Vec<Vector2<f32>>, f32) {
+    let (core, radius) = shape.core().expect("heightfields are handled separately");
+    let rotation = Rotation2::new(placement.angle);
+    (core.into_iter().map(|vertex| placement.position + rotation * vertex).collect(), radius)
+}
+
+/// Contacts between terrain and a convex shape, normals pointing up out of the terrain
+fn collide_terrain(heights: &[f32], spacing: f32, origin: Vector2<f32>, shape: &Shape, placement: Placement) -> Vec<Contact> {
+    if heights.len() < 2 || spacing <= 0.0 {
+        return Vec::new();
+    }
+    let (core, radius) = world_core(shape, placement);
+    let left = core.iter().map(|v| v.x).fold(f32::INFINITY, f32::min) - radius - origin.x;
+    let right = core.iter().map(|v| v.x).fold(f32::NEG_INFINITY, f32::max) + radius - origin.x;
+
+    // Only the cells under the shape can touch it
+    let first = (left / spacing).floor().max(0.0) as usize;
+    let last = ((right / spacing).floor().max(0.0) as usize).min(heights.len() - 2);
+    let bottom = heights.iter().copied().fold(f32::INFINITY, f32::min) - TERRAIN_DEPTH;
+
+    (first..=last)
+        .filter_map(|cell| {
+            let x0 = origin.x + cell as f32 * spacing;
+            let x1 = x0 + spacing;
+            // Each cell is a convex quad, counter-clockwise from bottom left
+            let quad = [
+                Vector2::new(x0, origin.y + bottom),
+                Vector2::new(x1, origin.y + bottom),
+                Vector2::new(x1, origin.y + heights[cell + 1]),
+                Vector2::new(x0, origin.y + heights[cell]),
+            ];
+            collide_convex(&quad, 0.0, &core, radius)
+        })
+        .collect()
+}
+
+/// Contact between two rounded convex cores, normal pointing from `a` to `b`
+fn collide_convex(a: &[Vector2<f32>], radius_a: f32, b: &[Vector2<f32>], radius_b: f32) -> Option<Contact> {
+    // Step 1: Separating axis test on the cores
+    // For each axis, project both cores and measure how much they overlap;
+    // a gap on any axis means the cores don't intersect
+    let mut candidates = axes(a);
+    candidates.extend(axes(b));
+    if candidates.is_empty() {
+        // Two points: the only axis that matters is the one joining them
+        candidates.push((b[0] - a[0]).try_normalize(f32::EPSILON).unwrap_or_else(Vector2::y));
+    }
+
+    let mut best: Option<(f32, Vector2<f32>)> = None;
+    for axis in candidates {
+        let (min_a, max_a) = project(a, axis);
+        let (min_b, max_b) = project(b, axis);
+        // Overlap if b is pushed out along +axis, or along -axis
+        let (overlap, normal) = if max_a - min_b < max_b - min_a {
+            (max_a - min_b, axis)
+        } else {
+            (max_b - min_a, -axis)
+        };
+        if overlap < 0.0 {
+            best = None;
+            break;
+        }
+        let smaller = match best {
+            Some((smallest, _)) => overlap < smallest,
+            None => true,
+        };
+        if smaller {
+            best = Some((overlap, normal));
+        }
+    }
+
+    // Step 2: Normal and depth, from the SAT if the cores overlap, or else
+    // from the closest points between them and the radii
+    let (normal, depth) = match best {
+        Some((overlap, normal)) => (normal, overlap + radius_a + radius_b),
+        None => {
+            let (closest_a, closest_b) = closest_points(a, b);
+            let delta = closest_b - closest_a;
+            let distance = delta.norm();
+            if distance >= radius_a + radius_b || distance <= f32::EPSILON {
+                return None;
+            }
+            (delta / distance, radius_a + radius_b - distance)
+        }
+    };
+
+    // Step 3: Contact points. Two faces resting on each other get up to two
+    // points by clipping one face against the other; anything else gets one
+    if a.len() >= 2 && b.len() >= 2 {
+        if let Some(contact) = clip(a, radius_a, b, radius_b, normal) {
+            return Some(contact);
+        }
+    }
+    let point = if a.len() == 1 {
+        a[0] + normal * (radius_a - depth / 2.0)
+    } else {
+        let deepest = b.iter().copied().min_by(|p, q| p.dot(&normal).total_cmp(&q.dot(&normal))).expect("cores aren't empty");
+        deepest - normal * (radius_b - depth / 2.0)
+    };
+    Some(Contact {
+        normal,
+        points: vec![ContactPoint { point, depth }],
+    })
+}
+
+/// Candidate separating axes for one core
+fn axes(core: &[Vector2<f32>]) -> Vec<Vector2<f32>> {
+    let mut axes: Vec<Vector2<f32>> = edges(core).iter().map(|edge| edge.normal).collect();
+    // A segment also needs its own direction, to separate it from points
+    // beyond its ends
+    if core.len() == 2 {
+        if let Some(direction) = (core[1] - core[0]).try_normalize(f32::EPSILON) {
+            axes.push(direction);
+        }
+    }
+    axes
+}
+
+/// Edges with outward normals: none for a point, both sides of a segment,
+/// and every side of a counter-clockwise polygon
+fn edges(core: &[Vector2<f32>]) -> Vec<Edge> {
+    let n = core.len();
+    if n < 2 {
+        return Vec::new();
+    }
+    let count = if n == 2 { 2 } else { n };
+    (0..count)
+        .filter_map(|i| {
+            let start = core[i];
+            let end = core[(i + 1) % n];
+            // The right-hand perpendicular points out of a counter-clockwise polygon
+            let direction = end - start;
+            let normal = Vector2::new(direction.y, -direction.x).try_normalize(f32::EPSILON)?;
+            Some(Edge { start, end, normal })
+        })
+        .collect()
+}
+
+/// Smallest and largest projection of the core's vertices onto `axis`
+fn project(core: &[Vector2<f32>], axis: Vector2<f32>) -> (f32, f32) {
+    core.iter()
+        .map(|vertex| vertex.dot(&axis))
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), p| (min.min(p), max.max(p)))
+}
+
+/// Closest pair of points between two cores that don't intersect
+///
+/// For convex shapes the closest pair always includes a vertex of one of
+/// them, so checking every vertex against every side of the other is exact
+fn closest_points(a: &[Vector2<f32>], b: &[Vector2<f32>]) -> (Vector2<f32>, Vector2<f32>) {
+    let mut best = (a[0], b[0]);
+    let mut best_distance = f32::INFINITY;
+    let mut consider = |p: Vector2<f32>, q: Vector2<f32>| {
+        let distance = (q - p).norm_squared();
+        if distance < best_distance {
+            best_distance = distance;
+            best = (p, q);
+        }
+    };
+    for &vertex in a {
+        for (start, end) in segments(b) {
+            consider(vertex, closest_on_segment(vertex, start, end));
+        }
+    }
+    for &vertex in b {
+        for (start, end) in segments(a) {
+            consider(closest_on_segment(vertex, start, end), vertex);
+        }
+    }
+    best
+}
+
+/// The sides of a core as segments; a point is a segment of zero length
+fn segments(core: &[Vector2<f32>]) -> Vec<(Vector2<f32>, Vector2<f32>)> {
+    match core.len() {
+        1 => vec![(core[0], core[0])],
+        2 => vec![(core[0], core[1])],
+        n => (0..n).map(|i| (core[i], core[(i + 1) % n])).collect(),
+    }
+}
+
+fn closest_on_segment(point: Vector2<f32>, start: Vector2<f32>, end: Vector2<f32>) -> Vector2<f32> {
+    let direction = end - start;
+    let length_squared = direction.norm_squared();
+    if length_squared <= f32::EPSILON {
+        return start;
+    }
+    let t = ((point - start).dot(&direction) / length_squared).clamp(0.0, 1.0);
+    start + direction * t
+}
+
+/// Contact points between the faces of `a` and `b` that face each other
+///
+/// The better aligned face is the reference; the other (incident) face is
+/// clipped to the reference face's width, and the clipped ends that are
+/// within reach of the reference face are the contact points
+fn clip(a: &[Vector2<f32>], radius_a: f32, b: &[Vector2<f32>], radius_b: f32, normal: Vector2<f32>) -> Option<Contact> {
+    let face_a = *edges(a).iter().max_by(|e, f| e.normal.dot(&normal).total_cmp(&f.normal.dot(&normal)))?;
+    let face_b = *edges(b).iter().max_by(|e, f| e.normal.dot(&-normal).total_cmp(&f.normal.dot(&-normal)))?;
+
+    // Prefer a's face unless b's is clearly better aligned, so the choice
+    // doesn't flicker between steps
+    let b_is_reference = face_b.normal.dot(&-normal) > face_a.normal.dot(&normal) + 1e-3;
+    let (reference, incident, radius_reference, radius_incident) = if b_is_reference {
+        (face_b, face_a, radius_b, radius_a)
+    } else {
+        (face_a, face_b, radius_a, radius_b)
+    };
+
+    let along = reference.end - reference.start;
+    let width = along.norm();
+    let tangent = along / width;
+    let offset = |p: Vector2<f32>| (p - reference.start).dot(&tangent);
+    let [p0, p1] = clip_half([incident.start, incident.end], offset)?;
+    let clipped = clip_half([p0, p1], |p| width - offset(p))?;
+
+    let reach = radius_reference + radius_incident;
+    let points: Vec<ContactPoint> = clipped
+        .into_iter()
+        .filter_map(|p| {
+            // How far the incident core is above the reference face
+            let separation = (p - reference.start).dot(&reference.normal);
+            (separation < reach).then(|| ContactPoint {
+                // Halfway between the two surfaces
+                point: p + reference.normal * ((radius_reference - radius_incident - separation) / 2.0),
+                depth: reach - separation,
+            })
+        })
+        .collect();
+    if points.is_empty() {
+        return None;
+    }
+
+    let normal = if b_is_reference { -reference.normal } else { reference.normal };
+    Some(Contact { normal, points })
+}
+
+/// The part of a segment where `distance` is non-negative
+fn clip_half(points: [Vector2<f32>; 2], distance: impl Fn(Vector2<f32>) -> f32) -> Option<[Vector2<f32>; 2]> {
+    let [p0, p1] = points;
+    let (d0, d1) = (distance(p0), distance(p1));
+    match (d0 >= 0.0, d1 >= 0.0) {
+        (true, true) => Some(points),
+        (false, false) => None,
+        // One end is outside: replace it with the crossing point
+        _ => {
+            let crossing = p0 + (p1 - p0) * (d0 / (d0 - d1));
+            Some(if d0 >= 0.0 { [p0, crossing] } else { [crossing, p1] })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: f32, y: f32) -> Placement {
+        Placement {
+            position: Vector2::new(x, y),
+            angle: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_shape_pairs() {
+        let circle = Shape::Circle { radius: 1.0 };
+        let crate_box = Shape::Rectangle { half_width: 1.0, half_height: 0.5 };
+        let capsule = Shape::Capsule { half_length: 2.0, radius: 0.25 };
+
+        // Circles 1.5 apart overlap by 0.5, normal along the line joining them
+        let contacts = collide(&circle, at(0.0, 0.0), &circle, at(1.5, 0.0));
+        assert_eq!(contacts.len(), 1);
+        assert!((contacts[0].normal - Vector2::x()).norm() < 1e-6);
+        assert!((contacts[0].points[0].depth - 0.5).abs() < 1e-6);
+        assert!(collide(&circle, at(0.0, 0.0), &circle, at(2.1, 0.0)).is_empty());
+
+        // A box resting on a wider box touches along a face: two points
+        let contacts = collide(&crate_box, at(0.0, 0.0), &crate_box, at(0.5, 0.9));
+        assert_eq!(contacts[0].points.len(), 2);
+        assert!((contacts[0].normal - Vector2::y()).norm() < 1e-6);
+        assert!(contacts[0].points.iter().all(|p| (p.depth - 0.1).abs() < 1e-5));
+
+        // A capsule lying on a box: flat contact through its radius
+        let contacts = collide(&crate_box, at(0.0, 0.0), &capsule, at(0.0, 0.7));
+        assert_eq!(contacts[0].points.len(), 2);
+        assert!((contacts[0].points[0].depth - 0.05).abs() < 1e-5);
+
+        // Normals always point from the first shape to the second
+        let contacts = collide(&capsule, at(0.0, 0.7), &crate_box, at(0.0, 0.0));
+        assert!((contacts[0].normal + Vector2::y()).norm() < 1e-6);
+
+        // A circle near a corner, but outside it, doesn't touch
+        assert!(collide(&crate_box, at(0.0, 0.0), &circle, at(1.8, 1.3)).is_empty());
+        // A circle inside the corner's reach does, pushed out diagonally
+        let contacts = collide(&crate_box, at(0.0, 0.0), &circle, at(1.5, 1.0));
+        assert!(contacts[0].normal.x > 0.5 && contacts[0].normal.y > 0.5);
+    }
+
+    #[test]
+    fn test_rotated_and_terrain_contacts() {
+        // A box standing on its corner, rotated 45°, just touching the ground
+        let diamond = Shape::Rectangle { half_width: 1.0, half_height: 1.0 };
+        let ground = Shape::Rectangle { half_width: 10.0, half_height: 1.0 };
+        let tilted = Placement {
+            position: Vector2::new(0.0, 1.0 + 2f32.sqrt() - 0.01),
+            angle: std::f32::consts::FRAC_PI_4,
+        };
+        let contacts = collide(&ground, at(0.0, 0.0), &diamond, tilted);
+        assert_eq!(contacts[0].points.len(), 1);
+        assert!((contacts[0].points[0].depth - 0.01).abs() < 1e-4);
+
+        // A slope rising 1 in 2: a circle resting on it is pushed out
+        // perpendicular to the slope, not straight up
+        let terrain = Shape::Heightfield {
+            heights: vec![0.0, 1.0, 2.0, 3.0],
+            spacing: 2.0,
+        };
+        let slope_normal = Vector2::new(-1.0, 2.0).normalize();
+        let on_slope = Vector2::new(3.0, 1.5) + slope_normal * 0.9;
+        let contacts = collide(&terrain, at(0.0, 0.0), &Shape::Circle { radius: 1.0 }, at(on_slope.x, on_slope.y));
+        assert!(!contacts.is_empty());
+        assert!(contacts.iter().all(|c| (c.normal - slope_normal).norm() < 1e-4));
+        assert!((contacts[0].points[0].depth - 0.1).abs() < 1e-4);
+
+        // Nothing happens beyond the end of the terrain
+        assert!(collide(&terrain, at(0.0, 0.0), &Shape::Circle { radius: 1.0 }, at(20.0, 0.0)).is_empty());
+    }
+}
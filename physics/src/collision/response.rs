@@ -0,0 +1,233 @@
+// Collision response - impulses that stop bodies going through each other
+//
+// Sequential impulses: each contact point in turn gets the impulse that
+// fixes its relative velocity, and the whole set is swept several times so
+// contacts that affect each other (a stack of boxes) settle together
+use nalgebra::Vector2;
+
+use super::{cross, ContactPoint};
+
+/// Passes over all contacts per step; more converges better, costs more
+pub const ITERATIONS: usize = 8;
+
+/// Overlap left alone so resting contacts don't jitter, in metres
+const SLOP: f32 = 0.005;
+
+/// Fraction of the remaining overlap removed each step
+const CORRECTION: f32 = 0.8;
+
+/// Impacts slower than this don't bounce, in m/s
+/// Without it, resting bodies would bounce on the tiny velocities gravity gives them
+const BOUNCE_THRESHOLD: f32 = 0.5;
+
+/// The parts of a body that collisions and joints read and change
+///
+/// Immovable bodies (terrain, kinematic objects) have zero inverse mass and
+/// inertia: no impulse can change their velocity
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolverBody {
+    pub inv_mass: f32,
+    pub inv_inertia: f32,
+    pub position: Vector2<f32>,
+    /// Rotation in radians; collisions leave it alone, joints correct it
+    pub angle: f32,
+    pub velocity: Vector2<f32>,
+    pub angular_velocity: f32,
+}
+
+impl SolverBody {
+    pub fn is_movable(&self) -> bool {
+        self.inv_mass > 0.0 || self.inv_inertia > 0.0
+    }
+
+    /// Velocity of the point at offset `r` from the centre of mass
+    ///
+    /// Spinning adds ω × r, which in 2D is (-ω r.y, ω r.x)
+    pub(crate) fn velocity_at(&self, r: Vector2<f32>) -> Vector2<f32> {
+        self.velocity + Vector2::new(-self.angular_velocity * r.y, self.angular_velocity * r.x)
+    }
+
+    pub(crate) fn apply_impulse(&mut self, impulse: Vector2<f32>, r: Vector2<f32>) {
+        self.velocity += impulse * self.inv_mass;
+        self.angular_velocity += self.inv_inertia * cross(r, impulse);
+    }
+}
+
+/// A contact between bodies[a] and bodies[b], ready for solving
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolverContact {
+    pub a: usize,
+    pub b: usize,
+    /// Unit vector pointing from a towards b
+    pub normal: Vector2<f32>,
+    pub points: Vec<ContactPoint>,
+    pub restitution: f32,
+    pub friction: f32,
+}
+
+/// Per-point values that stay fixed while iterating
+struct PointSolver {
+    contact: usize,
+    r_a: Vector2<f32>,
+    r_b: Vector2<f32>,
+    normal_mass: f32,
+    tangent_mass: f32,
+    /// Target closing speed after the bounce
+    bounce: f32,
+    /// Impulses so far; clamping the totals, not each step's share, is what
+    /// lets the solver take back an impulse it overdid earlier
+    normal_impulse: f32,
+    tangent_impulse: f32,
+}
+
+/// Apply collision impulses and separate overlapping bodies
+///
+/// Returns the closing speed of each contact before solving (positive when
+/// the bodies were approaching), for impact reporting
+pub fn solve(bodies: &mut [SolverBody], contacts: &[SolverContact]) -> Vec<f32> {
+    let mut impact_speeds = vec![0.0f32; contacts.len()];
+
+    // Step 1: Prepare each point
+    let mut points = Vec::new();
+    for (index, contact) in contacts.iter().enumerate() {
+        let (a, b) = (bodies[contact.a], bodies[contact.b]);
+        let tangent = Vector2::new(-contact.normal.y, contact.normal.x);
+        for point in &contact.points {
+            let r_a = point.point - a.position;
+            let r_b = point.point - b.position;
+
+            // Effective mass along a direction: how much the relative
+            // velocity changes per unit impulse, inverted
+            let effective_mass = |direction: Vector2<f32>| {
+                let k = a.inv_mass + b.inv_mass + a.inv_inertia * cross(r_a, direction).powi(2) + b.inv_inertia * cross(r_b, direction).powi(2);
+                if k > 0.0 { 1.0 / k } else { 0.0 }
+            };
+
+            let closing = -(b.velocity_at(r_b) - a.velocity_at(r_a)).dot(&contact.normal);
+            impact_speeds[index] = impact_speeds[index].max(closing);
+            points.push(PointSolver {
+                contact: index,
+                r_a,
+                r_b,
+                normal_mass: effective_mass(contact.normal),
+                tangent_mass: effective_mass(tangent),
+                bounce: if closing > BOUNCE_THRESHOLD { contact.restitution * closing } else { 0.0 },
+                normal_impulse: 0.0,
+                tangent_impulse: 0.0,
+            });
+        }
+    }
+
+    // Step 2: Sweep over the points, fixing velocities
+    for _ in 0..ITERATIONS {
+        for point in &mut points {
+            let contact = &contacts[point.contact];
+            let (mut a, mut b) = (bodies[contact.a], bodies[contact.b]);
+            let normal = contact.normal;
+            let tangent = Vector2::new(-normal.y, normal.x);
+
+            // Normal impulse: stop the approach (plus bounce), never pull
+            let relative = b.velocity_at(point.r_b) - a.velocity_at(point.r_a);
+            let impulse = -(relative.dot(&normal) - point.bounce) * point.normal_mass;
+            let total = (point.normal_impulse + impulse).max(0.0);
+            let impulse = normal * (total - point.normal_impulse);
+            point.normal_impulse = total;
+            a.apply_impulse(-impulse, point.r_a);
+            b.apply_impulse(impulse, point.r_b);
+
+            // Friction: oppose sliding, up to μ times the normal impulse
+            let relative = b.velocity_at(point.r_b) - a.velocity_at(point.r_a);
+            let impulse = -relative.dot(&tangent) * point.tangent_mass;
+            let limit = contact.friction * point.normal_impulse;
+            let total = (point.tangent_impulse + impulse).clamp(-limit, limit);
+            let impulse = tangent * (total - point.tangent_impulse);
+            point.tangent_impulse = total;
+            a.apply_impulse(-impulse, point.r_a);
+            b.apply_impulse(impulse, point.r_b);
+
+            bodies[contact.a] = a;
+            bodies[contact.b] = b;
+        }
+    }
+
+    // Step 3: Push overlapping bodies apart, lighter ones further
+    // Velocity impulses stop bodies sinking deeper; this removes the overlap
+    // that built up before the contact was found
+    for contact in contacts {
+        let total_inv_mass = bodies[contact.a].inv_mass + bodies[contact.b].inv_mass;
+        if total_inv_mass <= 0.0 {
+            continue;
+        }
+        let depth = contact.points.iter().map(|point| point.depth).fold(0.0, f32::max);
+        let push = contact.normal * ((depth - SLOP).max(0.0) * CORRECTION / total_inv_mass);
+        let inv_mass_a = bodies[contact.a].inv_mass;
+        let inv_mass_b = bodies[contact.b].inv_mass;
+        bodies[contact.a].position -= push * inv_mass_a;
+        bodies[contact.b].position += push * inv_mass_b;
+    }
+
+    impact_speeds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ground() -> SolverBody {
+        SolverBody {
+            inv_mass: 0.0,
+            inv_inertia: 0.0,
+            position: Vector2::zeros(),
+            angle: 0.0,
+            velocity: Vector2::zeros(),
+            angular_velocity: 0.0,
+        }
+    }
+
+    fn ball(velocity: Vector2<f32>) -> SolverBody {
+        SolverBody {
+            inv_mass: 1.0,
+            inv_inertia: 0.0,
+            position: Vector2::new(0.0, 1.0),
+            angle: 0.0,
+            velocity,
+            angular_velocity: 0.0,
+        }
+    }
+
+    fn landing(restitution: f32, friction: f32) -> SolverContact {
+        SolverContact {
+            a: 0,
+            b: 1,
+            normal: Vector2::y(),
+            points: vec![ContactPoint {
+                point: Vector2::new(0.0, 0.0),
+                depth: 0.1,
+            }],
+            restitution,
+            friction,
+        }
+    }
+
+    #[test]
+    fn test_restitution_and_friction() {
+        // Bouncy, frictionless: vertical speed reverses, horizontal is kept
+        let mut bodies = [ground(), ball(Vector2::new(3.0, -4.0))];
+        let speeds = solve(&mut bodies, &[landing(0.5, 0.0)]);
+        assert_eq!(speeds, vec![4.0]);
+        assert!((bodies[1].velocity - Vector2::new(3.0, 2.0)).norm() < 1e-5);
+        // The overlap is mostly removed, and only the movable body moves
+        assert!((bodies[1].position.y - (1.0 + 0.8 * 0.095)).abs() < 1e-5);
+        assert_eq!(bodies[0], ground());
+
+        // Dead and rough: friction can remove at most μ times the normal impulse
+        let mut bodies = [ground(), ball(Vector2::new(3.0, -4.0))];
+        solve(&mut bodies, &[landing(0.0, 0.5)]);
+        assert!((bodies[1].velocity - Vector2::new(1.0, 0.0)).norm() < 1e-5);
+
+        // Separating bodies are left alone
+        let mut bodies = [ground(), ball(Vector2::new(0.0, 2.0))];
+        solve(&mut bodies, &[landing(1.0, 1.0)]);
+        assert!((bodies[1].velocity - Vector2::new(0.0, 2.0)).norm() < 1e-6);
+    }
+}
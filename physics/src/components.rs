@@ -0,0 +1,309 @@
+// Physics components - the state a body carries through the simulation
+//
+// Position, Velocity, Rotation and Mass started out in the simulator crate;
+// they live here so the physics systems can use them, and the simulator
+// re-exports them from simulator::components
+use nalgebra::Vector2;
+use serde::{Deserialize, Serialize};
+
+/// 2D position component
+/// 
+/// This represents where an entity is located in 2D space
+/// Components in ECS are just data - no behavior/methods for game logic
+/// 
+/// The #[derive(...)] is a "derive macro" that automatically generates code:
+/// - Debug: Lets you print the struct with {:?} 
+/// - Clone: Lets you make copies with .clone()
+/// - Copy: Lets you copy with just assignment (very cheap)
+/// - PartialEq: Lets you compare with == and !=
+/// - Serialize/Deserialize: Lets you save/load to files or send over network
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Position {
+    /// X coordinate in world space
+    /// 'pub' means other modules can read/write this field directly
+    /// f32 is a 32-bit floating point number (like float in C/Java)
+    pub x: f32,
+    
+    /// Y coordinate in world space  
+    /// In 2D games, usually +X = right, +Y = up (or down, depending on system)
+    pub y: f32,
+}
+
+// Implementation block - where we define methods for Position
+// This is like defining class methods in other languages
+impl Position {
+    /// Create a new Position with specific coordinates
+    /// 
+    /// This is an "associated function" (like a static method)
+    /// No 'self' parameter means you call it like: Position::new(1.0, 2.0)
+    /// 'Self' is an alias for the current type (Position)
+    pub fn new(x: f32, y: f32) -> Self {
+        Self { x, y }   // Struct literal syntax - creates a new Position
+    }
+    
+    /// Create a position at the origin (0, 0)
+    /// 
+    /// Another associated function - provides a convenient default
+    /// This is a common Rust pattern for creating "default" values
+    pub fn zero() -> Self {
+        Self { x: 0.0, y: 0.0 }
+    }
+    
+    /// Convert to a nalgebra Vector2 for math operations
+    /// 
+    /// This is a method (has &self parameter)  
+    /// &self means "borrow self immutably" - we can read but not modify
+    /// Methods are called like: position.to_vector()
+    pub fn to_vector(&self) -> Vector2<f32> {
+        Vector2::new(self.x, self.y)  // Create a nalgebra vector
+    }
+    
+    /// Create a Position from a nalgebra Vector2
+    /// 
+    /// Associated function for conversion from vector math
+    /// This lets us easily convert between our component and math library
+    pub fn from_vector(v: Vector2<f32>) -> Self {
+        Self { x: v.x, y: v.y }
+    }
+}
+
+/// 2D velocity component
+/// 
+/// This represents how fast and in what direction an entity is moving
+/// Velocity is typically in units per second (e.g., meters/second, pixels/second)
+/// Positive X usually means moving right, positive Y means moving up
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Velocity {
+    /// Velocity in X direction (horizontal speed)
+    pub x: f32,
+    
+    /// Velocity in Y direction (vertical speed)  
+    pub y: f32,
+}
+
+impl Velocity {
+    /// Create a new velocity with specific X and Y components
+    pub fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+    
+    /// Create a zero velocity (not moving)
+    /// 
+    /// Useful for stationary objects or when you want to stop something
+    pub fn zero() -> Self {
+        Self { x: 0.0, y: 0.0 }
+    }
+    
+    /// Convert to nalgebra Vector2 for vector math operations
+    /// 
+    /// Vector math is useful for operations like:
+    /// - Adding velocities together
+    /// - Rotating velocity vectors  
+    /// - Normalizing to unit vectors
+    pub fn to_vector(&self) -> Vector2<f32> {
+        Vector2::new(self.x, self.y)
+    }
+    
+    /// Create velocity from a nalgebra Vector2
+    pub fn from_vector(v: Vector2<f32>) -> Self {
+        Self { x: v.x, y: v.y }
+    }
+    
+    /// Calculate the magnitude (speed) of this velocity
+    /// 
+    /// Uses the Pythagorean theorem: magnitude = sqrt(x² + y²)
+    /// This gives you the overall speed regardless of direction
+    /// For example: velocity (3, 4) has magnitude 5
+    pub fn magnitude(&self) -> f32 {
+        (self.x * self.x + self.y * self.y).sqrt()
+    }
+}
+
+/// Rotation component (in radians)
+/// 
+/// Represents how much an entity is rotated from its default orientation
+/// Radians are the standard unit for angles in programming and math:
+/// - 0 radians = 0 degrees (facing right, typically)
+/// - π/2 radians = 90 degrees  
+/// - π radians = 180 degrees
+/// - 2π radians = 360 degrees (full circle)
+/// 
+/// Why radians? Math functions (sin, cos, etc.) expect radians, and they
+/// make calculations simpler (no need to convert constantly)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Rotation {
+    /// Angle in radians
+    /// Positive typically means counter-clockwise rotation
+    pub angle: f32,
+}
+
+impl Rotation {
+    /// Create a new rotation with a specific angle in radians
+    pub fn new(angle: f32) -> Self {
+        Self { angle }
+    }
+    
+    /// Create a rotation of 0 (no rotation)
+    pub fn zero() -> Self {
+        Self { angle: 0.0 }
+    }
+    
+    /// Create a rotation from degrees
+    /// 
+    /// Since humans think in degrees but computers prefer radians,
+    /// this helper function converts for you
+    /// Example: Rotation::degrees(90.0) creates a 90-degree rotation
+    pub fn degrees(degrees: f32) -> Self {
+        Self { 
+            angle: degrees.to_radians()  // Built-in conversion method
+        }
+    }
+    
+    /// Convert this rotation to degrees
+    /// 
+    /// Useful for displaying rotation values to users or debugging
+    /// Most people understand "90 degrees" better than "1.57 radians"
+    pub fn to_degrees(&self) -> f32 {
+        self.angle.to_degrees()  // Built-in conversion method
+    }
+}
+
+/// Mass component for physics calculations
+/// 
+/// Represents how much matter an entity contains
+/// Mass affects:
+/// - How much force is needed to accelerate the object (F = ma)
+/// - How objects behave in collisions
+/// - Gravitational effects
+/// - Inertia (resistance to changes in motion)
+/// 
+/// Units are typically in kilograms, but can be any consistent unit
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Mass {
+    /// Mass value in kilograms (or your chosen unit)
+    /// Should be positive - negative mass would be very strange physics!
+    pub value: f32,
+}
+
+impl Mass {
+    /// Create a new mass with a specific value
+    pub fn new(value: f32) -> Self {
+        Self { value }
+    }
+}
+
+/// Force component for entities
+/// 
+/// Acts as an accumulator: systems in the forces and aerodynamics stages add
+/// their contribution with `add`, the RigidBodySystem turns the total into
+/// acceleration and clears it, so every physics step starts from zero
+/// Units are newtons, in world space
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Force {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Force {
+    pub fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+    
+    pub fn zero() -> Self {
+        Self { x: 0.0, y: 0.0 }
+    }
+    
+    pub fn to_vector(&self) -> Vector2<f32> {
+        Vector2::new(self.x, self.y)
+    }
+    
+    /// Add another force to the total
+    pub fn add(&mut self, force: Force) {
+        self.x += force.x;
+        self.y += force.y;
+    }
+    
+    /// Reset the total to zero, ready for the next step
+    pub fn clear(&mut self) {
+        *self = Self::zero();
+    }
+    
+    /// Torque this force causes when applied at `offset` from the centre of mass
+    /// 
+    /// In 2D the cross product offset × force is a single number:
+    /// positive spins the body counter-clockwise
+    pub fn torque_at(&self, offset: Vector2<f32>) -> Torque {
+        Torque::new(offset.x * self.y - offset.y * self.x)
+    }
+}
+
+/// Torque component - the turning counterpart of Force
+/// 
+/// Accumulated and cleared the same way as Force
+/// Units are newton-metres, positive counter-clockwise
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Torque {
+    pub value: f32,
+}
+
+impl Torque {
+    pub fn new(value: f32) -> Self {
+        Self { value }
+    }
+    
+    pub fn zero() -> Self {
+        Self { value: 0.0 }
+    }
+    
+    /// Add another torque to the total
+    pub fn add(&mut self, torque: Torque) {
+        self.value += torque.value;
+    }
+    
+    /// Reset the total to zero, ready for the next step
+    pub fn clear(&mut self) {
+        *self = Self::zero();
+    }
+}
+
+/// Rigid body component - makes an entity respond to forces and torques
+/// 
+/// A rigid body also needs Position, Mass, Velocity and Rotation: its linear
+/// mass is the Mass component, this holds what's needed for rotation. Add
+/// Force and Torque components, or a ForceModel, to push it around; without
+/// them it keeps its current motion
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RigidBody {
+    /// Moment of inertia about the centre of mass, in kg·m²
+    /// 
+    /// The rotational equivalent of mass: how hard the body is to spin up.
+    /// Zero or less means the body never turns, whatever the torque
+    pub inertia: f32,
+    
+    /// Spin rate in radians per second, positive counter-clockwise
+    pub angular_velocity: f32,
+}
+
+impl RigidBody {
+    /// A body with the given moment of inertia, not spinning
+    pub fn new(inertia: f32) -> Self {
+        Self {
+            inertia,
+            angular_velocity: 0.0,
+        }
+    }
+    
+    /// A solid rectangle, e.g. a fuselage seen from the side
+    /// 
+    /// Inertia of a rectangle about its centre: m * (w² + h²) / 12
+    pub fn rectangle(mass: Mass, width: f32, height: f32) -> Self {
+        Self::new(mass.value * (width * width + height * height) / 12.0)
+    }
+    
+    /// A solid disc, e.g. a wheel
+    /// 
+    /// Inertia of a disc about its centre: m * r² / 2
+    pub fn disc(mass: Mass, radius: f32) -> Self {
+        Self::new(mass.value * radius * radius / 2.0)
+    }
+}
@@ -0,0 +1,160 @@
+// Force models - forces written as functions of a body's state
+//
+// Forces added to the Force component are summed once per step and then held
+// constant while the step is integrated. A ForceModel is evaluated instead:
+// the RigidBodySystem asks it for the force at whatever trial state the
+// integrator is looking at, which is what RK4 and Verlet need to be accurate
+use std::sync::Arc;
+
+use ecs::{Entity, World};
+use nalgebra::Vector2;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{Mass, Position};
+use crate::constants::GRAVITY;
+use crate::integrator::BodyState;
+
+/// A force plus the torque that comes with it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Wrench {
+    pub force: Vector2<f32>,
+    pub torque: f32,
+}
+
+impl Wrench {
+    pub fn zero() -> Self {
+        Self {
+            force: Vector2::zeros(),
+            torque: 0.0,
+        }
+    }
+    
+    pub fn from_force(force: Vector2<f32>) -> Self {
+        Self { force, torque: 0.0 }
+    }
+}
+
+impl std::ops::Add for Wrench {
+    type Output = Wrench;
+    
+    fn add(self, other: Wrench) -> Wrench {
+        Wrench {
+            force: self.force + other.force,
+            torque: self.torque + other.torque,
+        }
+    }
+}
+
+/// A force that can be evaluated at any state: the derivative function
+/// integrators call
+/// 
+/// `wrench` must be pure: the result may only depend on `state` and on world
+/// data that doesn't change during the step (components like Mass or Spring).
+/// RK4 calls it four times per step at states the body may never reach, so
+/// it must not write anything or use the body's stored Position or Velocity
+pub trait ForceModel: Send + Sync {
+    /// Force and torque on `entity` if it were in `state`
+    fn wrench(&self, world: &World, entity: Entity, state: &BodyState) -> Wrench;
+}
+
+/// The force models acting on every rigid body, stored as a resource
+/// 
+/// Models are shared behind Arc, so cloning the resource for a forked world
+/// is cheap and both worlds keep using the same models
+#[derive(Clone, Default)]
+pub struct ForceModels {
+    models: Vec<Arc<dyn ForceModel>>,
+}
+
+impl ForceModels {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn add(&mut self, model: impl ForceModel + 'static) -> &mut Self {
+        self.models.push(Arc::new(model));
+        self
+    }
+    
+    pub fn with(mut self, model: impl ForceModel + 'static) -> Self {
+        self.add(model);
+        self
+    }
+    
+    pub fn len(&self) -> usize {
+        self.models.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.models.is_empty()
+    }
+    
+    /// Total force and torque from every model
+    pub fn wrench(&self, world: &World, entity: Entity, state: &BodyState) -> Wrench {
+        self.models
+            .iter()
+            .fold(Wrench::zero(), |total, model| total + model.wrench(world, entity, state))
+    }
+}
+
+/// Uniform gravity: m * g on every body with a Mass
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Gravity {
+    /// Acceleration in m/s²
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Gravity {
+    pub fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+    
+    /// 9.81 m/s² towards -Y
+    pub fn earth() -> Self {
+        Self::new(0.0, -GRAVITY)
+    }
+}
+
+impl ForceModel for Gravity {
+    fn wrench(&self, world: &World, entity: Entity, _state: &BodyState) -> Wrench {
+        let mass = world.get_component::<Mass>(entity).map_or(0.0, |mass| mass.value);
+        Wrench::from_force(Vector2::new(self.x, self.y) * mass)
+    }
+}
+
+/// Spring component - ties a body to a fixed point
+/// 
+/// Pulls with stiffness * distance towards the anchor, and damping slows the
+/// body down along the way. Only bodies with this component feel SpringForce
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Spring {
+    /// Point in world space the spring is fixed to
+    pub anchor: Position,
+    /// Newtons per metre of stretch
+    pub stiffness: f32,
+    /// Newtons per m/s of velocity; 0 for a spring that oscillates forever
+    pub damping: f32,
+}
+
+impl Spring {
+    pub fn new(anchor: Position, stiffness: f32, damping: f32) -> Self {
+        Self { anchor, stiffness, damping }
+    }
+}
+
+/// Force model for the Spring component (Hooke's law plus linear damping)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SpringForce;
+
+impl ForceModel for SpringForce {
+    fn wrench(&self, world: &World, entity: Entity, state: &BodyState) -> Wrench {
+        match world.get_component::<Spring>(entity) {
+            Ok(spring) => {
+                let stretch = state.position - spring.anchor.to_vector();
+                Wrench::from_force(-stretch * spring.stiffness - state.velocity * spring.damping)
+            }
+            Err(_) => Wrench::zero(),
+        }
+    }
+}
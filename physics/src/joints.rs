@@ -0,0 +1,505 @@
+// Joints - constraints that hold two bodies together
+//
+// Hinged control surfaces, tow ropes and kite tethers are all joints. The
+// JointSystem runs right after the rigid bodies have moved: it changes their
+// velocities with impulses until every joint is satisfied (sequential
+// impulses, like collision response), then nudges positions and angles back
+// onto the joints so errors don't build up
+use ecs::{Entity, EntityMap, MapEntities};
+use nalgebra::{Matrix2, Rotation2, Vector2};
+use serde::{Deserialize, Serialize};
+
+use crate::collision::cross;
+use crate::collision::response::SolverBody;
+use crate::components::{Force, Position, Torque};
+use crate::forces::Wrench;
+
+/// Passes over all joints per step; chains of joints need more to stiffen up
+pub const ITERATIONS: usize = 10;
+
+/// Passes that pull drifted anchors back together
+const POSITION_ITERATIONS: usize = 4;
+
+/// Fraction of the remaining position error removed per pass
+const CORRECTION: f32 = 0.8;
+
+/// Allowed range of a revolute joint's angle, in radians
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AngleLimits {
+    pub lower: f32,
+    pub upper: f32,
+}
+
+/// Drives a revolute joint at a set speed, with limited torque
+///
+/// A servo for a control surface: set `speed` towards the deflection you
+/// want, and the joint gets there unless the air pushes back harder than
+/// `max_torque`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Motor {
+    /// Target speed of b relative to a, in rad/s
+    pub speed: f32,
+    /// Most torque the motor can give, in N·m
+    pub max_torque: f32,
+}
+
+/// What a joint holds fixed
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum JointKind {
+    /// A pin: the anchors stay together, the bodies turn freely
+    /// (within the limits, if any)
+    Revolute {
+        limits: Option<AngleLimits>,
+        motor: Option<Motor>,
+    },
+
+    /// The anchors stay `length` apart, like a rigid rod
+    /// With `rope`, they can come closer but never further apart
+    Distance {
+        length: f32,
+        rope: bool,
+    },
+
+    /// Welded: the anchors stay together and the bodies can't turn
+    Fixed,
+}
+
+/// Joint component - links bodies a and b
+///
+/// A joint lives on an entity of its own, so a body can have any number of
+/// them: spawn one with world.spawn((Joint::revolute(wing, hinge, aileron, Position::zero()),))
+///
+/// Bodies with a RigidBody and a positive Mass are moved by the joint; any
+/// other entity with a Position is a fixed anchor point (a winch on the
+/// ground). Joints whose entities are gone, or have no Position, do nothing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Joint {
+    pub a: Entity,
+    pub b: Entity,
+
+    /// Where the joint attaches to each body, in that body's frame
+    pub anchor_a: Position,
+    pub anchor_b: Position,
+
+    pub kind: JointKind,
+
+    /// Angle of b minus angle of a at which the joint angle is zero
+    /// Fixed joints hold it, revolute limits are measured from it
+    pub reference_angle: f32,
+
+    /// Force the joint applied to b last step, in newtons; a got the opposite
+    /// Set by JointSystem, for structural load monitoring
+    #[serde(default)]
+    pub reaction: Force,
+
+    /// Torque the joint applied to b last step, on top of the torque from
+    /// `reaction` acting at the anchor; the hinge moment for revolute joints
+    #[serde(default)]
+    pub reaction_torque: Torque,
+}
+
+impl Joint {
+    pub fn new(a: Entity, anchor_a: Position, b: Entity, anchor_b: Position, kind: JointKind) -> Self {
+        Self {
+            a,
+            b,
+            anchor_a,
+            anchor_b,
+            kind,
+            reference_angle: 0.0,
+            reaction: Force::zero(),
+            reaction_torque: Torque::zero(),
+        }
+    }
+
+    /// A hinge, free to turn either way
+    pub fn revolute(a: Entity, anchor_a: Position, b: Entity, anchor_b: Position) -> Self {
+        Self::new(a, anchor_a, b, anchor_b, JointKind::Revolute { limits: None, motor: None })
+    }
+
+    /// A rigid rod between the anchors
+    pub fn distance(a: Entity, anchor_a: Position, b: Entity, anchor_b: Position, length: f32) -> Self {
+        Self::new(a, anchor_a, b, anchor_b, JointKind::Distance { length, rope: false })
+    }
+
+    /// A rope: it goes slack, but never stretches past `length`
+    pub fn rope(a: Entity, anchor_a: Position, b: Entity, anchor_b: Position, length: f32) -> Self {
+        Self::new(a, anchor_a, b, anchor_b, JointKind::Distance { length, rope: true })
+    }
+
+    pub fn fixed(a: Entity, anchor_a: Position, b: Entity, anchor_b: Position) -> Self {
+        Self::new(a, anchor_a, b, anchor_b, JointKind::Fixed)
+    }
+
+    pub fn with_reference_angle(mut self, reference_angle: f32) -> Self {
+        self.reference_angle = reference_angle;
+        self
+    }
+
+    /// Limit a revolute joint's angle; other joints are unchanged
+    pub fn with_limits(mut self, lower: f32, upper: f32) -> Self {
+        if let JointKind::Revolute { limits, .. } = &mut self.kind {
+            *limits = Some(AngleLimits { lower, upper });
+        }
+        self
+    }
+
+    /// Drive a revolute joint; other joints are unchanged
+    pub fn with_motor(mut self, speed: f32, max_torque: f32) -> Self {
+        if let JointKind::Revolute { motor, .. } = &mut self.kind {
+            *motor = Some(Motor { speed, max_torque });
+        }
+        self
+    }
+
+    /// Change a revolute joint's motor speed, e.g. from a control policy
+    pub fn set_motor_speed(&mut self, speed: f32) {
+        if let JointKind::Revolute { motor: Some(motor), .. } = &mut self.kind {
+            motor.speed = speed;
+        }
+    }
+
+    /// The joint angle for bodies at these angles: 0 at the reference angle
+    pub fn angle(&self, angle_a: f32, angle_b: f32) -> f32 {
+        angle_b - angle_a - self.reference_angle
+    }
+}
+
+impl MapEntities for Joint {
+    fn map_entities(&mut self, map: &EntityMap) {
+        self.a.map_entities(map);
+        self.b.map_entities(map);
+    }
+}
+
+/// A joint between bodies[a] and bodies[b], ready for solving
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolverJoint {
+    pub a: usize,
+    pub b: usize,
+    pub anchor_a: Vector2<f32>,
+    pub anchor_b: Vector2<f32>,
+    pub kind: JointKind,
+    pub reference_angle: f32,
+}
+
+/// Which side of its limits a revolute joint is on
+#[derive(Clone, Copy, PartialEq)]
+enum Limit {
+    Free,
+    AtLower,
+    AtUpper,
+}
+
+/// Per-joint values that stay fixed while iterating, and the impulses so far
+struct JointSolver {
+    r_a: Vector2<f32>,
+    r_b: Vector2<f32>,
+    /// Inverse of the 2x2 effective mass matrix for keeping the anchors together
+    point_mass: Matrix2<f32>,
+    angular_mass: f32,
+    /// Distance joints: unit vector from anchor a to anchor b, and the effective mass along it
+    axis: Vector2<f32>,
+    axial_mass: f32,
+    /// Ropes are only solved while taut
+    taut: bool,
+    limit: Limit,
+
+    point_impulse: Vector2<f32>,
+    axial_impulse: f32,
+    angular_impulse: f32,
+    motor_impulse: f32,
+}
+
+/// World-space offsets from each centre of mass to its anchor
+fn arms(joint: &SolverJoint, a: &SolverBody, b: &SolverBody) -> (Vector2<f32>, Vector2<f32>) {
+    (Rotation2::new(a.angle) * joint.anchor_a, Rotation2::new(b.angle) * joint.anchor_b)
+}
+
+/// How much a unit impulse at the anchors changes their relative velocity,
+/// inverted; zero when neither body can move
+fn point_mass(a: &SolverBody, b: &SolverBody, r_a: Vector2<f32>, r_b: Vector2<f32>) -> Matrix2<f32> {
+    let m = a.inv_mass + b.inv_mass;
+    let (i_a, i_b) = (a.inv_inertia, b.inv_inertia);
+    let k = Matrix2::new(
+        m + i_a * r_a.y * r_a.y + i_b * r_b.y * r_b.y,
+        -i_a * r_a.x * r_a.y - i_b * r_b.x * r_b.y,
+        -i_a * r_a.x * r_a.y - i_b * r_b.x * r_b.y,
+        m + i_a * r_a.x * r_a.x + i_b * r_b.x * r_b.x,
+    );
+    k.try_inverse().unwrap_or_else(Matrix2::zeros)
+}
+
+/// The same along a single direction
+fn axial_mass(a: &SolverBody, b: &SolverBody, r_a: Vector2<f32>, r_b: Vector2<f32>, axis: Vector2<f32>) -> f32 {
+    let k = a.inv_mass + b.inv_mass + a.inv_inertia * cross(r_a, axis).powi(2) + b.inv_inertia * cross(r_b, axis).powi(2);
+    if k > 0.0 { 1.0 / k } else { 0.0 }
+}
+
+fn angular_mass(a: &SolverBody, b: &SolverBody) -> f32 {
+    let k = a.inv_inertia + b.inv_inertia;
+    if k > 0.0 { 1.0 / k } else { 0.0 }
+}
+
+/// Anchor b minus anchor a, in world space
+fn separation(a: &SolverBody, b: &SolverBody, r_a: Vector2<f32>, r_b: Vector2<f32>) -> Vector2<f32> {
+    b.position + r_b - a.position - r_a
+}
+
+/// Move a body as if `impulse` had acted at offset `r` for one unit of time
+fn shift(body: &mut SolverBody, impulse: Vector2<f32>, r: Vector2<f32>) {
+    body.position += impulse * body.inv_mass;
+    body.angle += body.inv_inertia * cross(r, impulse);
+}
+
+/// Enforce every joint on bodies that have just been integrated over `dt`
+///
+/// Returns the force and torque each joint applied to its body b
+pub fn solve(bodies: &mut [SolverBody], joints: &[SolverJoint], dt: f32) -> Vec<Wrench> {
+    // Step 1: Prepare each joint
+    let mut solvers: Vec<JointSolver> = joints
+        .iter()
+        .map(|joint| {
+            let (a, b) = (&bodies[joint.a], &bodies[joint.b]);
+            let (r_a, r_b) = arms(joint, a, b);
+            let offset = separation(a, b, r_a, r_b);
+            let distance = offset.norm();
+            let axis = if distance > f32::EPSILON { offset / distance } else { Vector2::zeros() };
+            let angle = b.angle - a.angle - joint.reference_angle;
+            let (taut, limit) = match joint.kind {
+                JointKind::Distance { length, rope } => (!rope || distance >= length, Limit::Free),
+                JointKind::Revolute { limits: Some(limits), .. } if angle <= limits.lower => (false, Limit::AtLower),
+                JointKind::Revolute { limits: Some(limits), .. } if angle >= limits.upper => (false, Limit::AtUpper),
+                _ => (false, Limit::Free),
+            };
+            JointSolver {
+                r_a,
+                r_b,
+                point_mass: point_mass(a, b, r_a, r_b),
+                angular_mass: angular_mass(a, b),
+                axis,
+                axial_mass: axial_mass(a, b, r_a, r_b, axis),
+                taut,
+                limit,
+                point_impulse: Vector2::zeros(),
+                axial_impulse: 0.0,
+                angular_impulse: 0.0,
+                motor_impulse: 0.0,
+            }
+        })
+        .collect();
+
+    // Step 2: Sweep over the joints, fixing velocities
+    for _ in 0..ITERATIONS {
+        for (joint, solver) in joints.iter().zip(&mut solvers) {
+            let (mut a, mut b) = (bodies[joint.a], bodies[joint.b]);
+            let (r_a, r_b) = (solver.r_a, solver.r_b);
+
+            // Turning: motor first, so the limits can overrule it
+            if let JointKind::Revolute { motor: Some(motor), .. } = joint.kind {
+                let impulse = -(b.angular_velocity - a.angular_velocity - motor.speed) * solver.angular_mass;
+                let limit = motor.max_torque * dt;
+                let total = (solver.motor_impulse + impulse).clamp(-limit, limit);
+                let impulse = total - solver.motor_impulse;
+                solver.motor_impulse = total;
+                a.angular_velocity -= a.inv_inertia * impulse;
+                b.angular_velocity += b.inv_inertia * impulse;
+            }
+            let locked = matches!(joint.kind, JointKind::Fixed);
+            if locked || solver.limit != Limit::Free {
+                let impulse = -(b.angular_velocity - a.angular_velocity) * solver.angular_mass;
+                // A limit only pushes back towards the allowed range
+                let total = match solver.limit {
+                    Limit::AtLower => (solver.angular_impulse + impulse).max(0.0),
+                    Limit::AtUpper => (solver.angular_impulse + impulse).min(0.0),
+                    Limit::Free => solver.angular_impulse + impulse,
+                };
+                let impulse = total - solver.angular_impulse;
+                solver.angular_impulse = total;
+                a.angular_velocity -= a.inv_inertia * impulse;
+                b.angular_velocity += b.inv_inertia * impulse;
+            }
+
+            // Anchors: no relative velocity at all, or none along the rod
+            let relative = b.velocity_at(r_b) - a.velocity_at(r_a);
+            match joint.kind {
+                JointKind::Revolute { .. } | JointKind::Fixed => {
+                    let impulse = -(solver.point_mass * relative);
+                    solver.point_impulse += impulse;
+                    a.apply_impulse(-impulse, r_a);
+                    b.apply_impulse(impulse, r_b);
+                }
+                JointKind::Distance { rope, .. } if solver.taut => {
+                    let impulse = -relative.dot(&solver.axis) * solver.axial_mass;
+                    // A rope only pulls
+                    let total = if rope { (solver.axial_impulse + impulse).min(0.0) } else { solver.axial_impulse + impulse };
+                    let impulse = total - solver.axial_impulse;
+                    solver.axial_impulse = total;
+                    a.apply_impulse(-solver.axis * impulse, r_a);
+                    b.apply_impulse(solver.axis * impulse, r_b);
+                }
+                JointKind::Distance { .. } => {}
+            }
+
+            bodies[joint.a] = a;
+            bodies[joint.b] = b;
+        }
+    }
+
+    // Step 3: Pull anchors and angles back where they belong
+    // The velocities are right now, but the bodies moved apart a little
+    // before the joint caught them, and that error would grow every step
+    for _ in 0..POSITION_ITERATIONS {
+        for joint in joints {
+            let (mut a, mut b) = (bodies[joint.a], bodies[joint.b]);
+
+            let angle = b.angle - a.angle - joint.reference_angle;
+            let angle_error = match joint.kind {
+                JointKind::Fixed => angle,
+                JointKind::Revolute { limits: Some(limits), .. } => angle - angle.clamp(limits.lower, limits.upper),
+                _ => 0.0,
+            };
+            if angle_error != 0.0 {
+                let impulse = -angle_error * angular_mass(&a, &b) * CORRECTION;
+                a.angle -= a.inv_inertia * impulse;
+                b.angle += b.inv_inertia * impulse;
+            }
+
+            let (r_a, r_b) = arms(joint, &a, &b);
+            let offset = separation(&a, &b, r_a, r_b);
+            let impulse = match joint.kind {
+                JointKind::Revolute { .. } | JointKind::Fixed => -(point_mass(&a, &b, r_a, r_b) * offset) * CORRECTION,
+                JointKind::Distance { length, rope } => {
+                    let distance = offset.norm();
+                    let stretch = if rope { (distance - length).max(0.0) } else { distance - length };
+                    if distance > f32::EPSILON && stretch != 0.0 {
+                        let axis = offset / distance;
+                        -axis * stretch * axial_mass(&a, &b, r_a, r_b, axis) * CORRECTION
+                    } else {
+                        Vector2::zeros()
+                    }
+                }
+            };
+            shift(&mut a, -impulse, r_a);
+            shift(&mut b, impulse, r_b);
+
+            bodies[joint.a] = a;
+            bodies[joint.b] = b;
+        }
+    }
+
+    // Impulse over the step is the average force times the step length
+    solvers
+        .iter()
+        .map(|solver| {
+            if dt <= 0.0 {
+                return Wrench::zero();
+            }
+            Wrench {
+                force: (solver.point_impulse + solver.axis * solver.axial_impulse) / dt,
+                torque: (solver.angular_impulse + solver.motor_impulse) / dt,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(inv_mass: f32, position: Vector2<f32>, velocity: Vector2<f32>) -> SolverBody {
+        SolverBody {
+            inv_mass,
+            inv_inertia: inv_mass,
+            position,
+            angle: 0.0,
+            velocity,
+            angular_velocity: 0.0,
+        }
+    }
+
+    fn joint(kind: JointKind, anchor_b: Vector2<f32>) -> SolverJoint {
+        SolverJoint {
+            a: 0,
+            b: 1,
+            anchor_a: Vector2::zeros(),
+            anchor_b,
+            kind,
+            reference_angle: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_rope_and_rod() {
+        let ground = body(0.0, Vector2::zeros(), Vector2::zeros());
+        let rope = joint(JointKind::Distance { length: 2.0, rope: true }, Vector2::zeros());
+
+        // Slack rope: nothing happens
+        let mut bodies = [ground, body(1.0, Vector2::new(1.0, 0.0), Vector2::new(3.0, 1.0))];
+        let reactions = solve(&mut bodies, &[rope], 0.1);
+        assert_eq!(bodies[1].velocity, Vector2::new(3.0, 1.0));
+        assert_eq!(reactions[0], Wrench::zero());
+
+        // Taut and stretched: stops moving outwards, keeps swinging, and is
+        // pulled back to length; the rope pulls b towards a
+        let mut bodies = [ground, body(1.0, Vector2::new(2.1, 0.0), Vector2::new(3.0, 1.0))];
+        let reactions = solve(&mut bodies, &[rope], 0.1);
+        assert!((bodies[1].velocity - Vector2::new(0.0, 1.0)).norm() < 1e-5);
+        assert!((bodies[1].position.norm() - 2.0).abs() < 0.01);
+        assert!((reactions[0].force - Vector2::new(-30.0, 0.0)).norm() < 1e-3);
+        assert_eq!(bodies[0], ground);
+
+        // A rod also pushes
+        let rod = joint(JointKind::Distance { length: 2.0, rope: false }, Vector2::zeros());
+        let mut bodies = [ground, body(1.0, Vector2::new(1.9, 0.0), Vector2::new(-3.0, 0.0))];
+        solve(&mut bodies, &[rod], 0.1);
+        assert!(bodies[1].velocity.norm() < 1e-5);
+        assert!((bodies[1].position.norm() - 2.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_revolute_limits_and_motor() {
+        let ground = body(0.0, Vector2::zeros(), Vector2::zeros());
+        let hinge = JointKind::Revolute {
+            limits: Some(AngleLimits { lower: -0.5, upper: 0.5 }),
+            motor: Some(Motor { speed: 2.0, max_torque: 1.0 }),
+        };
+
+        // Inside the limits the motor spins b up about the pin, as far as its
+        // torque allows: Δω = τ dt / (I + m r²) = 1 * 0.1 / 2
+        let mut bodies = [ground, body(1.0, Vector2::new(1.0, 0.0), Vector2::zeros())];
+        let reactions = solve(&mut bodies, &[joint(hinge, Vector2::new(-1.0, 0.0))], 0.1);
+        assert!((bodies[1].angular_velocity - 0.05).abs() < 1e-4, "{:?}", bodies[1]);
+        assert!((reactions[0].torque - 1.0).abs() < 1e-4);
+        // The anchor stays on the pin, so b swings about it
+        let anchor = bodies[1].position + Rotation2::new(bodies[1].angle) * Vector2::new(-1.0, 0.0);
+        assert!(anchor.norm() < 1e-3);
+
+        // Past the upper limit the motor can't push further, and b is turned back
+        let mut past = body(1.0, Vector2::new(1.0, 0.0), Vector2::zeros());
+        past.angle = 0.6;
+        past.angular_velocity = 1.0;
+        let mut bodies = [ground, past];
+        solve(&mut bodies, &[joint(hinge, Vector2::new(-1.0, 0.0))], 0.1);
+        assert!(bodies[1].angular_velocity <= 1e-5, "{:?}", bodies[1]);
+        assert!(bodies[1].angle < 0.6);
+    }
+
+    #[test]
+    fn test_fixed_joint_welds() {
+        // Two bodies side by side, one spinning: welded, they turn as one
+        let mut spinning = body(1.0, Vector2::zeros(), Vector2::zeros());
+        spinning.angular_velocity = 2.0;
+        let mut bodies = [spinning, body(1.0, Vector2::new(1.0, 0.0), Vector2::zeros())];
+        let weld = SolverJoint {
+            anchor_a: Vector2::new(0.5, 0.0),
+            ..joint(JointKind::Fixed, Vector2::new(-0.5, 0.0))
+        };
+        solve(&mut bodies, &[weld], 0.01);
+        let (a, b) = (bodies[0], bodies[1]);
+        assert!((a.angular_velocity - b.angular_velocity).abs() < 1e-4);
+        assert!(a.angular_velocity > 0.0 && a.angular_velocity < 2.0);
+        // b moves as a point on a would
+        assert!((b.velocity - a.velocity_at(b.position - a.position)).norm() < 1e-4);
+    }
+}
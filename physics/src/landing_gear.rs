@@ -0,0 +1,172 @@
+// Landing gear - spring-damper struts with wheels, for takeoff and landing
+use nalgebra::{Rotation2, Vector2};
+use serde::{Deserialize, Serialize};
+
+use crate::components::Position;
+use crate::integrator::BodyState;
+use crate::terrain::Terrain;
+
+/// Below this sliding speed friction fades out linearly, in m/s
+///
+/// Full friction at any speed would flip direction every step around zero
+/// and make a parked aircraft jitter
+const SLIP_SPEED: f32 = 0.1;
+
+/// A wheel touching the ground
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GroundContact {
+    /// Where the wheel touches, in world space
+    pub point: Vector2<f32>,
+    /// Force on the body at that point, in newtons
+    pub force: Vector2<f32>,
+    /// How far the strut is compressed, in metres
+    pub compression: f32,
+    /// Spring-damper force along the ground normal, in newtons
+    pub load: f32,
+}
+
+/// One gear leg: a strut pointing down from the body, with a wheel at the end
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Strut {
+    /// Where the strut is attached, in the body's frame
+    pub mount: Position,
+
+    /// From the mount to the bottom of the wheel, fully extended, in metres
+    pub length: f32,
+
+    /// Spring force per metre of compression, in N/m
+    pub stiffness: f32,
+
+    /// Damper force per m/s of compression speed, in N·s/m
+    pub damping: f32,
+
+    /// Rolling resistance coefficient, about 0.02 on tarmac
+    pub rolling_friction: f32,
+
+    /// Friction coefficient with the brake fully on, about 0.5 on dry tarmac
+    pub brake_friction: f32,
+
+    /// Brake command, from 0 (off) to 1 (full)
+    #[serde(default)]
+    pub brake: f32,
+
+    /// How far the strut is compressed, in metres; set by LandingGearSystem
+    #[serde(default)]
+    pub compression: f32,
+
+    /// Force the ground pushes back with, in newtons; set by LandingGearSystem
+    #[serde(default)]
+    pub load: f32,
+}
+
+impl Strut {
+    /// A strut with typical tarmac friction and the brake off
+    pub fn new(mount: Position, length: f32, stiffness: f32, damping: f32) -> Self {
+        Self {
+            mount,
+            length,
+            stiffness,
+            damping,
+            rolling_friction: 0.02,
+            brake_friction: 0.5,
+            brake: 0.0,
+            compression: 0.0,
+            load: 0.0,
+        }
+    }
+
+    pub fn in_contact(&self) -> bool {
+        self.load > 0.0
+    }
+
+    /// Where the wheel touches the ground and the force on the body there
+    ///
+    /// None when the wheel is in the air. Only reads, so it can be called
+    /// for any trial state
+    pub fn ground_contact(&self, state: &BodyState, terrain: &Terrain) -> Option<GroundContact> {
+        let rotation = Rotation2::new(state.angle);
+        let mount = state.position + rotation * self.mount.to_vector();
+        let down = rotation * Vector2::new(0.0, -1.0);
+        let wheel = mount + down * self.length;
+
+        // Compression: how far the ground pushes the wheel back up the strut
+        // Struts pointing sideways or up can't touch the ground
+        let normal = terrain.normal_at(wheel.x);
+        let below = (terrain.height_at(wheel.x) - wheel.y) * normal.y;
+        let along = -down.dot(&normal);
+        if below <= 0.0 || along <= 0.0 {
+            return None;
+        }
+        let compression = (below / along).min(self.length);
+        let contact = wheel - down * compression;
+
+        // Velocity of the contact point: body velocity plus spin, ω × r
+        let r = contact - state.position;
+        let velocity = state.velocity + Vector2::new(-state.angular_velocity * r.y, state.angular_velocity * r.x);
+
+        // Spring-damper along the ground normal; the ground only pushes
+        let closing = -velocity.dot(&normal);
+        let load = (self.stiffness * compression + self.damping * closing).max(0.0);
+
+        // Wheel friction along the ground, opposing the rolling direction
+        let tangent = Vector2::new(normal.y, -normal.x);
+        let sliding = velocity.dot(&tangent);
+        let brake = self.brake.clamp(0.0, 1.0);
+        let friction = self.rolling_friction + (self.brake_friction - self.rolling_friction) * brake;
+        let friction_force = -(sliding / SLIP_SPEED).clamp(-1.0, 1.0) * friction * load;
+
+        Some(GroundContact {
+            point: contact,
+            force: normal * load + tangent * friction_force,
+            compression,
+            load,
+        })
+    }
+}
+
+/// Landing gear component - the struts an aircraft rests on
+///
+/// The LandingGearSystem adds the struts' ground forces to the entity's
+/// Force and Torque every physics step, and records each strut's compression
+/// and load for observations (weight on wheels, hard landing detection)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LandingGear {
+    pub struts: Vec<Strut>,
+}
+
+impl LandingGear {
+    pub fn new(struts: Vec<Strut>) -> Self {
+        Self { struts }
+    }
+
+    /// Tricycle-style gear for a 2D side view: main wheels just behind the
+    /// centre of mass, a nose wheel well ahead of it
+    ///
+    /// Each strut is stiff enough to hold the whole weight at a fifth of its
+    /// length, and critically damped for half the mass so landings don't bounce
+    pub fn tricycle(mass: f32, main_x: f32, nose_x: f32, length: f32) -> Self {
+        let stiffness = 5.0 * mass * crate::constants::GRAVITY / length;
+        let damping = 2.0 * (stiffness * mass / 2.0).sqrt();
+        Self::new(vec![
+            Strut::new(Position::new(main_x, 0.0), length, stiffness, damping),
+            Strut::new(Position::new(nose_x, 0.0), length, stiffness, damping),
+        ])
+    }
+
+    /// Set every strut's brake, from 0 (off) to 1 (full)
+    pub fn set_brakes(&mut self, brake: f32) {
+        for strut in &mut self.struts {
+            strut.brake = brake;
+        }
+    }
+
+    /// True when any wheel is touching the ground
+    pub fn on_ground(&self) -> bool {
+        self.struts.iter().any(Strut::in_contact)
+    }
+
+    /// Total force the ground pushes back with, in newtons
+    pub fn weight_on_wheels(&self) -> f32 {
+        self.struts.iter().map(|strut| strut.load).sum()
+    }
+}
@@ -1,37 +1,28 @@
-// Physics module - placeholder for Phase 2 implementation
-// This will contain rigid body physics, force integration, etc.
+// Physics module - rigid body state, the systems that integrate it, and collisions
+// Everything is 2D except the three_d module, its 6-DOF counterpart
 
-use nalgebra::Vector2;
-use serde::{Deserialize, Serialize};
+pub mod collision;
+pub mod components;
+pub mod forces;
+pub mod integrator;
+pub mod joints;
+pub mod landing_gear;
+pub mod systems;
+pub mod terrain;
+pub mod three_d;
 
-/// Force component for entities
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
-pub struct Force {
-    pub x: f32,
-    pub y: f32,
-}
-
-impl Force {
-    pub fn new(x: f32, y: f32) -> Self {
-        Self { x, y }
-    }
-    
-    pub fn zero() -> Self {
-        Self { x: 0.0, y: 0.0 }
-    }
-    
-    pub fn to_vector(&self) -> Vector2<f32> {
-        Vector2::new(self.x, self.y)
-    }
-}
+pub use collision::{Collider, CollisionEvent, ContactManifold, ContactPoint, Contacts, Shape};
+pub use components::{Force, Mass, Position, RigidBody, Rotation, Torque, Velocity};
+pub use forces::{ForceModel, ForceModels, Gravity, Spring, SpringForce, Wrench};
+pub use integrator::{Acceleration, BodyState, Integrator};
+pub use joints::{AngleLimits, Joint, JointKind, Motor};
+pub use landing_gear::{GroundContact, LandingGear, Strut};
+pub use systems::{CollisionSystem, JointSystem, LandingGearSystem, RigidBodySystem};
+pub use terrain::{Terrain, TerrainError};
+pub use three_d::{Force3, ForceModel3, ForceModels3, Orientation, Position3, RigidBody3, RigidBodySystem3, Torque3, Velocity3};
 
 /// Placeholder for physics constants
 pub mod constants {
     pub const GRAVITY: f32 = 9.81; // m/s²
     pub const AIR_DENSITY: f32 = 1.225; // kg/m³ at sea level
 }
-
-/// Placeholder for physics systems (to be implemented in Phase 2)
-pub mod systems {
-    // Will contain integration systems, collision detection, etc.
-}
\ No newline at end of file
@@ -0,0 +1,808 @@
+// Physics systems - turn forces into motion, and keep bodies from overlapping
+use std::collections::{BTreeSet, HashMap};
+
+use ecs::{Access, EcsResult, Entity, System, World};
+use nalgebra::Vector2;
+
+use crate::collision::narrowphase::{self, Placement};
+use crate::collision::response::{self, SolverBody, SolverContact};
+use crate::collision::{broadphase, Collider, CollisionEvent, ContactManifold, Contacts};
+use crate::components::{Force, Mass, Position, RigidBody, Rotation, Torque, Velocity};
+use crate::forces::ForceModels;
+use crate::integrator::{Acceleration, BodyState, Integrator};
+use crate::joints::{self, Joint, SolverJoint};
+use crate::landing_gear::LandingGear;
+use crate::terrain::Terrain;
+
+/// Integrates rigid bodies: force → acceleration → velocity → position
+/// 
+/// For each entity with RigidBody, Mass, Position, Velocity and Rotation:
+/// - linear acceleration is force / Mass (Newton's second law, a = F/m)
+/// - angular acceleration is torque / RigidBody::inertia
+/// - the Integrator (the body's own component, else the world's resource,
+///   else semi-implicit Euler) advances position, velocity, rotation and
+///   angular velocity
+/// - Force and Torque are cleared for the next step
+/// 
+/// The force is the Force component, held constant over the step, plus the
+/// ForceModels resource, evaluated at every trial state the integrator asks for
+/// 
+/// MovementSystem leaves rigid bodies alone, so they're only moved once
+#[derive(Clone)]
+pub struct RigidBodySystem {
+    name: String,
+}
+
+/// Everything about one body that stays fixed while it's integrated
+struct BodyStep {
+    entity: Entity,
+    state: BodyState,
+    /// Force and Torque components, summed earlier in the step
+    accumulated: Acceleration,
+    mass: f32,
+    inertia: f32,
+    integrator: Option<Integrator>,
+}
+
+impl RigidBodySystem {
+    pub fn new() -> Self {
+        Self {
+            name: "RigidBodySystem".to_string(),
+        }
+    }
+}
+
+impl Default for RigidBodySystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl System for RigidBodySystem {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    
+    /// Exclusive access (the default): force models may read any component
+    /// of any entity while the bodies are being integrated
+    fn run(&mut self, world: &mut World, delta_time: f32) -> EcsResult<()> {
+        // Step 1: Gather every body's state; the query ends with the statement
+        // A body without positive mass or inertia can't be accelerated
+        // (it would divide by zero), so it just keeps moving as it was
+        let bodies: Vec<BodyStep> = world
+            .query::<(Entity, (&Position, &Velocity, &Rotation), &RigidBody, &Mass, Option<&Force>, Option<&Torque>, Option<&Integrator>)>()?
+            .iter()
+            .map(|(entity, (position, velocity, rotation), body, mass, force, torque, integrator)| BodyStep {
+                entity,
+                state: BodyState {
+                    position: position.to_vector(),
+                    velocity: velocity.to_vector(),
+                    angle: rotation.angle,
+                    angular_velocity: body.angular_velocity,
+                },
+                accumulated: Acceleration {
+                    linear: force.map_or(Acceleration::zero().linear, |force| force.to_vector()),
+                    angular: torque.map_or(0.0, |torque| torque.value),
+                },
+                mass: mass.value,
+                inertia: body.inertia,
+                integrator: integrator.copied(),
+            })
+            .collect();
+        
+        // Copy the shared settings out so the World is free for writing later
+        // Cloning ForceModels only clones the Arcs, not the models
+        let models = world.resource::<ForceModels>().map(|models| models.clone()).unwrap_or_default();
+        let default_integrator = world.resource::<Integrator>().map(|integrator| *integrator).unwrap_or_default();
+        
+        // Step 2: Integrate each body, with the world only read
+        let world_ref: &World = world;
+        let results: Vec<(Entity, BodyState)> = bodies
+            .iter()
+            .map(|body| {
+                // The derivative function: acceleration at a trial state
+                let acceleration = |state: &BodyState| {
+                    let wrench = models.wrench(world_ref, body.entity, state);
+                    Acceleration {
+                        linear: if body.mass > 0.0 { (body.accumulated.linear + wrench.force) / body.mass } else { Acceleration::zero().linear },
+                        angular: if body.inertia > 0.0 { (body.accumulated.angular + wrench.torque) / body.inertia } else { 0.0 },
+                    }
+                };
+                let integrator = body.integrator.unwrap_or(default_integrator);
+                (body.entity, integrator.step(body.state, delta_time, acceleration))
+            })
+            .collect();
+        
+        // Step 3: Write the new states back and empty the accumulators
+        for (entity, state) in results {
+            if let Some(position) = world.get_component_mut::<Position>(entity) {
+                *position = Position::from_vector(state.position);
+            }
+            if let Some(velocity) = world.get_component_mut::<Velocity>(entity) {
+                *velocity = Velocity::from_vector(state.velocity);
+            }
+            if let Some(rotation) = world.get_component_mut::<Rotation>(entity) {
+                rotation.angle = state.angle;
+            }
+            if let Some(body) = world.get_component_mut::<RigidBody>(entity) {
+                body.angular_velocity = state.angular_velocity;
+            }
+            if let Some(force) = world.get_component_mut::<Force>(entity) {
+                force.clear();
+            }
+            if let Some(torque) = world.get_component_mut::<Torque>(entity) {
+                torque.clear();
+            }
+        }
+        
+        Ok(())
+    }
+    
+    fn initialize(&mut self, world: &mut World) -> EcsResult<()> {
+        world.register_component::<RigidBody>();
+        world.register_component::<Force>();
+        world.register_component::<Torque>();
+        world.register_component::<Integrator>();
+        Ok(())
+    }
+    
+    fn fork(&self) -> Option<Box<dyn System>> {
+        Some(Box::new(self.clone()))
+    }
+}
+
+/// Detects collisions between colliders and resolves them
+/// 
+/// Runs after integration: finds every pair of touching colliders, bounces
+/// and slows the bodies with impulses (restitution and friction), and pushes
+/// them apart so they don't overlap. Then it publishes what it found:
+/// - the Contacts resource lists every contact from this step
+/// - a CollisionEvent is sent whenever two colliders start touching
+/// 
+/// Only entities with a RigidBody and a positive Mass are moved; every
+/// other collider is immovable
+#[derive(Clone)]
+pub struct CollisionSystem {
+    name: String,
+    /// Pairs touching after the previous step, by Entity::to_bits with the
+    /// smaller first, so only new contacts are sent as events
+    touching: BTreeSet<(u64, u64)>,
+}
+
+impl CollisionSystem {
+    pub fn new() -> Self {
+        Self {
+            name: "CollisionSystem".to_string(),
+            touching: BTreeSet::new(),
+        }
+    }
+}
+
+impl Default for CollisionSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl System for CollisionSystem {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    
+    fn run(&mut self, world: &mut World, _delta_time: f32) -> EcsResult<()> {
+        // Step 1: Gather colliders and the bodies they belong to
+        let mut query = world.query::<(Entity, &Collider, &Position, Option<&Rotation>, Option<&Velocity>, Option<&RigidBody>, Option<&Mass>)>()?;
+        let colliders: Vec<_> = query.iter().collect();
+        let mut entities = Vec::with_capacity(colliders.len());
+        let mut placements = Vec::with_capacity(colliders.len());
+        let mut bodies = Vec::with_capacity(colliders.len());
+        for (entity, _, position, rotation, velocity, rigid_body, mass) in &colliders {
+            let placement = Placement {
+                position: position.to_vector(),
+                angle: rotation.map_or(0.0, |rotation| rotation.angle),
+            };
+            // Only rigid bodies with mass respond to impacts
+            let (inv_mass, inv_inertia) = match (rigid_body, mass) {
+                (Some(body), Some(mass)) if mass.value > 0.0 => (1.0 / mass.value, if body.inertia > 0.0 { 1.0 / body.inertia } else { 0.0 }),
+                _ => (0.0, 0.0),
+            };
+            entities.push(*entity);
+            placements.push(placement);
+            bodies.push(SolverBody {
+                inv_mass,
+                inv_inertia,
+                position: placement.position,
+                angle: placement.angle,
+                velocity: velocity.map_or(Vector2::zeros(), |velocity| velocity.to_vector()),
+                angular_velocity: rigid_body.map_or(0.0, |body| body.angular_velocity),
+            });
+        }
+        
+        // Step 2: Broadphase, then exact contacts for the candidate pairs
+        let aabbs: Vec<_> = colliders
+            .iter()
+            .zip(&placements)
+            .map(|((_, collider, ..), placement)| collider.aabb(placement.position, placement.angle))
+            .collect();
+        let mut contacts = Vec::new();
+        for (a, b) in broadphase::sweep_and_prune(&aabbs) {
+            let (collider_a, collider_b) = (colliders[a].1, colliders[b].1);
+            for contact in narrowphase::collide(&collider_a.shape, placements[a], &collider_b.shape, placements[b]) {
+                contacts.push(SolverContact {
+                    a,
+                    b,
+                    normal: contact.normal,
+                    points: contact.points,
+                    restitution: collider_a.restitution.max(collider_b.restitution),
+                    friction: (collider_a.friction * collider_b.friction).sqrt(),
+                });
+            }
+        }
+        drop(colliders);
+        drop(query);
+        
+        // Step 3: Resolve contacts; two immovable colliders touching (terrain
+        // and a building) are still reported, but have nothing to resolve
+        let (movable, fixed): (Vec<SolverContact>, Vec<SolverContact>) = contacts
+            .into_iter()
+            .partition(|contact| bodies[contact.a].is_movable() || bodies[contact.b].is_movable());
+        let impact_speeds = response::solve(&mut bodies, &movable);
+        
+        for (&entity, body) in entities.iter().zip(&bodies) {
+            if !body.is_movable() {
+                continue;
+            }
+            if let Some(position) = world.get_component_mut::<Position>(entity) {
+                *position = Position::from_vector(body.position);
+            }
+            if let Some(velocity) = world.get_component_mut::<Velocity>(entity) {
+                *velocity = Velocity::from_vector(body.velocity);
+            }
+            if let Some(rigid_body) = world.get_component_mut::<RigidBody>(entity) {
+                rigid_body.angular_velocity = body.angular_velocity;
+            }
+        }
+        
+        // Step 4: Publish contacts, and events for the ones that are new
+        let contacts: Vec<SolverContact> = movable.into_iter().chain(fixed).collect();
+        let impact_speeds = impact_speeds.into_iter().chain(std::iter::repeat(0.0));
+        let mut touching = BTreeSet::new();
+        let mut events = Vec::new();
+        for (contact, impact_speed) in contacts.iter().zip(impact_speeds) {
+            let (a, b) = (entities[contact.a], entities[contact.b]);
+            // A pair can have several contacts (one per terrain cell); only its first counts
+            // Which of the two comes first follows query order, which changes
+            // when an entity moves archetype, so order the key by value
+            let pair = (a.to_bits().min(b.to_bits()), a.to_bits().max(b.to_bits()));
+            if touching.insert(pair) && !self.touching.contains(&pair) {
+                events.push(CollisionEvent {
+                    a,
+                    b,
+                    normal: contact.normal,
+                    point: contact.points[0].point,
+                    impact_speed,
+                });
+            }
+        }
+        self.touching = touching;
+        
+        let manifolds = contacts
+            .into_iter()
+            .map(|contact| ContactManifold {
+                a: entities[contact.a],
+                b: entities[contact.b],
+                normal: contact.normal,
+                points: contact.points,
+            })
+            .collect();
+        world.insert_resource(Contacts::new(manifolds));
+        for event in events {
+            world.send_event(event)?;
+        }
+        Ok(())
+    }
+    
+    fn initialize(&mut self, world: &mut World) -> EcsResult<()> {
+        world.register_component::<Collider>();
+        world.add_event::<CollisionEvent>();
+        world.insert_resource(Contacts::default());
+        Ok(())
+    }
+    
+    fn fork(&self) -> Option<Box<dyn System>> {
+        Some(Box::new(self.clone()))
+    }
+}
+
+/// Enforces joints between bodies that have just been integrated
+/// 
+/// Runs after the RigidBodySystem: for every Joint it corrects the two
+/// bodies' velocities, positions and rotations so the joint holds, and
+/// stores the force and torque that took in the joint's reaction fields
+#[derive(Clone)]
+pub struct JointSystem {
+    name: String,
+}
+
+impl JointSystem {
+    pub fn new() -> Self {
+        Self {
+            name: "JointSystem".to_string(),
+        }
+    }
+}
+
+impl Default for JointSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A jointed body as the solver sees it, or None if it's gone or has no Position
+fn joint_body(world: &World, entity: Entity) -> EcsResult<Option<SolverBody>> {
+    if !world.entity_exists(entity) {
+        return Ok(None);
+    }
+    let Some(position) = world.get_optional_component::<Position>(entity)? else {
+        return Ok(None);
+    };
+    let rigid_body = world.get_optional_component::<RigidBody>(entity)?;
+    // Same rule as collisions: only rigid bodies with mass are moved
+    let (inv_mass, inv_inertia) = match (rigid_body.as_deref(), world.get_optional_component::<Mass>(entity)?.as_deref()) {
+        (Some(body), Some(mass)) if mass.value > 0.0 => (1.0 / mass.value, if body.inertia > 0.0 { 1.0 / body.inertia } else { 0.0 }),
+        _ => (0.0, 0.0),
+    };
+    Ok(Some(SolverBody {
+        inv_mass,
+        inv_inertia,
+        position: position.to_vector(),
+        angle: world.get_optional_component::<Rotation>(entity)?.map_or(0.0, |rotation| rotation.angle),
+        velocity: world.get_optional_component::<Velocity>(entity)?.map_or(Vector2::zeros(), |velocity| velocity.to_vector()),
+        angular_velocity: rigid_body.map_or(0.0, |body| body.angular_velocity),
+    }))
+}
+
+impl System for JointSystem {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    
+    fn run(&mut self, world: &mut World, delta_time: f32) -> EcsResult<()> {
+        // Step 1: Gather joints, and each body they link once
+        let mut query = world.query::<(Entity, &Joint)>()?;
+        let joint_list: Vec<(Entity, Joint)> = query.iter().map(|(entity, joint)| (entity, joint.clone())).collect();
+        drop(query);
+        
+        let mut index: HashMap<Entity, usize> = HashMap::new();
+        let mut entities = Vec::new();
+        let mut bodies = Vec::new();
+        let mut body_index = |entity: Entity| -> EcsResult<Option<usize>> {
+            if let Some(&i) = index.get(&entity) {
+                return Ok(Some(i));
+            }
+            let Some(body) = joint_body(world, entity)? else {
+                return Ok(None);
+            };
+            index.insert(entity, bodies.len());
+            entities.push(entity);
+            bodies.push(body);
+            Ok(Some(bodies.len() - 1))
+        };
+        let mut solved = Vec::with_capacity(joint_list.len());
+        let mut solver_joints = Vec::with_capacity(joint_list.len());
+        for (entity, joint) in &joint_list {
+            let (Some(a), Some(b)) = (body_index(joint.a)?, body_index(joint.b)?) else {
+                continue;
+            };
+            solved.push(*entity);
+            solver_joints.push(SolverJoint {
+                a,
+                b,
+                anchor_a: joint.anchor_a.to_vector(),
+                anchor_b: joint.anchor_b.to_vector(),
+                kind: joint.kind,
+                reference_angle: joint.reference_angle,
+            });
+        }
+        
+        // Step 2: Solve, and write the corrected bodies back
+        let reactions = joints::solve(&mut bodies, &solver_joints, delta_time);
+        for (&entity, body) in entities.iter().zip(&bodies) {
+            if !body.is_movable() {
+                continue;
+            }
+            if let Some(position) = world.get_component_mut::<Position>(entity) {
+                *position = Position::from_vector(body.position);
+            }
+            if let Some(rotation) = world.get_component_mut::<Rotation>(entity) {
+                rotation.angle = body.angle;
+            }
+            if let Some(velocity) = world.get_component_mut::<Velocity>(entity) {
+                *velocity = Velocity::from_vector(body.velocity);
+            }
+            if let Some(rigid_body) = world.get_component_mut::<RigidBody>(entity) {
+                rigid_body.angular_velocity = body.angular_velocity;
+            }
+        }
+        
+        // Step 3: Record reactions; joints that weren't solved carry nothing
+        for (entity, _) in &joint_list {
+            if let Some(joint) = world.get_component_mut::<Joint>(*entity) {
+                joint.reaction = Force::zero();
+                joint.reaction_torque = Torque::zero();
+            }
+        }
+        for (entity, reaction) in solved.iter().zip(reactions) {
+            if let Some(joint) = world.get_component_mut::<Joint>(*entity) {
+                joint.reaction = Force::new(reaction.force.x, reaction.force.y);
+                joint.reaction_torque = Torque::new(reaction.torque);
+            }
+        }
+        Ok(())
+    }
+    
+    fn initialize(&mut self, world: &mut World) -> EcsResult<()> {
+        world.register_component::<Joint>();
+        Ok(())
+    }
+    
+    fn fork(&self) -> Option<Box<dyn System>> {
+        Some(Box::new(self.clone()))
+    }
+}
+
+/// Pushes landing gear wheels up out of the ground
+/// 
+/// Runs in the forces stage: for each entity with LandingGear and a Force
+/// accumulator, every strut whose wheel is below the Terrain resource (flat
+/// ground at y = 0 if there is none) adds its spring-damper and wheel
+/// friction force to Force, and the matching torque to Torque if the entity
+/// has one. The RigidBodySystem then integrates them with everything else
+#[derive(Clone)]
+pub struct LandingGearSystem {
+    name: String,
+}
+
+impl LandingGearSystem {
+    pub fn new() -> Self {
+        Self {
+            name: "LandingGearSystem".to_string(),
+        }
+    }
+}
+
+impl Default for LandingGearSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl System for LandingGearSystem {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    
+    fn access(&self) -> Access {
+        Access::new()
+            .read::<Position>()
+            .read::<Velocity>()
+            .read::<Rotation>()
+            .read::<RigidBody>()
+            .write::<LandingGear>()
+            .write::<Force>()
+            .write::<Torque>()
+            .read_resource::<Terrain>()
+    }
+    
+    fn run_shared(&mut self, world: &World, _delta_time: f32) -> EcsResult<()> {
+        let flat = Terrain::default();
+        let terrain_ref = world.resource::<Terrain>().ok();
+        let terrain: &Terrain = terrain_ref.as_deref().unwrap_or(&flat);
+        
+        let mut query = world.query::<(&Position, &Velocity, &Rotation, Option<&RigidBody>, &mut LandingGear, &mut Force, Option<&mut Torque>)>()?;
+        for (position, velocity, rotation, body, gear, force, mut torque) in query.iter() {
+            let state = BodyState {
+                position: position.to_vector(),
+                velocity: velocity.to_vector(),
+                angle: rotation.angle,
+                angular_velocity: body.map_or(0.0, |body| body.angular_velocity),
+            };
+            for strut in &mut gear.struts {
+                let Some(contact) = strut.ground_contact(&state, terrain) else {
+                    strut.compression = 0.0;
+                    strut.load = 0.0;
+                    continue;
+                };
+                strut.compression = contact.compression;
+                strut.load = contact.load;
+                
+                let wheel_force = Force::new(contact.force.x, contact.force.y);
+                force.add(wheel_force);
+                if let Some(torque) = torque.as_deref_mut() {
+                    torque.add(wheel_force.torque_at(contact.point - state.position));
+                }
+            }
+        }
+        Ok(())
+    }
+    
+    fn initialize(&mut self, world: &mut World) -> EcsResult<()> {
+        world.register_component::<LandingGear>();
+        world.register_component::<Force>();
+        world.register_component::<Torque>();
+        Ok(())
+    }
+    
+    fn fork(&self) -> Option<Box<dyn System>> {
+        Some(Box::new(self.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forces::{Gravity, Spring, SpringForce};
+    
+    #[test]
+    fn test_rigid_body_integration() {
+        let mut world = World::new();
+        let mut system = RigidBodySystem::new();
+        system.initialize(&mut world).unwrap();
+        
+        let body = world
+            .spawn((
+                Position::zero(),
+                Velocity::zero(),
+                Rotation::zero(),
+                Mass::new(2.0),
+                RigidBody::rectangle(Mass::new(2.0), 3.0, 0.0),
+                Force::new(4.0, -2.0),
+                Torque::new(3.0),
+            ))
+            .unwrap();
+        
+        system.run(&mut world, 0.5).unwrap();
+        
+        // a = F/m = (2, -1), α = τ/I = 3/1.5 = 2, semi-implicit Euler by default
+        assert_eq!(*world.get_component::<Velocity>(body).unwrap(), Velocity::new(1.0, -0.5));
+        assert_eq!(*world.get_component::<Position>(body).unwrap(), Position::new(0.5, -0.25));
+        let spin = world.get_component::<RigidBody>(body).unwrap().angular_velocity;
+        assert!((spin - 1.0).abs() < 1e-6);
+        assert!((world.get_component::<Rotation>(body).unwrap().angle - 0.5).abs() < 1e-6);
+        
+        // The accumulators start the next step empty, so velocities stop
+        // changing but the body keeps moving and spinning
+        assert_eq!(*world.get_component::<Force>(body).unwrap(), Force::zero());
+        assert_eq!(*world.get_component::<Torque>(body).unwrap(), Torque::zero());
+        system.run(&mut world, 0.5).unwrap();
+        assert_eq!(*world.get_component::<Velocity>(body).unwrap(), Velocity::new(1.0, -0.5));
+        assert!((world.get_component::<Rotation>(body).unwrap().angle - 1.0).abs() < 1e-6);
+    }
+    
+    #[test]
+    fn test_force_torque_at_offset() {
+        // Pushing up on the right end of a lever spins it counter-clockwise
+        let torque = Force::new(0.0, 10.0).torque_at(nalgebra::Vector2::new(2.0, 0.0));
+        assert_eq!(torque, Torque::new(20.0));
+        
+        let mut total = Force::zero();
+        total.add(Force::new(1.0, 2.0));
+        total.add(Force::new(-3.0, 1.0));
+        assert_eq!(total, Force::new(-2.0, 3.0));
+    }
+    
+    #[test]
+    fn test_force_models_and_integrator_choice() {
+        let mut world = World::new();
+        let mut system = RigidBodySystem::new();
+        system.initialize(&mut world).unwrap();
+        world.insert_resource(ForceModels::new().with(Gravity::earth()).with(SpringForce));
+        world.insert_resource(Integrator::VelocityVerlet);
+        
+        let body = |world: &mut World, x: f32| {
+            world.spawn((Position::new(x, 0.0), Velocity::zero(), Rotation::zero(), Mass::new(1.0), RigidBody::new(1.0))).unwrap()
+        };
+        // Spring with k = 1 around x = 0, plus gravity
+        let bob = body(&mut world, 1.0);
+        world.add_component(bob, Spring::new(Position::zero(), 1.0, 0.0)).unwrap();
+        // Falls freely, integrated with RK4 whatever the world default is
+        let stone = body(&mut world, 5.0);
+        world.add_component(stone, Integrator::Rk4).unwrap();
+        
+        let energy = |world: &World| {
+            let position = world.get_component::<Position>(bob).unwrap().to_vector();
+            let velocity = world.get_component::<Velocity>(bob).unwrap().to_vector();
+            0.5 * position.norm_squared() + 0.5 * velocity.norm_squared() + 9.81 * position.y
+        };
+        let initial = energy(&world);
+        for _ in 0..600 {
+            system.run(&mut world, 0.01).unwrap();
+        }
+        
+        // Spring plus gravity is conservative: Verlet keeps the energy
+        assert!((energy(&world) - initial).abs() < 1e-2 * initial.abs().max(1.0));
+        
+        // y = -½gt² after 6 s, exactly for RK4 under constant gravity
+        let stone_y = world.get_component::<Position>(stone).unwrap().y;
+        assert!((stone_y + 0.5 * 9.81 * 36.0).abs() < 0.05, "stone at {}", stone_y);
+        assert_eq!(world.get_component::<Position>(stone).unwrap().x, 5.0);
+    }
+    
+    #[test]
+    fn test_box_lands_on_terrain() {
+        use crate::collision::{Collider, CollisionEvent, Contacts};
+        use ecs::Events;
+        
+        let mut world = World::new();
+        let mut integrate = RigidBodySystem::new();
+        let mut collide = CollisionSystem::new();
+        integrate.initialize(&mut world).unwrap();
+        collide.initialize(&mut world).unwrap();
+        world.insert_resource(ForceModels::new().with(Gravity::earth()));
+        
+        let ground = world.spawn((Position::new(-50.0, 0.0), Collider::heightfield(vec![0.0; 11], 10.0))).unwrap();
+        let crate_box = world
+            .spawn((
+                Position::new(0.0, 3.0),
+                Velocity::new(2.0, 0.0),
+                Rotation::zero(),
+                Mass::new(1.0),
+                RigidBody::rectangle(Mass::new(1.0), 1.0, 1.0),
+                Collider::rectangle(0.5, 0.5).with_friction(0.8),
+            ))
+            .unwrap();
+        let mut reader = world.resource::<Events<CollisionEvent>>().unwrap().reader();
+        
+        let mut impacts = Vec::new();
+        for _ in 0..3000 {
+            integrate.run(&mut world, 0.001).unwrap();
+            collide.run(&mut world, 0.001).unwrap();
+            let events = world.resource::<Events<CollisionEvent>>().unwrap();
+            impacts.extend(reader.read(&events).copied());
+            drop(events);
+            world.update_events();
+        }
+        
+        // One impact, at about √(2 g h) for the 2.5 m drop to the box's bottom
+        assert_eq!(impacts.len(), 1);
+        assert_eq!(impacts[0].other(crate_box), Some(ground));
+        assert!((impacts[0].impact_speed - (2.0f32 * 9.81 * 2.5).sqrt()).abs() < 0.1, "{:?}", impacts[0]);
+        
+        // Friction stopped the slide, and it rests flat on the ground
+        let position = *world.get_component::<Position>(crate_box).unwrap();
+        let velocity = *world.get_component::<Velocity>(crate_box).unwrap();
+        assert!((position.y - 0.5).abs() < 0.02, "resting at {:?}", position);
+        assert!(velocity.magnitude() < 0.05, "still moving at {:?}", velocity);
+        assert!(world.get_component::<Rotation>(crate_box).unwrap().angle.abs() < 0.01);
+        assert!(world.resource::<Contacts>().unwrap().touching(crate_box, ground));
+    }
+    
+    #[test]
+    fn test_contact_survives_reordering() {
+        use crate::collision::{Collider, CollisionEvent};
+        use ecs::Events;
+
+        let mut world = World::new();
+        let mut integrate = RigidBodySystem::new();
+        let mut collide = CollisionSystem::new();
+        integrate.initialize(&mut world).unwrap();
+        collide.initialize(&mut world).unwrap();
+        world.insert_resource(ForceModels::new().with(Gravity::earth()));
+
+        let ground = world.spawn((Position::new(-50.0, 0.0), Collider::heightfield(vec![0.0; 11], 10.0))).unwrap();
+        world
+            .spawn((Position::new(0.0, 1.0), Velocity::zero(), Rotation::zero(), Mass::new(1.0), RigidBody::disc(Mass::new(1.0), 0.5), Collider::circle(0.5)))
+            .unwrap();
+        let mut reader = world.resource::<Events<CollisionEvent>>().unwrap().reader();
+
+        let mut impacts = 0;
+        for step in 0..2000 {
+            // Mid-contact the ground moves archetype, so the query meets the ball first
+            if step == 1000 {
+                world.add_component(ground, Velocity::zero()).unwrap();
+            }
+            integrate.run(&mut world, 0.001).unwrap();
+            collide.run(&mut world, 0.001).unwrap();
+            let events = world.resource::<Events<CollisionEvent>>().unwrap();
+            impacts += reader.read(&events).count();
+            drop(events);
+            world.update_events();
+        }
+        assert_eq!(impacts, 1);
+    }
+
+    #[test]
+    fn test_landing_and_braking() {
+        let mut world = World::new();
+        let mut gear = LandingGearSystem::new();
+        let mut integrate = RigidBodySystem::new();
+        gear.initialize(&mut world).unwrap();
+        integrate.initialize(&mut world).unwrap();
+        world.insert_resource(ForceModels::new().with(Gravity::earth()));
+        world.insert_resource(Integrator::VelocityVerlet);
+        // A runway sloping down 1% to the right
+        world.insert_resource(Terrain::profile(-1000.0, 2000.0, vec![10.0, -10.0]));
+        
+        // 1 t aircraft with 1 m struts, touching down at 30 m/s while sinking at 2 m/s
+        let mass = 1000.0;
+        let aircraft = world
+            .spawn((
+                Position::new(0.0, 1.2),
+                Velocity::new(30.0, -2.0),
+                Rotation::zero(),
+                Mass::new(mass),
+                RigidBody::rectangle(Mass::new(mass), 8.0, 1.0),
+                LandingGear::tricycle(mass, -0.5, 2.5, 1.0),
+                Force::zero(),
+                Torque::zero(),
+            ))
+            .unwrap();
+        let mut step = |world: &mut World, steps: usize| {
+            for _ in 0..steps {
+                gear.run(world, 0.001).unwrap();
+                integrate.run(world, 0.001).unwrap();
+            }
+        };
+        
+        // Settled on the gear without bouncing, still rolling
+        step(&mut world, 2000);
+        let landing_gear = world.get_component::<LandingGear>(aircraft).unwrap().clone();
+        let velocity = *world.get_component::<Velocity>(aircraft).unwrap();
+        assert!(landing_gear.struts.iter().all(|strut| strut.in_contact()));
+        assert!((landing_gear.weight_on_wheels() - mass * 9.81).abs() < 0.01 * mass * 9.81);
+        // Most of the weight is on the main wheels, nearer the centre of mass
+        assert!(landing_gear.struts[0].load > 4.0 * landing_gear.struts[1].load);
+        assert!(velocity.x > 28.0 && velocity.y.abs() < 0.5, "rolling at {:?}", velocity);
+        // Steady in pitch, a little nose up: the lightly loaded nose strut compresses less
+        assert!(world.get_component::<RigidBody>(aircraft).unwrap().angular_velocity.abs() < 0.01);
+        
+        // Full brakes stop it on the runway
+        world.get_component_mut::<LandingGear>(aircraft).unwrap().set_brakes(1.0);
+        step(&mut world, 10000);
+        let velocity = *world.get_component::<Velocity>(aircraft).unwrap();
+        assert!(velocity.magnitude() < 0.05, "still moving at {:?}", velocity);
+        assert!(world.get_component::<RigidBody>(aircraft).unwrap().angular_velocity.abs() < 0.01);
+        assert!(world.get_component::<LandingGear>(aircraft).unwrap().on_ground());
+    }
+    
+    #[test]
+    fn test_pendulum_joint_reaction() {
+        let mut world = World::new();
+        let mut integrate = RigidBodySystem::new();
+        let mut joints = JointSystem::new();
+        integrate.initialize(&mut world).unwrap();
+        joints.initialize(&mut world).unwrap();
+        world.insert_resource(ForceModels::new().with(Gravity::earth()));
+        
+        // A 2 kg ball on a 1 m arm, hinged to a fixed pivot and let go level with it
+        let pivot = world.spawn((Position::zero(),)).unwrap();
+        let ball = world
+            .spawn((Position::new(1.0, 0.0), Velocity::zero(), Rotation::zero(), Mass::new(2.0), RigidBody::disc(Mass::new(2.0), 0.05)))
+            .unwrap();
+        let hinge = world.spawn((Joint::revolute(pivot, Position::zero(), ball, Position::new(-1.0, 0.0)),)).unwrap();
+        
+        // Swing down to the bottom, a quarter period (about 0.59 s)
+        let mut peak = 0.0f32;
+        for _ in 0..590 {
+            integrate.run(&mut world, 0.001).unwrap();
+            joints.run(&mut world, 0.001).unwrap();
+            peak = peak.max(world.get_component::<Joint>(hinge).unwrap().reaction.y);
+        }
+        
+        // The arm kept its length, and the ball turned with it
+        let position = world.get_component::<Position>(ball).unwrap().to_vector();
+        assert!((position.norm() - 1.0).abs() < 1e-3, "ball at {:?}", position);
+        let angle = world.get_component::<Rotation>(ball).unwrap().angle;
+        assert!((angle - position.y.atan2(position.x)).abs() < 1e-3);
+        
+        // At the bottom the arm holds the weight plus the centripetal force,
+        // m g + m v² / L = 3 m g for a point mass; the pivot itself never moved
+        assert!((peak - 3.0 * 2.0 * 9.81).abs() < 0.03 * 3.0 * 2.0 * 9.81, "peak load {}", peak);
+        assert_eq!(*world.get_component::<Position>(pivot).unwrap(), Position::zero());
+        
+        // Removing the pivot leaves the joint with nothing to hold
+        world.remove_entity(pivot).unwrap();
+        joints.run(&mut world, 0.001).unwrap();
+        assert_eq!(world.get_component::<Joint>(hinge).unwrap().reaction, Force::zero());
+    }
+}
@@ -0,0 +1,166 @@
+// Terrain - the ground aircraft take off from and land on
+use std::path::Path;
+
+use nalgebra::Vector2;
+use serde::{Deserialize, Serialize};
+
+use crate::collision::Collider;
+use crate::components::Position;
+
+/// Everything that can go wrong loading a terrain profile
+#[derive(Debug, thiserror::Error)]
+pub enum TerrainError {
+    #[error("Can't read terrain profile: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Terrain profile line {line}: {problem}")]
+    Invalid { line: usize, problem: String },
+
+    #[error("Terrain profile has no samples")]
+    Empty,
+}
+
+/// Ground height along X, stored as a resource
+///
+/// A 1D heightmap: heights[i] is the ground height at x = origin_x + i * spacing,
+/// with straight lines in between. Beyond either end the ground stays at the
+/// end height, so a flat runway can be a single sample
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Terrain {
+    pub origin_x: f32,
+    pub spacing: f32,
+    pub heights: Vec<f32>,
+}
+
+impl Default for Terrain {
+    /// Flat ground at y = 0
+    fn default() -> Self {
+        Self::flat(0.0)
+    }
+}
+
+impl Terrain {
+    /// Flat ground at the given height, everywhere
+    pub fn flat(height: f32) -> Self {
+        Self {
+            origin_x: 0.0,
+            spacing: 1.0,
+            heights: vec![height],
+        }
+    }
+
+    pub fn profile(origin_x: f32, spacing: f32, heights: Vec<f32>) -> Self {
+        Self { origin_x, spacing, heights }
+    }
+
+    /// Parse a profile: one `x, height` sample per line, evenly spaced in x
+    ///
+    /// Commas or whitespace separate the columns; blank lines and lines
+    /// starting with # are skipped, so the file can carry a header comment
+    pub fn parse(text: &str) -> Result<Self, TerrainError> {
+        let mut samples: Vec<(f32, f32)> = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |problem: String| TerrainError::Invalid { line: index + 1, problem };
+
+            let columns: Vec<&str> = line.split(|c: char| c == ',' || c.is_whitespace()).filter(|c| !c.is_empty()).collect();
+            let [x, height] = columns[..] else {
+                return Err(invalid(format!("expected `x, height`, got `{}`", line)));
+            };
+            let number = |text: &str| text.parse::<f32>().ok().filter(|value| value.is_finite());
+            let (Some(x), Some(height)) = (number(x), number(height)) else {
+                return Err(invalid(format!("`{}` isn't two numbers", line)));
+            };
+
+            // Samples must be evenly spaced, so each can be found by index
+            if let [.., (before_last, _), (last, _)] = samples[..] {
+                let spacing = last - before_last;
+                if ((x - last) - spacing).abs() > spacing * 1e-3 {
+                    return Err(invalid(format!("x = {} breaks the even spacing of {}", x, spacing)));
+                }
+            } else if let [(first, _)] = samples[..] {
+                if x <= first {
+                    return Err(invalid(format!("x must increase, got {} after {}", x, first)));
+                }
+            }
+            samples.push((x, height));
+        }
+
+        match samples[..] {
+            [] => Err(TerrainError::Empty),
+            [(_, height)] => Ok(Self::flat(height)),
+            [(first, _), (second, _), ..] => Ok(Self::profile(first, second - first, samples.iter().map(|&(_, height)| height).collect())),
+        }
+    }
+
+    /// Read and parse a profile file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TerrainError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Position along the profile in samples, clamped to the ends
+    fn sample_index(&self, x: f32) -> f32 {
+        let last = self.heights.len().saturating_sub(1) as f32;
+        ((x - self.origin_x) / self.spacing).clamp(0.0, last)
+    }
+
+    /// Ground height at x
+    pub fn height_at(&self, x: f32) -> f32 {
+        if self.heights.len() < 2 {
+            return self.heights.first().copied().unwrap_or(0.0);
+        }
+        let t = self.sample_index(x);
+        let i = (t.floor() as usize).min(self.heights.len() - 2);
+        let fraction = t - i as f32;
+        self.heights[i] + (self.heights[i + 1] - self.heights[i]) * fraction
+    }
+
+    /// Slope (rise over run) at x; zero beyond the ends
+    pub fn slope_at(&self, x: f32) -> f32 {
+        let inside = self.heights.len() >= 2 && x >= self.origin_x && x < self.origin_x + self.spacing * (self.heights.len() - 1) as f32;
+        if !inside {
+            return 0.0;
+        }
+        let i = (self.sample_index(x).floor() as usize).min(self.heights.len() - 2);
+        (self.heights[i + 1] - self.heights[i]) / self.spacing
+    }
+
+    /// Unit vector pointing up out of the ground at x
+    pub fn normal_at(&self, x: f32) -> Vector2<f32> {
+        Vector2::new(-self.slope_at(x), 1.0).normalize()
+    }
+
+    /// A heightfield collider covering the ground from `from_x` to `to_x`
+    ///
+    /// Spawn the pair as an entity to let the CollisionSystem stop bodies
+    /// (a fuselage, a dropped store) at the ground too
+    pub fn collider(&self, from_x: f32, to_x: f32) -> (Position, Collider) {
+        let spacing = if self.heights.len() < 2 { to_x - from_x } else { self.spacing };
+        let count = ((to_x - from_x) / spacing).ceil().max(1.0) as usize + 1;
+        let heights = (0..count).map(|i| self.height_at(from_x + i as f32 * spacing)).collect();
+        (Position::new(from_x, 0.0), Collider::heightfield(heights, spacing))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_terrain_profile() {
+        let terrain = Terrain::parse("# x, height\n0, 0\n100, 10\n200, 10\n\n300 0\n").unwrap();
+        assert_eq!(terrain, Terrain::profile(0.0, 100.0, vec![0.0, 10.0, 10.0, 0.0]));
+        assert_eq!(terrain.height_at(50.0), 5.0);
+        assert_eq!(terrain.height_at(-1000.0), 0.0);
+        assert!((terrain.normal_at(50.0) - Vector2::new(-0.1, 1.0).normalize()).norm() < 1e-6);
+        assert_eq!(terrain.normal_at(150.0), Vector2::y());
+
+        assert!(matches!(Terrain::parse("0, 0\n10, 1\n25, 2"), Err(TerrainError::Invalid { line: 3, .. })));
+        assert!(matches!(Terrain::parse("0, high"), Err(TerrainError::Invalid { line: 1, .. })));
+        assert!(matches!(Terrain::parse("# nothing"), Err(TerrainError::Empty)));
+        assert_eq!(Terrain::parse("5, 2.5").unwrap().height_at(1e6), 2.5);
+    }
+}
@@ -0,0 +1,239 @@
+// 3D physics components - the 3D counterparts of Position, Velocity,
+// Rotation, RigidBody, Force and Torque; Mass is shared by both modes
+use nalgebra::{Matrix3, UnitQuaternion, Vector3};
+use serde::{Deserialize, Serialize};
+
+/// 3D position component, in metres
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Position3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Position3 {
+    pub fn new(x: f32, y: f32, z: f32) -> Self {
+        Self { x, y, z }
+    }
+    
+    pub fn zero() -> Self {
+        Self::new(0.0, 0.0, 0.0)
+    }
+    
+    pub fn to_vector(&self) -> Vector3<f32> {
+        Vector3::new(self.x, self.y, self.z)
+    }
+    
+    pub fn from_vector(v: Vector3<f32>) -> Self {
+        Self::new(v.x, v.y, v.z)
+    }
+}
+
+/// 3D velocity component, in m/s
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Velocity3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Velocity3 {
+    pub fn new(x: f32, y: f32, z: f32) -> Self {
+        Self { x, y, z }
+    }
+    
+    pub fn zero() -> Self {
+        Self::new(0.0, 0.0, 0.0)
+    }
+    
+    pub fn to_vector(&self) -> Vector3<f32> {
+        Vector3::new(self.x, self.y, self.z)
+    }
+    
+    pub fn from_vector(v: Vector3<f32>) -> Self {
+        Self::new(v.x, v.y, v.z)
+    }
+    
+    /// Speed, regardless of direction
+    pub fn magnitude(&self) -> f32 {
+        self.to_vector().norm()
+    }
+}
+
+/// Orientation component - which way a body faces in 3D
+/// 
+/// Stored as a unit quaternion (w + xi + yj + zk), which turns smoothly in
+/// every direction; roll, pitch and yaw angles lock up when the nose points
+/// straight up. Use from_euler and euler to work in angles anyway
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Orientation {
+    pub w: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Orientation {
+    /// Level, nose along +x
+    pub fn identity() -> Self {
+        Self::from_quaternion(UnitQuaternion::identity())
+    }
+    
+    /// From angles in radians, applied yaw first, then pitch, then roll
+    /// 
+    /// - roll: about the nose, positive dips the left wing
+    /// - pitch: positive raises the nose, like a 2D Rotation
+    /// - yaw: positive turns the nose right, towards -z
+    pub fn from_euler(roll: f32, pitch: f32, yaw: f32) -> Self {
+        let yaw = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), yaw);
+        let pitch = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), pitch);
+        let roll = UnitQuaternion::from_axis_angle(&Vector3::x_axis(), roll);
+        Self::from_quaternion(yaw * pitch * roll)
+    }
+    
+    /// (roll, pitch, yaw) in radians, the inverse of from_euler
+    pub fn euler(&self) -> (f32, f32, f32) {
+        let m = self.to_quaternion().to_rotation_matrix().into_inner();
+        let pitch = m[(1, 0)].clamp(-1.0, 1.0).asin();
+        let yaw = (-m[(2, 0)]).atan2(m[(0, 0)]);
+        let roll = (-m[(1, 2)]).atan2(m[(1, 1)]);
+        (roll, pitch, yaw)
+    }
+    
+    /// As a nalgebra quaternion, renormalised against rounding drift
+    pub fn to_quaternion(&self) -> UnitQuaternion<f32> {
+        UnitQuaternion::from_quaternion(nalgebra::Quaternion::new(self.w, self.x, self.y, self.z))
+    }
+    
+    pub fn from_quaternion(q: UnitQuaternion<f32>) -> Self {
+        Self {
+            w: q.w,
+            x: q.i,
+            y: q.j,
+            z: q.k,
+        }
+    }
+    
+    /// Turn a body-frame vector into world space
+    pub fn to_world(&self, v: Vector3<f32>) -> Vector3<f32> {
+        self.to_quaternion() * v
+    }
+    
+    /// Turn a world-space vector into the body frame
+    pub fn to_body(&self, v: Vector3<f32>) -> Vector3<f32> {
+        self.to_quaternion().inverse() * v
+    }
+}
+
+/// Force accumulator for 3D bodies, in newtons, world space
+/// 
+/// Summed and cleared each step exactly like the 2D Force
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Force3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Force3 {
+    pub fn new(x: f32, y: f32, z: f32) -> Self {
+        Self { x, y, z }
+    }
+    
+    pub fn zero() -> Self {
+        Self::new(0.0, 0.0, 0.0)
+    }
+    
+    pub fn to_vector(&self) -> Vector3<f32> {
+        Vector3::new(self.x, self.y, self.z)
+    }
+    
+    pub fn add(&mut self, force: Force3) {
+        self.x += force.x;
+        self.y += force.y;
+        self.z += force.z;
+    }
+    
+    pub fn clear(&mut self) {
+        *self = Self::zero();
+    }
+    
+    /// Torque this force causes when applied at `offset` from the centre of
+    /// mass, both in world space: offset × force
+    pub fn torque_at(&self, offset: Vector3<f32>) -> Torque3 {
+        let torque = offset.cross(&self.to_vector());
+        Torque3::new(torque.x, torque.y, torque.z)
+    }
+}
+
+/// Torque accumulator for 3D bodies, in newton-metres, world space
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Torque3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Torque3 {
+    pub fn new(x: f32, y: f32, z: f32) -> Self {
+        Self { x, y, z }
+    }
+    
+    pub fn zero() -> Self {
+        Self::new(0.0, 0.0, 0.0)
+    }
+    
+    pub fn to_vector(&self) -> Vector3<f32> {
+        Vector3::new(self.x, self.y, self.z)
+    }
+    
+    pub fn add(&mut self, torque: Torque3) {
+        self.x += torque.x;
+        self.y += torque.y;
+        self.z += torque.z;
+    }
+    
+    pub fn clear(&mut self) {
+        *self = Self::zero();
+    }
+}
+
+/// Rigid body component for 3D - makes the RigidBodySystem3 integrate an entity
+/// 
+/// Needs Position3, Velocity3, Orientation and Mass alongside it
+/// 
+/// Both fields are in the body frame, the natural one for an aircraft:
+/// the inertia tensor doesn't change as it turns, and the angular velocity
+/// is the roll, yaw and pitch rates a gyro on board would measure
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RigidBody3 {
+    /// Inertia tensor about the centre of mass, in kg·m²
+    pub inertia: Matrix3<f32>,
+    /// Rad/s about the body's x (roll), y (yaw) and z (pitch) axes
+    pub angular_velocity: Vector3<f32>,
+}
+
+impl RigidBody3 {
+    pub fn new(inertia: Matrix3<f32>) -> Self {
+        Self {
+            inertia,
+            angular_velocity: Vector3::zeros(),
+        }
+    }
+    
+    /// A body whose principal axes are its body axes, so the tensor is diagonal
+    pub fn principal(roll: f32, yaw: f32, pitch: f32) -> Self {
+        Self::new(Matrix3::from_diagonal(&Vector3::new(roll, yaw, pitch)))
+    }
+    
+    /// Solid box: `length` along the nose, `height` up, `span` across the wings
+    pub fn cuboid(mass: f32, length: f32, height: f32, span: f32) -> Self {
+        let (l2, h2, s2) = (length * length, height * height, span * span);
+        Self::principal(mass * (h2 + s2) / 12.0, mass * (l2 + s2) / 12.0, mass * (l2 + h2) / 12.0)
+    }
+    
+    /// Angular velocity in world space, for a body facing `orientation`
+    pub fn world_angular_velocity(&self, orientation: &Orientation) -> Vector3<f32> {
+        orientation.to_world(self.angular_velocity)
+    }
+}
@@ -0,0 +1,95 @@
+// Force models for 3D bodies - the 3D counterpart of forces.rs
+use std::sync::Arc;
+
+use ecs::{Entity, World};
+use nalgebra::Vector3;
+
+use super::integrator::BodyState3;
+use crate::components::Mass;
+use crate::forces::Gravity;
+
+/// A force plus the torque that comes with it, both in world space
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Wrench3 {
+    pub force: Vector3<f32>,
+    pub torque: Vector3<f32>,
+}
+
+impl Wrench3 {
+    pub fn zero() -> Self {
+        Self {
+            force: Vector3::zeros(),
+            torque: Vector3::zeros(),
+        }
+    }
+    
+    pub fn from_force(force: Vector3<f32>) -> Self {
+        Self {
+            force,
+            torque: Vector3::zeros(),
+        }
+    }
+}
+
+impl std::ops::Add for Wrench3 {
+    type Output = Wrench3;
+    
+    fn add(self, other: Wrench3) -> Wrench3 {
+        Wrench3 {
+            force: self.force + other.force,
+            torque: self.torque + other.torque,
+        }
+    }
+}
+
+/// A force that can be evaluated at any 3D state
+/// 
+/// Same rules as ForceModel: pure, and only reading `state` plus world data
+/// that stays fixed during the step
+pub trait ForceModel3: Send + Sync {
+    fn wrench(&self, world: &World, entity: Entity, state: &BodyState3) -> Wrench3;
+}
+
+/// The force models acting on every 3D rigid body, stored as a resource
+#[derive(Clone, Default)]
+pub struct ForceModels3 {
+    models: Vec<Arc<dyn ForceModel3>>,
+}
+
+impl ForceModels3 {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn add(&mut self, model: impl ForceModel3 + 'static) -> &mut Self {
+        self.models.push(Arc::new(model));
+        self
+    }
+    
+    pub fn with(mut self, model: impl ForceModel3 + 'static) -> Self {
+        self.add(model);
+        self
+    }
+    
+    pub fn len(&self) -> usize {
+        self.models.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.models.is_empty()
+    }
+    
+    pub fn wrench(&self, world: &World, entity: Entity, state: &BodyState3) -> Wrench3 {
+        self.models
+            .iter()
+            .fold(Wrench3::zero(), |total, model| total + model.wrench(world, entity, state))
+    }
+}
+
+/// Gravity acts in the x-y plane in both modes, so one value serves both
+impl ForceModel3 for Gravity {
+    fn wrench(&self, world: &World, entity: Entity, _state: &BodyState3) -> Wrench3 {
+        let mass = world.get_component::<Mass>(entity).map_or(0.0, |mass| mass.value);
+        Wrench3::from_force(Vector3::new(self.x, self.y, 0.0) * mass)
+    }
+}
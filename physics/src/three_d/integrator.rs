@@ -0,0 +1,142 @@
+// 3D integration - the same three integrators, stepping 6-DOF states
+//
+// Position and velocity work exactly as in 2D. Orientation is a quaternion,
+// so instead of adding angular velocity × dt to an angle, each step turns
+// it by the rotation vector (body rates × dt)
+use nalgebra::{UnitQuaternion, Vector3};
+
+use crate::integrator::Integrator;
+
+/// The part of a 3D rigid body that integrators change
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BodyState3 {
+    pub position: Vector3<f32>,
+    pub velocity: Vector3<f32>,
+    pub orientation: UnitQuaternion<f32>,
+    /// Body frame, rad/s
+    pub angular_velocity: Vector3<f32>,
+}
+
+/// Rate of change of a 3D body's velocities
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Acceleration3 {
+    /// World space, m/s²
+    pub linear: Vector3<f32>,
+    /// Body frame, rad/s²
+    pub angular: Vector3<f32>,
+}
+
+impl Acceleration3 {
+    pub fn zero() -> Self {
+        Self {
+            linear: Vector3::zeros(),
+            angular: Vector3::zeros(),
+        }
+    }
+}
+
+impl BodyState3 {
+    /// This state moved along (velocity, acceleration) for `dt`
+    fn advanced(&self, velocity: Vector3<f32>, angular_velocity: Vector3<f32>, acceleration: Acceleration3, dt: f32) -> Self {
+        Self {
+            position: self.position + velocity * dt,
+            velocity: self.velocity + acceleration.linear * dt,
+            orientation: turned(self.orientation, angular_velocity * dt),
+            angular_velocity: self.angular_velocity + acceleration.angular * dt,
+        }
+    }
+}
+
+/// Turn an orientation by a body-frame rotation vector (axis × angle)
+/// 
+/// Renormalised every time so rounding errors can't skew the body
+fn turned(orientation: UnitQuaternion<f32>, rotation: Vector3<f32>) -> UnitQuaternion<f32> {
+    let mut turned = orientation * UnitQuaternion::from_scaled_axis(rotation);
+    turned.renormalize();
+    turned
+}
+
+impl Integrator {
+    /// Advance a 3D `state` by `dt` seconds; see `step` for the 2D version
+    /// and the rules for the derivative function
+    pub fn step3(self, state: BodyState3, dt: f32, mut acceleration: impl FnMut(&BodyState3) -> Acceleration3) -> BodyState3 {
+        match self {
+            Integrator::SemiImplicitEuler => {
+                let a = acceleration(&state);
+                let velocity = state.velocity + a.linear * dt;
+                let angular_velocity = state.angular_velocity + a.angular * dt;
+                BodyState3 {
+                    position: state.position + velocity * dt,
+                    velocity,
+                    orientation: turned(state.orientation, angular_velocity * dt),
+                    angular_velocity,
+                }
+            }
+            Integrator::VelocityVerlet => {
+                let a0 = acceleration(&state);
+                let moved = BodyState3 {
+                    position: state.position + state.velocity * dt + a0.linear * (0.5 * dt * dt),
+                    velocity: state.velocity + a0.linear * dt,
+                    orientation: turned(state.orientation, (state.angular_velocity + a0.angular * (0.5 * dt)) * dt),
+                    angular_velocity: state.angular_velocity + a0.angular * dt,
+                };
+                let a1 = acceleration(&moved);
+                BodyState3 {
+                    velocity: state.velocity + (a0.linear + a1.linear) * (0.5 * dt),
+                    angular_velocity: state.angular_velocity + (a0.angular + a1.angular) * (0.5 * dt),
+                    ..moved
+                }
+            }
+            Integrator::Rk4 => {
+                let k1 = acceleration(&state);
+                let s2 = state.advanced(state.velocity, state.angular_velocity, k1, dt / 2.0);
+                let k2 = acceleration(&s2);
+                let s3 = state.advanced(s2.velocity, s2.angular_velocity, k2, dt / 2.0);
+                let k3 = acceleration(&s3);
+                let s4 = state.advanced(s3.velocity, s3.angular_velocity, k3, dt);
+                let k4 = acceleration(&s4);
+                
+                let velocity = (state.velocity + (s2.velocity + s3.velocity) * 2.0 + s4.velocity) / 6.0;
+                let angular_velocity = (state.angular_velocity + (s2.angular_velocity + s3.angular_velocity) * 2.0 + s4.angular_velocity) / 6.0;
+                let linear = (k1.linear + (k2.linear + k3.linear) * 2.0 + k4.linear) / 6.0;
+                let angular = (k1.angular + (k2.angular + k3.angular) * 2.0 + k4.angular) / 6.0;
+                state.advanced(velocity, angular_velocity, Acceleration3 { linear, angular }, dt)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const ALL: [Integrator; 3] = [Integrator::SemiImplicitEuler, Integrator::VelocityVerlet, Integrator::Rk4];
+    
+    #[test]
+    fn test_ballistic_and_spinning_3d() {
+        // Thrown up and sideways while spinning about the nose at π rad/s
+        let start = BodyState3 {
+            position: Vector3::zeros(),
+            velocity: Vector3::new(10.0, 20.0, -5.0),
+            orientation: UnitQuaternion::identity(),
+            angular_velocity: Vector3::new(std::f32::consts::PI, 0.0, 0.0),
+        };
+        let gravity = |_: &BodyState3| Acceleration3 {
+            linear: Vector3::new(0.0, -9.81, 0.0),
+            angular: Vector3::zeros(),
+        };
+        for integrator in ALL {
+            let mut state = start;
+            for _ in 0..1000 {
+                state = integrator.step3(state, 0.001, gravity);
+            }
+            
+            // After 1 s: the ballistic arc, and half a turn about the nose
+            let expected = Vector3::new(10.0, 20.0 - 0.5 * 9.81, -5.0);
+            assert!((state.position - expected).norm() < 0.02, "{:?}: {:?}", integrator, state.position);
+            let top = state.orientation * Vector3::y();
+            assert!((top - Vector3::new(0.0, -1.0, 0.0)).norm() < 1e-3, "{:?}: top points {:?}", integrator, top);
+            assert!((state.orientation.norm() - 1.0).abs() < 1e-5);
+        }
+    }
+}
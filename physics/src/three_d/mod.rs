@@ -0,0 +1,22 @@
+// 3D mode - six degrees of freedom, for full flight control
+//
+// The 2D simulation is the x-y plane of this 3D world: x forward, y up, and
+// z towards the viewer. A 2D Rotation is a turn about z, so positive angles
+// pitch the nose up in both modes
+//
+// Bodies have their own axes too (the body frame): x out of the nose, y out
+// of the top and z out of the left wing, matching the world axes when the
+// aircraft flies level along +x. Roll turns about body x, yaw about body y
+// and pitch about body z
+//
+// The 3D types sit alongside the 2D ones rather than replacing them; a
+// scenario picks which set it uses
+pub mod components;
+pub mod forces;
+pub mod integrator;
+pub mod systems;
+
+pub use components::{Force3, Orientation, Position3, RigidBody3, Torque3, Velocity3};
+pub use forces::{ForceModel3, ForceModels3, Wrench3};
+pub use integrator::{Acceleration3, BodyState3};
+pub use systems::RigidBodySystem3;
@@ -0,0 +1,230 @@
+// 3D physics systems
+use ecs::{EcsResult, Entity, System, World};
+use nalgebra::{Matrix3, Vector3};
+
+use super::components::{Force3, Orientation, Position3, RigidBody3, Torque3, Velocity3};
+use super::forces::ForceModels3;
+use super::integrator::{Acceleration3, BodyState3};
+use crate::components::Mass;
+use crate::integrator::Integrator;
+
+/// Integrates 3D rigid bodies, the 3D counterpart of RigidBodySystem
+/// 
+/// For each entity with RigidBody3, Mass, Position3, Velocity3 and Orientation:
+/// - linear acceleration is force / Mass
+/// - angular acceleration comes from Euler's equations in the body frame,
+///   I α = τ - ω × (I ω); the last term is what makes a spinning body
+///   precess and tumble instead of just turning faster
+/// - the Integrator (component, else resource, else semi-implicit Euler)
+///   advances the state, and Force3 and Torque3 are cleared
+/// 
+/// The force is Force3 plus the ForceModels3 resource, the torque Torque3
+/// plus the models' torques, all in world space
+#[derive(Clone)]
+pub struct RigidBodySystem3 {
+    name: String,
+}
+
+/// Everything about one body that stays fixed while it's integrated
+struct BodyStep3 {
+    entity: Entity,
+    state: BodyState3,
+    force: Vector3<f32>,
+    torque: Vector3<f32>,
+    mass: f32,
+    inertia: Matrix3<f32>,
+    /// None when the tensor can't be inverted; the body then doesn't turn faster or slower
+    inverse_inertia: Option<Matrix3<f32>>,
+    integrator: Option<Integrator>,
+}
+
+impl RigidBodySystem3 {
+    pub fn new() -> Self {
+        Self {
+            name: "RigidBodySystem3".to_string(),
+        }
+    }
+}
+
+impl Default for RigidBodySystem3 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl System for RigidBodySystem3 {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    
+    fn run(&mut self, world: &mut World, delta_time: f32) -> EcsResult<()> {
+        // Step 1: Gather every body's state
+        let bodies: Vec<BodyStep3> = world
+            .query::<(Entity, (&Position3, &Velocity3, &Orientation), &RigidBody3, &Mass, Option<&Force3>, Option<&Torque3>, Option<&Integrator>)>()?
+            .iter()
+            .map(|(entity, (position, velocity, orientation), body, mass, force, torque, integrator)| BodyStep3 {
+                entity,
+                state: BodyState3 {
+                    position: position.to_vector(),
+                    velocity: velocity.to_vector(),
+                    orientation: orientation.to_quaternion(),
+                    angular_velocity: body.angular_velocity,
+                },
+                force: force.map_or(Vector3::zeros(), |force| force.to_vector()),
+                torque: torque.map_or(Vector3::zeros(), |torque| torque.to_vector()),
+                mass: mass.value,
+                inertia: body.inertia,
+                inverse_inertia: body.inertia.try_inverse(),
+                integrator: integrator.copied(),
+            })
+            .collect();
+        
+        let models = world.resource::<ForceModels3>().map(|models| models.clone()).unwrap_or_default();
+        let default_integrator = world.resource::<Integrator>().map(|integrator| *integrator).unwrap_or_default();
+        
+        // Step 2: Integrate each body, with the world only read
+        let world_ref: &World = world;
+        let results: Vec<(Entity, BodyState3)> = bodies
+            .iter()
+            .map(|body| {
+                let acceleration = |state: &BodyState3| {
+                    let wrench = models.wrench(world_ref, body.entity, state);
+                    let linear = if body.mass > 0.0 { (body.force + wrench.force) / body.mass } else { Vector3::zeros() };
+                    let angular = match body.inverse_inertia {
+                        Some(inverse) => {
+                            // Euler's equations work in the body frame
+                            let torque = state.orientation.inverse() * (body.torque + wrench.torque);
+                            let omega = state.angular_velocity;
+                            inverse * (torque - omega.cross(&(body.inertia * omega)))
+                        }
+                        None => Vector3::zeros(),
+                    };
+                    Acceleration3 { linear, angular }
+                };
+                let integrator = body.integrator.unwrap_or(default_integrator);
+                (body.entity, integrator.step3(body.state, delta_time, acceleration))
+            })
+            .collect();
+        
+        // Step 3: Write the new states back and empty the accumulators
+        for (entity, state) in results {
+            if let Some(position) = world.get_component_mut::<Position3>(entity) {
+                *position = Position3::from_vector(state.position);
+            }
+            if let Some(velocity) = world.get_component_mut::<Velocity3>(entity) {
+                *velocity = Velocity3::from_vector(state.velocity);
+            }
+            if let Some(orientation) = world.get_component_mut::<Orientation>(entity) {
+                *orientation = Orientation::from_quaternion(state.orientation);
+            }
+            if let Some(body) = world.get_component_mut::<RigidBody3>(entity) {
+                body.angular_velocity = state.angular_velocity;
+            }
+            if let Some(force) = world.get_component_mut::<Force3>(entity) {
+                force.clear();
+            }
+            if let Some(torque) = world.get_component_mut::<Torque3>(entity) {
+                torque.clear();
+            }
+        }
+        
+        Ok(())
+    }
+    
+    fn initialize(&mut self, world: &mut World) -> EcsResult<()> {
+        world.register_component::<Position3>();
+        world.register_component::<Velocity3>();
+        world.register_component::<Orientation>();
+        world.register_component::<RigidBody3>();
+        world.register_component::<Force3>();
+        world.register_component::<Torque3>();
+        world.register_component::<Integrator>();
+        Ok(())
+    }
+    
+    fn fork(&self) -> Option<Box<dyn System>> {
+        Some(Box::new(self.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forces::Gravity;
+    
+    #[test]
+    fn test_rigid_body_3d() {
+        let mut world = World::new();
+        let mut system = RigidBodySystem3::new();
+        system.initialize(&mut world).unwrap();
+        world.insert_resource(ForceModels3::new().with(Gravity::earth()));
+        world.insert_resource(Integrator::Rk4);
+        
+        // Thrown along +x and pushed sideways, with a torque pitching it up
+        let body = world
+            .spawn((
+                Position3::zero(),
+                Velocity3::new(10.0, 0.0, 0.0),
+                Orientation::identity(),
+                Mass::new(2.0),
+                RigidBody3::principal(1.0, 1.0, 0.5),
+                Force3::new(0.0, 0.0, 4.0),
+                Torque3::new(0.0, 0.0, 1.0),
+            ))
+            .unwrap();
+        system.run(&mut world, 0.5).unwrap();
+        
+        // a = F/m + g, and the pitch rate is τ / I = 2 rad/s²
+        let velocity = world.get_component::<Velocity3>(body).unwrap().to_vector();
+        assert!((velocity - Vector3::new(10.0, -9.81 * 0.5, 1.0)).norm() < 1e-4, "{:?}", velocity);
+        let spin = world.get_component::<RigidBody3>(body).unwrap().angular_velocity;
+        assert!((spin - Vector3::new(0.0, 0.0, 1.0)).norm() < 1e-4, "{:?}", spin);
+        let (roll, pitch, yaw) = world.get_component::<Orientation>(body).unwrap().euler();
+        assert!(roll.abs() < 1e-5 && yaw.abs() < 1e-5);
+        assert!((pitch - 0.25).abs() < 1e-4, "pitch {}", pitch);
+        assert_eq!(*world.get_component::<Force3>(body).unwrap(), Force3::zero());
+        
+        // Spinning about the axis with the middle moment of inertia is unstable:
+        // a tiny wobble grows until the body flips (the tennis racket effect)
+        let racket = world
+            .spawn((
+                Position3::zero(),
+                Velocity3::zero(),
+                Orientation::identity(),
+                Mass::new(1.0),
+                RigidBody3 {
+                    inertia: Matrix3::from_diagonal(&Vector3::new(1.0, 2.0, 3.0)),
+                    angular_velocity: Vector3::new(0.0, 5.0, 0.01),
+                },
+            ))
+            .unwrap();
+        let energy = |world: &World| {
+            let body = world.get_component::<RigidBody3>(racket).unwrap();
+            0.5 * body.angular_velocity.dot(&(body.inertia * body.angular_velocity))
+        };
+        let initial = energy(&world);
+        let mut flipped = false;
+        for _ in 0..5000 {
+            system.run(&mut world, 0.001).unwrap();
+            flipped |= world.get_component::<RigidBody3>(racket).unwrap().angular_velocity.y < 0.0;
+        }
+        assert!(flipped);
+        assert!((energy(&world) - initial).abs() < 1e-3 * initial);
+    }
+    
+    #[test]
+    fn test_euler_angles_round_trip() {
+        let orientation = Orientation::from_euler(0.3, -0.2, 1.1);
+        let (roll, pitch, yaw) = orientation.euler();
+        assert!((roll - 0.3).abs() < 1e-5 && (pitch + 0.2).abs() < 1e-5 && (yaw - 1.1).abs() < 1e-5);
+        
+        // Pitching up raises the nose, yawing turns it right (towards -z),
+        // rolling dips the left wing (+z)
+        let nose = Orientation::from_euler(0.0, 0.5, 0.0).to_world(Vector3::x());
+        assert!(nose.y > 0.0);
+        let nose = Orientation::from_euler(0.0, 0.0, 0.5).to_world(Vector3::x());
+        assert!(nose.z < 0.0);
+        let left_wing = Orientation::from_euler(0.5, 0.0, 0.0).to_world(Vector3::z());
+        assert!(left_wing.y < 0.0);
+    }
+}
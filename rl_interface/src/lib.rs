@@ -39,6 +39,12 @@ impl Observation {
     }
 }
 
+impl Default for Observation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Action from RL agent
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Action {
@@ -1,192 +1,14 @@
 // Import statements - bring external types into scope
-use nalgebra::Vector2;                    // 2D vector math from nalgebra crate
+use aerodynamics::{AeroProperties, Airfoil, Gust, Wind, Wind3};  // Lift/drag data from the aerodynamics crate
 use serde::{Deserialize, Serialize};      // For converting to/from JSON, binary, etc.
+use ecs::{Bundle, BundleTypes, BundleWriter, Children, Parent, SnapshotRegistry, World};  // ECS types we build on
 
-/// 2D position component
-/// 
-/// This represents where an entity is located in 2D space
-/// Components in ECS are just data - no behavior/methods for game logic
-/// 
-/// The #[derive(...)] is a "derive macro" that automatically generates code:
-/// - Debug: Lets you print the struct with {:?} 
-/// - Clone: Lets you make copies with .clone()
-/// - Copy: Lets you copy with just assignment (very cheap)
-/// - PartialEq: Lets you compare with == and !=
-/// - Serialize/Deserialize: Lets you save/load to files or send over network
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
-pub struct Position {
-    /// X coordinate in world space
-    /// 'pub' means other modules can read/write this field directly
-    /// f32 is a 32-bit floating point number (like float in C/Java)
-    pub x: f32,
-    
-    /// Y coordinate in world space  
-    /// In 2D games, usually +X = right, +Y = up (or down, depending on system)
-    pub y: f32,
-}
-
-// Implementation block - where we define methods for Position
-// This is like defining class methods in other languages
-impl Position {
-    /// Create a new Position with specific coordinates
-    /// 
-    /// This is an "associated function" (like a static method)
-    /// No 'self' parameter means you call it like: Position::new(1.0, 2.0)
-    /// 'Self' is an alias for the current type (Position)
-    pub fn new(x: f32, y: f32) -> Self {
-        Self { x, y }   // Struct literal syntax - creates a new Position
-    }
-    
-    /// Create a position at the origin (0, 0)
-    /// 
-    /// Another associated function - provides a convenient default
-    /// This is a common Rust pattern for creating "default" values
-    pub fn zero() -> Self {
-        Self { x: 0.0, y: 0.0 }
-    }
-    
-    /// Convert to a nalgebra Vector2 for math operations
-    /// 
-    /// This is a method (has &self parameter)  
-    /// &self means "borrow self immutably" - we can read but not modify
-    /// Methods are called like: position.to_vector()
-    pub fn to_vector(&self) -> Vector2<f32> {
-        Vector2::new(self.x, self.y)  // Create a nalgebra vector
-    }
-    
-    /// Create a Position from a nalgebra Vector2
-    /// 
-    /// Associated function for conversion from vector math
-    /// This lets us easily convert between our component and math library
-    pub fn from_vector(v: Vector2<f32>) -> Self {
-        Self { x: v.x, y: v.y }
-    }
-}
-
-/// 2D velocity component
-/// 
-/// This represents how fast and in what direction an entity is moving
-/// Velocity is typically in units per second (e.g., meters/second, pixels/second)
-/// Positive X usually means moving right, positive Y means moving up
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
-pub struct Velocity {
-    /// Velocity in X direction (horizontal speed)
-    pub x: f32,
-    
-    /// Velocity in Y direction (vertical speed)  
-    pub y: f32,
-}
-
-impl Velocity {
-    /// Create a new velocity with specific X and Y components
-    pub fn new(x: f32, y: f32) -> Self {
-        Self { x, y }
-    }
-    
-    /// Create a zero velocity (not moving)
-    /// 
-    /// Useful for stationary objects or when you want to stop something
-    pub fn zero() -> Self {
-        Self { x: 0.0, y: 0.0 }
-    }
-    
-    /// Convert to nalgebra Vector2 for vector math operations
-    /// 
-    /// Vector math is useful for operations like:
-    /// - Adding velocities together
-    /// - Rotating velocity vectors  
-    /// - Normalizing to unit vectors
-    pub fn to_vector(&self) -> Vector2<f32> {
-        Vector2::new(self.x, self.y)
-    }
-    
-    /// Create velocity from a nalgebra Vector2
-    pub fn from_vector(v: Vector2<f32>) -> Self {
-        Self { x: v.x, y: v.y }
-    }
-    
-    /// Calculate the magnitude (speed) of this velocity
-    /// 
-    /// Uses the Pythagorean theorem: magnitude = sqrt(x² + y²)
-    /// This gives you the overall speed regardless of direction
-    /// For example: velocity (3, 4) has magnitude 5
-    pub fn magnitude(&self) -> f32 {
-        (self.x * self.x + self.y * self.y).sqrt()
-    }
-}
-
-/// Rotation component (in radians)
-/// 
-/// Represents how much an entity is rotated from its default orientation
-/// Radians are the standard unit for angles in programming and math:
-/// - 0 radians = 0 degrees (facing right, typically)
-/// - π/2 radians = 90 degrees  
-/// - π radians = 180 degrees
-/// - 2π radians = 360 degrees (full circle)
-/// 
-/// Why radians? Math functions (sin, cos, etc.) expect radians, and they
-/// make calculations simpler (no need to convert constantly)
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
-pub struct Rotation {
-    /// Angle in radians
-    /// Positive typically means counter-clockwise rotation
-    pub angle: f32,
-}
-
-impl Rotation {
-    /// Create a new rotation with a specific angle in radians
-    pub fn new(angle: f32) -> Self {
-        Self { angle }
-    }
-    
-    /// Create a rotation of 0 (no rotation)
-    pub fn zero() -> Self {
-        Self { angle: 0.0 }
-    }
-    
-    /// Create a rotation from degrees
-    /// 
-    /// Since humans think in degrees but computers prefer radians,
-    /// this helper function converts for you
-    /// Example: Rotation::degrees(90.0) creates a 90-degree rotation
-    pub fn degrees(degrees: f32) -> Self {
-        Self { 
-            angle: degrees.to_radians()  // Built-in conversion method
-        }
-    }
-    
-    /// Convert this rotation to degrees
-    /// 
-    /// Useful for displaying rotation values to users or debugging
-    /// Most people understand "90 degrees" better than "1.57 radians"
-    pub fn to_degrees(&self) -> f32 {
-        self.angle.to_degrees()  // Built-in conversion method
-    }
-}
-
-/// Mass component for physics calculations
-/// 
-/// Represents how much matter an entity contains
-/// Mass affects:
-/// - How much force is needed to accelerate the object (F = ma)
-/// - How objects behave in collisions
-/// - Gravitational effects
-/// - Inertia (resistance to changes in motion)
-/// 
-/// Units are typically in kilograms, but can be any consistent unit
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
-pub struct Mass {
-    /// Mass value in kilograms (or your chosen unit)
-    /// Should be positive - negative mass would be very strange physics!
-    pub value: f32,
-}
-
-impl Mass {
-    /// Create a new mass with a specific value
-    pub fn new(value: f32) -> Self {
-        Self { value }
-    }
-}
+// The physics state of a body is defined in the physics crate, so its systems
+// can use it too. Re-exporting keeps simulator::components::Position working
+pub use physics::{Collider, Force, Integrator, Joint, LandingGear, Mass, Position, RigidBody, Rotation, Spring, Torque, Velocity};
+pub use physics::{Force3, Orientation, Position3, RigidBody3, Torque3, Velocity3};
+use physics::Terrain;
+use crate::world::SimRng;
 
 /// Name component for debugging and identification
 /// 
@@ -254,4 +76,178 @@ impl From<String> for Name {
 // - let name: Name = "hello".into();
 // - let name: Name = my_string.into();
 // 
-// This is part of Rust's "coherence" system that prevents conflicting implementations
\ No newline at end of file
+// This is part of Rust's "coherence" system that prevents conflicting implementations
+
+/// Placement of a part relative to the entity it's attached to
+/// 
+/// Aircraft are built as hierarchies: wings, control surfaces and engines are
+/// child entities of the fuselage (see World::set_parent). A child with a
+/// LocalTransform gets its world-space Position and Rotation computed every
+/// step by the TransformPropagationSystem, so it follows its parent around
+/// 
+/// The offset is in the parent's frame: an engine at (0, -2) stays 2 units
+/// "below" the fuselage's centre whichever way the fuselage is pointing
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LocalTransform {
+    /// Offset from the parent's position, before the parent's rotation is applied
+    pub offset: Position,
+    
+    /// Rotation relative to the parent's rotation
+    pub rotation: Rotation,
+}
+
+impl LocalTransform {
+    /// Create a local transform from an offset and a relative rotation
+    pub fn new(offset: Position, rotation: Rotation) -> Self {
+        Self { offset, rotation }
+    }
+    
+    /// A part at the given offset, pointing the same way as its parent
+    pub fn from_offset(x: f32, y: f32) -> Self {
+        Self::new(Position::new(x, y), Rotation::zero())
+    }
+    
+    /// World-space position and rotation of a child with this local transform
+    /// 
+    /// The offset is rotated by the parent's angle (a standard 2D rotation
+    /// matrix), then added to the parent's position. Angles simply add up
+    pub fn to_world(&self, parent_position: Position, parent_rotation: Rotation) -> (Position, Rotation) {
+        let (sin, cos) = parent_rotation.angle.sin_cos();
+        let offset = self.offset;
+        let position = Position::new(
+            parent_position.x + offset.x * cos - offset.y * sin,
+            parent_position.y + offset.x * sin + offset.y * cos,
+        );
+        (position, Rotation::new(parent_rotation.angle + self.rotation.angle))
+    }
+}
+
+/// Everything a free-flying aircraft body needs, spawned in one go
+/// 
+/// Bundles group components that always travel together, so spawning an
+/// aircraft is one call instead of five add_component calls:
+/// 
+/// world.spawn(AircraftBundle::new("Glider", Position::new(0.0, 100.0), Mass::new(300.0)))?
+/// 
+/// Struct update syntax tweaks the defaults:
+/// AircraftBundle { velocity: Velocity::new(30.0, 0.0), ..AircraftBundle::new(...) }
+#[derive(Debug, Clone)]
+pub struct AircraftBundle {
+    pub name: Name,
+    pub position: Position,
+    pub velocity: Velocity,
+    pub rotation: Rotation,
+    pub mass: Mass,
+}
+
+impl AircraftBundle {
+    /// An aircraft at rest, pointing along +X
+    pub fn new(name: &str, position: Position, mass: Mass) -> Self {
+        Self {
+            name: Name::new(name),
+            position,
+            velocity: Velocity::zero(),
+            rotation: Rotation::zero(),
+            mass,
+        }
+    }
+}
+
+// Implementing Bundle tells World::spawn which components we contain
+// (register_components) and then hands it each one (write_components)
+// The two lists must match - same types, one component each
+impl Bundle for AircraftBundle {
+    fn register_components(&self, types: &mut BundleTypes<'_>) {
+        types.add::<Name>();
+        types.add::<Position>();
+        types.add::<Velocity>();
+        types.add::<Rotation>();
+        types.add::<Mass>();
+    }
+    
+    fn write_components(self, writer: &mut BundleWriter<'_>) {
+        writer.write(self.name);
+        writer.write(self.position);
+        writer.write(self.velocity);
+        writer.write(self.rotation);
+        writer.write(self.mass);
+    }
+}
+
+/// Registry of every simulator component and resource that goes into World snapshots
+/// 
+/// The names are what snapshot files refer to, so don't rename them
+/// (rename the Rust types freely - the files won't notice)
+/// New components and resources must be added here to survive a save/restore
+pub fn snapshot_registry() -> SnapshotRegistry {
+    let mut registry = SnapshotRegistry::new();
+    registry.register_component::<Position>("Position");
+    registry.register_component::<Velocity>("Velocity");
+    registry.register_component::<Rotation>("Rotation");
+    registry.register_component::<Mass>("Mass");
+    registry.register_component::<RigidBody>("RigidBody");
+    registry.register_component::<Force>("Force");
+    registry.register_component::<Torque>("Torque");
+    registry.register_component::<Integrator>("Integrator");
+    registry.register_component::<Spring>("Spring");
+    registry.register_component::<Collider>("Collider");
+    registry.register_component::<LandingGear>("LandingGear");
+    registry.register_component::<Position3>("Position3");
+    registry.register_component::<Velocity3>("Velocity3");
+    registry.register_component::<Orientation>("Orientation");
+    registry.register_component::<RigidBody3>("RigidBody3");
+    registry.register_component::<Force3>("Force3");
+    registry.register_component::<Torque3>("Torque3");
+    registry.register_component::<Name>("Name");
+    registry.register_component::<LocalTransform>("LocalTransform");
+    registry.register_component::<AeroProperties>("AeroProperties");
+    registry.register_component::<Airfoil>("Airfoil");
+    
+    // The hierarchy and joints hold entity handles, which are remapped when a snapshot loads
+    registry.register_component_with_entities::<Parent>("Parent");
+    registry.register_component_with_entities::<Children>("Children");
+    registry.register_component_with_entities::<Joint>("Joint");
+    
+    // Resources a scenario sets up, and where the random numbers have got to
+    registry.register_resource::<Wind>("Wind");
+    registry.register_resource::<Wind3>("Wind3");
+    registry.register_resource::<Gust>("Gust");
+    registry.register_resource::<Terrain>("Terrain");
+    registry.register_resource::<Integrator>("Integrator");
+    registry.register_resource::<SimRng>("SimRng");
+    registry
+}
+
+/// Let World::fork copy every simulator component
+/// 
+/// Forking clones components directly, so each one needs Clone
+/// New components must be added here too, or forking a world that uses them fails
+pub fn register_cloneable(world: &mut World) {
+    world.register_cloneable::<Position>();
+    world.register_cloneable::<Velocity>();
+    world.register_cloneable::<Rotation>();
+    world.register_cloneable::<Mass>();
+    world.register_cloneable::<RigidBody>();
+    world.register_cloneable::<Force>();
+    world.register_cloneable::<Torque>();
+    world.register_cloneable::<Integrator>();
+    world.register_cloneable::<Spring>();
+    world.register_cloneable::<Collider>();
+    world.register_cloneable::<LandingGear>();
+    world.register_cloneable::<Joint>();
+    world.register_cloneable::<Position3>();
+    world.register_cloneable::<Velocity3>();
+    world.register_cloneable::<Orientation>();
+    world.register_cloneable::<RigidBody3>();
+    world.register_cloneable::<Force3>();
+    world.register_cloneable::<Torque3>();
+    world.register_cloneable::<Name>();
+    world.register_cloneable::<LocalTransform>();
+    world.register_cloneable::<AeroProperties>();
+    world.register_cloneable::<Airfoil>();
+    
+    // The World registers these itself in set_parent, but a snapshot
+    // restore adds them directly, so register them up front too
+    world.register_cloneable::<Parent>();
+    world.register_cloneable::<Children>();
+}
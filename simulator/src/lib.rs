@@ -0,0 +1,8 @@
+// Simulator library - components, systems and the simulation world
+// The binary in main.rs is a thin driver on top of these modules
+
+pub mod components;
+pub mod recording;
+pub mod scenario;
+pub mod systems;
+pub mod world;
@@ -1,22 +1,227 @@
-use anyhow::Result;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Instant;
 
-mod components;
-mod systems;
-mod world;
+use anyhow::{Context, Result};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 
-use world::SimWorld;
+use ecs::EcsError;
+use physics::{Terrain, TerrainError};
+use simulator::components::{AircraftBundle, Mass, Position, Velocity};
+use simulator::recording::{Frame, Recording};
+use simulator::scenario::{Scenario, ScenarioError};
+use simulator::world::SimWorld;
+
+// Exit codes, so scripts can tell what kind of failure happened
+// 2 is also taken: clap uses it for bad command-line arguments
+const EXIT_FAILURE: u8 = 1;
+const EXIT_INVALID_SCENARIO: u8 = 3;
+const EXIT_SIMULATION_ERROR: u8 = 4;
+
+const EXIT_CODES_HELP: &str = "Exit codes:
+  0  success
+  1  I/O error or unreadable file
+  2  bad command-line arguments
+  3  invalid scenario or terrain profile (syntax, unknown components or systems, bad values)
+  4  the simulation itself failed (an ECS error while building or stepping)";
+
+/// Command-line interface of the aerodynamic simulator
+/// 
+/// #[derive(Parser)] makes clap generate the argument parser from this struct:
+/// doc comments become help text, fields become arguments
+#[derive(Parser, Debug)]
+#[command(name = "simulator", version, about = "ECS-based aerodynamic simulator", after_help = EXIT_CODES_HELP)]
+struct Cli {
+    /// What to do; without a subcommand the built-in demo runs
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run a scenario file, or the built-in demo if no file is given
+    Run(RunArgs),
+    
+    /// Check a scenario file for problems without running it
+    Validate {
+        scenario: PathBuf,
+    },
+    
+    /// Time stepping, forking and checkpointing on a synthetic world
+    Bench(BenchArgs),
+    
+    /// Print a recording made with `run --record`
+    Replay {
+        file: PathBuf,
+        
+        /// Simulated seconds between printed frames
+        #[arg(long, default_value_t = 1.0)]
+        interval: f32,
+    },
+    
+    /// Convert a recording made with `run --record` to CSV or JSON
+    Export {
+        file: PathBuf,
+        
+        #[arg(long, value_enum)]
+        format: ExportFormat,
+        
+        /// Where to write the export; standard output if omitted
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Args, Debug, Default)]
+struct RunArgs {
+    /// Scenario file in RON format
+    scenario: Option<PathBuf>,
+    
+    /// Seconds to simulate, instead of the scenario's duration
+    #[arg(long)]
+    duration: Option<f32>,
+    
+    /// Frame length in seconds, instead of the scenario's time step
+    #[arg(long)]
+    dt: Option<f32>,
+    
+    /// Random seed, instead of the scenario's; it picks the gusts of
+    /// scenarios that run TurbulenceSystem
+    #[arg(long)]
+    seed: Option<u64>,
+    
+    /// Skip per-step console output (DebugSystem and progress lines)
+    #[arg(long)]
+    headless: bool,
+    
+    /// Save every body's state after every step to this file
+    #[arg(long)]
+    record: Option<PathBuf>,
+    
+    /// Ground profile with one `x, height` sample per line, instead of the scenario's terrain
+    #[arg(long)]
+    terrain: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct BenchArgs {
+    /// Number of moving bodies to simulate
+    #[arg(long, default_value_t = 10_000)]
+    entities: usize,
+    
+    /// Number of frames to step
+    #[arg(long, default_value_t = 600)]
+    steps: usize,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum ExportFormat {
+    Csv,
+    Json,
+}
 
 /// Main entry point for the aerodynamic simulator
-fn main() -> Result<()> {
+/// 
+/// Returning ExitCode instead of Result lets us pick the process exit code
+fn main() -> ExitCode {
+    // parse() prints help or a usage error and exits by itself when needed
+    let cli = Cli::parse();
+    
+    match execute(cli.command.unwrap_or_else(|| Command::Run(RunArgs::default()))) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", error_message(&err));
+            ExitCode::from(exit_code(&err))
+        }
+    }
+}
+
+/// The error with its causes, e.g. "Can't load x: Recording I/O failed: not found"
+/// 
+/// Our error types already include their cause's text in their own message,
+/// so causes that are already part of the message are skipped
+fn error_message(err: &anyhow::Error) -> String {
+    let mut message = err.to_string();
+    for cause in err.chain().skip(1) {
+        let cause = cause.to_string();
+        if !message.contains(&cause) {
+            message.push_str(": ");
+            message.push_str(&cause);
+        }
+    }
+    message
+}
+
+/// Pick the exit code for an error by the kind of error at its root
+fn exit_code(err: &anyhow::Error) -> u8 {
+    // An error can be wrapped in context, so look through the whole chain
+    for cause in err.chain() {
+        if let Some(scenario_error) = cause.downcast_ref::<ScenarioError>() {
+            return match scenario_error {
+                ScenarioError::Io { .. } => EXIT_FAILURE,
+                ScenarioError::Parse(_) | ScenarioError::Invalid(_) => EXIT_INVALID_SCENARIO,
+                ScenarioError::Ecs(_) => EXIT_SIMULATION_ERROR,
+            };
+        }
+        if let Some(terrain_error) = cause.downcast_ref::<TerrainError>() {
+            return match terrain_error {
+                TerrainError::Io(_) => EXIT_FAILURE,
+                TerrainError::Invalid { .. } | TerrainError::Empty => EXIT_INVALID_SCENARIO,
+            };
+        }
+        if cause.downcast_ref::<EcsError>().is_some() {
+            return EXIT_SIMULATION_ERROR;
+        }
+    }
+    EXIT_FAILURE
+}
+
+fn execute(command: Command) -> Result<()> {
+    match command {
+        Command::Run(args) => run(args),
+        Command::Validate { scenario } => validate(scenario),
+        Command::Bench(args) => bench(args),
+        Command::Replay { file, interval } => replay(file, interval),
+        Command::Export { file, format, output } => export(file, format, output),
+    }
+}
+
+/// `simulator run`: build the world, step it, optionally record it
+fn run(args: RunArgs) -> Result<()> {
     println!("🚀 Aerodynamic Simulator Starting...");
     println!("=====================================");
     
-    // Create and initialize the simulation world
-    let mut sim_world = SimWorld::new();
-    sim_world.initialize()?;
+    // Command-line values override the file's settings before validation,
+    // so e.g. a negative --dt is reported like a bad value in the file
+    let mut scenario = match &args.scenario {
+        Some(path) => Scenario::load(path)?,
+        None => Scenario::default(),
+    };
+    if let Some(duration) = args.duration {
+        scenario.settings.duration = duration;
+    }
+    if let Some(dt) = args.dt {
+        scenario.settings.time_step = dt;
+    }
+    if let Some(seed) = args.seed {
+        scenario.settings.seed = seed;
+    }
+    if let Some(path) = &args.terrain {
+        scenario.settings.terrain = Terrain::load(path).with_context(|| format!("Can't load terrain {}", path.display()))?;
+    }
+    if args.headless {
+        let systems = scenario.system_names().into_iter().filter(|&name| name != "DebugSystem");
+        scenario.systems = Some(systems.map(String::from).collect());
+    }
     
-    // Add some test entities to see the ECS in action
-    sim_world.populate_with_test_entities()?;
+    // Create and initialize the simulation world
+    let mut sim_world = SimWorld::from_scenario(&scenario)?;
+    if args.scenario.is_none() {
+        // Add some test entities to see the ECS in action
+        sim_world.populate_with_test_entities()?;
+    }
     
     // Print initial state
     let stats = sim_world.stats();
@@ -26,9 +231,30 @@ fn main() -> Result<()> {
     println!("  Time Step: {:.4}s", stats.time_step);
     println!();
     
-    // Run the simulation for 5 seconds
-    println!("Running simulation...");
-    sim_world.run_for(5.0)?;
+    let steps = sim_world.steps_for(scenario.settings.duration);
+    let mut recording = args.record.as_ref().map(|_| Recording {
+        scenario: scenario.name.clone(),
+        time_step: sim_world.time_step,
+        seed: scenario.settings.seed,
+        frames: Vec::with_capacity(steps),
+    });
+    
+    println!("Running simulation for {:.2}s ({} steps)...", scenario.settings.duration, steps);
+    sim_world.run_steps(steps, |sim, step| {
+        if let Some(recording) = recording.as_mut() {
+            recording.frames.push(Frame::capture(&sim.world, sim.total_time)?);
+        }
+        // Print progress every 60 steps (1 second at 60 FPS)
+        if !args.headless && step % 60 == 0 {
+            println!("Step {}/{} (Time: {:.2}s)", step + 1, steps, sim.total_time);
+        }
+        Ok(())
+    })?;
+    
+    if let (Some(recording), Some(path)) = (recording, &args.record) {
+        recording.save(path).with_context(|| format!("Can't save recording to {}", path.display()))?;
+        println!("Recorded {} frames to {}", recording.frames.len(), path.display());
+    }
     
     // Print final state
     let final_stats = sim_world.stats();
@@ -38,8 +264,112 @@ fn main() -> Result<()> {
     println!("  Final entity count: {}", final_stats.entity_count);
     
     println!("=====================================");
-    println!("✅ ECS-based Simulator Demo Complete");
+    println!("✅ ECS-based Simulator Run Complete");
+    
+    Ok(())
+}
+
+/// `simulator validate`: report every problem in a scenario file
+fn validate(path: PathBuf) -> Result<()> {
+    let scenario = Scenario::load(&path)?;
+    scenario.validate()?;
+    println!(
+        "✅ {} is valid: {} entities, systems: {}",
+        path.display(),
+        scenario.entities.len(),
+        scenario.system_names().join(", ")
+    );
+    Ok(())
+}
+
+/// `simulator bench`: how fast the core operations are on a big world
+fn bench(args: BenchArgs) -> Result<()> {
+    // No DebugSystem: printing every body would swamp the timings
+    let scenario = Scenario {
+        systems: Some(vec!["MovementSystem".to_string(), "TransformPropagationSystem".to_string()]),
+        ..Scenario::default()
+    };
+    let mut sim_world = SimWorld::from_scenario(&scenario)?;
+    
+    let start = Instant::now();
+    for i in 0..args.entities {
+        sim_world.world.spawn(AircraftBundle {
+            velocity: Velocity::new(10.0, 1.0),
+            ..AircraftBundle::new("Body", Position::new(i as f32, 0.0), Mass::new(1.0))
+        })?;
+    }
+    let spawn_time = start.elapsed();
+    
+    let start = Instant::now();
+    sim_world.run_steps(args.steps, |_, _| Ok(()))?;
+    let step_time = start.elapsed();
+    
+    let start = Instant::now();
+    let fork = sim_world.fork()?;
+    let fork_time = start.elapsed();
+    drop(fork);
+    
+    let start = Instant::now();
+    let checkpoint = sim_world.checkpoint()?;
+    sim_world.restore(&checkpoint)?;
+    let checkpoint_time = start.elapsed();
+    
+    // as_secs_f64() turns a Duration into fractional seconds
+    let frames_per_second = args.steps as f64 / step_time.as_secs_f64();
+    println!("Bench: {} bodies, {} frames", args.entities, args.steps);
+    println!("  spawn:                {:>10.3} ms", spawn_time.as_secs_f64() * 1e3);
+    println!("  step:                 {:>10.3} ms/frame ({:.1} frames/s)", step_time.as_secs_f64() * 1e3 / args.steps.max(1) as f64, frames_per_second);
+    println!("  fork:                 {:>10.3} ms", fork_time.as_secs_f64() * 1e3);
+    println!("  checkpoint + restore: {:>10.3} ms", checkpoint_time.as_secs_f64() * 1e3);
+    Ok(())
+}
+
+/// `simulator replay`: print a recording at a fixed simulated-time interval
+fn replay(path: PathBuf, interval: f32) -> Result<()> {
+    let recording = Recording::load(&path).with_context(|| format!("Can't load recording {}", path.display()))?;
+    let title = if recording.scenario.is_empty() { "demo" } else { recording.scenario.as_str() };
+    println!("Replaying {} ({} frames, seed {})", title, recording.frames.len(), recording.seed);
+    
+    // Always print the first frame, then one every 'interval' seconds
+    let mut next_print = f32::NEG_INFINITY;
+    for frame in &recording.frames {
+        if frame.time < next_print {
+            continue;
+        }
+        next_print = frame.time + interval;
+        
+        println!("=== t = {:.2}s ===", frame.time);
+        for body in &frame.bodies {
+            println!(
+                "Entity {} {}: Pos({:.2}, {:.2}, {:.2}) Vel({:.2}, {:.2}, {:.2})",
+                body.entity,
+                body.name.as_deref().unwrap_or("-"),
+                body.x,
+                body.y,
+                body.z,
+                body.vx,
+                body.vy,
+                body.vz
+            );
+        }
+    }
+    Ok(())
+}
+
+/// `simulator export`: write a recording as CSV or JSON
+fn export(path: PathBuf, format: ExportFormat, output: Option<PathBuf>) -> Result<()> {
+    let recording = Recording::load(&path).with_context(|| format!("Can't load recording {}", path.display()))?;
     
+    // Box<dyn Write> lets one variable hold either a file or standard output
+    let mut out: Box<dyn Write> = match &output {
+        Some(output) => Box::new(File::create(output).with_context(|| format!("Can't create {}", output.display()))?),
+        None => Box::new(std::io::stdout().lock()),
+    };
+    match format {
+        ExportFormat::Csv => recording.write_csv(&mut out)?,
+        ExportFormat::Json => recording.write_json(&mut out)?,
+    }
+    out.flush()?;
     Ok(())
 }
 
@@ -64,9 +394,666 @@ mod tests {
         assert!(sim_world.stats().total_time > 0.0);
     }
     
+    #[test]
+    fn test_checkpoint_restore() {
+        use simulator::components::Position;
+        
+        let mut sim_world = SimWorld::new();
+        sim_world.initialize().unwrap();
+        sim_world.populate_with_test_entities().unwrap();
+        let checkpoint = sim_world.checkpoint().unwrap();
+        let positions = |sim: &SimWorld| {
+            let mut query = sim.world.query::<&Position>().unwrap();
+            query.iter().copied().collect::<Vec<_>>()
+        };
+        let saved = positions(&sim_world);
+        
+        // Run ahead, then roll back to the checkpoint
+        sim_world.run_for(0.5).unwrap();
+        assert_ne!(positions(&sim_world), saved);
+        sim_world.restore(&checkpoint).unwrap();
+        assert_eq!(sim_world.stats().entity_count, 3);
+        assert_eq!(positions(&sim_world), saved);
+        assert!(sim_world.dump_ron().unwrap().contains("Moving Object"));
+    }
+
+    #[test]
+    fn test_checkpoint_resources_and_rng() {
+        use aerodynamics::{AeroProperties, Wind};
+        use rand::Rng;
+        use simulator::components::{Force, Position, RigidBody, Rotation, Torque};
+        use simulator::world::SimRng;
+        
+        // Frames of exactly 16 substeps, so none is left pending at the checkpoint
+        let mut sim_world = SimWorld::new();
+        sim_world.time_step = 1.0 / 64.0;
+        sim_world.physics_step = 1.0 / 1024.0;
+        sim_world.world.insert_resource(Wind { velocity_x: -5.0, ..Wind::calm() });
+        sim_world.set_seed(7);
+        sim_world.initialize_with(&["AeroSystem", "RigidBodySystem"]).unwrap();
+        let glider = sim_world
+            .world
+            .spawn((
+                Position::new(0.0, 500.0),
+                Velocity::new(40.0, 0.0),
+                Rotation::new(0.05),
+                Mass::new(300.0),
+                RigidBody::new(1000.0),
+                AeroProperties::new(0.5, 0.05, 15.0),
+                Force::zero(),
+                Torque::zero(),
+            ))
+            .unwrap();
+        sim_world.run_steps(10, |_, _| Ok(())).unwrap();
+        let checkpoint = sim_world.checkpoint().unwrap();
+        
+        // The next random number, then the glider's path for half a second
+        let run_on = |sim: &mut SimWorld| {
+            let draw = sim.world.resource_mut::<SimRng>().unwrap().0.gen::<u64>();
+            let mut path = Vec::new();
+            sim.run_steps(32, |sim, _| {
+                path.push(*sim.world.query::<&Position>()?.iter().next().unwrap());
+                Ok(())
+            })
+            .unwrap();
+            (draw, path)
+        };
+        let first = run_on(&mut sim_world);
+        
+        // Change the weather and move the generator on, then roll back
+        sim_world.world.insert_resource(Wind { velocity_x: 15.0, ..Wind::calm() });
+        sim_world.world.resource_mut::<SimRng>().unwrap().0.gen::<u64>();
+        sim_world.restore(&checkpoint).unwrap();
+        assert_eq!(sim_world.world.resource::<Wind>().unwrap().velocity_x, -5.0);
+        assert_eq!(run_on(&mut sim_world), first);
+        assert!(!sim_world.world.entity_exists(glider));
+        assert!(sim_world.dump_ron().unwrap().contains("SimRng"));
+    }
+
+    #[test]
+    fn test_restore_hierarchy() {
+        use ecs::Entity;
+        use simulator::components::{LocalTransform, Name, Position, Rotation};
+
+        // Removing a parent removes its children, so restore must not
+        // try to remove the child a second time
+        let mut sim_world = SimWorld::new();
+        sim_world.initialize().unwrap();
+        let fuselage = sim_world.world.spawn((Name::new("Fuselage"), Position::new(0.0, 100.0), Rotation::zero())).unwrap();
+        let wing = sim_world.world.spawn((Name::new("Wing"), Position::zero(), Rotation::zero(), LocalTransform::new(Position::new(0.5, 0.0), Rotation::zero()))).unwrap();
+        sim_world.world.set_parent(wing, fuselage).unwrap();
+        let checkpoint = sim_world.checkpoint().unwrap();
+
+        sim_world.restore(&checkpoint).unwrap();
+        sim_world.restore(&checkpoint).unwrap();
+        assert_eq!(sim_world.stats().entity_count, 2);
+        let mut query = sim_world.world.query::<(Entity, &Name)>().unwrap();
+        let named: Vec<_> = query.iter().map(|(entity, name)| (entity, name.value.clone())).collect();
+        drop(query);
+        let (wing, _) = named.iter().find(|(_, name)| name == "Wing").unwrap();
+        let (fuselage, _) = named.iter().find(|(_, name)| name == "Fuselage").unwrap();
+        assert_eq!(sim_world.world.parent(*wing).unwrap(), Some(*fuselage));
+    }
+    
+    #[test]
+    fn test_fork() {
+        use simulator::components::Position;
+        
+        let mut sim_world = SimWorld::new();
+        sim_world.initialize().unwrap();
+        sim_world.populate_with_test_entities().unwrap();
+        // Leave part of a physics substep pending in the accumulator
+        sim_world.advance(0.0105).unwrap();
+        let positions = |sim: &SimWorld| {
+            let mut query = sim.world.query::<&Position>().unwrap();
+            query.iter().copied().collect::<Vec<_>>()
+        };
+        
+        let mut fork = sim_world.fork().unwrap();
+        let before = positions(&sim_world);
+        fork.run_for(0.5).unwrap();
+        assert_eq!(positions(&sim_world), before);
+        
+        // Same starting state, same frames: the original catches up exactly
+        sim_world.run_for(0.5).unwrap();
+        assert_eq!(positions(&sim_world), positions(&fork));
+        assert_eq!(fork.stats().system_count, sim_world.stats().system_count);
+    }
+    
+    #[test]
+    fn test_turbulence() {
+        use aerodynamics::{AeroProperties, Gust, Wind};
+        use simulator::components::{Force, Position, RigidBody, Rotation, Torque};
+        
+        // A glider in gusty air, in frames of exactly 16 physics substeps
+        let glider = |turbulence: f32, seed: u64| {
+            let mut sim_world = SimWorld::new();
+            sim_world.time_step = 1.0 / 64.0;
+            sim_world.physics_step = 1.0 / 1024.0;
+            sim_world.world.insert_resource(Wind { turbulence, ..Wind::calm() });
+            sim_world.set_seed(seed);
+            sim_world.initialize_with(&["TurbulenceSystem", "AeroSystem", "RigidBodySystem"]).unwrap();
+            sim_world
+                .world
+                .spawn((
+                    Position::new(0.0, 500.0),
+                    Velocity::new(40.0, 0.0),
+                    Rotation::zero(),
+                    Mass::new(300.0),
+                    RigidBody::new(1000.0),
+                    AeroProperties::new(0.5, 0.05, 15.0),
+                    Force::zero(),
+                    Torque::zero(),
+                ))
+                .unwrap();
+            sim_world
+        };
+        let fly = |sim: &mut SimWorld| {
+            sim.run_steps(64, |_, _| Ok(())).unwrap();
+            let position = *sim.world.query::<&Position>().unwrap().iter().next().unwrap();
+            position
+        };
+        
+        // The seed picks the gusts, which push the glider around
+        let mut gusty = glider(2.0, 1);
+        let checkpoint = gusty.checkpoint().unwrap();
+        let path = fly(&mut gusty);
+        assert_ne!(*gusty.world.resource::<Gust>().unwrap(), Gust::default());
+        assert_eq!(fly(&mut glider(2.0, 1)), path);
+        assert_ne!(fly(&mut glider(2.0, 2)), path);
+        
+        // The gust and the generator are restored with the rest of the world
+        gusty.restore(&checkpoint).unwrap();
+        assert_eq!(fly(&mut gusty), path);
+        
+        // Calm air doesn't draw from the generator, so the seed doesn't matter
+        assert_eq!(fly(&mut glider(0.0, 1)), fly(&mut glider(0.0, 2)));
+    }
+    
+    #[test]
+    fn test_towed_glider() {
+        use physics::{ForceModels, Gravity, Joint};
+        use simulator::components::{Name, Position, RigidBody, Rotation};
+        
+        let mut sim_world = SimWorld::new();
+        sim_world.initialize().unwrap();
+        sim_world.world.insert_resource(ForceModels::new().with(Gravity::earth()));
+        
+        // A tow plane flying level at 30 m/s (moved by MovementSystem, so the
+        // rope can't slow it) and a glider 20 m behind on a 30 m rope
+        let tug = sim_world.world.spawn((Name::new("Tow plane"), Position::new(0.0, 100.0), Velocity::new(30.0, 0.0))).unwrap();
+        let glider = sim_world
+            .world
+            .spawn((Name::new("Glider"), Position::new(-20.0, 100.0), Velocity::zero(), Rotation::zero(), Mass::new(300.0), RigidBody::new(500.0)))
+            .unwrap();
+        let rope = Joint::rope(tug, Position::zero(), glider, Position::new(1.0, 0.0), 30.0);
+        sim_world.world.spawn((Name::new("Tow rope"), rope)).unwrap();
+        sim_world.run_for(3.0).unwrap();
+        
+        // The rope went taut and dragged the glider along behind
+        let distance = |sim: &SimWorld| {
+            let tug = sim.world.get_component::<Position>(tug).unwrap().to_vector();
+            tug.metric_distance(&sim.world.get_component::<Position>(glider).unwrap().to_vector())
+        };
+        assert!(distance(&sim_world) < 30.0 + 1.0 + 0.05, "rope stretched to {}", distance(&sim_world));
+        assert!(sim_world.world.get_component::<Velocity>(glider).unwrap().x > 20.0);
+        let mut query = sim_world.world.query::<&Joint>().unwrap();
+        let load = query.iter().next().unwrap().reaction.to_vector().norm();
+        assert!(load > 300.0 * 9.81 * 0.5, "rope load {}", load);
+        drop(query);
+        
+        // Snapshots keep the rope tied to the same bodies
+        let checkpoint = sim_world.checkpoint().unwrap();
+        sim_world.restore(&checkpoint).unwrap();
+        let mut query = sim_world.world.query::<&Joint>().unwrap();
+        let rope = query.iter().next().unwrap().clone();
+        drop(query);
+        let name = |entity| sim_world.world.get_component::<Name>(entity).unwrap().value.clone();
+        assert_eq!((name(rope.a), name(rope.b)), ("Tow plane".to_string(), "Glider".to_string()));
+    }
+    
+    #[test]
+    fn test_transform_propagation() {
+        use simulator::components::{LocalTransform, Position, Rotation, Velocity};
+        
+        let mut sim_world = SimWorld::new();
+        sim_world.initialize().unwrap();
+        let world = &mut sim_world.world;
+        
+        // A fuselage pointing straight up, with a wing and a wingtip light
+        let fuselage = world.create_entity();
+        world.add_component(fuselage, Position::new(10.0, 0.0)).unwrap();
+        world.add_component(fuselage, Velocity::new(0.0, 60.0)).unwrap();
+        world.add_component(fuselage, Rotation::degrees(90.0)).unwrap();
+        let wing = world.create_entity();
+        world.add_component(wing, LocalTransform::from_offset(2.0, 0.0)).unwrap();
+        world.set_parent(wing, fuselage).unwrap();
+        let light = world.create_entity();
+        world.add_component(light, LocalTransform::new(Position::new(1.0, 0.0), Rotation::degrees(90.0))).unwrap();
+        world.set_parent(light, wing).unwrap();
+        
+        sim_world.step().unwrap();
+        let world = &sim_world.world;
+        let fuselage_y = world.get_component::<Position>(fuselage).unwrap().y;
+        assert!(fuselage_y > 0.0);
+        
+        // Offsets along the parent's x axis end up along world +y
+        let wing_position = *world.get_component::<Position>(wing).unwrap();
+        assert!((wing_position.x - 10.0).abs() < 1e-4);
+        assert!((wing_position.y - (fuselage_y + 2.0)).abs() < 1e-4);
+        let light_position = *world.get_component::<Position>(light).unwrap();
+        assert!((light_position.y - (fuselage_y + 3.0)).abs() < 1e-4);
+        assert!((world.get_component::<Rotation>(light).unwrap().to_degrees() - 180.0).abs() < 1e-3);
+        
+        // Removing the fuselage takes its parts with it
+        sim_world.world.remove_entity(fuselage).unwrap();
+        assert_eq!(sim_world.world.entity_count(), 0);
+    }
+    
+    #[test]
+    fn test_scenario_loading() {
+        use aerodynamics::{AeroProperties, Wind};
+        use simulator::scenario::Scenario;
+        
+        let scenario = Scenario::from_ron(include_str!("../scenarios/demo.ron")).unwrap();
+        let sim_world = SimWorld::from_scenario(&scenario).unwrap();
+        assert_eq!(sim_world.stats().entity_count, 3);
+        assert_eq!(sim_world.stats().system_count, 3);
+        assert_eq!(sim_world.world.resource::<Wind>().unwrap().velocity_x, -2.0);
+        
+        let mut query = sim_world.world.query::<(&simulator::components::Name, &AeroProperties)>().unwrap();
+        let named: Vec<_> = query.iter().map(|(name, _)| name.value.clone()).collect();
+        assert_eq!(named, vec!["Moving Object".to_string()]);
+    }
+    
+    #[test]
+    fn test_scenario_validation_report() {
+        use simulator::scenario::{Scenario, ScenarioError};
+        
+        let text = r#"(
+            settings: (time_step: 0.0),
+            systems: ["MovementSystem", "AutopilotSystem"],
+            entities: [
+                (name: "Brick", components: { "Mass": (value: -1.0), "Fuel": (litres: 40.0) }),
+                (components: { "Position": (x: 1.0) }),
+            ],
+        )"#;
+        let scenario = Scenario::from_ron(text).unwrap();
+        
+        // Every problem is reported, not just the first
+        let Err(ScenarioError::Invalid(problems)) = SimWorld::from_scenario(&scenario) else {
+            panic!("invalid scenario was accepted");
+        };
+        assert_eq!(problems.len(), 5, "{:#?}", problems);
+        assert!(problems[0].contains("time_step"));
+        assert!(problems[1].contains("AutopilotSystem"));
+        assert!(problems.iter().any(|p| p.contains("\"Brick\": Mass")));
+        assert!(problems.iter().any(|p| p.contains("unknown component `Fuel`")));
+        assert!(problems.iter().any(|p| p.starts_with("entities[1]: Position")));
+        
+        // Misspelled top-level fields are caught while parsing
+        assert!(matches!(Scenario::from_ron("(setings: ())"), Err(ScenarioError::Parse(_))));
+    }
+    
+    #[test]
+    fn test_collision_scenario() {
+        use ecs::Events;
+        use physics::{CollisionEvent, Contacts, ForceModels, Gravity};
+        use simulator::components::Name;
+        
+        let text = r#"(
+            settings: (duration: 3.0),
+            systems: ["RigidBodySystem", "CollisionSystem"],
+            entities: [
+                (name: "Ground", components: {
+                    "Position": (x: -100.0, y: 0.0),
+                    "Collider": (shape: (type: "Heightfield", heights: [0.0, 0.0, 0.0], spacing: 100.0)),
+                }),
+                (name: "Ball", components: {
+                    "Position": (x: 0.0, y: 5.0),
+                    "Velocity": (x: 0.0, y: 0.0),
+                    "Rotation": (angle: 0.0),
+                    "Mass": (value: 2.0),
+                    "RigidBody": (inertia: 0.25, angular_velocity: 0.0),
+                    "Collider": (shape: (type: "Circle", radius: 0.5), restitution: 0.5),
+                }),
+            ],
+        )"#;
+        let mut sim_world = SimWorld::from_scenario(&Scenario::from_ron(text).unwrap()).unwrap();
+        sim_world.world.insert_resource(ForceModels::new().with(Gravity::earth()));
+        let mut reader = sim_world.world.resource::<Events<CollisionEvent>>().unwrap().reader();
+        
+        let mut bounces = 0;
+        sim_world
+            .run_steps(sim_world.steps_for(3.0), |sim, _| {
+                let events = sim.world.resource::<Events<CollisionEvent>>()?;
+                bounces += reader.read(&events).count();
+                Ok(())
+            })
+            .unwrap();
+        
+        // It bounces a few times, then rests on the ground
+        assert!(bounces >= 2, "bounced {} times", bounces);
+        let mut query = sim_world.world.query::<(ecs::Entity, &Name, &Position)>().unwrap();
+        let (ball, _, position) = query.iter().find(|(_, name, _)| name.value == "Ball").unwrap();
+        assert!((position.y - 0.5).abs() < 0.02, "ball at {:?}", position);
+        drop(query);
+        assert!(sim_world.world.resource::<Contacts>().unwrap().involving(ball).next().is_some());
+        
+        // Bad shapes are reported like any other bad value
+        let bad = text.replace("spacing: 100.0", "spacing: 0.0").replace("radius: 0.5", "radius: -1.0");
+        let Err(ScenarioError::Invalid(problems)) = Scenario::from_ron(&bad).unwrap().validate() else {
+            panic!("invalid colliders were accepted");
+        };
+        assert_eq!(problems.len(), 2, "{:#?}", problems);
+    }
+    
+    #[test]
+    fn test_landing_gear_scenario() {
+        use physics::{ForceModels, Gravity, LandingGear};
+        
+        // A 500 kg glider rolling onto a runway that starts 0.2 m lower
+        let text = r#"(
+            settings: (duration: 3.0, terrain: (origin_x: 0.0, spacing: 10.0, heights: [0.0, -0.2])),
+            systems: ["LandingGearSystem", "RigidBodySystem"],
+            entities: [
+                (name: "Glider", components: {
+                    "Position": (x: 0.0, y: 0.8),
+                    "Velocity": (x: 10.0, y: 0.0),
+                    "Rotation": (angle: 0.0),
+                    "Mass": (value: 500.0),
+                    "RigidBody": (inertia: 1000.0, angular_velocity: 0.0),
+                    "LandingGear": (struts: [
+                        (mount: (x: -1.0, y: 0.0), length: 1.0, stiffness: 50000.0, damping: 5000.0, rolling_friction: 0.02, brake_friction: 0.5),
+                        (mount: (x: 1.0, y: 0.0), length: 1.0, stiffness: 50000.0, damping: 5000.0, rolling_friction: 0.02, brake_friction: 0.5, brake: 1.0),
+                    ]),
+                }),
+            ],
+        )"#;
+        let mut sim_world = SimWorld::from_scenario(&Scenario::from_ron(text).unwrap()).unwrap();
+        sim_world.world.insert_resource(ForceModels::new().with(Gravity::earth()));
+        sim_world.run_steps(sim_world.steps_for(3.0), |_, _| Ok(())).unwrap();
+        
+        // The struts carry the glider's weight, on the lower part of the runway
+        let mut query = sim_world.world.query::<(&Position, &LandingGear)>().unwrap();
+        let (position, gear) = query.iter().next().unwrap();
+        assert!(position.x > 10.0, "stopped short at {:?}", position);
+        assert!((gear.weight_on_wheels() - 500.0 * 9.81).abs() < 50.0, "{:?}", gear);
+        assert!(gear.struts.iter().all(|strut| strut.in_contact() && strut.compression < 0.2));
+        drop(query);
+        
+        // Struts that can't hold anything up, or ground without samples, are reported
+        let bad = text.replace("length: 1.0, stiffness: 50000.0", "length: -1.0, stiffness: 50000.0").replace("heights: [0.0, -0.2]", "heights: []");
+        let Err(ScenarioError::Invalid(problems)) = Scenario::from_ron(&bad).unwrap().validate() else {
+            panic!("invalid landing gear was accepted");
+        };
+        assert_eq!(problems.len(), 2, "{:#?}", problems);
+    }
+
+    #[test]
+    fn test_stall_scenario() {
+        use aerodynamics::AeroProperties;
+        use physics::{ForceModels, Gravity};
+        use simulator::components::Name;
+
+        // Two gliders at 40 m/s, one at a cruising angle of attack and one
+        // pitched well past the stall; no torque acts, so both hold their attitude
+        let glider = r#"(name: "NAME", components: {
+            "Position": (x: 0.0, y: 500.0),
+            "Velocity": (x: 40.0, y: 0.0),
+            "Rotation": (angle: ANGLE),
+            "Mass": (value: 300.0),
+            "RigidBody": (inertia: 1000.0, angular_velocity: 0.0),
+            "AeroProperties": (lift_coefficient: 0.0, drag_coefficient: 0.0, wing_area: 15.0, angle_of_attack: 0.0),
+            "Airfoil": (lift_slope: 5.0, zero_lift_angle: -0.035, stall_angle: 0.26, stall_sharpness: 50.0,
+                        zero_lift_drag: 0.025, aspect_ratio: 8.0, oswald_efficiency: 0.8, flat_plate_normal: 2.0),
+        })"#;
+        let text = format!(
+            r#"(systems: ["AeroSystem", "RigidBodySystem"], entities: [{}, {}])"#,
+            glider.replace("NAME", "Cruising").replace("ANGLE", "0.05"),
+            glider.replace("NAME", "Stalled").replace("ANGLE", "0.4"),
+        );
+        let mut sim_world = SimWorld::from_scenario(&Scenario::from_ron(&text).unwrap()).unwrap();
+        sim_world.world.insert_resource(ForceModels::new().with(Gravity::earth()));
+        let gliders = |sim: &SimWorld| {
+            let mut query = sim.world.query::<(&Name, &Velocity, &AeroProperties)>().unwrap();
+            query.iter().map(|(name, velocity, aero)| (name.value.clone(), velocity.magnitude(), aero.clone())).collect::<Vec<_>>()
+        };
+
+        // The system works out each angle of attack and looks up the curve
+        sim_world.run_steps(1, |_, _| Ok(())).unwrap();
+        let early = gliders(&sim_world);
+        let [(_, _, cruising), (_, _, stalled)] = &early[..] else {
+            panic!("expected two gliders, got {:?}", early);
+        };
+        assert!((cruising.angle_of_attack - 0.05).abs() < 0.01 && (stalled.angle_of_attack - 0.4).abs() < 0.01, "{:?}", early);
+        assert!(stalled.drag_coefficient > 4.0 * cruising.drag_coefficient, "{:?}", early);
+
+        // The stalled wing's flat-plate drag bleeds off more speed
+        sim_world.run_steps(sim_world.steps_for(1.0), |_, _| Ok(())).unwrap();
+        let late = gliders(&sim_world);
+        assert!(late[1].1 < late[0].1 - 3.0, "{:?}", late);
+
+        // Curves that make no sense are reported
+        let bad = text.replace("aspect_ratio: 8.0", "aspect_ratio: 0.0");
+        let Err(ScenarioError::Invalid(problems)) = Scenario::from_ron(&bad).unwrap().validate() else {
+            panic!("invalid airfoil was accepted");
+        };
+        assert_eq!(problems.len(), 2, "{:#?}", problems);
+    }
+
+    #[test]
+    fn test_3d_scenario() {
+        use aerodynamics::AeroProperties;
+        use physics::{Force3, ForceModels3, Gravity, Orientation, Position3};
+        use simulator::components::Name;
+
+        // A 300 kg glider at 40 m/s whose wings carry its weight, in a 5 m/s crosswind,
+        // and a wing on a test stand: no RigidBody3, so nothing moves it, but the
+        // air still acts on it
+        let text = r#"(
+            settings: (dimensions: Three, crosswind: 5.0),
+            entities: [
+                (name: "Glider", components: {
+                    "Position3": (x: 0.0, y: 500.0, z: 0.0),
+                    "Velocity3": (x: 40.0, y: 0.0, z: 0.0),
+                    "Orientation": (roll: 0.0, pitch: 0.0, yaw: 0.0),
+                    "Mass": (value: 300.0),
+                    "RigidBody3": (roll: 1000.0, yaw: 2500.0, pitch: 1800.0),
+                    "AeroProperties": (lift_coefficient: 0.2, drag_coefficient: 0.02, wing_area: 15.0, angle_of_attack: 0.0),
+                }),
+                (name: "Test stand", components: {
+                    "Position3": (x: 0.0, y: 10.0, z: 0.0), "Velocity3": (x: 40.0, y: 0.0, z: 0.0), "Orientation": (pitch: 0.1),
+                    "AeroProperties": (lift_coefficient: 0.2, drag_coefficient: 0.02, wing_area: 15.0, angle_of_attack: 0.0),
+                }),
+            ],
+        )"#;
+        let scenario = Scenario::from_ron(text).unwrap();
+        assert_eq!(scenario.system_names(), ["AeroSystem3", "RigidBodySystem3"]);
+        let mut sim_world = SimWorld::from_scenario(&scenario).unwrap();
+        sim_world.world.insert_resource(ForceModels3::new().with(Gravity::earth()));
+        let checkpoint = sim_world.checkpoint().unwrap();
+        sim_world.run_steps(sim_world.steps_for(2.0), |_, _| Ok(())).unwrap();
+
+        // Lift holds it up, and the crosswind drags it towards +z
+        let final_state = |sim: &SimWorld| {
+            let mut query = sim.world.query::<(&Name, &Position3, &Orientation)>().unwrap();
+            let (_, position, orientation) = query.iter().find(|(name, _, _)| name.value == "Glider").unwrap();
+            (*position, *orientation)
+        };
+        let (position, orientation) = final_state(&sim_world);
+        assert!((position.y - 500.0).abs() < 1.0, "{:?}", position);
+        assert!(position.x > 70.0 && position.z > 0.0, "{:?}", position);
+        assert!((orientation.w * orientation.w + orientation.x * orientation.x + orientation.y * orientation.y + orientation.z * orientation.z - 1.0).abs() < 1e-4);
+
+        // The stand's wing got Force3 from the scenario, so AeroSystem3 worked out
+        // its angle of attack and lift instead of skipping it
+        let mut query = sim_world.world.query::<(&Name, &AeroProperties, &Force3)>().unwrap();
+        let (_, stand, force) = query.iter().find(|(name, _, _)| name.value == "Test stand").unwrap();
+        assert!((stand.angle_of_attack - 0.1).abs() < 1e-3, "{:?}", stand);
+        assert!(force.y > 0.0, "{:?}", force);
+        drop(query);
+
+        // 3D components survive a checkpoint like 2D ones
+        sim_world.restore(&checkpoint).unwrap();
+        assert_eq!(final_state(&sim_world).0, Position3::new(0.0, 500.0, 0.0));
+
+        // Bad 3D values are reported with the rest
+        let bad = text.replace("crosswind: 5.0", "crosswind: inf").replace("pitch: 1800.0", "pitch: -1800.0").replace("z: 0.0),\n                    \"Velocity3\"", "z: NaN),\n                    \"Velocity3\"");
+        let Err(ScenarioError::Invalid(problems)) = Scenario::from_ron(&bad).unwrap().validate() else {
+            panic!("invalid 3D scenario was accepted");
+        };
+        assert_eq!(problems.len(), 3, "{:#?}", problems);
+    }
+
+    #[test]
+    fn test_cli_arguments_and_exit_codes() {
+        let cli = Cli::try_parse_from(["simulator", "run", "glider.ron", "--dt", "0.01", "--seed", "7", "--headless"]).unwrap();
+        let Some(Command::Run(args)) = cli.command else {
+            panic!("expected the run subcommand");
+        };
+        assert_eq!(args.scenario, Some(PathBuf::from("glider.ron")));
+        assert_eq!((args.dt, args.seed, args.headless), (Some(0.01), Some(7), true));
+        
+        // Unknown formats are rejected by clap, which exits with code 2
+        let err = Cli::try_parse_from(["simulator", "export", "run.bin", "--format", "xml"]).unwrap_err();
+        assert_eq!(err.exit_code(), 2);
+        
+        let invalid = anyhow::Error::from(ScenarioError::Invalid(vec!["bad".to_string()]));
+        assert_eq!(exit_code(&invalid), EXIT_INVALID_SCENARIO);
+        let failed = anyhow::Error::from(EcsError::EntityNotFound(ecs::Entity::from_bits(1))).context("while stepping");
+        assert_eq!(exit_code(&failed), EXIT_SIMULATION_ERROR);
+        let terrain = anyhow::Error::from(TerrainError::Empty).context("Can't load terrain runway.txt");
+        assert_eq!(exit_code(&terrain), EXIT_INVALID_SCENARIO);
+        assert_eq!(exit_code(&anyhow::anyhow!("disk full")), EXIT_FAILURE);
+    }
+    
+    #[test]
+    fn test_record_and_export() {
+        let path = std::env::temp_dir().join(format!("simulator-test-{}.bin", std::process::id()));
+        execute(Command::Run(RunArgs {
+            duration: Some(0.5),
+            headless: true,
+            record: Some(path.clone()),
+            ..RunArgs::default()
+        }))
+        .unwrap();
+        
+        let recording = Recording::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(recording.frames.len(), 30);
+        assert_eq!(recording.frames[0].bodies.len(), 3);
+        
+        let mut csv = Vec::new();
+        recording.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().count(), 1 + 30 * 3);
+        assert!(csv.lines().nth(1).unwrap().contains("\"Moving Object\""));
+        
+        let mut json = Vec::new();
+        recording.write_json(&mut json).unwrap();
+        let parsed: Recording = serde_json::from_slice(&json).unwrap();
+        assert_eq!(parsed, recording);
+    }
+    
+    #[test]
+    fn test_seed_changes_run() {
+        let dir = std::env::temp_dir();
+        let scenario = dir.join(format!("simulator-seed-{}.ron", std::process::id()));
+        std::fs::write(
+            &scenario,
+            r#"(
+                settings: (duration: 1.0, wind: (velocity_x: 0.0, velocity_y: 0.0, turbulence: 2.0)),
+                systems: ["TurbulenceSystem", "AeroSystem", "RigidBodySystem"],
+                entities: [
+                    (name: "Glider", components: {
+                        "Position": (x: 0.0, y: 500.0),
+                        "Velocity": (x: 40.0, y: 0.0),
+                        "Rotation": (angle: 0.0),
+                        "Mass": (value: 300.0),
+                        "RigidBody": (inertia: 1000.0, angular_velocity: 0.0),
+                        "AeroProperties": (lift_coefficient: 0.5, drag_coefficient: 0.05, wing_area: 15.0, angle_of_attack: 0.0),
+                    }),
+                ],
+            )"#,
+        )
+        .unwrap();
+        let run_with = |seed: u64| {
+            let record = dir.join(format!("simulator-seed-{}-{}.bin", std::process::id(), seed));
+            execute(Command::Run(RunArgs {
+                scenario: Some(scenario.clone()),
+                seed: Some(seed),
+                headless: true,
+                record: Some(record.clone()),
+                ..RunArgs::default()
+            }))
+            .unwrap();
+            let recording = Recording::load(&record).unwrap();
+            std::fs::remove_file(&record).unwrap();
+            recording
+        };
+        
+        let first = run_with(1);
+        let again = run_with(1);
+        let other = run_with(2);
+        std::fs::remove_file(&scenario).unwrap();
+        assert_eq!((first.seed, other.seed), (1, 2));
+        assert_eq!(first.frames, again.frames);
+        assert_ne!(first.frames.last(), other.frames.last());
+    }
+    
+    #[test]
+    fn test_record_3d() {
+        let dir = std::env::temp_dir();
+        let scenario = dir.join(format!("simulator-3d-{}.ron", std::process::id()));
+        let path = dir.join(format!("simulator-3d-{}.bin", std::process::id()));
+        std::fs::write(
+            &scenario,
+            r#"(
+                settings: (dimensions: Three, duration: 0.5, crosswind: 5.0),
+                entities: [
+                    (name: "Glider", components: {
+                        "Position3": (x: 0.0, y: 500.0, z: 0.0),
+                        "Velocity3": (x: 40.0, y: 0.0, z: 0.0),
+                        "Orientation": (roll: 0.2, pitch: 0.05, yaw: 0.0),
+                        "Mass": (value: 300.0),
+                        "RigidBody3": (roll: 1000.0, yaw: 2500.0, pitch: 1800.0),
+                        "AeroProperties": (lift_coefficient: 0.2, drag_coefficient: 0.02, wing_area: 15.0, angle_of_attack: 0.0),
+                    }),
+                ],
+            )"#,
+        )
+        .unwrap();
+        execute(Command::Run(RunArgs {
+            scenario: Some(scenario.clone()),
+            headless: true,
+            record: Some(path.clone()),
+            ..RunArgs::default()
+        }))
+        .unwrap();
+        
+        let recording = Recording::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&scenario).unwrap();
+        assert_eq!(recording.frames.len(), 30);
+        
+        // The crosswind pushes the glider along z, and its attitude is kept
+        let first = &recording.frames[0].bodies[..];
+        let last = &recording.frames[29].bodies[..];
+        assert_eq!((first.len(), first[0].name.as_deref()), (1, Some("Glider")));
+        assert!(last[0].x > 15.0 && last[0].z > first[0].z && last[0].vz > 0.0, "{:?}", last);
+        assert!((first[0].roll - 0.2).abs() < 0.01 && (first[0].angle - 0.05).abs() < 0.01, "{:?}", first);
+        
+        let mut csv = Vec::new();
+        recording.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().count(), 1 + 30);
+        assert_eq!(csv.lines().nth(1).unwrap().split(',').count(), 12);
+    }
+    
     #[test]
     fn test_components_basic_functionality() {
-        use components::*;
+        use simulator::components::*;
         
         let pos = Position::new(1.0, 2.0);
         assert_eq!(pos.x, 1.0);
@@ -0,0 +1,145 @@
+// Recordings - the state of every body over a run, for replay and export
+//
+// `simulator run --record FILE` captures a Frame after every step, and the
+// `replay` and `export` subcommands read the file back
+use std::io::Write;
+use std::path::Path;
+
+use ecs::{Entity, World, Without};
+use serde::{Deserialize, Serialize};
+
+use crate::components::{Name, Orientation, Position, Position3, Rotation, Velocity, Velocity3};
+
+/// Everything that can go wrong saving, loading or exporting a recording
+#[derive(Debug, thiserror::Error)]
+pub enum RecordingError {
+    #[error("Recording I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Recording isn't valid: {0}")]
+    Decode(#[from] bincode::Error),
+
+    #[error("JSON export failed: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// One body's state at one moment
+///
+/// 2D bodies leave z, vz, roll and yaw at zero
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BodyState {
+    pub entity: Entity,
+    pub name: Option<String>,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub vx: f32,
+    pub vy: f32,
+    pub vz: f32,
+    /// Rotation in radians; for 3D bodies the pitch, which turns the same way
+    pub angle: f32,
+    /// Roll and yaw of 3D bodies in radians, see Orientation::euler
+    pub roll: f32,
+    pub yaw: f32,
+}
+
+/// Every body with a Position or Position3, after one step
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Frame {
+    /// Simulation time at the end of the step, in seconds
+    pub time: f32,
+    pub bodies: Vec<BodyState>,
+}
+
+impl Frame {
+    /// Capture the state of every positioned entity in the world, 2D bodies
+    /// first, then 3D ones
+    ///
+    /// Velocity and Rotation (or Velocity3 and Orientation) are optional:
+    /// bodies without them record zeros. An entity with both a Position and
+    /// a Position3 is recorded once, as a 2D body
+    pub fn capture(world: &World, time: f32) -> ecs::EcsResult<Self> {
+        let mut query = world.query::<(Entity, &Position, Option<&Velocity>, Option<&Rotation>, Option<&Name>)>()?;
+        let mut bodies: Vec<BodyState> = query
+            .iter()
+            .map(|(entity, position, velocity, rotation, name)| BodyState {
+                entity,
+                name: name.map(|name| name.value.clone()),
+                x: position.x,
+                y: position.y,
+                z: 0.0,
+                vx: velocity.map_or(0.0, |v| v.x),
+                vy: velocity.map_or(0.0, |v| v.y),
+                vz: 0.0,
+                angle: rotation.map_or(0.0, |r| r.angle),
+                roll: 0.0,
+                yaw: 0.0,
+            })
+            .collect();
+
+        let mut query = world.query_filtered::<(Entity, &Position3, Option<&Velocity3>, Option<&Orientation>, Option<&Name>), Without<Position>>()?;
+        bodies.extend(query.iter().map(|(entity, position, velocity, orientation, name)| {
+            let (roll, pitch, yaw) = orientation.map_or((0.0, 0.0, 0.0), Orientation::euler);
+            BodyState {
+                entity,
+                name: name.map(|name| name.value.clone()),
+                x: position.x,
+                y: position.y,
+                z: position.z,
+                vx: velocity.map_or(0.0, |v| v.x),
+                vy: velocity.map_or(0.0, |v| v.y),
+                vz: velocity.map_or(0.0, |v| v.z),
+                angle: pitch,
+                roll,
+                yaw,
+            }
+        }));
+        Ok(Self { time, bodies })
+    }
+}
+
+/// A whole run, frame by frame
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Recording {
+    /// Name of the scenario that was run, empty for the built-in demo
+    pub scenario: String,
+    pub time_step: f32,
+    pub seed: u64,
+    pub frames: Vec<Frame>,
+}
+
+impl Recording {
+    /// Save as bincode, which keeps long recordings small
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), RecordingError> {
+        std::fs::write(path, bincode::serialize(self)?)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RecordingError> {
+        Ok(bincode::deserialize(&std::fs::read(path)?)?)
+    }
+
+    /// Write one row per body per frame, with a header row
+    pub fn write_csv(&self, out: &mut impl Write) -> Result<(), RecordingError> {
+        writeln!(out, "time,entity,name,x,y,z,vx,vy,vz,angle,roll,yaw")?;
+        for frame in &self.frames {
+            for body in &frame.bodies {
+                // Names are quoted, with inner quotes doubled as CSV requires
+                let name = body.name.as_deref().unwrap_or("").replace('"', "\"\"");
+                writeln!(
+                    out,
+                    "{},{},\"{}\",{},{},{},{},{},{},{},{},{}",
+                    frame.time, body.entity, name, body.x, body.y, body.z, body.vx, body.vy, body.vz, body.angle, body.roll, body.yaw
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the whole recording as pretty-printed JSON
+    pub fn write_json(&self, out: &mut impl Write) -> Result<(), RecordingError> {
+        serde_json::to_writer_pretty(&mut *out, self)?;
+        writeln!(out)?;
+        Ok(())
+    }
+}
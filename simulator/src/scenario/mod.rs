@@ -0,0 +1,565 @@
+// Scenario files - describe a simulation in RON instead of hardcoding it in Rust
+//
+// A scenario lists global settings, the systems to run and the entities to
+// spawn. SimWorld::from_scenario turns one into a ready-to-run world
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use aerodynamics::{AeroProperties, Airfoil, Wind};
+use physics::{Collider, Force, Force3, Integrator, LandingGear, Orientation, Position3, RigidBody3, Shape, Terrain, Torque, Torque3, Velocity3};
+use ecs::{Component, EcsError, EntityBuilder};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{Mass, Name, Position, RigidBody, Rotation, Velocity};
+use crate::systems::{DEFAULT_SYSTEMS, DEFAULT_SYSTEMS_3D, OPTIONAL_SYSTEMS};
+
+/// Everything that can go wrong loading a scenario
+#[derive(Debug, thiserror::Error)]
+pub enum ScenarioError {
+    #[error("Can't read scenario {path}: {source}")]
+    Io { path: String, source: std::io::Error },
+
+    /// The file isn't valid RON, or doesn't have the scenario's shape
+    #[error("Scenario isn't valid RON: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+
+    /// The file parsed, but some values make no sense
+    #[error("Scenario has {} problem(s):\n  {}", .0.len(), .0.join("\n  "))]
+    Invalid(Vec<String>),
+
+    #[error(transparent)]
+    Ecs(#[from] EcsError),
+}
+
+/// A simulation described as data
+///
+/// Example file:
+///
+/// (
+///     name: "Glider drop",
+///     settings: (time_step: 0.02, duration: 10.0, wind: (velocity_x: -3.0, velocity_y: 0.0, turbulence: 0.0)),
+///     systems: ["MovementSystem"],
+///     entities: [
+///         (
+///             name: "Glider",
+///             components: {
+///                 "Position": (x: 0.0, y: 500.0),
+///                 "Velocity": (x: 25.0, y: 0.0),
+///                 "Mass": (value: 300.0),
+///             },
+///         ),
+///     ],
+/// )
+///
+/// Every field is optional. Components are keyed by the same names snapshots
+/// use, so adding a component type means adding it to COMPONENTS below
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scenario {
+    /// Human-readable title, printed when the scenario loads
+    pub name: String,
+
+    pub settings: Settings,
+
+    /// Names of the systems to run (System::name); None runs every default system
+    /// Optional ones such as TurbulenceSystem have to be listed
+    pub systems: Option<Vec<String>>,
+
+    pub entities: Vec<EntitySpec>,
+}
+
+/// Global simulation settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// Frame length in seconds
+    pub time_step: f32,
+
+    /// Fixed step the physics stages advance by, in seconds
+    pub physics_step: f32,
+
+    /// How long the simulator binary runs the scenario for, in seconds
+    pub duration: f32,
+
+    /// Wind, inserted into the world as a resource
+    pub wind: Wind,
+
+    /// Seed for the shared random number generator (SimRng), which drives
+    /// the gusts when the scenario runs TurbulenceSystem
+    pub seed: u64,
+    
+    /// How rigid bodies are integrated: SemiImplicitEuler, VelocityVerlet or Rk4
+    pub integrator: Integrator,
+
+    /// Ground height along X, inserted into the world as a resource
+    /// (origin_x: 0.0, spacing: 100.0, heights: [0.0, 5.0, 2.0]); flat at y = 0 by default
+    pub terrain: Terrain,
+
+    /// Two for the side-view simulation, Three for full 6-DOF flight
+    pub dimensions: Dimensions,
+
+    /// Wind blowing along Z, in m/s; only 3D systems feel it
+    pub crosswind: f32,
+}
+
+/// Which set of components and systems a scenario is written for
+///
+/// The 2D and 3D types live side by side (Position and Position3, ...), so
+/// this only changes which systems run when the scenario doesn't list its own
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Dimensions {
+    #[default]
+    Two,
+    Three,
+}
+
+impl Default for Settings {
+    /// The same settings SimWorld::new uses, with 5 seconds of calm air
+    fn default() -> Self {
+        Self {
+            time_step: 1.0 / 60.0,
+            physics_step: 1.0 / 1000.0,
+            duration: 5.0,
+            wind: Wind::calm(),
+            seed: 0,
+            integrator: Integrator::default(),
+            terrain: Terrain::default(),
+            dimensions: Dimensions::Two,
+            crosswind: 0.0,
+        }
+    }
+}
+
+/// One entity to spawn
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EntitySpec {
+    /// Becomes the entity's Name component
+    pub name: Option<String>,
+
+    /// Component values keyed by component name
+    ///
+    /// ron::Value holds any RON value, so we can parse the whole file before
+    /// knowing each component's type, and report every unknown name at once
+    pub components: BTreeMap<String, ron::Value>,
+}
+
+/// Decodes one component from RON and adds it to an entity being built
+type LoadFn = fn(ron::Value, &mut EntityBuilder) -> Result<(), String>;
+
+/// Components a scenario can give its entities, by name
+///
+/// The names match snapshot_registry, so one name means one type everywhere
+const COMPONENTS: [(&str, LoadFn); 13] = [
+    ("Position", load::<Position>),
+    ("Velocity", load::<Velocity>),
+    ("Rotation", load::<Rotation>),
+    ("Mass", load::<Mass>),
+    ("AeroProperties", load::<AeroProperties>),
+    ("Airfoil", load::<Airfoil>),
+    ("RigidBody", load::<RigidBody>),
+    ("Collider", load_collider),
+    ("LandingGear", load::<LandingGear>),
+    ("Position3", load::<Position3>),
+    ("Velocity3", load::<Velocity3>),
+    ("Orientation", load_orientation),
+    ("RigidBody3", load_rigid_body_3d),
+];
+
+/// Components whose systems push the body around through Force and Torque
+///
+/// Scenario files don't list those accumulators; entities with any of these
+/// components get them added, once however many there are: Force and Torque
+/// in 2D scenarios, Force3 and Torque3 in 3D ones. A RigidBody3 always gets
+/// Force3 and Torque3, since RigidBodySystem3 reads them
+const PUSHES_BODY: [&str; 2] = ["AeroProperties", "LandingGear"];
+
+impl Scenario {
+    /// Parse a scenario from RON text
+    ///
+    /// This only checks the file's shape; validate checks the values
+    pub fn from_ron(text: &str) -> Result<Self, ScenarioError> {
+        // IMPLICIT_SOME lets files write `name: "Glider"` instead of `name: Some("Glider")`
+        let options = ron::Options::default().with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME);
+        Ok(options.from_str(text)?)
+    }
+
+    /// Read and parse a scenario file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ScenarioError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|source| ScenarioError::Io {
+            path: path.display().to_string(),
+            source,
+        })?;
+        Self::from_ron(&text)
+    }
+
+    /// Check every value in the scenario, reporting all problems at once
+    pub fn validate(&self) -> Result<(), ScenarioError> {
+        self.entity_builders().map(drop)
+    }
+
+    /// The systems to run, in the order the scenario lists them
+    pub fn system_names(&self) -> Vec<&str> {
+        match &self.systems {
+            Some(systems) => systems.iter().map(String::as_str).collect(),
+            None => match self.settings.dimensions {
+                Dimensions::Two => DEFAULT_SYSTEMS.to_vec(),
+                Dimensions::Three => DEFAULT_SYSTEMS_3D.to_vec(),
+            },
+        }
+    }
+
+    /// Validate the scenario and decode each entity into an EntityBuilder
+    ///
+    /// Problems are collected rather than returned one by one, so a broken
+    /// file can be fixed in one go instead of one error per run
+    pub fn entity_builders(&self) -> Result<Vec<EntityBuilder>, ScenarioError> {
+        let mut problems = self.settings.problems();
+
+        let known: Vec<&str> = DEFAULT_SYSTEMS.iter().chain(&DEFAULT_SYSTEMS_3D).chain(&OPTIONAL_SYSTEMS).copied().collect();
+        for name in self.system_names() {
+            if !known.contains(&name) {
+                problems.push(format!("systems: unknown system `{}` (known: {})", name, known.join(", ")));
+            }
+        }
+
+        let mut builders = Vec::with_capacity(self.entities.len());
+        for (index, entity) in self.entities.iter().enumerate() {
+            // Locate problems by index and, when there is one, by name
+            let label = match &entity.name {
+                Some(name) => format!("entities[{}] \"{}\"", index, name),
+                None => format!("entities[{}]", index),
+            };
+
+            let mut builder = EntityBuilder::new();
+            if let Some(name) = &entity.name {
+                builder.add(Name::new(name));
+            }
+
+            for (component, value) in &entity.components {
+                match COMPONENTS.iter().find(|(known, _)| known == component) {
+                    Some((_, load)) => {
+                        if let Err(problem) = load(value.clone(), &mut builder) {
+                            problems.push(format!("{}: {}: {}", label, component, problem));
+                        }
+                    }
+                    None => {
+                        let known: Vec<&str> = COMPONENTS.iter().map(|(known, _)| *known).collect();
+                        problems.push(format!("{}: unknown component `{}` (known: {})", label, component, known.join(", ")));
+                    }
+                }
+            }
+            let pushed = entity.components.keys().any(|component| PUSHES_BODY.contains(&component.as_str()));
+            let three = self.settings.dimensions == Dimensions::Three;
+            if pushed && !three {
+                builder.add_bundle((Force::zero(), Torque::zero()));
+            }
+            if (pushed && three) || entity.components.contains_key("RigidBody3") {
+                builder.add_bundle((Force3::default(), Torque3::default()));
+            }
+            builders.push(builder);
+        }
+
+        if problems.is_empty() {
+            Ok(builders)
+        } else {
+            Err(ScenarioError::Invalid(problems))
+        }
+    }
+}
+
+impl Settings {
+    fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut check = |ok: bool, problem: String| {
+            if !ok {
+                problems.push(problem);
+            }
+        };
+
+        // NaN fails every comparison, so "x > 0.0" also rejects it
+        check(self.time_step > 0.0 && self.time_step.is_finite(), format!("settings.time_step must be positive, got {}", self.time_step));
+        check(self.physics_step > 0.0 && self.physics_step.is_finite(), format!("settings.physics_step must be positive, got {}", self.physics_step));
+        check(self.duration >= 0.0 && self.duration.is_finite(), format!("settings.duration can't be negative, got {}", self.duration));
+        check(
+            self.wind.velocity_x.is_finite() && self.wind.velocity_y.is_finite(),
+            "settings.wind velocity must be finite".to_string(),
+        );
+        check(self.crosswind.is_finite(), format!("settings.crosswind must be finite, got {}", self.crosswind));
+        check(self.wind.turbulence >= 0.0, format!("settings.wind.turbulence can't be negative, got {}", self.wind.turbulence));
+        check(self.terrain.spacing > 0.0 && self.terrain.spacing.is_finite(), format!("settings.terrain.spacing must be positive, got {}", self.terrain.spacing));
+        check(!self.terrain.heights.is_empty(), "settings.terrain.heights needs at least one height".to_string());
+        check(
+            self.terrain.origin_x.is_finite() && self.terrain.heights.iter().all(|height| height.is_finite()),
+            "settings.terrain values must be finite".to_string(),
+        );
+        problems
+    }
+}
+
+/// Values a component can hold but that make no physical sense
+trait Validate {
+    fn validate(&self) -> Result<(), String>;
+}
+
+/// Fail unless every value is a finite number
+fn finite(values: &[f32]) -> Result<(), String> {
+    match values.iter().find(|value| !value.is_finite()) {
+        Some(value) => Err(format!("values must be finite numbers, got {}", value)),
+        None => Ok(()),
+    }
+}
+
+impl Validate for Position {
+    fn validate(&self) -> Result<(), String> {
+        finite(&[self.x, self.y])
+    }
+}
+
+impl Validate for Velocity {
+    fn validate(&self) -> Result<(), String> {
+        finite(&[self.x, self.y])
+    }
+}
+
+impl Validate for Rotation {
+    fn validate(&self) -> Result<(), String> {
+        finite(&[self.angle])
+    }
+}
+
+impl Validate for Mass {
+    fn validate(&self) -> Result<(), String> {
+        if self.value > 0.0 && self.value.is_finite() {
+            Ok(())
+        } else {
+            Err(format!("value must be positive, got {}", self.value))
+        }
+    }
+}
+
+impl Validate for AeroProperties {
+    fn validate(&self) -> Result<(), String> {
+        finite(&[self.lift_coefficient, self.drag_coefficient, self.wing_area, self.angle_of_attack])?;
+        if self.wing_area < 0.0 || self.drag_coefficient < 0.0 {
+            return Err("wing_area and drag_coefficient can't be negative".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Validate for Airfoil {
+    fn validate(&self) -> Result<(), String> {
+        finite(&[
+            self.lift_slope,
+            self.zero_lift_angle,
+            self.stall_angle,
+            self.stall_sharpness,
+            self.zero_lift_drag,
+            self.aspect_ratio,
+            self.oswald_efficiency,
+            self.flat_plate_normal,
+        ])?;
+        if self.aspect_ratio <= 0.0 || self.oswald_efficiency <= 0.0 {
+            return Err("aspect_ratio and oswald_efficiency must be positive".to_string());
+        }
+        if self.stall_angle <= 0.0 || self.stall_sharpness <= 0.0 {
+            return Err("stall_angle and stall_sharpness must be positive".to_string());
+        }
+        if self.zero_lift_drag < 0.0 || self.flat_plate_normal < 0.0 {
+            return Err("zero_lift_drag and flat_plate_normal can't be negative".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Validate for RigidBody {
+    fn validate(&self) -> Result<(), String> {
+        finite(&[self.inertia, self.angular_velocity])?;
+        if self.inertia < 0.0 {
+            return Err(format!("inertia can't be negative, got {}", self.inertia));
+        }
+        Ok(())
+    }
+}
+
+impl Validate for Position3 {
+    fn validate(&self) -> Result<(), String> {
+        finite(&[self.x, self.y, self.z])
+    }
+}
+
+impl Validate for Velocity3 {
+    fn validate(&self) -> Result<(), String> {
+        finite(&[self.x, self.y, self.z])
+    }
+}
+
+impl Validate for LandingGear {
+    fn validate(&self) -> Result<(), String> {
+        for (index, strut) in self.struts.iter().enumerate() {
+            let values = [strut.length, strut.stiffness, strut.damping, strut.rolling_friction, strut.brake_friction];
+            finite(&values).and_then(|_| strut.mount.validate()).map_err(|problem| format!("struts[{}]: {}", index, problem))?;
+            if strut.length <= 0.0 {
+                return Err(format!("struts[{}]: length must be positive, got {}", index, strut.length));
+            }
+            if values[1..].iter().any(|&value| value < 0.0) {
+                return Err(format!("struts[{}]: stiffness, damping and friction can't be negative", index));
+            }
+            if !(0.0..=1.0).contains(&strut.brake) {
+                return Err(format!("struts[{}]: brake must be between 0 and 1, got {}", index, strut.brake));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Decode a component of type T, check it, and add it to the builder
+fn load<T: Component + DeserializeOwned + Validate>(value: ron::Value, builder: &mut EntityBuilder) -> Result<(), String> {
+    let component: T = value.into_rust().map_err(|err| err.to_string())?;
+    component.validate()?;
+    builder.add(component);
+    Ok(())
+}
+
+/// Collider as written in scenario files
+///
+/// ron::Value forgets enum variant names, so instead of `Circle(radius: 0.5)`
+/// the shape names its variant in a `type` field:
+///
+/// "Collider": (shape: (type: "Circle", radius: 0.5), restitution: 0.3),
+/// "Collider": (shape: (type: "Heightfield", heights: [0.0, 2.0, 1.5], spacing: 50.0)),
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ColliderSpec {
+    shape: ShapeSpec,
+    #[serde(default)]
+    restitution: f32,
+    #[serde(default = "default_friction")]
+    friction: f32,
+}
+
+/// Collider::new's friction
+fn default_friction() -> f32 {
+    0.5
+}
+
+/// Shape with its variant in a `type` field (serde's internally tagged enums)
+#[derive(Deserialize)]
+#[serde(tag = "type", deny_unknown_fields)]
+enum ShapeSpec {
+    Circle { radius: f32 },
+    Rectangle { half_width: f32, half_height: f32 },
+    Capsule { half_length: f32, radius: f32 },
+    Polygon { vertices: Vec<Position> },
+    Heightfield { heights: Vec<f32>, spacing: f32 },
+}
+
+/// Decode and check a ColliderSpec, and add the Collider it describes
+fn load_collider(value: ron::Value, builder: &mut EntityBuilder) -> Result<(), String> {
+    let spec: ColliderSpec = value.into_rust().map_err(|err| err.to_string())?;
+    let positive = |name: &str, value: f32| {
+        if value > 0.0 && value.is_finite() {
+            Ok(())
+        } else {
+            Err(format!("{} must be positive, got {}", name, value))
+        }
+    };
+
+    let mut collider = match spec.shape {
+        ShapeSpec::Circle { radius } => {
+            positive("radius", radius)?;
+            Collider::circle(radius)
+        }
+        ShapeSpec::Rectangle { half_width, half_height } => {
+            positive("half_width", half_width)?;
+            positive("half_height", half_height)?;
+            Collider::rectangle(half_width, half_height)
+        }
+        ShapeSpec::Capsule { half_length, radius } => {
+            finite(&[half_length])?;
+            positive("radius", radius)?;
+            Collider::capsule(half_length, radius)
+        }
+        ShapeSpec::Polygon { vertices } => {
+            for vertex in &vertices {
+                vertex.validate()?;
+            }
+            Collider::polygon(vertices).ok_or("polygon needs at least 3 vertices and must be convex")?
+        }
+        ShapeSpec::Heightfield { heights, spacing } => {
+            positive("spacing", spacing)?;
+            finite(&heights)?;
+            if heights.len() < 2 {
+                return Err("heightfield needs at least 2 heights".to_string());
+            }
+            Collider::new(Shape::Heightfield { heights, spacing })
+        }
+    };
+
+    if !(0.0..=1.0).contains(&spec.restitution) {
+        return Err(format!("restitution must be between 0 and 1, got {}", spec.restitution));
+    }
+    if !(spec.friction >= 0.0 && spec.friction.is_finite()) {
+        return Err(format!("friction can't be negative, got {}", spec.friction));
+    }
+    collider.restitution = spec.restitution;
+    collider.friction = spec.friction;
+    builder.add(collider);
+    Ok(())
+}
+
+
+/// Orientation as written in scenario files: angles in radians instead of a
+/// quaternion, applied yaw first, then pitch, then roll
+///
+/// "Orientation": (roll: 0.0, pitch: 0.1, yaw: 0.0),
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct OrientationSpec {
+    roll: f32,
+    pitch: f32,
+    yaw: f32,
+}
+
+/// Decode and check an OrientationSpec, and add the Orientation it describes
+fn load_orientation(value: ron::Value, builder: &mut EntityBuilder) -> Result<(), String> {
+    let spec: OrientationSpec = value.into_rust().map_err(|err| err.to_string())?;
+    finite(&[spec.roll, spec.pitch, spec.yaw])?;
+    builder.add(Orientation::from_euler(spec.roll, spec.pitch, spec.yaw));
+    Ok(())
+}
+
+/// RigidBody3 as written in scenario files: the principal moments of inertia
+/// instead of a full tensor, and the body-frame spin rates
+///
+/// "RigidBody3": (roll: 1200.0, yaw: 3500.0, pitch: 2500.0, angular_velocity: (x: 0.0, y: 0.0, z: 0.0)),
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RigidBody3Spec {
+    roll: f32,
+    yaw: f32,
+    pitch: f32,
+    #[serde(default)]
+    angular_velocity: Option<Velocity3>,
+}
+
+/// Decode and check a RigidBody3Spec, and add the RigidBody3 it describes
+/// (entity_builders adds its Force3 and Torque3)
+fn load_rigid_body_3d(value: ron::Value, builder: &mut EntityBuilder) -> Result<(), String> {
+    let spec: RigidBody3Spec = value.into_rust().map_err(|err| err.to_string())?;
+    finite(&[spec.roll, spec.yaw, spec.pitch])?;
+    if spec.roll < 0.0 || spec.yaw < 0.0 || spec.pitch < 0.0 {
+        return Err("moments of inertia can't be negative".to_string());
+    }
+
+    let mut body = RigidBody3::principal(spec.roll, spec.yaw, spec.pitch);
+    if let Some(spin) = spec.angular_velocity {
+        spin.validate()?;
+        body.angular_velocity = nalgebra::Vector3::new(spin.x, spin.y, spin.z);
+    }
+    builder.add(body);
+    Ok(())
+}
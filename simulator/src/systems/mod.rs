@@ -1,6 +1,43 @@
 // Import statements - bring types and functions from other modules into scope
-use ecs::{System, World, EcsResult};      // Our ECS types from the ecs crate
-use crate::components::{Position, Velocity}; // Component types from our components module
+use ecs::{Access, Children, Entity, System, World, EcsResult, Parent, With, Without};  // Our ECS types from the ecs crate
+use physics::RigidBody;
+use aerodynamics::{Gust, Wind, Wind3};
+use rand::Rng;
+use crate::components::{LocalTransform, Position, Rotation, Velocity}; // Component types from our components module
+use crate::world::SimRng;
+
+// Rigid body integration, joints, collisions and landing gear live in the physics crate next to their components
+pub use physics::{CollisionSystem, JointSystem, LandingGearSystem, RigidBodySystem, RigidBodySystem3};
+pub use aerodynamics::{AeroSystem, AeroSystem3};
+
+/// Simulation stages, in the order they run every frame
+/// 
+/// Each stage finishes before the next one starts, so putting a system in the
+/// right stage is what keeps the physics consistent: forces are summed before
+/// integration uses them, and collisions are resolved after bodies have moved
+/// Within a stage, use SystemConfig::before/after for finer ordering
+pub mod stage {
+    pub const INPUT: &str = "input";
+    pub const FORCES: &str = "forces";
+    pub const AERODYNAMICS: &str = "aerodynamics";
+    pub const INTEGRATION: &str = "integration";
+    pub const COLLISION: &str = "collision";
+    pub const TELEMETRY: &str = "telemetry";
+    
+    /// Every stage, in execution order
+    pub const ALL: [&str; 6] = [INPUT, FORCES, AERODYNAMICS, INTEGRATION, COLLISION, TELEMETRY];
+}
+
+/// Names of the systems SimWorld::initialize adds, in the order it adds them
+/// 
+/// These are the System::name values, which is also how scenario files pick systems
+pub const DEFAULT_SYSTEMS: [&str; 8] = ["LandingGearSystem", "AeroSystem", "RigidBodySystem", "JointSystem", "MovementSystem", "TransformPropagationSystem", "CollisionSystem", "DebugSystem"];
+
+/// The systems a 3D scenario runs unless it lists its own (see scenario::Dimensions)
+pub const DEFAULT_SYSTEMS_3D: [&str; 2] = ["AeroSystem3", "RigidBodySystem3"];
+
+/// Systems that only run when a scenario lists them
+pub const OPTIONAL_SYSTEMS: [&str; 1] = ["TurbulenceSystem"];
 
 /// Simple movement system that updates positions based on velocities
 /// 
@@ -11,6 +48,11 @@ use crate::components::{Position, Velocity}; // Component types from our compone
 /// new_position = old_position + (velocity * time)
 /// 
 /// This is called "Euler integration" - the simplest way to simulate movement
+/// 
+/// It's only meant for kinematic objects, whose velocity is set directly.
+/// Entities with a RigidBody are skipped: the RigidBodySystem moves them with
+/// a proper integrator, since explicit Euler drifts on orbits and oscillations
+#[derive(Clone)]
 pub struct MovementSystem {
     /// Name for debugging and identification
     /// All systems need a name so we can track them and debug issues
@@ -30,6 +72,13 @@ impl MovementSystem {
     }
 }
 
+// Default lets MovementSystem::default() work the same as MovementSystem::new()
+impl Default for MovementSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // Implement the System trait for MovementSystem
 // 
 // This is how we tell the ECS that MovementSystem is a system that can be run
@@ -43,64 +92,58 @@ impl System for MovementSystem {
         &self.name  // Borrow a reference to the String's contents
     }
     
+    /// Declares which data this system touches
+    /// 
+    /// The dispatcher uses this to run systems that don't touch the same data
+    /// at the same time on different threads. We write Position and only read
+    /// Velocity, so e.g. a system that only reads Velocity can run alongside us
+    fn access(&self) -> Access {
+        Access::new().write::<Position>().read::<Velocity>()
+    }
+    
     /// The main system logic - called every frame
     /// 
     /// Parameters:
     /// - &mut self: Mutable reference to this system (we might change internal state)
-    /// - world: Mutable reference to the ECS world (we'll modify entity positions)
+    /// - world: Shared reference to the ECS world - queries check borrows at runtime,
+    ///   so we can still modify positions, and other systems can run at the same time
     /// - delta_time: Time since last frame in seconds (for frame-rate independent movement)
     /// 
     /// Returns: EcsResult<()> which is Result<(), EcsError>
     /// - Ok(()) means the system ran successfully
     /// - Err(error) means something went wrong
-    fn run(&mut self, world: &mut World, delta_time: f32) -> EcsResult<()> {
-        // Step 1: Get all entities that exist in the world
+    fn run_shared(&mut self, world: &World, delta_time: f32) -> EcsResult<()> {
+        // Step 1: Query every entity that has BOTH a Position and a Velocity
         // 
-        // world.entities() returns an iterator over all entities
-        // .collect() converts the iterator into a Vec<Entity>
+        // The tuple (&mut Position, &Velocity) describes what we want to borrow:
+        // - &mut Position: we will modify positions
+        // - &Velocity: we only read velocities
         // 
-        // Why collect first? Because we need to avoid "borrowing conflicts":
-        // - We're about to borrow world mutably to modify components
-        // - If we kept the iterator, we'd have both mutable and immutable borrows
-        // - Rust prevents this to avoid data races
-        let entities: Vec<_> = world.entities().collect();
+        // The query checks these borrows at runtime, so asking for something like
+        // (&mut Position, &Position) returns an error instead of aliasing memory
+        // The '?' operator passes that error up to the dispatcher
+        // Without<RigidBody> filters out the bodies RigidBodySystem moves
+        let mut query = world.query_filtered::<(&mut Position, &Velocity), Without<RigidBody>>()?;
         
-        // Step 2: Process each entity
+        // Step 2: Process each matching entity
         // 
-        // for loop iterates over each entity in our collected vector
-        for entity in entities {
-            // Step 3: Check if entity has both components first
+        // Entities without both components are skipped by the query itself
+        // This is the power of ECS: entities can have any combination of components
+        for (position, velocity) in query.iter() {
+            // Step 3: Apply movement physics
             // 
-            // We need to check existence separately to avoid borrowing conflicts
-            // Rust doesn't allow borrowing world both mutably and immutably at the same time
-            let has_both_components = world.has_component::<Velocity>(entity) 
-                && world.has_component::<Position>(entity);
-            
-            if has_both_components {
-                // Step 4: Get velocity first (immutable borrow)
-                let velocity = *world.get_component::<Velocity>(entity).unwrap();
-                
-                // Step 5: Get position mutably (mutable borrow)
-                // The immutable borrow above is finished, so this is safe
-                if let Some(position) = world.get_component_mut::<Position>(entity) {
-                    // Step 6: Apply movement physics
-                    // 
-                    // Basic Euler integration: position += velocity * time
-                    // This simulates movement at the given velocity over the time period
-                    // 
-                    // delta_time makes movement frame-rate independent:
-                    // - At 60 FPS: delta_time ≈ 0.0167 seconds
-                    // - At 30 FPS: delta_time ≈ 0.0333 seconds  
-                    // - Same velocity will move the same distance per second regardless of framerate
-                    position.x += velocity.x * delta_time;
-                    position.y += velocity.y * delta_time;
-                }
-            }
-            // If the entity doesn't have both Position and Velocity, we simply skip it
-            // This is the power of ECS: entities can have any combination of components
+            // Basic Euler integration: position += velocity * time
+            // This simulates movement at the given velocity over the time period
+            // 
+            // delta_time makes movement frame-rate independent:
+            // - At 60 FPS: delta_time ≈ 0.0167 seconds
+            // - At 30 FPS: delta_time ≈ 0.0333 seconds  
+            // - Same velocity will move the same distance per second regardless of framerate
+            position.x += velocity.x * delta_time;
+            position.y += velocity.y * delta_time;
         }
         
-        // Step 5: Return success
+        // Step 4: Return success
         // () is the "unit type" - like void in C, but it's an actual value in Rust
         Ok(())
     }
@@ -129,6 +172,205 @@ impl System for MovementSystem {
         
         Ok(())
     }
+    
+    /// Copy this system for a forked simulation
+    /// 
+    /// Box::new(self.clone()) works because of #[derive(Clone)] above
+    /// Returning Some is what lets SimWorld::fork copy the dispatcher
+    fn fork(&self) -> Option<Box<dyn System>> {
+        Some(Box::new(self.clone()))
+    }
+}
+
+/// Places attached parts in world space
+/// 
+/// Children with a LocalTransform (wings, control surfaces, engines...) get
+/// their Position and Rotation recomputed from their parent's every step,
+/// so they follow the fuselage wherever the integrator moved it
+/// 
+/// Runs in the integration stage after MovementSystem, so parts are in
+/// place before collision checks see them
+#[derive(Clone)]
+pub struct TransformPropagationSystem {
+    name: String,
+}
+
+impl TransformPropagationSystem {
+    pub fn new() -> Self {
+        Self {
+            name: "TransformPropagationSystem".to_string(),
+        }
+    }
+}
+
+impl Default for TransformPropagationSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl System for TransformPropagationSystem {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    
+    /// Walks the hierarchy and writes positions as it goes
+    /// 
+    /// A child's world position depends on its parent's, which might live in
+    /// the same column, so instead of one big query this system takes '&mut World'
+    /// (the default exclusive access) and reads and writes entity by entity
+    fn run(&mut self, world: &mut World, _delta_time: f32) -> EcsResult<()> {
+        // Step 1: Find the roots - entities with children but no parent
+        // The query borrow ends with this statement, freeing the World for writes
+        let roots: Vec<Entity> = world
+            .query_filtered::<Entity, (With<Children>, Without<Parent>)>()?
+            .iter()
+            .collect();
+        
+        // Step 2: Walk down from each root
+        // 
+        // The stack holds (entity, its world position, its world rotation)
+        // An explicit stack instead of recursion keeps deep hierarchies safe
+        let mut stack: Vec<(Entity, Position, Rotation)> = roots
+            .into_iter()
+            .map(|root| Ok((root, world_position(world, root)?, world_rotation(world, root)?)))
+            .collect::<EcsResult<_>>()?;
+        
+        while let Some((parent, parent_position, parent_rotation)) = stack.pop() {
+            for child in world.children(parent)? {
+                // Copy the LocalTransform out so we stop borrowing the World
+                let transform = world.get_optional_component::<LocalTransform>(child)?.map(|local| *local);
+                
+                // Parts without a LocalTransform keep whatever world position
+                // they have, and their own children are placed relative to it
+                let (position, rotation) = match transform {
+                    Some(local) => {
+                        let (position, rotation) = local.to_world(parent_position, parent_rotation);
+                        set_or_add(world, child, position)?;
+                        set_or_add(world, child, rotation)?;
+                        (position, rotation)
+                    }
+                    None => (world_position(world, child)?, world_rotation(world, child)?),
+                };
+                stack.push((child, position, rotation));
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// No state besides the name, so a clone is a perfect copy
+    fn fork(&self) -> Option<Box<dyn System>> {
+        Some(Box::new(self.clone()))
+    }
+}
+
+/// An entity's Position, or the origin if it has none
+fn world_position(world: &World, entity: Entity) -> EcsResult<Position> {
+    Ok(world.get_optional_component::<Position>(entity)?.map_or(Position::zero(), |position| *position))
+}
+
+/// An entity's Rotation, or no rotation if it has none
+fn world_rotation(world: &World, entity: Entity) -> EcsResult<Rotation> {
+    Ok(world.get_optional_component::<Rotation>(entity)?.map_or(Rotation::zero(), |rotation| *rotation))
+}
+
+/// Overwrite a component in place, or add it if the entity doesn't have one yet
+/// 
+/// Writing through get_component_mut keeps the entity in its archetype table;
+/// add_component would replace it too, but only the first time needs the move
+fn set_or_add<T: ecs::Component>(world: &mut World, entity: Entity, value: T) -> EcsResult<()> {
+    match world.get_component_mut::<T>(entity) {
+        Some(component) => *component = value,
+        None => world.add_component(entity, value)?,
+    }
+    Ok(())
+}
+
+/// How long a gust lasts on average, in seconds
+const GUST_TIME: f32 = 1.0;
+
+/// Random gusts on top of the mean wind
+/// 
+/// Every step the Gust resource fades towards calm and gets a random kick
+/// from SimRng (a first-order Gauss-Markov process), so gusts last about
+/// GUST_TIME and each component spreads by Wind::turbulence m/s. The seed
+/// therefore decides the gusts, and with it the whole run
+/// 
+/// Turbulence comes from Wind, or Wind3 when there's no 2D wind. Without
+/// any, the gust stays calm and the generator isn't touched
+/// 
+/// It's one of the OPTIONAL_SYSTEMS, so only scenarios that list it get gusts
+#[derive(Clone)]
+pub struct TurbulenceSystem {
+    name: String,
+}
+
+impl TurbulenceSystem {
+    pub fn new() -> Self {
+        Self {
+            name: "TurbulenceSystem".to_string(),
+        }
+    }
+}
+
+impl Default for TurbulenceSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl System for TurbulenceSystem {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    
+    fn access(&self) -> Access {
+        Access::new()
+            .read_resource::<Wind>()
+            .read_resource::<Wind3>()
+            .write_resource::<Gust>()
+            .write_resource::<SimRng>()
+    }
+    
+    fn run_shared(&mut self, world: &World, delta_time: f32) -> EcsResult<()> {
+        let turbulence = match world.resource::<Wind>() {
+            Ok(wind) => wind.turbulence,
+            Err(_) => world.resource::<Wind3>().map_or(0.0, |wind| wind.turbulence),
+        };
+        let mut gust = world.resource_mut::<Gust>()?;
+        let gust = &mut *gust;
+        if turbulence <= 0.0 {
+            *gust = Gust::default();
+            return Ok(());
+        }
+        
+        // Exact for any step length: the spread stays at `turbulence`
+        let fade = (-delta_time / GUST_TIME).exp();
+        let kick = turbulence * (1.0 - fade * fade).sqrt();
+        let mut rng = world.resource_mut::<SimRng>()?;
+        for velocity in [&mut gust.velocity_x, &mut gust.velocity_y, &mut gust.velocity_z] {
+            *velocity = *velocity * fade + kick * gaussian(&mut rng.0);
+        }
+        Ok(())
+    }
+    
+    fn initialize(&mut self, world: &mut World) -> EcsResult<()> {
+        world.insert_resource(Gust::default());
+        Ok(())
+    }
+    
+    fn fork(&self) -> Option<Box<dyn System>> {
+        Some(Box::new(self.clone()))
+    }
+}
+
+/// A standard normal sample, by the Box-Muller transform
+fn gaussian(rng: &mut impl Rng) -> f32 {
+    // 1 - u is in (0, 1], so the logarithm stays finite
+    let u = 1.0 - rng.gen::<f32>();
+    let v = rng.gen::<f32>();
+    (-2.0 * u.ln()).sqrt() * (std::f32::consts::TAU * v).cos()
 }
 
 /// Debug system that prints entity information
@@ -137,91 +379,78 @@ impl System for MovementSystem {
 /// information about entities in the world. It's not essential for gameplay,
 /// but very useful for understanding what's happening in your simulation.
 /// 
-/// This demonstrates a different kind of system - one that reads data but
-/// doesn't modify game state (except for its own internal timer)
+/// This demonstrates a different kind of system - one that only reads data
+/// 
+/// It prints every time it runs, so how often it prints is decided where it's
+/// added to the dispatcher, with a RunCriteria such as a fixed timestep
+#[derive(Clone)]
 pub struct DebugSystem {
     /// Name for identification
     name: String,
-    
-    /// How often to print debug info (in seconds)
-    /// For example, 2.0 means print every 2 seconds
-    print_interval: f32,
-    
-    /// Time elapsed since last print (in seconds)
-    /// This is internal state that the system maintains between runs
-    /// We accumulate delta_time here until we reach print_interval
-    elapsed: f32,
 }
 
 impl DebugSystem {
-    /// Create a new debug system with a specified print interval
+    /// Create a new debug system
     /// 
-    /// Parameters:
-    /// - print_interval: How often to print debug info in seconds
-    /// 
-    /// Example: DebugSystem::new(1.0) prints debug info every second
-    pub fn new(print_interval: f32) -> Self {
+    /// Example: adding it with SystemConfig::new().run_criteria(RunCriteria::fixed_timestep(1.0))
+    /// prints debug info every second
+    pub fn new() -> Self {
         Self {
             name: "DebugSystem".to_string(),
-            print_interval,  // Field init shorthand - same as print_interval: print_interval
-            elapsed: 0.0,    // Start with no elapsed time
         }
     }
 }
 
+impl Default for DebugSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl System for DebugSystem {
     fn name(&self) -> &str {
         &self.name
     }
     
+    /// The debug system only reads, so it never blocks other readers
+    fn access(&self) -> Access {
+        Access::new().read::<Position>().read::<Velocity>()
+    }
+    
     /// The main debug system logic
     /// 
-    /// This system uses a timer pattern: accumulate time until we reach
-    /// our target interval, then do something and reset the timer
-    fn run(&mut self, world: &mut World, delta_time: f32) -> EcsResult<()> {
-        // Step 1: Update our internal timer
-        // 
-        // Add the time since last frame to our elapsed time counter
-        // delta_time is typically a small number like 0.016 (60 FPS)
-        self.elapsed += delta_time;
-        
-        // Step 2: Check if it's time to print debug info
-        // 
-        // Only proceed if enough time has passed since our last print
-        if self.elapsed >= self.print_interval {
-            // Reset the timer for the next cycle
-            // We don't set it to 0.0 in case we overshot the interval slightly
-            // This keeps the timing more accurate over long periods
-            self.elapsed = 0.0;
-            
-            // Step 3: Print header
+    /// There's no timer here: the dispatcher's run criteria only call us
+    /// when it's time to print
+    fn run_shared(&mut self, world: &World, _delta_time: f32) -> EcsResult<()> {
+        {
+            // Step 1: Print header
             // 
             // === makes it easy to spot debug output in console logs
             println!("=== Debug Info ===");
             println!("Entities: {}", world.entity_count());
             
-            // Step 4: Print info about each entity
+            // Step 2: Print info about each entity
             // 
             // Iterate over all entities and print their components
             for entity in world.entities() {
                 // Start printing this entity's info
-                // {:?} is Rust's debug format - it prints the internal structure
-                // entity.id() gives us the unique identifier for this entity
-                print!("Entity {:?}: ", entity.id());
+                // {} uses Entity's Display format: slot index and generation, e.g. 3v0
+                print!("Entity {}: ", entity);
                 
                 // Try to get Position component and print it if it exists
                 // 
                 // if let Some(pos) = ... is pattern matching:
-                // - If get_component returns Some(position), extract it as 'pos'
-                // - If get_component returns None, skip this block
-                if let Some(pos) = world.get_component::<Position>(entity) {
+                // - If get_optional_component returns Some(position), extract it as 'pos'
+                // - If it returns None, skip this block
+                // The ? passes on real errors, like a component we forgot to declare
+                if let Some(pos) = world.get_optional_component::<Position>(entity)? {
                     // print! (no 'ln') prints without a newline
                     // {:.2} means "format as floating point with 2 decimal places"
                     print!("Pos({:.2}, {:.2}) ", pos.x, pos.y);
                 }
                 
                 // Try to get Velocity component and print it if it exists
-                if let Some(vel) = world.get_component::<Velocity>(entity) {
+                if let Some(vel) = world.get_optional_component::<Velocity>(entity)? {
                     print!("Vel({:.2}, {:.2}) ", vel.x, vel.y);
                 }
                 
@@ -247,11 +476,13 @@ impl System for DebugSystem {
         // exists but I'm not using it, so don't warn me about it"
         // This is common for trait methods where not all implementations
         // need all parameters
-        
-        // Print startup message with our configuration
-        // {:.2} formats the float with 2 decimal places
-        println!("DebugSystem initialized (interval: {:.2}s)", self.print_interval);
+        println!("DebugSystem initialized");
         
         Ok(())
     }
+    
+    /// Forked simulations print too, so rollouts can be debugged the same way
+    fn fork(&self) -> Option<Box<dyn System>> {
+        Some(Box::new(self.clone()))
+    }
 }
\ No newline at end of file
@@ -1,59 +1,257 @@
-use ecs::{World, SystemDispatcher, EcsResult};
-use crate::components::{Position, Velocity, Name, Mass, Rotation};
-use crate::systems::{MovementSystem, DebugSystem};
+use aerodynamics::{Gust, Wind, Wind3};
+use physics::{Contacts, ForceModels, ForceModels3, Integrator, Terrain};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use ecs::{Entity, Parent, Without, World, RunCriteria, DEFAULT_MAX_SUBSTEPS, SnapshotRegistry, SystemConfig, SystemDispatcher, EcsError, EcsResult};
+use crate::components::{register_cloneable, snapshot_registry, AircraftBundle, Position, Velocity, Name, Mass};
+use crate::scenario::{Scenario, ScenarioError};
+use crate::systems::{stage, AeroSystem, AeroSystem3, CollisionSystem, JointSystem, LandingGearSystem, MovementSystem, DebugSystem, RigidBodySystem, RigidBodySystem3, TransformPropagationSystem, TurbulenceSystem, DEFAULT_SYSTEMS};
+
+/// Random number generator shared by every system, stored as a resource
+/// 
+/// Systems that need randomness (turbulence, sensor noise...) borrow it with
+/// world.resource_mut::<SimRng>() instead of seeding their own, so one seed
+/// reproduces a whole run. ChaCha8 gives the same sequence on every platform
+/// 
+/// Snapshots store the seed and how far along the stream it is, so a
+/// restored generator carries on with the same numbers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "SimRngState", into = "SimRngState")]
+pub struct SimRng(pub ChaCha8Rng);
+
+impl SimRng {
+    pub fn seed(seed: u64) -> Self {
+        Self(ChaCha8Rng::seed_from_u64(seed))
+    }
+}
+
+/// A ChaCha8Rng's position as plain data
+/// 
+/// The word position is a u128, which not every format supports, so it's
+/// split into its high and low halves
+#[derive(Serialize, Deserialize)]
+struct SimRngState {
+    seed: [u8; 32],
+    stream: u64,
+    word_pos: (u64, u64),
+}
+
+impl From<SimRng> for SimRngState {
+    fn from(rng: SimRng) -> Self {
+        let word_pos = rng.0.get_word_pos();
+        Self {
+            seed: rng.0.get_seed(),
+            stream: rng.0.get_stream(),
+            word_pos: ((word_pos >> 64) as u64, word_pos as u64),
+        }
+    }
+}
+
+impl From<SimRngState> for SimRng {
+    fn from(state: SimRngState) -> Self {
+        let mut rng = ChaCha8Rng::from_seed(state.seed);
+        rng.set_stream(state.stream);
+        rng.set_word_pos(((state.word_pos.0 as u128) << 64) | state.word_pos.1 as u128);
+        Self(rng)
+    }
+}
 
 /// SimWorld wraps the ECS World and manages the simulation loop
 pub struct SimWorld {
     pub world: World,
     pub dispatcher: SystemDispatcher,
     pub time_step: f32,
+    /// Fixed step the physics stages advance by, however long a frame takes
+    pub physics_step: f32,
     pub total_time: f32,
+    /// Serializers for checkpointing the world
+    pub snapshots: SnapshotRegistry,
 }
 
 impl SimWorld {
     /// Create a new simulation world
     pub fn new() -> Self {
+        let mut world = World::new();
+        register_cloneable(&mut world);
+        world.register_cloneable_resource::<Wind>();
+        world.register_cloneable_resource::<Wind3>();
+        world.register_cloneable_resource::<Gust>();
+        world.register_cloneable_resource::<SimRng>();
+        world.register_cloneable_resource::<Integrator>();
+        world.register_cloneable_resource::<ForceModels>();
+        world.register_cloneable_resource::<ForceModels3>();
+        world.register_cloneable_resource::<Contacts>();
+        world.register_cloneable_resource::<Terrain>();
+        
+        // Seed 0 unless told otherwise, so runs are reproducible by default
+        world.insert_resource(SimRng::seed(0));
+        // Flat ground at y = 0 until a scenario loads a terrain profile
+        world.insert_resource(Terrain::default());
+        
         Self {
-            world: World::new(),
+            world,
             dispatcher: SystemDispatcher::new(),
             time_step: 1.0 / 60.0, // 60 FPS
+            physics_step: 1.0 / 1000.0, // 1 kHz
             total_time: 0.0,
+            snapshots: snapshot_registry(),
         }
     }
     
     /// Initialize the simulation with default systems
     pub fn initialize(&mut self) -> EcsResult<()> {
-        // Add core systems
-        self.dispatcher.add_system(MovementSystem::new(), &mut self.world)?;
-        self.dispatcher.add_system(DebugSystem::new(2.0), &mut self.world)?;
+        self.initialize_with(&DEFAULT_SYSTEMS)
+    }
+    
+    /// Initialize the simulation with only the named systems
+    /// 
+    /// Names are System::name values from DEFAULT_SYSTEMS, DEFAULT_SYSTEMS_3D
+    /// or OPTIONAL_SYSTEMS; scenario files use this to pick their systems.
+    /// Unknown names are an error
+    pub fn initialize_with(&mut self, systems: &[&str]) -> EcsResult<()> {
+        // Stages run in order every frame, whatever order systems are added in
+        for name in stage::ALL {
+            self.dispatcher.add_stage(name);
+        }
+        
+        // Physics runs in fixed substeps so results don't depend on frame rate;
+        // the four stages run together once per substep. A frame can catch up
+        // at most a few frames' worth, so one long pause doesn't snowball
+        let frame_substeps = (self.time_step / self.physics_step).ceil() as usize;
+        self.dispatcher.set_stage_criteria(
+            &[stage::FORCES, stage::AERODYNAMICS, stage::INTEGRATION, stage::COLLISION],
+            RunCriteria::fixed_timestep(self.physics_step).with_max_substeps(DEFAULT_MAX_SUBSTEPS.max(4 * frame_substeps)),
+        )?;
+        self.dispatcher.set_stage_criteria(&[stage::TELEMETRY], RunCriteria::fixed_rate(10.0))?;
+        
+        // Add the requested systems
+        // Ordering against a system that isn't there is an error, so 'after'
+        // constraints are only added for the integrators that are enabled
+        let integrators: Vec<&str> = ["RigidBodySystem", "MovementSystem"]
+            .into_iter()
+            .filter(|integrator| systems.contains(integrator))
+            .collect();
+        for &name in systems {
+            match name {
+                "RigidBodySystem" => {
+                    self.dispatcher.add_system_with(RigidBodySystem::new(), SystemConfig::new().in_stage(stage::INTEGRATION), &mut self.world)?;
+                }
+                "TurbulenceSystem" => {
+                    // Gusts change with every substep, before the aero systems feel them
+                    self.dispatcher.add_system_with(TurbulenceSystem::new(), SystemConfig::new().in_stage(stage::FORCES), &mut self.world)?;
+                }
+                "LandingGearSystem" => {
+                    self.dispatcher.add_system_with(LandingGearSystem::new(), SystemConfig::new().in_stage(stage::FORCES), &mut self.world)?;
+                }
+                "AeroSystem" => {
+                    self.dispatcher.add_system_with(AeroSystem::new(), SystemConfig::new().in_stage(stage::AERODYNAMICS), &mut self.world)?;
+                }
+                "JointSystem" => {
+                    // Joints correct the bodies the integrators just moved
+                    let mut config = SystemConfig::new().in_stage(stage::INTEGRATION);
+                    for &integrator in &integrators {
+                        config = config.after(integrator);
+                    }
+                    self.dispatcher.add_system_with(JointSystem::new(), config, &mut self.world)?;
+                }
+                "MovementSystem" => {
+                    self.dispatcher.add_system_with(MovementSystem::new(), SystemConfig::new().in_stage(stage::INTEGRATION), &mut self.world)?;
+                }
+                "TransformPropagationSystem" => {
+                    // Attached parts follow their parent once it has moved
+                    let mut config = SystemConfig::new().in_stage(stage::INTEGRATION);
+                    for &integrator in &integrators {
+                        config = config.after(integrator);
+                    }
+                    if systems.contains(&"JointSystem") {
+                        config = config.after("JointSystem");
+                    }
+                    self.dispatcher.add_system_with(TransformPropagationSystem::new(), config, &mut self.world)?;
+                }
+                "RigidBodySystem3" => {
+                    self.dispatcher.add_system_with(RigidBodySystem3::new(), SystemConfig::new().in_stage(stage::INTEGRATION), &mut self.world)?;
+                }
+                "AeroSystem3" => {
+                    self.dispatcher.add_system_with(AeroSystem3::new(), SystemConfig::new().in_stage(stage::AERODYNAMICS), &mut self.world)?;
+                }
+                "CollisionSystem" => {
+                    self.dispatcher.add_system_with(CollisionSystem::new(), SystemConfig::new().in_stage(stage::COLLISION), &mut self.world)?;
+                }
+                "DebugSystem" => {
+                    self.dispatcher.add_system_with(
+                        DebugSystem::new(),
+                        SystemConfig::new().in_stage(stage::TELEMETRY).run_criteria(RunCriteria::fixed_timestep(2.0)),
+                        &mut self.world,
+                    )?;
+                }
+                unknown => return Err(EcsError::UnknownSystemLabel(unknown.to_string())),
+            }
+        }
+        
+        // Catch ordering mistakes now rather than on the first step
+        self.dispatcher.build()?;
         
         println!("SimWorld initialized with {} systems", self.dispatcher.system_count());
         Ok(())
     }
     
+    /// Build a world from a scenario: settings, wind, systems and entities
+    /// 
+    /// The whole scenario is validated before anything is created, so a bad
+    /// file fails with a report of every problem in it
+    pub fn from_scenario(scenario: &Scenario) -> Result<Self, ScenarioError> {
+        let entities = scenario.entity_builders()?;
+        
+        let mut sim_world = Self::new();
+        sim_world.time_step = scenario.settings.time_step;
+        sim_world.physics_step = scenario.settings.physics_step;
+        sim_world.world.insert_resource(scenario.settings.wind);
+        sim_world.world.insert_resource(Wind3 {
+            velocity_z: scenario.settings.crosswind,
+            ..Wind3::from(scenario.settings.wind)
+        });
+        sim_world.world.insert_resource(scenario.settings.integrator);
+        sim_world.world.insert_resource(scenario.settings.terrain.clone());
+        sim_world.set_seed(scenario.settings.seed);
+        sim_world.initialize_with(&scenario.system_names())?;
+        
+        // Each EntityBuilder is a bundle, so every entity is one spawn
+        for entity in entities {
+            sim_world.world.spawn(entity)?;
+        }
+        
+        if !scenario.name.is_empty() {
+            println!("Loaded scenario \"{}\" with {} entities", scenario.name, sim_world.world.entity_count());
+        }
+        Ok(sim_world)
+    }
+    
     /// Add some sample entities for testing
     pub fn populate_with_test_entities(&mut self) -> EcsResult<()> {
         // Create a moving entity
-        let entity1 = self.world.create_entity();
-        self.world.add_component(entity1, Name::new("Moving Object"))?;
-        self.world.add_component(entity1, Position::new(0.0, 0.0))?;
-        self.world.add_component(entity1, Velocity::new(10.0, 5.0))?;
-        self.world.add_component(entity1, Mass::new(1.0))?;
-        self.world.add_component(entity1, Rotation::zero())?;
+        // spawn inserts the whole bundle at once and hands back the new Entity
+        self.world.spawn(AircraftBundle {
+            velocity: Velocity::new(10.0, 5.0),
+            ..AircraftBundle::new("Moving Object", Position::new(0.0, 0.0), Mass::new(1.0))
+        })?;
         
+        // Plain tuples of components are bundles too
         // Create a stationary entity
-        let entity2 = self.world.create_entity();
-        self.world.add_component(entity2, Name::new("Stationary Object"))?;
-        self.world.add_component(entity2, Position::new(50.0, 30.0))?;
-        self.world.add_component(entity2, Velocity::zero())?;
-        self.world.add_component(entity2, Mass::new(2.5))?;
+        self.world.spawn((
+            Name::new("Stationary Object"),
+            Position::new(50.0, 30.0),
+            Velocity::zero(),
+            Mass::new(2.5),
+        ))?;
         
         // Create another moving entity
-        let entity3 = self.world.create_entity();
-        self.world.add_component(entity3, Name::new("Fast Object"))?;
-        self.world.add_component(entity3, Position::new(-20.0, 10.0))?;
-        self.world.add_component(entity3, Velocity::new(-15.0, 8.0))?;
-        self.world.add_component(entity3, Mass::new(0.5))?;
+        self.world.spawn((
+            Name::new("Fast Object"),
+            Position::new(-20.0, 10.0),
+            Velocity::new(-15.0, 8.0),
+            Mass::new(0.5),
+        ))?;
         
         println!("Created {} test entities", self.world.entity_count());
         Ok(())
@@ -61,28 +259,109 @@ impl SimWorld {
     
     /// Step the simulation forward by one time step
     pub fn step(&mut self) -> EcsResult<()> {
-        self.dispatcher.run_systems(&mut self.world, self.time_step)?;
-        self.total_time += self.time_step;
+        self.advance(self.time_step)
+    }
+    
+    /// Advance the simulation by however much real time has passed
+    /// 
+    /// Frames can take any length; fixed-step stages catch up in whole
+    /// substeps and carry the remainder into the next call
+    pub fn advance(&mut self, real_dt: f32) -> EcsResult<()> {
+        self.dispatcher.run_systems(&mut self.world, real_dt)?;
+        self.total_time += real_dt;
         Ok(())
     }
     
+    /// Reseed the shared random number generator
+    pub fn set_seed(&mut self, seed: u64) {
+        self.world.insert_resource(SimRng::seed(seed));
+    }
+    
+    /// Number of time steps in `duration` seconds, to the nearest step
+    /// 
+    /// Rounded rather than truncated: 0.5 / (1/60) is 29.999... in f32
+    pub fn steps_for(&self, duration: f32) -> usize {
+        (duration / self.time_step).round() as usize
+    }
+    
     /// Run the simulation for a specified duration
     pub fn run_for(&mut self, duration: f32) -> EcsResult<()> {
-        let steps = (duration / self.time_step) as usize;
+        let steps = self.steps_for(duration);
         println!("Running simulation for {:.2}s ({} steps)", duration, steps);
         
-        for step in 0..steps {
-            self.step()?;
-            
+        self.run_steps(steps, |sim, step| {
             // Print progress every 60 steps (1 second at 60 FPS)
             if step % 60 == 0 {
-                println!("Step {}/{} (Time: {:.2}s)", step + 1, steps, self.total_time);
+                println!("Step {}/{} (Time: {:.2}s)", step + 1, steps, sim.total_time);
             }
+            Ok(())
+        })
+    }
+    
+    /// Run a number of time steps, calling `after_step` after each one
+    /// 
+    /// after_step gets the world and the step's index, e.g. to print progress
+    /// or record the state. 'impl FnMut' accepts any closure that may change
+    /// what it captured, like pushing into a Vec
+    /// An error from after_step stops the run and is returned
+    pub fn run_steps(&mut self, steps: usize, mut after_step: impl FnMut(&SimWorld, usize) -> EcsResult<()>) -> EcsResult<()> {
+        for step in 0..steps {
+            self.step()?;
+            after_step(self, step)?;
         }
-        
         Ok(())
     }
     
+    /// Save every entity's components and the registered resources as
+    /// bincode, e.g. to checkpoint an RL episode
+    /// 
+    /// The resources are the wind, terrain, integrator and the random
+    /// generator's state (see components::snapshot_registry). ForceModels hold
+    /// code rather than data and Contacts are rebuilt every collision step,
+    /// so neither is saved
+    pub fn checkpoint(&self) -> EcsResult<Vec<u8>> {
+        self.snapshots.save_bincode(&self.world)
+    }
+    
+    /// Replace every entity and saved resource with the ones from checkpoint
+    /// 
+    /// Systems and their state are kept, including time left over from a
+    /// partial physics substep; only the world's contents change
+    pub fn restore(&mut self, checkpoint: &[u8]) -> EcsResult<()> {
+        // remove_entity takes children with their parent, so only remove the
+        // roots; removing a child after its parent would find it already gone
+        let roots: Vec<Entity> = self.world.query_filtered::<Entity, Without<Parent>>()?.iter().collect();
+        for entity in roots {
+            self.world.remove_entity(entity)?;
+        }
+        self.snapshots.load_bincode(&mut self.world, checkpoint)?;
+        Ok(())
+    }
+    
+    /// Copy the whole simulation, e.g. to try out an action in a rollout
+    /// 
+    /// The copy starts exactly where this one is - same entities, same clock,
+    /// same partial physics substep - and then runs on its own. It's an
+    /// in-memory clone, much cheaper than checkpoint followed by restore
+    /// 
+    /// Fails if a component, resource or system can't be cloned
+    /// (see components::register_cloneable and System::fork)
+    pub fn fork(&self) -> EcsResult<SimWorld> {
+        Ok(SimWorld {
+            world: self.world.fork()?,
+            dispatcher: self.dispatcher.fork()?,
+            time_step: self.time_step,
+            physics_step: self.physics_step,
+            total_time: self.total_time,
+            snapshots: self.snapshots.clone(),
+        })
+    }
+    
+    /// Dump the world as RON, for reading or diffing by hand
+    pub fn dump_ron(&self) -> EcsResult<String> {
+        self.snapshots.save_ron(&self.world)
+    }
+    
     /// Get simulation statistics
     pub fn stats(&self) -> SimStats {
         SimStats {